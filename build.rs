@@ -0,0 +1,59 @@
+//! Build script that adds the git commit hash to the version string, if `tere` is built from a git
+//! checkout. The full version string is passed to the compiler in the `TERE_VERSION` environment
+//! variable, and the date of the commit in `TERE_COMMIT_DATE` (which is empty if it's not known).
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let version = env!("CARGO_PKG_VERSION");
+    let version = match git_commit_info("%h") {
+        Some(hash) => format!("{} ({})", version, hash),
+        None => version.to_string(),
+    };
+    println!("cargo:rustc-env=TERE_VERSION={}", version);
+    println!(
+        "cargo:rustc-env=TERE_COMMIT_DATE={}",
+        git_commit_info("%cs").unwrap_or_default()
+    );
+}
+
+/// Get information about the current git commit, such as the short hash, in the given `git show`
+/// format. Returns None if we're not in a git checkout, or if git is not available.
+fn git_commit_info(format: &str) -> Option<String> {
+    let git_head = Path::new(".git").join("HEAD");
+    if !git_head.exists() {
+        // Don't try to run git if we're not in a checkout (for example when building from
+        // crates.io), otherwise we might pick up the hash of some unrelated parent repository.
+        return None;
+    }
+
+    // Rebuild when the commit changes. HEAD changes when switching branches, and the branch ref
+    // changes when making a new commit.
+    println!("cargo:rerun-if-changed={}", git_head.display());
+    if let Some(head_ref) = std::fs::read_to_string(&git_head)
+        .ok()
+        .and_then(|s| s.strip_prefix("ref: ").map(|r| r.trim().to_string()))
+    {
+        let ref_path = Path::new(".git").join(head_ref);
+        if ref_path.exists() {
+            println!("cargo:rerun-if-changed={}", ref_path.display());
+        }
+    }
+
+    let output = Command::new("git")
+        .args(["show", "--no-patch", &format!("--format={}", format), "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let info = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if info.is_empty() {
+        None
+    } else {
+        Some(info)
+    }
+}
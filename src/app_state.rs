@@ -1,32 +1,73 @@
-/// This module contains structs related to handling the application state,
-/// independent of a "graphical" front-end, such as crossterm.
+//! This module contains structs related to handling the application state,
+//! independent of a "graphical" front-end, such as crossterm.
 
-use std::collections::BTreeMap;
+use std::cell::{OnceCell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
 use std::ffi::OsStr;
 use std::io::{Error as IOError, ErrorKind, Result as IOResult};
+use std::ops::Range;
 use std::path::{Component, Path, PathBuf};
-use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::SystemTime;
 
-use regex::Regex;
+use globset::GlobMatcher;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::settings::{
     TereSettings,
-    DeprecationWarnings,
+    Warnings,
     CaseSensitiveMode,
     GapSearchMode,
+    FuzzyAlgo,
+    SearchScope,
     SortMode,
+    ListingMode,
+    HeaderFormat,
+    CopyPathFormat,
+    SizeFormat,
+    TimeStyle,
+    Prefs,
+    read_prefs,
+    remove_invalid_prefs,
+    write_prefs,
 };
 
 #[path = "history.rs"]
 mod history;
+
+#[path = "prefetch.rs"]
+mod prefetch;
+#[path = "archive.rs"]
+mod archive;
+pub use archive::split_archive_path;
+use archive::{is_archive, read_archive_entries, ExtractDir};
+pub use prefetch::ListingReceiver;
+use prefetch::PrefetchCache;
 use history::HistoryTree;
 
 use crate::error::TereError;
+use crate::icons::icon_for;
+use crate::matcher::{new_matcher, Matcher};
 
 pub const NO_MATCHES_MSG: &str = "No matches";
 
+/// The number of spaces between columns in the grid layout
+pub const GRID_COLUMN_GAP: usize = 2;
+
+/// The maximum number of previous searches to remember
+const SEARCH_HISTORY_MAX_LEN: usize = 100;
+
+/// Read at most this many bytes of a file when previewing it
+const PREVIEW_MAX_BYTES: u64 = 4096;
+/// Read at most this many entries of a folder when previewing it, so that moving the cursor over
+/// large folders stays fast
+const PREVIEW_MAX_ENTRIES: usize = 1000;
+pub const BINARY_PREVIEW_MSG: &str = "<binary>";
+
 /// The match locations of a given item. A list of *byte offsets* into the item's name that match
 /// the current search pattern.
 pub type MatchesLocType = Vec<(usize, usize)>;
@@ -37,9 +78,16 @@ struct MatchesVec {
     all_items: Vec<CustomDirEntry>,
     // Each key-value pair in this map corresponds to an item in `all_items` that matches the
     // current search. The key is the item's index in `all_items`, while the value contains the
-    // regex match locations. We use a BTreeMap to always keep the matches sorted, so that they are
+    // match locations. We use a BTreeMap to always keep the matches sorted, so that they are
     // in the same order relative to each other as they are in `all_items`.
     matches: BTreeMap<usize, MatchesLocType>,
+    // The index in `all_items` of the match with the highest score. If there are several, this is
+    // the first one of them.
+    best_match: Option<usize>,
+    // The number of folders and files in `all_items`, not counting the parent folder '..'. These
+    // are cached so that they don't have to be recomputed every time the footer is drawn.
+    num_folders: usize,
+    num_files: usize,
 }
 
 impl MatchesVec {
@@ -57,42 +105,128 @@ impl MatchesVec {
     }
 
     /// Update the collection of matching items by going through all items in the full collection
-    /// and testing a regex pattern against the filenames
-    pub fn update_matches(&mut self, search_ptn: &Regex, case_sensitive: bool) {
-        self.matches.clear();
+    /// and matching the part of the filenames given by `scope` with `matcher`
+    ///
+    /// If `ignore_diacritics` is true, the diacritics are removed from the names before matching,
+    /// and the match locations are mapped back to the characters of the original names.
+    pub fn update_matches(
+        &mut self,
+        matcher: &dyn Matcher,
+        case_sensitive: bool,
+        ignore_diacritics: bool,
+        scope: SearchScope,
+    ) {
+        let mut best: Option<(i64, usize)> = None;
         self.matches = self
             .all_items
             .iter()
             .enumerate()
             .filter_map(|(i, item)| {
-                let target = if case_sensitive {
-                    item.file_name_checked()
+                let name = item.file_name_checked();
+                let (offset, target) = search_target(&name, scope);
+                let (target, sources) = if ignore_diacritics {
+                    let (folded, sources) = fold_diacritics(target, case_sensitive);
+                    (folded, Some(sources))
+                } else if case_sensitive {
+                    (target.to_string(), None)
                 } else {
-                    item.file_name_checked().to_lowercase()
+                    (target.to_lowercase(), None)
                 };
-                let mut capture_locations = search_ptn.capture_locations();
-                if search_ptn
-                    .captures_read(&mut capture_locations, &target)
-                    .is_some()
-                {
-                    // have to do it this way using range because capture_locations has no iter() method
-                    let locs = (1..capture_locations.len())
-                        .filter_map(|i| capture_locations.get(i))
-                        .collect();
-                    Some((i, locs))
-                } else {
-                    None
+                let (score, positions) = matcher.find(&target)?;
+                if best.is_none_or(|(best_score, _)| score > best_score) {
+                    best = Some((score, i));
                 }
+
+                // The byte range in the original target of the matching character at `pos` in the
+                // (folded) target
+                let to_original = |pos: usize| {
+                    let end = pos + target[pos..].chars().next().map_or(0, char::len_utf8);
+                    match &sources {
+                        Some(sources) => (sources[pos].start, sources[end - 1].end),
+                        None => (pos, end),
+                    }
+                };
+                // The locations are relative to the target, shift them to be relative to the
+                // whole name, which is what is drawn.
+                let locs = positions
+                    .into_iter()
+                    .map(to_original)
+                    .map(|(start, end)| (start + offset, end + offset))
+                    .collect();
+                Some((i, locs))
             })
             .collect();
+        self.best_match = best.map(|(_, i)| i);
+    }
+}
+
+/// Remove the diacritics from `text`, so that for example both the precomposed 'é' and 'e'
+/// followed by a combining acute accent become 'e'. The text is also lowercased if not
+/// `case_sensitive`. Returns the new text, and for each of its bytes, the byte range in `text` of
+/// the character that it came from, including the combining marks after it.
+fn fold_diacritics(text: &str, case_sensitive: bool) -> (String, Vec<Range<usize>>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut sources = Vec::with_capacity(text.len());
+    for (start, c) in text.char_indices() {
+        let source = start..start + c.len_utf8();
+        let len_before = folded.len();
+        for base in std::iter::once(c).nfd().filter(|c| !is_combining_mark(*c)) {
+            if case_sensitive {
+                folded.push(base);
+            } else {
+                folded.extend(base.to_lowercase());
+            }
+        }
+        if folded.len() > len_before {
+            sources.resize(folded.len(), source);
+        } else {
+            // A combining mark, it belongs to the character before it
+            for prev in sources.iter_mut().rev().take_while(|r| r.end == start) {
+                prev.end = source.end;
+            }
+        }
     }
+    (folded, sources)
+}
+
+/// The part of `name` that the search is matched against with the given search scope, and its byte
+/// offset in `name`. Names without an extension have an empty extension, and their stem is the
+/// whole name.
+fn search_target(name: &str, scope: SearchScope) -> (usize, &str) {
+    // With recursive search, the names are paths relative to the current folder. Only the last
+    // component is searched, like in a normal listing.
+    let name_start = name.rfind(std::path::MAIN_SEPARATOR).map_or(0, |i| i + 1);
+    let name = &name[name_start..];
+    let path = Path::new(name);
+    let (offset, target) = match scope {
+        SearchScope::Name => (0, name),
+        SearchScope::Extension => match path.extension().and_then(OsStr::to_str) {
+            Some(ext) => (name.len() - ext.len(), ext),
+            None => (name.len(), ""),
+        },
+        SearchScope::Stem => (0, path.file_stem().and_then(OsStr::to_str).unwrap_or(name)),
+    };
+    (name_start + offset, target)
 }
 
 impl From<Vec<CustomDirEntry>> for MatchesVec {
     fn from(vec: Vec<CustomDirEntry>) -> Self {
+        let (num_folders, num_files) = vec
+            .iter()
+            .filter(|e| e.file_name_checked() != "..")
+            .fold((0, 0), |(n_folders, n_files), e| {
+                if e.is_dir() {
+                    (n_folders + 1, n_files)
+                } else {
+                    (n_folders, n_files + 1)
+                }
+            });
         Self {
             all_items: vec,
             matches: BTreeMap::new(),
+            best_match: None,
+            num_folders,
+            num_files,
         }
     }
 }
@@ -101,10 +235,21 @@ impl From<Vec<CustomDirEntry>> for MatchesVec {
 #[derive(Clone)]
 pub struct CustomDirEntry {
     _path: std::path::PathBuf,
-    pub metadata: Option<std::fs::Metadata>,
+    /// The file type as reported when reading the folder, without following symlinks. On most
+    /// platforms, this doesn't require a separate system call for each entry.
+    _file_type: Option<std::fs::FileType>,
+    /// The metadata is loaded lazily when it's first needed, because reading it for every entry
+    /// is slow in huge folders. It is None if the metadata can't be read.
+    _metadata: OnceCell<Option<std::fs::Metadata>>,
     /// The symlink target is None if this entry is not a symlink
     pub symlink_target: Option<std::path::PathBuf>,
     _file_name: std::ffi::OsString,
+    /// For entries inside archives, which don't exist on the file system, whether the entry is a
+    /// folder. None for normal entries.
+    _archive_is_dir: Option<bool>,
+    /// The file name cut to the width (in terminal columns) that it was last drawn with, so that
+    /// very long names are not processed again every time the listing is drawn.
+    _fitted_name: RefCell<Option<(usize, String)>>,
 }
 
 impl CustomDirEntry {
@@ -119,22 +264,72 @@ impl CustomDirEntry {
         &self._path
     }
 
+    /// The length of the file name in bytes
+    pub fn file_name_len(&self) -> usize {
+        self._file_name.len()
+    }
+
+    /// The file name (like `file_name_checked`), cut to the grapheme clusters that fit in `width`
+    /// columns of the terminal. The result is cached until it's asked for with a different width,
+    /// i.e. until the window is resized.
+    pub fn file_name_fitting_width(&self, width: usize) -> String {
+        let mut cache = self._fitted_name.borrow_mut();
+        match &*cache {
+            Some((cached_width, name)) if *cached_width == width => name.clone(),
+            _ => {
+                let mut total_width = 0;
+                let name: String = self
+                    ._file_name
+                    .to_str()
+                    .unwrap_or_default()
+                    .graphemes(true)
+                    .take_while(|g| {
+                        total_width += g.width();
+                        total_width <= width
+                    })
+                    .collect();
+                *cache = Some((width, name.clone()));
+                name
+            }
+        }
+    }
+
+    /// The metadata of this entry, with symlinks followed. This is read from the file system when
+    /// it's first needed, and cached after that.
+    pub fn metadata(&self) -> Option<&std::fs::Metadata> {
+        self._metadata
+            .get_or_init(|| std::fs::metadata(&self._path).ok())
+            .as_ref()
+    }
+
     pub fn is_dir(&self) -> bool {
-        match &self.metadata {
-            Some(m) => m.is_dir(),
-            None => false,
+        if let Some(is_dir) = self._archive_is_dir {
+            return is_dir;
         }
+        match &self._file_type {
+            // If we know that this is not a symlink, we don't need the metadata
+            Some(t) if !t.is_symlink() => t.is_dir(),
+            // Note: the metadata traverses symlinks, so this returns true for symlinks to folders
+            _ => self.metadata().map(|m| m.is_dir()).unwrap_or(false),
+        }
+    }
+
+    /// Return true if this entry is a symlink whose target can't be accessed, for example because
+    /// the target doesn't exist, or the link is part of a cycle of symlinks.
+    pub fn is_broken_symlink(&self) -> bool {
+        // the metadata follows symlinks, so it's missing if the target can't be resolved
+        self.symlink_target.is_some() && self.metadata().is_none()
     }
 
     pub fn created(&self) -> SystemTime {
-        match &self.metadata {
+        match self.metadata() {
             Some(m) => m.created().unwrap_or(SystemTime::UNIX_EPOCH),
             None => SystemTime::UNIX_EPOCH,
         }
     }
 
     pub fn modified(&self) -> SystemTime {
-        match &self.metadata {
+        match self.metadata() {
             Some(m) => m.modified().unwrap_or(SystemTime::UNIX_EPOCH),
             None => SystemTime::UNIX_EPOCH,
         }
@@ -143,12 +338,21 @@ impl CustomDirEntry {
 
 impl From<std::fs::DirEntry> for CustomDirEntry {
     fn from(e: std::fs::DirEntry) -> Self {
+        let file_type = e.file_type().ok();
+        // Only try to read the link if the entry is a symlink (or we don't know), to avoid an
+        // extra system call for every entry.
+        let symlink_target = match file_type {
+            Some(t) if !t.is_symlink() => None,
+            _ => std::fs::read_link(e.path()).ok(),
+        };
         Self {
             _path: e.path(),
-            // Note: this traverses symlinks, so is_dir will return true for symlinks as well.
-            metadata: std::fs::metadata(e.path()).ok(),
-            symlink_target: std::fs::read_link(e.path()).ok(),
+            _file_type: file_type,
+            _metadata: OnceCell::new(),
+            symlink_target,
             _file_name: e.file_name(),
+            _archive_is_dir: None,
+            _fitted_name: RefCell::new(None),
         }
     }
 }
@@ -157,11 +361,302 @@ impl From<&std::path::Path> for CustomDirEntry {
     fn from(p: &std::path::Path) -> Self {
         Self {
             _path: p.to_path_buf(),
-            metadata: p.metadata().ok(),
+            _file_type: None,
+            _metadata: OnceCell::new(),
             symlink_target: p.read_link().ok(),
             _file_name: p.file_name().unwrap_or(p.as_os_str()).to_os_string(),
+            _archive_is_dir: None,
+            _fitted_name: RefCell::new(None),
+        }
+    }
+}
+
+/// Read the entries of a folder. Entries whose metadata can't be read (for example, because of
+/// missing permissions or a broken symlink) are still included, with their metadata set to None.
+/// Only entries that can't be read at all (i.e. not even their name is known) are skipped.
+pub fn read_dir_entries<P: AsRef<Path>>(path: P) -> IOResult<Vec<CustomDirEntry>> {
+    #[cfg(windows)]
+    if is_drive_list(path.as_ref()) {
+        return Ok(list_drives());
+    }
+    Ok(std::fs::read_dir(path)?
+        .filter_map(|e| e.ok())
+        .map(CustomDirEntry::from)
+        .collect())
+}
+
+/// Like `read_dir_entries`, but if `cancel` is set while reading, stop and return an `Interrupted`
+/// error. Reading a large folder on a slow disk can take a long time.
+fn read_dir_entries_cancellable(path: &Path, cancel: &AtomicBool) -> IOResult<Vec<CustomDirEntry>> {
+    if is_drive_list(path) {
+        return read_dir_entries(path);
+    }
+    let mut ret = vec![];
+    for entry in std::fs::read_dir(path)? {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(IOError::new(ErrorKind::Interrupted, "cancelled"));
+        }
+        if let Ok(entry) = entry {
+            ret.push(CustomDirEntry::from(entry));
+        }
+    }
+    Ok(ret)
+}
+
+/// Read the entries of a folder and its subfolders, up to `max_depth` levels of subfolders below
+/// it. The entries are named by their path relative to `path`. Symlinks to folders are not
+/// followed, to avoid cycles, and subfolders that can't be read are skipped, as well as the
+/// subfolders named in `exclude`. If `cancel` is set while reading, stop and return an
+/// `Interrupted` error.
+pub fn read_dir_entries_recursive<P: AsRef<Path>>(
+    path: P,
+    max_depth: usize,
+    exclude: &[String],
+    cancel: &AtomicBool,
+) -> IOResult<Vec<CustomDirEntry>> {
+    let root = path.as_ref();
+    let mut ret = read_dir_entries_cancellable(root, cancel)?;
+    // the folders that are still to be read, as (relative path, depth)
+    let mut folders: Vec<(PathBuf, usize)> = Vec::new();
+    let add_subfolders = |entries: &[CustomDirEntry], depth: usize, folders: &mut Vec<_>| {
+        if depth <= max_depth {
+            folders.extend(
+                entries
+                    .iter()
+                    .filter(|e| e.is_dir() && e.symlink_target.is_none())
+                    .filter(|e| !is_excluded_name(Path::new(&e._file_name), exclude))
+                    .map(|e| (PathBuf::from(&e._file_name), depth)),
+            );
+        }
+    };
+    add_subfolders(&ret, 1, &mut folders);
+
+    while let Some((folder, depth)) = folders.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(IOError::new(ErrorKind::Interrupted, "cancelled"));
+        }
+        let mut entries = match read_dir_entries_cancellable(&root.join(&folder), cancel) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::Interrupted => return Err(e),
+            Err(_) => continue,
+        };
+        for entry in entries.iter_mut() {
+            entry._file_name = folder.join(&entry._file_name).into_os_string();
+        }
+        add_subfolders(&entries, depth + 1, &mut folders);
+        ret.extend(entries);
+    }
+    Ok(ret)
+}
+
+/// The settings that affect which entries are read for the listing. These are separate from
+/// `TereSettings` so that they can be sent to the thread that reads the folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListingOptions {
+    /// How many levels of subfolders to list, if recursive search is on
+    pub recursive_depth: Option<usize>,
+    /// Whether to hide the items ignored by git
+    pub gitignore: bool,
+    /// The names of the folders not to look into (see `--exclude`), unless they are shown
+    pub exclude: Vec<String>,
+}
+
+/// Read the entries to show in the listing of `path`: the contents of the folder, and with
+/// recursive search, the contents of its subfolders too.
+pub fn read_listing_entries(
+    path: &Path,
+    options: &ListingOptions,
+    cancel: &AtomicBool,
+) -> IOResult<Vec<CustomDirEntry>> {
+    // the drive list is not a real folder, so there's nothing to recurse into or ignore
+    if is_drive_list(path) {
+        return read_dir_entries(path);
+    }
+    // archives are not searched recursively, and they have no gitignore files
+    if let Some((archive, inner)) = split_archive_path(path) {
+        return read_archive_entries(archive, inner);
+    }
+    let entries = match options.recursive_depth {
+        Some(depth) => read_dir_entries_recursive(path, depth, &options.exclude, cancel)?,
+        None => read_dir_entries_cancellable(path, cancel)?,
+    };
+    if options.gitignore {
+        remove_gitignored(path, entries, options.recursive_depth.unwrap_or(0), &options.exclude, cancel)
+    } else {
+        Ok(entries)
+    }
+}
+
+/// The root folder of the git repository that `path` is in, if any
+fn git_repo_root(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|p| p.join(".git").exists())
+}
+
+/// Remove the entries that are ignored by git, i.e. matched by the `.gitignore` files of the
+/// repository, `.git/info/exclude` or the global gitignore file, like ripgrep and fd do. The
+/// entries are named by their path relative to `path`, and they are in at most `max_depth` levels
+/// of subfolders, not including the folders named in `exclude`. Outside of git repositories,
+/// nothing is removed, without reading anything.
+fn remove_gitignored(
+    path: &Path,
+    entries: Vec<CustomDirEntry>,
+    max_depth: usize,
+    exclude: &[String],
+    cancel: &AtomicBool,
+) -> IOResult<Vec<CustomDirEntry>> {
+    if git_repo_root(path).is_none() {
+        return Ok(entries);
+    }
+
+    // The walk takes care of the precedence of the ignore files in the repository, so just
+    // collect the paths that it doesn't skip.
+    let mut kept = HashSet::new();
+    let walk = ignore::WalkBuilder::new(path)
+        .hidden(false)
+        .max_depth(Some(max_depth + 1))
+        .filter_entry({
+            let exclude = exclude.to_vec();
+            move |item| item.depth() == 0 || !is_excluded_name(item.path(), &exclude)
+        })
+        .build();
+    for item in walk.filter_map(|item| item.ok()) {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(IOError::new(ErrorKind::Interrupted, "cancelled"));
+        }
+        if let Ok(relative) = item.path().strip_prefix(path) {
+            kept.insert(relative.as_os_str().to_os_string());
+        }
+    }
+    Ok(entries.into_iter().filter(|e| kept.contains(&e._file_name)).collect())
+}
+
+/// True if some of the folders listed in `entries` (as read by `read_dir_entries_recursive` with
+/// `max_depth`) were not read, because they are deeper than `max_depth`
+fn depth_limit_reached(entries: &[CustomDirEntry], max_depth: usize) -> bool {
+    entries.iter().any(|e| {
+        e.is_dir()
+            && e.symlink_target.is_none()
+            && Path::new(&e._file_name).components().count() > max_depth
+    })
+}
+
+/// On Windows, the list of drives is shown as the parent of the drive roots (like 'This PC' in
+/// Explorer). It's not a real folder, so it's represented by an empty path.
+#[cfg(windows)]
+pub fn is_drive_list(path: &Path) -> bool {
+    path.as_os_str().is_empty()
+}
+
+/// There's no list of drives on other platforms, because they have only one root.
+#[cfg(not(windows))]
+pub fn is_drive_list(_path: &Path) -> bool {
+    false
+}
+
+/// The roots of the drives that exist, e.g. `C:\` and `D:\`. The entries are named by the full
+/// root path, so that they can be entered from the drive list.
+#[cfg(windows)]
+fn list_drives() -> Vec<CustomDirEntry> {
+    ('A'..='Z')
+        .map(|letter| PathBuf::from(format!("{}:\\", letter)))
+        .filter(|root| root.is_dir())
+        .map(|root| CustomDirEntry::from(root.as_path()))
+        .collect()
+}
+
+/// Read the entries of a folder, and filter and sort them according to the settings. This doesn't
+/// include the parent folder `..`.
+pub fn list_folder<P: AsRef<Path>>(path: P, settings: &TereSettings) -> IOResult<Vec<CustomDirEntry>> {
+    let mut entries = read_dir_entries(path)?;
+    remove_excluded(&mut entries, settings);
+    Ok(filter_and_sort_entries(entries, settings))
+}
+
+/// True if the last component of `path` is one of the folder names in `exclude`
+fn is_excluded_name(path: &Path, exclude: &[String]) -> bool {
+    path.file_name().is_some_and(|name| exclude.iter().any(|x| name == x.as_str()))
+}
+
+/// Remove the folders hidden by `--exclude` from `entries`, and return how many were removed. With
+/// recursive search, the contents of the excluded folders are not read in the first place.
+fn remove_excluded(entries: &mut Vec<CustomDirEntry>, settings: &TereSettings) -> usize {
+    if settings.exclude.is_empty() || settings.show_excluded {
+        return 0;
+    }
+    let len = entries.len();
+    entries.retain(|e| !(e.is_dir() && is_excluded_name(Path::new(&e._file_name), &settings.exclude)));
+    len - entries.len()
+}
+
+/// Filter and sort entries read with `read_dir_entries` according to the settings. The excluded
+/// folders have to be removed with `remove_excluded` first.
+fn filter_and_sort_entries(entries: Vec<CustomDirEntry>, settings: &TereSettings) -> Vec<CustomDirEntry> {
+    let mut entries: Box<dyn Iterator<Item = CustomDirEntry> + '_> = Box::new(entries.into_iter());
+
+    if let Some(file_filter) = &settings.file_filter {
+        // with recursive search, the entries in subfolders are named by their relative path, but
+        // the pattern is for the file name
+        entries = Box::new(entries.filter(move |e| {
+            e.is_dir() || Path::new(&e._file_name).file_name().is_some_and(|name| file_filter.is_match(name))
+        }));
+    }
+
+    if settings.modified_filter {
+        let since = SystemTime::now()
+            .checked_sub(settings.modified_window)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        entries = Box::new(entries.filter(move |e| e.is_dir() || e.modified() >= since));
+    }
+
+    match settings.listing_mode {
+        ListingMode::All => {}
+        ListingMode::FoldersOnly => {
+            entries = Box::new(entries.filter(|e| e.is_dir()));
+        }
+        ListingMode::FilesOnly => {
+            entries = Box::new(entries.filter(|e| !e.is_dir()));
         }
     }
+
+    let mut entries: Vec<CustomDirEntry> = entries.collect();
+    sort_entries(&mut entries, &settings.sort_mode);
+    entries
+}
+
+/// Sort entries according to the sort mode, folders first.
+fn sort_entries(entries: &mut [CustomDirEntry], sort_mode: &SortMode) {
+    entries.sort_by(|a, b| {
+        match (a.is_dir(), b.is_dir()) {
+            (true, true) | (false, false) => {
+                match sort_mode {
+                    SortMode::Name => {
+                        // both are dirs or files, compare by name.
+                        // partial_cmp for strings always returns Some, so unwrap is ok here
+                        a.file_name_checked()
+                            .to_lowercase()
+                            .partial_cmp(&b.file_name_checked().to_lowercase())
+                            .unwrap()
+                    }
+                    SortMode::Created => {
+                        // b > a for sorting most recently created first
+                        b.created().partial_cmp(&a.created()).unwrap()
+                    }
+                    SortMode::Modified => {
+                        // b > a for sorting most recently modified first
+                        b.modified().partial_cmp(&a.modified()).unwrap()
+                    }
+                }
+            }
+            // Otherwise, put folders first
+            (true, false) => std::cmp::Ordering::Less,
+            (false, true) => std::cmp::Ordering::Greater,
+        }
+    });
+}
+
+/// Add the path to an IO error message, so that it's clear which folder the error refers to.
+pub fn with_path_context(err: IOError, path: &Path) -> IOError {
+    IOError::new(err.kind(), format!("could not open '{}': {}", path.display(), err))
 }
 
 /// The type of the `ls_output_buf` buffer of the app state
@@ -178,31 +673,76 @@ pub struct TereAppState {
     // including ".." (the parent folder).
     ls_output_buf: LsBufType,
 
+    // The summed size of the files in ls_output_buf, if the total size is shown
+    total_size: Option<u64>,
+
+    // The number of items that were hidden from the listing by --exclude
+    num_excluded: usize,
+
+    // True if the recursive search left out some subfolders because of the depth limit
+    depth_limit_reached: bool,
+
     // Have to manually keep track of the logical absolute path of our app, see https://stackoverflow.com/a/70309860/5208725
     pub current_path: PathBuf,
 
+    // The working directory from which the app was started.
+    initial_path: PathBuf,
+
     // The row on which the cursor is currently on, counted starting from the
     // top of the screen (not from the start of `ls_output_buf`). Note that this
-    // doesn't have anything to do with the crossterm cursor position.
+    // doesn't have anything to do with the crossterm cursor position. In the grid layout, this is
+    // the index of the cell that the cursor is on, counting the cells row by row.
     pub cursor_pos: usize,
 
-    // The top of the screen corresponds to this row in the `ls_output_buf`.
+    // The top of the screen corresponds to this row in the `ls_output_buf`. In the grid layout,
+    // this is the index of the item in the top left corner, which is always a multiple of the
+    // number of columns.
     pub scroll_pos: usize,
 
+    // The number of columns and the width of each column in the main window. There's only one
+    // column, unless the grid layout is enabled and the window is wide enough.
+    grid_columns: usize,
+    grid_column_width: usize,
+
     search_string: String,
 
+    // Previous searches, the most recent one first.
+    search_history: VecDeque<String>,
+    // The position in search_history of the search that was recalled last, if any.
+    search_history_pos: Option<usize>,
+
     pub header_msg: String,
     pub info_msg: String,
 
     _settings: TereSettings,
 
     history: HistoryTree,
+
+    // The folder that was shown before the current one, for going back to it like 'cd -'
+    previous_path: Option<PathBuf>,
+
+    // The index of the item that the cursor was on when we last left a folder, for each folder
+    // visited during this session.
+    cursor_memory: HashMap<PathBuf, usize>,
+
+    // The search that was active when we last left a folder, for each folder visited during this
+    // session. This is only used if the remember_search setting is enabled.
+    search_memory: HashMap<PathBuf, String>,
+
+    // The preferences at startup, for saving only the ones that were changed during the session
+    initial_prefs: Prefs,
+
+    // The listings of folders that are read in the background before entering them
+    prefetch: PrefetchCache,
+
+    // The temporary folder that files are extracted to from archives
+    extract_dir: ExtractDir,
 }
 
 impl TereAppState {
     /// Initialize the app state with the given settings. Note that the window dimensions are
     /// initialized to one, they need to be updated manually afterwards.
-    pub fn init(settings: TereSettings, warnings: &DeprecationWarnings) -> Result<Self, TereError> {
+    pub fn init(settings: TereSettings, warnings: &Warnings) -> Result<Self, TereError> {
         // Try to read the current folder from the PWD environment variable, since it doesn't have
         // symlinks resolved (this is what we want). If this fails for some reason (on windows?),
         // default to std::env::current_dir, which has resolved symlinks.
@@ -210,7 +750,32 @@ impl TereAppState {
             .map(PathBuf::from)
             .or_else(|_| std::env::current_dir())?;
 
-        let info_msg = if warnings.is_empty() {
+        // Start from the folder given on the command line, if applicable. Relative paths are
+        // resolved against the current working directory. If the folder can't be opened, start
+        // from the current working directory and show an error.
+        let mut start_path_error = None;
+        let initial_path = cwd.clone();
+        let cwd = match &settings.start_path {
+            Some(path) => {
+                let path = normalize_path(&cwd.join(path));
+                match std::fs::read_dir(&path).and_then(|_| std::env::set_current_dir(&path)) {
+                    Ok(()) => path,
+                    Err(e) => {
+                        start_path_error = Some(format!(
+                            "error: could not open '{}': {}",
+                            path.display(),
+                            e
+                        ));
+                        cwd
+                    }
+                }
+            }
+            None => cwd,
+        };
+
+        let info_msg = if let Some(msg) = start_path_error {
+            msg
+        } else if warnings.is_empty() {
             format!(
                 "{} {} - Type something to search, press '?' to view help or Esc to exit.",
                 env!("CARGO_PKG_NAME"),
@@ -225,15 +790,30 @@ impl TereAppState {
             main_win_w: 1,
             main_win_h: 1,
             ls_output_buf: vec![].into(),
+            total_size: None,
+            num_excluded: 0,
+            depth_limit_reached: false,
             current_path: cwd.clone(),
+            initial_path,
             cursor_pos: 0,
             scroll_pos: 0,
+            grid_columns: 1,
+            grid_column_width: 1,
             header_msg: "".into(),
             info_msg,
             search_string: "".into(),
+            search_history: VecDeque::new(),
+            search_history_pos: None,
             _settings: settings,
             history: HistoryTree::from_abs_path(cwd.clone()),
+            previous_path: None,
+            cursor_memory: HashMap::new(),
+            search_memory: HashMap::new(),
+            initial_prefs: Prefs::new(),
+            prefetch: PrefetchCache::default(),
+            extract_dir: ExtractDir::default(),
         };
+        ret.initial_prefs = ret.settings().pref_args();
 
         //read history tree from file, if applicable
         if let Some(hist_file) = &ret.settings().history_file {
@@ -272,6 +852,24 @@ impl TereAppState {
                 .create(parent_dir)?;
             std::fs::write(hist_file, serde_json::to_string(&self.history)?)?;
         }
+
+        if let Some(prefs_file) = &self.settings().prefs_file {
+            // Only update the preferences that were changed, so that the options given on the
+            // command line for this session are not saved
+            let changed: Prefs = self
+                .settings()
+                .pref_args()
+                .into_iter()
+                .filter(|(name, args)| self.initial_prefs.get(name) != Some(args))
+                .collect();
+            if !changed.is_empty() {
+                let mut prefs = read_prefs(prefs_file)?;
+                // the invalid preferences were already skipped with a warning when starting
+                remove_invalid_prefs(&mut prefs);
+                prefs.extend(changed);
+                write_prefs(prefs_file, &prefs)?;
+            }
+        }
         Ok(())
     }
 
@@ -279,6 +877,70 @@ impl TereAppState {
     // Helpers for reading the current state //
     ///////////////////////////////////////////
 
+    /// The settings for reading the listing of a folder
+    pub fn listing_options(&self) -> ListingOptions {
+        let settings = self.settings();
+        ListingOptions {
+            recursive_depth: settings
+                .recursive_search
+                .then_some(settings.recursive_search_depth.min(settings.max_depth)),
+            gitignore: settings.gitignore,
+            exclude: if settings.show_excluded { Vec::new() } else { settings.exclude.clone() },
+        }
+    }
+
+    /// The absolute path of the folder under the cursor that should be prefetched, if any. Folders
+    /// are not prefetched with recursive search, because reading them can take a lot of work.
+    fn prefetch_target(&self) -> Option<PathBuf> {
+        let options = self.listing_options();
+        if self.settings().no_prefetch || options.recursive_depth.is_some() {
+            return None;
+        }
+        let item = self.get_item_under_cursor().filter(|item| item.is_dir())?;
+        let path = normalize_path(&self.current_path.join(item.file_name_checked()));
+        if is_drive_list(&path) || self.prefetch.contains(&path, &options) {
+            None
+        } else {
+            Some(path)
+        }
+    }
+
+    /// True if the folder under the cursor should be prefetched and it hasn't been yet
+    pub fn needs_prefetch(&self) -> bool {
+        self.prefetch_target().is_some()
+    }
+
+    /// Start reading the folder under the cursor in the background, if it should be prefetched
+    pub fn prefetch_item_under_cursor(&mut self) {
+        if let Some(path) = self.prefetch_target() {
+            let options = self.listing_options();
+            self.prefetch.start(path, options);
+        }
+    }
+
+    /// The channel where the prefetched listing of `path` is received, and the flag for cancelling
+    /// the reading, if it has been prefetched recently with the current settings
+    pub fn take_prefetched(&mut self, path: &Path) -> Option<(ListingReceiver, Arc<AtomicBool>)> {
+        let options = self.listing_options();
+        self.prefetch.take(path, &options)
+    }
+
+    /// The icon to draw in front of an item, followed by a space, or an empty string if icons are
+    /// not enabled
+    pub fn icon_prefix(&self, item: &CustomDirEntry) -> String {
+        if self.settings().icons {
+            let icon = icon_for(&item.file_name_checked(), item.is_dir(), &self.settings().icon_map);
+            format!("{} ", icon)
+        } else {
+            String::new()
+        }
+    }
+
+    /// The folder that was shown before the current one, if any
+    pub fn previous_path(&self) -> Option<&PathBuf> {
+        self.previous_path.as_ref()
+    }
+
     pub fn settings(&self) -> &TereSettings {
         &self._settings
     }
@@ -296,6 +958,49 @@ impl TereAppState {
         self.ls_output_buf.all_items.len()
     }
 
+    /// True if the recursive search didn't read some subfolders because of the depth limit
+    pub fn depth_limit_reached(&self) -> bool {
+        self.depth_limit_reached
+    }
+
+    /// The counts in the footer formatted with `template`. The total size of the folder is given as
+    /// `size`, since it might be computed in the background.
+    pub fn format_footer_counts(&self, template: &FooterTemplate, size: Option<&str>) -> String {
+        let cursor_idx = self.cursor_pos_to_visible_item_index(self.cursor_pos);
+        let index_in_matches = self.visible_match_indices().iter().position(|x| *x == cursor_idx);
+        template.fill(|name| match name {
+            "cursor" => (cursor_idx + 1).to_string(),
+            "total" => self.num_visible_items().to_string(),
+            "dirs" => self.num_folders().to_string(),
+            "files" => self.num_files().to_string(),
+            "size" => size.unwrap_or_default().to_string(),
+            "match" if self.is_searching() => index_in_matches.map_or(0, |i| i + 1).to_string(),
+            "matches" if self.is_searching() => self.num_matching_items().to_string(),
+            _ => "0".to_string(),
+        })
+    }
+
+    /// The number of items that were hidden from the listing because of `--exclude`
+    pub fn num_excluded(&self) -> usize {
+        self.num_excluded
+    }
+
+    /// The number of folders in the current directory, not counting the parent folder.
+    pub fn num_folders(&self) -> usize {
+        self.ls_output_buf.num_folders
+    }
+
+    /// The number of files (i.e. non-folders) in the current directory.
+    pub fn num_files(&self) -> usize {
+        self.ls_output_buf.num_files
+    }
+
+    /// The summed size of the files in the current directory (not including subfolders), or None if
+    /// the total size is not shown.
+    pub fn total_size(&self) -> Option<u64> {
+        self.total_size
+    }
+
     /// The number of items that match the current search.
     pub fn num_matching_items(&self) -> usize {
         self.ls_output_buf.matches.len()
@@ -331,23 +1036,123 @@ impl TereAppState {
         }
     }
 
+    /// The number of columns in the main window. This is one, unless the grid layout is enabled.
+    pub fn grid_columns(&self) -> usize {
+        self.grid_columns
+    }
+
+    /// The width of the columns in the grid layout, including the gap between columns.
+    pub fn grid_column_width(&self) -> usize {
+        self.grid_column_width
+    }
+
+    /// The number of columns available for the listing in the main window. This is the full width
+    /// of the window, unless the preview pane is shown, minus the padding on both sides.
+    pub fn listing_width(&self) -> usize {
+        let width = if self.settings().preview_enabled {
+            self.main_win_w / 2
+        } else {
+            self.main_win_w
+        };
+        width.saturating_sub(2 * self.settings().padding)
+    }
+
     /// Convert a cursor position (in the range 0..window_height) to an index
     /// into the currently visible items.
     pub fn cursor_pos_to_visible_item_index(&self, cursor_pos: usize) -> usize {
-        (cursor_pos + self.scroll_pos) as usize
+        cursor_pos + self.scroll_pos
     }
 
     pub fn get_item_at_cursor_pos(&self, cursor_pos: usize) -> Option<&CustomDirEntry> {
-        let idx = self.cursor_pos_to_visible_item_index(cursor_pos) as usize;
+        let idx = self.cursor_pos_to_visible_item_index(cursor_pos);
         self.visible_items().get(idx).copied()
     }
 
     /// Returns None if the visible items is empty, or if the state is
     /// inconsistent and the cursor is outside the currently visible items.
-    fn get_item_under_cursor(&self) -> Option<&CustomDirEntry> {
+    pub fn get_item_under_cursor(&self) -> Option<&CustomDirEntry> {
         self.get_item_at_cursor_pos(self.cursor_pos)
     }
 
+    /// The full (logical) path of the item under the cursor, or None if there are no visible items.
+    pub fn path_of_item_under_cursor(&self) -> Option<PathBuf> {
+        self.get_item_under_cursor()
+            .map(|item| normalize_path(&self.current_path.join(item.file_name_checked())))
+    }
+
+    /// The path to copy to the clipboard: the path of the item under the cursor, or the current
+    /// folder if the cursor is on '..' or there are no items. Depending on the settings, the path
+    /// is either absolute or relative to the folder where the app was started.
+    pub fn path_to_copy(&self) -> PathBuf {
+        let path = self
+            .get_item_under_cursor()
+            .filter(|item| item.file_name_checked() != "..")
+            .and(self.path_of_item_under_cursor())
+            .unwrap_or_else(|| self.current_path.clone());
+        match self.settings().copy_path_format {
+            CopyPathFormat::Absolute => path,
+            CopyPathFormat::Relative => relative_path(&path, &self.initial_path),
+        }
+    }
+
+    /// The path to print on exit when `path` is selected. Depending on the settings, this is
+    /// either the absolute path, or the path relative to a base folder, if it's inside the base.
+    pub fn path_to_print(&self, path: &Path) -> PathBuf {
+        match &self.settings().relative_to {
+            Some(base) => path_inside(path, &normalize_path(&self.initial_path.join(base))),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Return true if the item under the cursor is a file, i.e. it exists and is not a folder.
+    pub fn is_file_under_cursor(&self) -> bool {
+        self.get_item_under_cursor().is_some_and(|item| !item.is_dir())
+    }
+
+    /// Return true if the item under the cursor is an archive that can be browsed like a folder
+    pub fn is_archive_under_cursor(&self) -> bool {
+        self.path_of_item_under_cursor()
+            .is_some_and(|path| is_archive(&path) && path.is_file())
+    }
+
+    /// The path of the item under the cursor for other programs, like `path_of_item_under_cursor`.
+    /// Files inside archives are extracted to a temporary folder first, and for folders inside
+    /// archives, the folder that contains the archive is used.
+    pub fn real_path_of_item_under_cursor(&self) -> IOResult<Option<PathBuf>> {
+        let path = match self.path_of_item_under_cursor() {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        match split_archive_path(&path) {
+            Some((archive, inner)) if !inner.as_os_str().is_empty() => {
+                if self.is_file_under_cursor() {
+                    self.extract_dir.extract(archive, inner).map(Some)
+                } else {
+                    Ok(archive.parent().map(Path::to_path_buf))
+                }
+            }
+            _ => Ok(Some(path)),
+        }
+    }
+
+    /// Don't remove `path` on exit, if it's a file that was extracted from an archive. The files
+    /// extracted from archives are removed on exit otherwise.
+    pub fn keep_extracted_file(&mut self, path: &Path) {
+        self.extract_dir.keep(path);
+    }
+
+    /// Return the lines to show in the preview pane for the item under the cursor. For folders,
+    /// this is a listing of the folder contents, and for files, the first few lines of the file.
+    pub fn preview_item_under_cursor(&self, max_lines: usize) -> Vec<String> {
+        match self.get_item_under_cursor() {
+            Some(item) if item.is_dir() => {
+                preview_dir(item.path(), max_lines, self.settings(), &self.listing_options())
+            }
+            Some(item) => preview_file(item.path(), max_lines),
+            None => vec![],
+        }
+    }
+
     /// Get the index of a filename into the currently visible items. Returns
     /// None if it's not found.
     fn index_of_filename<S: AsRef<OsStr>>(&self, fname: S) -> Option<usize> {
@@ -357,7 +1162,7 @@ impl TereAppState {
     }
 
     pub fn get_match_locations_at_cursor_pos(&self, cursor_pos: usize) -> Option<&MatchesLocType> {
-        let idx = self.cursor_pos_to_visible_item_index(cursor_pos) as usize;
+        let idx = self.cursor_pos_to_visible_item_index(cursor_pos);
         if self.settings().filter_search {
             // NOTE: we assume that the matches is a sorted map
             self.ls_output_buf.matches.values().nth(idx)
@@ -383,62 +1188,67 @@ impl TereAppState {
     //////////////////////////////////////
 
     pub fn update_header(&mut self) {
-        self.header_msg = format!("{}", self.current_path.display());
+        self.header_msg = format_header(
+            &self.current_path,
+            self.main_win_w,
+            self.settings().header_format,
+        );
     }
 
     pub fn update_main_window_dimensions(&mut self, w: usize, h: usize) {
         let delta_h = h.saturating_sub(self.main_win_h);
         self.main_win_w = w;
         self.main_win_h = h;
+        self.update_header(); // the shortened header depends on the width
+        self.update_grid_columns();
         self.move_cursor(0, false); // make sure that cursor is within view
         if delta_h > 0 {
             // height is increasing, scroll backwards as much as possible
             let old_scroll_pos = self.scroll_pos;
-            self.scroll_pos = self.scroll_pos.saturating_sub(delta_h);
+            self.scroll_pos = self.scroll_pos.saturating_sub(delta_h * self.grid_columns);
             self.cursor_pos += old_scroll_pos - self.scroll_pos;
+            self.scroll_below_separator(0);
         }
     }
 
     pub fn update_ls_output_buf(&mut self) -> IOResult<()> {
-        let entries = std::fs::read_dir(std::path::Component::CurDir)?;
-
-        let mut entries: Box<dyn Iterator<Item = CustomDirEntry>> = Box::new(
-            entries.filter_map(|e| e.ok()).map(CustomDirEntry::from),
-        );
-
-        if self.settings().folders_only {
-            entries = Box::new(entries.filter(|e| e.path().is_dir()));
-        }
-
-        let mut new_output_buf: Vec<CustomDirEntry> = entries.collect();
+        // Read the absolute path instead of the working directory, so that the git repository
+        // that contains the current folder can be found from its ancestors
+        let entries = read_listing_entries(
+            &self.current_path,
+            &self.listing_options(),
+            &AtomicBool::new(false),
+        )
+        .map_err(|e| with_path_context(e, &self.current_path))?;
+        self.refresh_with_entries(entries);
+        Ok(())
+    }
 
-        new_output_buf.sort_by(|a, b| {
-            match (a.is_dir(), b.is_dir()) {
-                (true, true) | (false, false) => {
-                    match &self.settings().sort_mode {
-                        SortMode::Name => {
-                            // both are dirs or files, compare by name.
-                            // partial_cmp for strings always returns Some, so unwrap is ok here
-                            a.file_name_checked()
-                                .to_lowercase()
-                                .partial_cmp(&b.file_name_checked().to_lowercase())
-                                .unwrap()
-                        }
-                        SortMode::Created => {
-                            // b > a for sorting most recently created first
-                            b.created().partial_cmp(&a.created()).unwrap()
-                        }
-                        SortMode::Modified => {
-                            // b > a for sorting most recently modified first
-                            b.modified().partial_cmp(&a.modified()).unwrap()
-                        }
-                    }
-                }
-                // Otherwise, put folders first
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-            }
+    /// Replace the listing of the current folder with `entries` (as returned by
+    /// `read_listing_entries`), keeping the cursor on the same item if it's still shown. This
+    /// allows reading the folder again in the background, for example after changing a filter.
+    pub fn refresh_with_entries(&mut self, entries: Vec<CustomDirEntry>) {
+        self.with_cursor_fixed_at_current_item(|self_| {
+            self_.set_ls_output_buf(entries);
+            self_.update_search_matches();
         });
+        // the item under the cursor might have been removed, make sure that the cursor is still
+        // within the listing
+        self.move_cursor(0, false);
+    }
+
+    /// Replace the listing with `entries` (as returned by `read_dir_entries`), filtered and sorted
+    /// according to the settings.
+    fn set_ls_output_buf(&mut self, mut entries: Vec<CustomDirEntry>) {
+        self.num_excluded = remove_excluded(&mut entries, self.settings());
+        // the drive list and archives are never read recursively
+        let is_real_folder =
+            !is_drive_list(&self.current_path) && split_archive_path(&self.current_path).is_none();
+        self.depth_limit_reached = match self.listing_options().recursive_depth {
+            Some(depth) if is_real_folder => depth_limit_reached(&entries, depth),
+            _ => false,
+        };
+        let mut new_output_buf = filter_and_sort_entries(entries, self.settings());
 
         // Add the parent directory entry after sorting to make sure it's always first
         new_output_buf.insert(
@@ -447,10 +1257,92 @@ impl TereAppState {
         );
 
         self.ls_output_buf = new_output_buf.into();
+        self.total_size = if self.settings().show_total_size {
+            Some(self.ls_output_buf.all_items.iter().map(file_size).sum())
+        } else {
+            None
+        };
+        self.update_grid_columns();
+    }
+
+    /// Update the number of columns in the grid layout, so that the columns are wide enough for
+    /// the longest file name. If the number of columns changes, the cursor stays on the same item.
+    fn update_grid_columns(&mut self) {
+        let (columns, column_width) = if self.settings().grid {
+            let name_width = self
+                .ls_output_buf
+                .all_items
+                .iter()
+                .map(|item| self.icon_prefix(item).width() + item.file_name_checked().width())
+                .max()
+                .unwrap_or(0)
+                + GRID_COLUMN_GAP
+                + self.settings().cursor_style.marker().map(|m| m.width()).unwrap_or(0);
+            let mut column_width = name_width;
+            let mut columns = (self.listing_width() / column_width).max(1);
+            if self.settings().number_mode {
+                // Make room for the numbers. This can only decrease the number of columns, so the
+                // numbers won't need more digits than what was reserved.
+                let max_digits = (self.main_win_h * columns).to_string().len();
+                column_width = name_width + max_digits + 1;
+                columns = (self.listing_width() / column_width).max(1);
+            }
+            (columns, column_width)
+        } else {
+            (1, self.listing_width().max(1))
+        };
+        self.grid_column_width = column_width;
+
+        if columns != self.grid_columns {
+            let pointer_pos = self.scroll_pos + self.cursor_pos;
+            self.grid_columns = columns;
+            self.scroll_pos = 0;
+            self.cursor_pos = 0;
+            self.move_cursor_to(pointer_pos);
+        }
+    }
+
+    /// Change the current folder to `path`, which is relative to the current folder, or absolute.
+    /// If the current folder has been deleted (for example by another process) and the target
+    /// folder doesn't exist either, go to the nearest existing parent of the target instead, and
+    /// return a message explaining what happened. The UI reads the folder in the background
+    /// instead, using `change_dir_target` and `change_dir_with_entries`.
+    #[allow(dead_code)] // This method is useful for tests
+    pub fn change_dir(&mut self, path: &str) -> IOResult<Option<String>> {
+        let (final_path, message) = self.change_dir_target(path);
+        let entries = read_listing_entries(&final_path, &self.listing_options(), &AtomicBool::new(false))
+            .map_err(|e| with_path_context(e, &final_path))?;
+        self.change_dir_with_entries(&final_path, entries)?;
+        Ok(message)
+    }
+
+    /// Create a folder named `name` in the current folder, and move the cursor to it. The search
+    /// is cleared, so that the new folder is not hidden by the search.
+    pub fn create_folder(&mut self, name: &str) -> IOResult<()> {
+        let invalid = |msg: &str| Err(IOError::new(ErrorKind::InvalidInput, msg.to_string()));
+        if name.is_empty() || name == "." || name == ".." {
+            return invalid("invalid folder name");
+        }
+        if name.contains(std::path::is_separator) {
+            return invalid("the folder name can't contain path separators");
+        }
+        if is_drive_list(&self.current_path) || split_archive_path(&self.current_path).is_some() {
+            return invalid("folders can't be created here");
+        }
+
+        let path = self.current_path.join(name);
+        std::fs::create_dir(&path).map_err(|e| {
+            IOError::new(e.kind(), format!("could not create '{}': {}", path.display(), e))
+        })?;
+        self.clear_search();
+        self.update_ls_output_buf()?;
+        self.move_cursor_to_filename(name);
         Ok(())
     }
 
-    pub fn change_dir(&mut self, path: &str) -> IOResult<()> {
+    /// Resolve the absolute path of the folder that `change_dir(path)` would go to, without
+    /// changing anything. Also returns the message that `change_dir` would return.
+    pub fn change_dir_target(&self, path: &str) -> (PathBuf, Option<String>) {
         // TODO: add option to use xdg-open (or similar) on files?
         // check out https://crates.io/crates/open
         // (or https://docs.rs/opener/0.4.1/opener/)
@@ -463,51 +1355,92 @@ impl TereAppState {
         };
         let target_path = PathBuf::from(target_path);
 
-        // NOTE: have to manually normalize path because the std doesn't have that feature yet, as
-        // of December 2021.
-        // see:
-        // - https://github.com/rust-lang/rfcs/issues/2208
-        // - https://github.com/gdzx/rfcs/commit/3c69f787b5b32fb9c9960c1e785e5cabcc794238
-        // - abs_path crate
-        // - relative_path crate
-        // This function is copy-pasted from cargo::util::paths::normalize_path, https://docs.rs/cargo-util/0.1.1/cargo_util/paths/fn.normalize_path.html, under the MIT license
-        fn normalize_path(path: &Path) -> PathBuf {
-            let mut components = path.components().peekable();
-            let mut ret = if let Some(c @ Component::Prefix(..)) = components.peek().cloned() {
-                components.next();
-                PathBuf::from(c.as_os_str())
-            } else {
-                PathBuf::new()
-            };
-
-            for component in components {
-                match component {
-                    Component::Prefix(..) => unreachable!(),
-                    Component::RootDir => {
-                        ret.push(component.as_os_str());
-                    }
-                    Component::CurDir => {}
-                    Component::ParentDir => {
-                        ret.pop();
-                    }
-                    Component::Normal(c) => {
-                        ret.push(c);
-                    }
-                }
-            }
-            ret
-        }
-
         let final_path = if target_path.is_absolute() {
             target_path
         } else {
             normalize_path(&self.current_path.join(target_path))
         };
 
+        // The parent of a drive root is the list of drives
+        if cfg!(windows)
+            && path == ".."
+            && self.current_path.parent().is_none()
+            && !is_drive_list(&self.current_path)
+        {
+            return (PathBuf::new(), None);
+        }
+
+        let mut message = None;
+        let final_path = if std::fs::symlink_metadata(&final_path).is_err()
+            && !self.current_path.is_dir()
+            && split_archive_path(&final_path).is_none()
+        {
+            match final_path
+                .ancestors()
+                .find(|p| p.is_dir())
+                .filter(|p| *p != final_path)
+            {
+                Some(ancestor) => {
+                    message = Some(format!(
+                        "'{}' no longer exists, moved to '{}'",
+                        final_path.display(),
+                        ancestor.display()
+                    ));
+                    ancestor.to_path_buf()
+                }
+                // nothing exists, not even the root, so we can only show the error
+                None => final_path,
+            }
+        } else {
+            final_path
+        };
+
+        (final_path, message)
+    }
+
+    /// Change the current folder to `final_path`, which has to be absolute, using `entries` (as
+    /// returned by `read_dir_entries`) as its contents. This allows reading the folder in the
+    /// background, and leaving the app state untouched if the reading fails or is cancelled.
+    pub fn change_dir_with_entries(
+        &mut self,
+        final_path: &Path,
+        entries: Vec<CustomDirEntry>,
+    ) -> IOResult<()> {
+        // If we're going up to the parent folder, remember the folder we're leaving, so that the
+        // cursor can be placed on it.
+        let previous_folder_name = if self.current_path.parent() == Some(final_path) {
+            self.current_path.file_name().map(|name| name.to_os_string())
+        } else if is_drive_list(final_path) {
+            // the drives are named by their root path
+            Some(self.current_path.as_os_str().to_os_string())
+        } else {
+            None
+        };
+
+        let search_string = self.search_string.clone();
         self.clear_search();
-        std::env::set_current_dir(&final_path)?;
-        self.current_path = PathBuf::from(&final_path);
-        self.update_ls_output_buf()?;
+        if self.settings().remember_search && !search_string.is_empty() {
+            self.search_memory.insert(self.current_path.clone(), search_string);
+        }
+        self.cursor_memory.insert(
+            self.current_path.clone(),
+            self.cursor_pos_to_visible_item_index(self.cursor_pos),
+        );
+        // The drive list can't be the working directory, so stay in the previous drive. Inside an
+        // archive, the working directory is the folder that contains the archive.
+        let working_dir = split_archive_path(final_path)
+            .and_then(|(archive, _)| archive.parent())
+            .unwrap_or(final_path);
+        if !is_drive_list(final_path) {
+            std::env::set_current_dir(working_dir).map_err(|e| with_path_context(e, working_dir))?;
+        }
+        // Refreshing the current folder doesn't count as visiting it. The drive list can't be
+        // passed to change_dir, so it's not remembered either.
+        if self.current_path != final_path && !is_drive_list(&self.current_path) {
+            self.previous_path = Some(self.current_path.clone());
+        }
+        self.current_path = final_path.to_path_buf();
+        self.set_ls_output_buf(entries);
 
         self.cursor_pos = 0;
         self.scroll_pos = 0;
@@ -518,17 +1451,66 @@ impl TereAppState {
         // / visit() and handling the special cases where target_path is '..' or a relative path or
         // so on, would be much more complicated and would risk having the history tree and logical
         // path out of sync.
-        self.history.change_dir(&final_path);
+        self.history.change_dir(final_path);
 
         // move cursor one position down, so we're not at '..' if we've entered a folder with no history
         self.move_cursor(1, false);
-        if let Some(prev_dir) = self.history.current_entry().last_visited_child_label() {
+        if let Some(prev_dir) = previous_folder_name {
+            self.move_cursor_to_filename(prev_dir);
+        } else if let Some(idx) = self.cursor_memory.get(&self.current_path).copied() {
+            // The folder contents might have changed since we were here, but move_cursor_to
+            // clamps the index to the items that are still there.
+            self.move_cursor_to(idx);
+        } else if let Some(prev_dir) = self.history.current_entry().last_visited_child_label() {
             self.move_cursor_to_filename(prev_dir);
         }
 
+        if self.settings().remember_search {
+            // If the search doesn't match anything any more, it's still restored, and the listing
+            // just shows that there are no matches.
+            if let Some(query) = self.search_memory.get(&self.current_path).cloned() {
+                self.advance_search(&query);
+            }
+        }
+
         Ok(())
     }
 
+    /// The absolute path of the next (if `forward` is true) or previous folder next to the current
+    /// one, i.e. in the same parent folder, in the order of the listing. Wraps around at the ends,
+    /// unless wrapping is disabled. Returns None if there's no such folder.
+    pub fn sibling_dir(&self, forward: bool) -> IOResult<Option<PathBuf>> {
+        let (parent, name) = match (self.current_path.parent(), self.current_path.file_name()) {
+            (Some(parent), Some(name)) => (parent, name),
+            // we're at the root
+            _ => return Ok(None),
+        };
+
+        let mut siblings: Vec<CustomDirEntry> = read_dir_entries(parent)
+            .map_err(|e| with_path_context(e, parent))?
+            .into_iter()
+            .filter(|e| e.is_dir())
+            .collect();
+        sort_entries(&mut siblings, &self.settings().sort_mode);
+
+        let idx = match siblings.iter().position(|e| e._file_name == name) {
+            Some(idx) => idx,
+            None => return Ok(None),
+        };
+        let n = siblings.len();
+        let new_idx = match (forward, self.settings().no_wrap) {
+            (true, _) if idx + 1 < n => idx + 1,
+            (true, false) => 0,
+            (false, _) if idx > 0 => idx - 1,
+            (false, false) => n - 1,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(new_idx)
+            .filter(|i| *i != idx)
+            .map(|i| parent.join(&siblings[i]._file_name)))
+    }
+
     /////////////////////////////////////////////
     // Functions for changing the app settings //
     /////////////////////////////////////////////
@@ -549,18 +1531,58 @@ impl TereAppState {
         self.advance_search("");
     }
 
+    pub fn set_search_scope(&mut self, search_scope: SearchScope) {
+        self._settings.search_scope = search_scope;
+        self.advance_search(""); // hacky, see the comment above in set_case_sensitive
+    }
+
     pub fn set_gap_search_mode(&mut self, gap_search_mode: GapSearchMode) {
         self._settings.gap_search_mode = gap_search_mode;
         self.advance_search(""); // hacky, see the comment above in set_case_sensitive
     }
 
+    /// Change the sort mode. Like the filters below, this doesn't update the listing, because
+    /// reading the folder can take a while, so the caller has to read the folder again and pass
+    /// the entries to `refresh_with_entries`.
     pub fn set_sort_mode(&mut self, sort_mode: SortMode) {
-        self.with_cursor_fixed_at_current_item(|self_| {
-            self_._settings.sort_mode = sort_mode;
-            self_.update_ls_output_buf().ok();
-            //TODO: should probably have a separate method for re-sorting the matches vector...
-            self_.update_search_matches();
-        });
+        self._settings.sort_mode = sort_mode;
+    }
+
+    /// Show all items, only folders or only files
+    pub fn set_listing_mode(&mut self, listing_mode: ListingMode) {
+        self._settings.listing_mode = listing_mode;
+    }
+
+    /// Show only the files matching `file_filter`, or all files if it's None.
+    pub fn set_file_filter(&mut self, file_filter: Option<GlobMatcher>) {
+        self._settings.file_filter = file_filter;
+    }
+
+    /// Show the folders excluded with `--exclude`, or hide them again
+    pub fn set_show_excluded(&mut self, show_excluded: bool) {
+        self._settings.show_excluded = show_excluded;
+    }
+
+    /// Show only the files modified within `modified_window`, or all files if `modified_filter`
+    /// is false.
+    pub fn set_modified_filter(&mut self, modified_filter: bool) {
+        self._settings.modified_filter = modified_filter;
+    }
+
+    pub fn set_number_mode(&mut self, number_mode: bool) {
+        self._settings.number_mode = number_mode;
+        self.update_grid_columns();
+    }
+
+    /// Turn recursive search on or off. This doesn't update the listing, because reading the
+    /// subfolders can take a while, so the caller has to read the folder again.
+    pub fn set_recursive_search(&mut self, recursive_search: bool) {
+        self._settings.recursive_search = recursive_search;
+    }
+
+    pub fn set_preview_enabled(&mut self, preview_enabled: bool) {
+        self._settings.preview_enabled = preview_enabled;
+        self.update_grid_columns();
     }
 
     /////////////////////////////////////
@@ -572,7 +1594,6 @@ impl TereAppState {
     pub fn move_cursor(&mut self, amount: isize, wrap: bool) {
         let old_cursor_pos = self.cursor_pos;
         let n_visible_items = self.visible_items().len();
-        let max_cursor_pos = self.main_win_h - 1;
         let old_scroll_pos = self.scroll_pos;
 
         // pointer_pos: the global location of the cursor in ls_output_buf
@@ -583,30 +1604,104 @@ impl TereAppState {
         } else {
             let pointer_pos_signed = isize::try_from(old_pointer_pos).unwrap_or(isize::MAX);
             let n_visible_signed = isize::try_from(n_visible_items).unwrap_or(isize::MAX);
-            let result = pointer_pos_signed + amount;
+            let result = pointer_pos_signed.saturating_add(amount);
             if wrap {
                 usize::try_from(result.rem_euclid(n_visible_signed)).unwrap_or(usize::MAX)
             } else {
-                usize::try_from(result.max(0).min(n_visible_signed - 1)).unwrap_or(usize::MAX)
+                // Stop exactly at the first or last item if the jump would go past it, e.g. when
+                // paging near the start or end of the listing.
+                usize::try_from(result.clamp(0, n_visible_signed - 1)).unwrap_or(usize::MAX)
             }
         };
 
+        // The listing is scrolled by whole rows, which contain more than one item in the grid
+        // layout. The positions below are in units of rows.
+        let columns = self.grid_columns;
+        let n_visible_rows = n_visible_items.div_ceil(columns);
+        // The main window has no rows at all if the terminal is very short.
+        let max_cursor_row = self.main_win_h.saturating_sub(1);
+        let old_scroll_row = old_scroll_pos / columns;
+        let new_pointer_row = new_pointer_pos / columns;
+
+        // The number of items to keep visible above and below the cursor. If the window is too
+        // small to fit the margins on both sides of the cursor, use as large margins as possible.
+        let margin = self.settings().scroll_off.min(max_cursor_row / 2);
+
         // update scroll position and calculate new cursor position
-        if n_visible_items <= max_cursor_pos {
+        let new_scroll_row = if n_visible_rows <= max_cursor_row {
             // all items fit on screen, set scroll to 0
-            self.scroll_pos = 0;
-            self.cursor_pos = new_pointer_pos;
-        } else if new_pointer_pos <= old_scroll_pos {
-            // new cursor position is above screen, scroll up
-            self.scroll_pos = new_pointer_pos;
-            self.cursor_pos = 0;
-        } else if new_pointer_pos >= old_scroll_pos + max_cursor_pos {
-            // new cursor position is below screen, scroll down
-            self.cursor_pos = max_cursor_pos;
-            self.scroll_pos = new_pointer_pos.saturating_sub(max_cursor_pos);
+            0
+        } else if new_pointer_row <= old_scroll_row + margin {
+            // new cursor position is above screen (or within the margin), scroll up
+            new_pointer_row.saturating_sub(margin)
+        } else if new_pointer_row + margin >= old_scroll_row + max_cursor_row {
+            // new cursor position is below screen (or within the margin), scroll down, but not
+            // past the end of the listing
+            let max_scroll_row = n_visible_rows.saturating_sub(max_cursor_row + 1);
+            (new_pointer_row + margin)
+                .saturating_sub(max_cursor_row)
+                .min(max_scroll_row.max(old_scroll_row))
         } else {
             // cursor stays within view, no need to change scroll position
-            self.cursor_pos = new_pointer_pos.saturating_sub(self.scroll_pos);
+            old_scroll_row
+        };
+        self.scroll_pos = new_scroll_row * columns;
+        self.cursor_pos = new_pointer_pos.saturating_sub(self.scroll_pos);
+
+        let margin_below = margin.min(n_visible_items.saturating_sub(new_pointer_pos + 1));
+        self.scroll_below_separator(margin_below);
+    }
+
+    /// The separator row takes up one row of the main window, so the cursor might have been pushed
+    /// below the window (or into the bottom margin). Scroll down until it's visible again.
+    fn scroll_below_separator(&mut self, margin_below: usize) {
+        while self.cursor_pos > 0
+            && self.separator_screen_row().is_some()
+            && self.screen_row_of_cursor_pos(self.cursor_pos) + margin_below >= self.main_win_h
+        {
+            self.scroll_pos += 1;
+            self.cursor_pos -= 1;
+        }
+    }
+
+    /// The index (into the visible items) of the first file after the folders, if the separator
+    /// row between folders and files is enabled and there are both folders and files.
+    fn separator_index(&self) -> Option<usize> {
+        if !self.settings().group_separator || self.grid_columns > 1 {
+            return None;
+        }
+        self.visible_items()
+            .iter()
+            .position(|item| !item.is_dir())
+            .filter(|idx| *idx > 0)
+    }
+
+    /// The row of the main window (starting from 0) where the separator between folders and files
+    /// is drawn, or None if it's not visible.
+    pub fn separator_screen_row(&self) -> Option<usize> {
+        self.separator_index()
+            .filter(|idx| *idx > self.scroll_pos)
+            .map(|idx| idx - self.scroll_pos)
+            .filter(|row| *row < self.main_win_h)
+    }
+
+    /// The row of the main window (starting from 0) where the item at `cursor_pos` is drawn. This
+    /// is the same as `cursor_pos`, unless the separator row is drawn above the item. The row may
+    /// be outside the main window if the item doesn't fit below the separator.
+    pub fn screen_row_of_cursor_pos(&self, cursor_pos: usize) -> usize {
+        match self.separator_screen_row() {
+            Some(sep) if cursor_pos >= sep => cursor_pos + 1,
+            _ => cursor_pos,
+        }
+    }
+
+    /// The cursor position of the item drawn on the given row of the main window, or None if the
+    /// separator is drawn on that row.
+    pub fn cursor_pos_at_screen_row(&self, row: usize) -> Option<usize> {
+        match self.separator_screen_row() {
+            Some(sep) if row == sep => None,
+            Some(sep) if row > sep => Some(row - 1),
+            _ => Some(row),
         }
     }
 
@@ -619,6 +1714,67 @@ impl TereAppState {
         );
     }
 
+    /// Return true if there's an item on the given row of the main window (starting from 0). In
+    /// the grid layout, `row` is the index of a cell in the main window.
+    pub fn is_valid_screen_row(&self, row: usize) -> bool {
+        self.screen_row_of_cursor_pos(row) < self.main_win_h * self.grid_columns
+            && self.scroll_pos + row < self.num_visible_items()
+    }
+
+    /// Move the cursor to the given row of the main window (starting from 0), without scrolling.
+    /// Does nothing if there's no item on that row.
+    pub fn move_cursor_to_screen_row(&mut self, row: usize) {
+        if self.is_valid_screen_row(row) {
+            self.cursor_pos = row;
+        }
+    }
+
+    /// Move the cursor forward (positive amount) or backward (negative amount) by `amount`
+    /// matches, stopping at the first or last match. If the cursor is not on a match, it is
+    /// counted from the next match below the cursor. Does nothing if there are no matches.
+    pub fn move_cursor_by_matches(&mut self, amount: isize) {
+        let match_indices = self.visible_match_indices();
+        if match_indices.is_empty() {
+            return;
+        }
+
+        let cur_idx = self.cursor_pos_to_visible_item_index(self.cursor_pos);
+        let last = match_indices.len() - 1;
+        let cur_idx_in_matches = match_indices
+            .iter()
+            .position(|i| *i >= cur_idx)
+            .unwrap_or(last);
+
+        let new_idx_in_matches = isize::try_from(cur_idx_in_matches)
+            .unwrap_or(isize::MAX)
+            .saturating_add(amount)
+            .clamp(0, isize::try_from(last).unwrap_or(isize::MAX));
+
+        // ok to unwrap, the index is clamped to be non-negative
+        self.move_cursor_to(match_indices[usize::try_from(new_idx_in_matches).unwrap()]);
+    }
+
+    /// Move the cursor to the first (if `top` is true) or last item of the listing. If searching,
+    /// move to the first or last match instead, like the vim commands 'gg' and 'G'.
+    pub fn move_cursor_to_top_or_bottom(&mut self, top: bool) {
+        let match_indices = self.visible_match_indices();
+
+        let target_idx = if self.is_searching() && !match_indices.is_empty() {
+            // OK to unwrap, we've checked that there are matches
+            if top {
+                *match_indices.first().unwrap()
+            } else {
+                *match_indices.last().unwrap()
+            }
+        } else if top {
+            0
+        } else {
+            self.num_visible_items().saturating_sub(1)
+        };
+
+        self.move_cursor_to(target_idx);
+    }
+
     /// Move cursor to the position of a given filename. If the filename was
     /// not found, don't move the cursor and return false, otherwise return true.
     pub fn move_cursor_to_filename<S: AsRef<OsStr>>(&mut self, fname: S) -> bool {
@@ -627,21 +1783,33 @@ impl TereAppState {
             .is_some()
     }
 
+    /// Move the cursor to the match with the highest score. Returns false if there are no matches.
+    pub fn move_cursor_to_best_match(&mut self) -> bool {
+        let best = self
+            .ls_output_buf
+            .best_match
+            .and_then(|i| self.ls_output_buf.all_items.get(i))
+            .map(|item| item.file_name_checked());
+        best.is_some_and(|name| self.move_cursor_to_filename(name))
+    }
+
     /// Move the cursor to the next or previous match in the current list of
     /// matches. If dir is positive, move to the next match, if it's negative,
     /// move to the previous match, and if it's zero, move the cursor to the
     /// current match.
     pub fn move_cursor_to_adjacent_match(&mut self, dir: isize) {
         if self.is_searching() {
+            let wrap = !self.settings().no_wrap;
+
             if self.num_matching_items() == 0 {
                 // if there are no matches, just move the cursor by one step
-                self.move_cursor(dir.signum(), true);
+                self.move_cursor(dir.signum(), wrap);
                 return;
             }
 
             if self.settings().filter_search {
                 // the only visible items are the matches, so we can just move the cursor
-                self.move_cursor(dir.signum(), true);
+                self.move_cursor(dir.signum(), wrap);
             } else {
                 let cur_idx = self.cursor_pos_to_visible_item_index(self.cursor_pos);
                 let kept_indices = &self.ls_output_buf.kept_indices();
@@ -669,6 +1837,11 @@ impl TereAppState {
                     *cur_idx_in_all
                 };
 
+                if !wrap && ((dir < 0 && new_row > cur_idx) || (dir > 0 && new_row < cur_idx)) {
+                    // we would have wrapped around, stay at the current match instead
+                    return;
+                }
+
                 self.move_cursor_to(new_row);
             }
         }
@@ -678,61 +1851,126 @@ impl TereAppState {
     // Search //
     ////////////
 
-    fn update_search_matches(&mut self) {
-        let is_case_sensitive = match self.settings().case_sensitive {
+    fn is_search_case_sensitive(&self) -> bool {
+        match self.settings().case_sensitive {
             CaseSensitiveMode::IgnoreCase => false,
             CaseSensitiveMode::CaseSensitive => true,
             CaseSensitiveMode::SmartCase => self.search_string.chars().any(|c| c.is_uppercase()),
-        };
-        let search_string = if is_case_sensitive {
-            self.search_string.clone()
-        } else {
-            self.search_string.to_lowercase()
-        };
+        }
+    }
 
-        // TODO: construct regex pattern inside MatchesVec instead? - it relies now on capture
-        // groups which are defined by the format!() parens here...
-        let mut regex_str = "".to_string();
-        let gap_search_mode = &self.settings().gap_search_mode;
-        if gap_search_mode == &GapSearchMode::NormalSearch {
-            let _ = write!(regex_str, "^({})", regex::escape(&search_string));
-        } else if gap_search_mode == &GapSearchMode::NormalSearchAnywhere {
-            let _ = write!(regex_str, "({})", regex::escape(&search_string));
+    /// Lowercase `text` unless the search is case sensitive, and remove the diacritics from it if
+    /// they are ignored
+    fn normalize_for_search(&self, text: &str) -> String {
+        let is_case_sensitive = self.is_search_case_sensitive();
+        if self.settings().ignore_diacritics {
+            fold_diacritics(text, is_case_sensitive).0
+        } else if is_case_sensitive {
+            text.to_string()
         } else {
-            // enable gap search. Add '^' to the regex to match only from the start if applicable.
-            if gap_search_mode == &GapSearchMode::GapSearchFromStart {
-                regex_str.push('^');
-            }
-            regex_str.push_str(
-                &search_string
-                    .chars()
-                    .map(|c| format!("({})", regex::escape(&c.to_string())))
-                    .collect::<Vec<String>>()
-                    .join(".*?"),
-            );
+            text.to_lowercase()
+        }
+    }
+
+    fn update_search_matches(&mut self) {
+        let is_case_sensitive = self.is_search_case_sensitive();
+        let ignore_diacritics = self.settings().ignore_diacritics;
+        let search_string = self.normalize_for_search(&self.search_string);
+
+        let matcher = new_matcher(
+            self.settings().fuzzy_algo,
+            &search_string,
+            &self.settings().gap_search_mode,
+        );
+        self.ls_output_buf.update_matches(
+            matcher.as_ref(),
+            is_case_sensitive,
+            ignore_diacritics,
+            self.settings().search_scope,
+        );
+    }
+
+    /// If the search query is a prefix of the name of exactly one item, and that item is a folder,
+    /// return the name of the folder. Other items may still match the search if they contain the
+    /// query elsewhere than at the start (or with gaps).
+    pub fn unique_prefix_match(&self) -> Option<String> {
+        if !self.is_searching() {
+            return None;
         }
 
-        // ok to unwrap, we have escaped the regex above
-        let search_ptn = Regex::new(&regex_str).unwrap();
-        self.ls_output_buf.update_matches(&search_ptn, is_case_sensitive);
+        let query = self.normalize_for_search(&self.search_string);
+
+        let scope = self.settings().search_scope;
+        let mut prefix_matches = self.ls_output_buf.all_items.iter().filter(|item| {
+            let name = item.file_name_checked();
+            name != ".." && self.normalize_for_search(search_target(&name, scope).1).starts_with(&query)
+        });
+
+        match (prefix_matches.next(), prefix_matches.next()) {
+            (Some(item), None) if item.is_dir() => Some(item.file_name_checked()),
+            _ => None,
+        }
     }
 
+    /// Clear the search, and save it in the search history. The search is also forgotten for the
+    /// current folder, so that it won't be restored when coming back here.
     pub fn clear_search(&mut self) {
+        self.add_search_to_history();
+        self.search_memory.remove(&self.current_path);
         self.with_cursor_fixed_at_current_item(|self_|
             self_.search_string.clear()
         );
     }
 
+    fn add_search_to_history(&mut self) {
+        self.search_history_pos = None;
+        if self.search_string.is_empty()
+            || self.search_history.front() == Some(&self.search_string)
+        {
+            return;
+        }
+        self.search_history.push_front(self.search_string.clone());
+        self.search_history.truncate(SEARCH_HISTORY_MAX_LEN);
+    }
+
+    /// Replace the current search with an older (if `older` is true) or newer search from the
+    /// search history. Going forward from the most recent search clears the search.
+    pub fn recall_search(&mut self, older: bool) {
+        let last = match self.search_history.len().checked_sub(1) {
+            Some(last) => last,
+            None => return,
+        };
+        let new_pos = match (self.search_history_pos, older) {
+            (None, true) => Some(0),
+            (Some(pos), true) => Some((pos + 1).min(last)),
+            (None, false) | (Some(0), false) => None,
+            (Some(pos), false) => Some(pos - 1),
+        };
+
+        self.with_cursor_fixed_at_current_item(|self_| {
+            self_.search_string.clear();
+            self_.update_search_matches();
+        });
+        if let Some(pos) = new_pos {
+            let query = self.search_history[pos].clone();
+            self.advance_search(&query);
+        }
+        self.search_history_pos = new_pos;
+    }
+
     pub fn advance_search(&mut self, query: &str) {
         // Can't use with_cursor_fixed_at_current_item, because the current item might not be a
         // match any more after updating the search string.
         let previous_item_under_cursor = self.get_item_under_cursor().cloned();
+        self.search_history_pos = None;
 
         self.search_string.push_str(query);
 
         self.update_search_matches();
 
-        if self.settings().filter_search {
+        if self.settings().fuzzy_algo == FuzzyAlgo::Skim && self.move_cursor_to_best_match() {
+            // the matches are ranked, so go to the best one instead of the nearest one
+        } else if self.settings().filter_search {
             if let Some(item) = previous_item_under_cursor {
                 if !self.move_cursor_to_filename(item.file_name_checked()) {
                     self.move_cursor_to(0);
@@ -747,20 +1985,491 @@ impl TereAppState {
         let previous_item_under_cursor = self.get_item_under_cursor().cloned();
 
         if self.search_string.pop().is_some() {
-            //TODO: keep cursor position when there were no matches? should somehow push cursor position onto some stack when advancing search.
+            self.on_search_erased(previous_item_under_cursor);
+        };
+    }
+
+    /// Erase the last word from the search, like Ctrl-w in readline. Words are separated by
+    /// non-alphanumeric characters, such as spaces, dots or hyphens. If this erases the whole
+    /// search, it's the same as clearing the search.
+    pub fn erase_search_word(&mut self) {
+        let new_len = self
+            .search_string
+            .trim_end_matches(|c: char| !c.is_alphanumeric())
+            .trim_end_matches(char::is_alphanumeric)
+            .len();
+
+        if new_len == 0 {
+            self.clear_search();
+        } else {
+            let previous_item_under_cursor = self.get_item_under_cursor().cloned();
+            self.search_string.truncate(new_len);
+            self.on_search_erased(previous_item_under_cursor);
+        }
+    }
 
-            self.update_search_matches();
+    /// Update the matches and move the cursor after characters have been erased from the search.
+    fn on_search_erased(&mut self, previous_item_under_cursor: Option<CustomDirEntry>) {
+        //TODO: keep cursor position when there were no matches? should somehow push cursor position onto some stack when advancing search.
 
-            if self.settings().filter_search {
-                if let Some(item) = previous_item_under_cursor {
-                    if !self.move_cursor_to_filename(item.file_name_checked()) {
-                        self.move_cursor_to(0);
+        self.search_history_pos = None;
+        self.update_search_matches();
+
+        if self.settings().filter_search {
+            if let Some(item) = previous_item_under_cursor {
+                if !self.move_cursor_to_filename(item.file_name_checked()) {
+                    self.move_cursor_to(0);
+                }
+            }
+        } else {
+            self.move_cursor_to_adjacent_match(0);
+        }
+    }
+}
+
+/// Normalize a path, i.e. resolve '.' and '..' components without accessing the file system.
+//
+// NOTE: have to manually normalize path because the std doesn't have that feature yet, as of
+// December 2021.
+// see:
+// - https://github.com/rust-lang/rfcs/issues/2208
+// - https://github.com/gdzx/rfcs/commit/3c69f787b5b32fb9c9960c1e785e5cabcc794238
+// - abs_path crate
+// - relative_path crate
+// This function is copy-pasted from cargo::util::paths::normalize_path, https://docs.rs/cargo-util/0.1.1/cargo_util/paths/fn.normalize_path.html, under the MIT license
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut components = path.components().peekable();
+    let mut ret = if let Some(c @ Component::Prefix(..)) = components.peek().cloned() {
+        components.next();
+        PathBuf::from(c.as_os_str())
+    } else {
+        PathBuf::new()
+    };
+
+    for component in components {
+        match component {
+            Component::Prefix(..) => unreachable!(),
+            Component::RootDir => {
+                ret.push(component.as_os_str());
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                ret.pop();
+            }
+            Component::Normal(c) => {
+                ret.push(c);
+            }
+        }
+    }
+    ret
+}
+
+/// Format a path for showing it in the header, according to the header format `style`. The result
+/// may still be wider than `width`, in which case it has to be truncated when drawing it.
+pub fn format_header(path: &Path, width: usize, style: HeaderFormat) -> String {
+    format_header_with_home(path, dirs::home_dir().as_deref(), width, style)
+}
+
+fn format_header_with_home(
+    path: &Path,
+    home: Option<&Path>,
+    width: usize,
+    style: HeaderFormat,
+) -> String {
+    if style == HeaderFormat::Full {
+        return path.display().to_string();
+    }
+
+    // Split the path into a prefix ('~' or the root) and the folder names after it. Don't replace
+    // the home folder if it's the root, since then every path would start with '~'.
+    let (prefix, rest) = match home
+        .filter(|home| home.parent().is_some())
+        .and_then(|home| path.strip_prefix(home).ok())
+    {
+        Some(rest) => ("~".to_string(), rest),
+        None => {
+            let root: PathBuf = path
+                .components()
+                .take_while(|c| matches!(c, Component::Prefix(..) | Component::RootDir))
+                .collect();
+            (root.display().to_string(), path.strip_prefix(&root).unwrap_or(path))
+        }
+    };
+    let mut names: Vec<String> = rest
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    let join = |names: &[String]| {
+        let mut ret = prefix.clone();
+        if !names.is_empty() && !ret.is_empty() && !ret.ends_with(std::path::is_separator) {
+            ret.push(std::path::MAIN_SEPARATOR);
+        }
+        ret + &names.join(std::path::MAIN_SEPARATOR_STR)
+    };
+
+    if style == HeaderFormat::Shortened {
+        // Shorten the folder names starting from the left, but never the last one, until the path
+        // fits. Hidden folders keep the dot, like '.config' -> '.c'.
+        for i in 0..names.len().saturating_sub(1) {
+            if join(&names).width() <= width {
+                break;
+            }
+            let keep = if names[i].starts_with('.') { 2 } else { 1 };
+            names[i] = names[i].graphemes(true).take(keep).collect();
+        }
+    }
+
+    join(&names)
+}
+
+/// Express the absolute path `path` relative to the absolute path `base`, without accessing the
+/// file system. If the paths have nothing in common (e.g. they are on different drives on
+/// Windows), `path` is returned as is.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+    let n_common = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if n_common == 0 {
+        return path.to_path_buf();
+    }
+
+    let mut ret: PathBuf = base_components[n_common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(path_components[n_common..].iter().copied())
+        .collect();
+    if ret.as_os_str().is_empty() {
+        ret.push(Component::CurDir);
+    }
+    ret
+}
+
+/// Express the absolute path `path` relative to the absolute path `base` if it's inside `base`.
+/// Unlike `relative_path`, this never goes up with `..`, and otherwise `path` is returned as is.
+fn path_inside(path: &Path, base: &Path) -> PathBuf {
+    match path.strip_prefix(base) {
+        Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// List the contents of a folder for the preview pane, filtered and sorted like the listing. Only
+/// the first `PREVIEW_MAX_ENTRIES` entries of the folder are read.
+fn preview_dir(path: &Path, max_lines: usize, settings: &TereSettings, options: &ListingOptions) -> Vec<String> {
+    let entries = match split_archive_path(path) {
+        Some((archive, inner)) => read_archive_entries(archive, inner),
+        None => std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .take(PREVIEW_MAX_ENTRIES)
+                    .map(CustomDirEntry::from)
+                    .collect()
+            })
+            .and_then(|entries| match options.gitignore {
+                true => remove_gitignored(path, entries, 0, &options.exclude, &AtomicBool::new(false)),
+                false => Ok(entries),
+            }),
+    };
+    match entries {
+        Ok(mut entries) => {
+            remove_excluded(&mut entries, settings);
+            filter_and_sort_entries(entries, settings)
+                .into_iter()
+                .take(max_lines)
+                .map(|e| {
+                    let name = replace_control_chars(&e.file_name_checked());
+                    if e.is_dir() { name + "/" } else { name }
+                })
+                .collect()
+        }
+        Err(e) => vec![format!("<{}>", e)],
+    }
+}
+
+/// Read the beginning of a file for the preview pane. Only the first `PREVIEW_MAX_BYTES` are read,
+/// so that previewing large files is fast.
+fn preview_file(path: &Path, max_lines: usize) -> Vec<String> {
+    use std::io::Read;
+
+    let mut buf = vec![];
+    match std::fs::File::open(path).and_then(|f| f.take(PREVIEW_MAX_BYTES).read_to_end(&mut buf)) {
+        Ok(_) => preview_text_lines(&buf, max_lines),
+        Err(e) => vec![format!("<{}>", e)],
+    }
+}
+
+/// Return true if the beginning of the file at `path` looks like text, using the same check as the
+/// preview pane.
+pub fn is_text_file(path: &Path) -> bool {
+    preview_file(path, 1) != [BINARY_PREVIEW_MSG]
+}
+
+/// Split the bytes read from a file into lines, or return `BINARY_PREVIEW_MSG` if they don't
+/// look like text.
+fn preview_text_lines(bytes: &[u8], max_lines: usize) -> Vec<String> {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        // The buffer may have been cut off in the middle of a multi-byte character, use the part
+        // that is valid.
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return vec![BINARY_PREVIEW_MSG.to_string()],
+    };
+
+    if text.contains('\0') {
+        return vec![BINARY_PREVIEW_MSG.to_string()];
+    }
+
+    text.lines()
+        .take(max_lines)
+        .map(|line| replace_control_chars(&line.replace('\t', "    ")))
+        .collect()
+}
+
+/// Replace the control characters in `text` with U+FFFD, so that printing it can't move the cursor
+/// or send escape sequences to the terminal
+fn replace_control_chars(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { char::REPLACEMENT_CHARACTER } else { c })
+        .collect()
+}
+
+/// A one-line summary of an entry for the details line: the permissions, owner and group (on unix),
+/// the size and the modification time. For broken symlinks, the target of the link instead.
+pub fn entry_details(entry: &CustomDirEntry, settings: &TereSettings) -> String {
+    if let (true, Some(target)) = (entry.is_broken_symlink(), &entry.symlink_target) {
+        return format!("broken link -> {}", target.display());
+    }
+    let metadata = match entry.metadata() {
+        Some(metadata) => metadata,
+        None => return "<could not read metadata>".to_string(),
+    };
+
+    let mut details = vec![];
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        details.push(format_mode(metadata.mode(), entry.symlink_target.is_some()));
+        details.push(
+            uzers::get_user_by_uid(metadata.uid())
+                .map(|user| user.name().to_string_lossy().into_owned())
+                .unwrap_or_else(|| metadata.uid().to_string()),
+        );
+        details.push(
+            uzers::get_group_by_gid(metadata.gid())
+                .map(|group| group.name().to_string_lossy().into_owned())
+                .unwrap_or_else(|| metadata.gid().to_string()),
+        );
+    }
+
+    #[cfg(not(unix))]
+    details.push(if metadata.permissions().readonly() { "read-only" } else { "read-write" }.to_string());
+
+    details.push(format_size(metadata.len(), settings.size_format));
+    if let Ok(mtime) = metadata.modified() {
+        details.push(format_time(mtime, SystemTime::now(), &settings.time_style));
+    }
+    details.join("  ")
+}
+
+/// Format unix permission bits like `ls -l`, e.g. `drwxr-xr-x`.
+#[cfg(unix)]
+fn format_mode(mode: u32, is_symlink: bool) -> String {
+    let file_type = match mode & 0o170000 {
+        _ if is_symlink => 'l',
+        0o040000 => 'd',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+
+    let mut ret = file_type.to_string();
+    // (read, write, execute) bits and the special bit (setuid, setgid, sticky) for each class
+    for (shift, special_bit, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        ret.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        ret.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        ret.push(match (bits & 0o1 != 0, mode & special_bit != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    ret
+}
+
+/// The size of a file in bytes. Folders (and items whose metadata can't be read) count as zero.
+fn file_size(entry: &CustomDirEntry) -> u64 {
+    if entry.is_dir() {
+        0
+    } else {
+        entry.metadata().map_or(0, |m| m.len())
+    }
+}
+
+/// The total size of the files in a folder and its subfolders, up to `max_depth` levels of
+/// subfolders below it. Symlinks are not followed, and items that can't be read are skipped, as
+/// well as the subfolders named in `exclude`. Returns the size, and whether some subfolders were
+/// skipped because of the depth limit.
+pub fn dir_size_recursive(path: &Path, max_depth: usize, exclude: &[String]) -> (u64, bool) {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return (0, false),
+    };
+    let mut truncated = false;
+    let size = entries
+        .filter_map(|e| e.ok())
+        .map(|e| match e.metadata() {
+            Ok(m) if m.is_dir() && is_excluded_name(&e.path(), exclude) => 0,
+            Ok(m) if m.is_dir() => match max_depth.checked_sub(1) {
+                Some(depth) => {
+                    let (size, subfolder_truncated) = dir_size_recursive(&e.path(), depth, exclude);
+                    truncated |= subfolder_truncated;
+                    size
+                }
+                None => {
+                    truncated = true;
+                    0
+                }
+            },
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum();
+    (size, truncated)
+}
+
+/// Format a file size in bytes, e.g. `1.5 KiB` with binary prefixes or `1.5 kB` with decimal ones.
+pub fn format_size(bytes: u64, format: SizeFormat) -> String {
+    let (base, units) = match format {
+        SizeFormat::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB"]),
+        SizeFormat::Decimal => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB"]),
+        SizeFormat::Bytes => return format!("{} B", bytes),
+    };
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= base && unit < units.len() - 1 {
+        size /= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// Format a modification time in the local time zone, or relative to `now`.
+pub fn format_time(time: SystemTime, now: SystemTime, style: &TimeStyle) -> String {
+    let format = match style {
+        TimeStyle::Iso => "%Y-%m-%d %H:%M",
+        TimeStyle::Relative => return format_relative_time(time, now),
+        TimeStyle::Format(format) => format,
+    };
+    chrono::DateTime::<chrono::Local>::from(time).format(format).to_string()
+}
+
+/// How long before (or after) `now` the time is, in the largest whole unit, e.g. `2h ago`.
+fn format_relative_time(time: SystemTime, now: SystemTime) -> String {
+    const UNITS: [(&str, u64); 6] = [
+        ("y", 365 * 24 * 60 * 60),
+        ("mo", 30 * 24 * 60 * 60),
+        ("w", 7 * 24 * 60 * 60),
+        ("d", 24 * 60 * 60),
+        ("h", 60 * 60),
+        ("m", 60),
+    ];
+    let (secs, in_future) = match now.duration_since(time) {
+        Ok(ago) => (ago.as_secs(), false),
+        Err(e) => (e.duration().as_secs(), true),
+    };
+    match UNITS.iter().find(|(_, unit_secs)| secs >= *unit_secs) {
+        None => "just now".to_string(),
+        Some((unit, unit_secs)) if in_future => format!("in {}{}", secs / unit_secs, unit),
+        Some((unit, unit_secs)) => format!("{}{} ago", secs / unit_secs, unit),
+    }
+}
+
+/// The placeholders that can be used in the footer templates given with `--footer-format` and
+/// `--search-footer-format`
+pub const FOOTER_PLACEHOLDERS: [&str; 7] = ["cursor", "total", "dirs", "files", "size", "match", "matches"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Placeholder(String),
+}
+
+/// A format string for the counts in the footer, with placeholders like `{cursor}` that are
+/// replaced by the values (see `FOOTER_PLACEHOLDERS`). Literal braces are written as `{{` and
+/// `}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FooterTemplate(Vec<TemplatePart>);
+
+impl std::str::FromStr for FooterTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    text.push(c);
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err("'{' without a matching '}'".to_string()),
+                        }
+                    }
+                    if !FOOTER_PLACEHOLDERS.contains(&name.as_str()) {
+                        return Err(format!(
+                            "unknown placeholder '{{{}}}', the placeholders are {}",
+                            name,
+                            FOOTER_PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", "),
+                        ));
                     }
+                    parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                    parts.push(TemplatePart::Placeholder(name));
                 }
-            } else {
-                self.move_cursor_to_adjacent_match(0);
+                '}' => return Err("'}' without a matching '{'".to_string()),
+                c => text.push(c),
             }
-        };
+        }
+        parts.push(TemplatePart::Text(text));
+        Ok(Self(parts))
+    }
+}
+
+impl FooterTemplate {
+    /// Replace the placeholders with the values given by `value`
+    pub fn fill<F: Fn(&str) -> String>(&self, value: F) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Text(text) => text.clone(),
+                TemplatePart::Placeholder(name) => value(name),
+            })
+            .collect()
     }
 }
 
@@ -789,15 +2498,29 @@ mod tests {
         TereAppState {
             cursor_pos: 0,
             scroll_pos: 0,
+            grid_columns: 1,
+            grid_column_width: 10,
             main_win_h: win_h,
             main_win_w: 10,
             current_path: "/".into(),
+            initial_path: "/".into(),
             ls_output_buf: buf,
+            total_size: None,
+            num_excluded: 0,
+            depth_limit_reached: false,
             header_msg: "".into(),
             info_msg: "".into(),
             search_string: "".into(),
+            search_history: VecDeque::new(),
+            search_history_pos: None,
             _settings: Default::default(),
             history: HistoryTree::from_abs_path("/"),
+            previous_path: None,
+            cursor_memory: HashMap::new(),
+            search_memory: HashMap::new(),
+            initial_prefs: Prefs::new(),
+            prefetch: PrefetchCache::default(),
+            extract_dir: ExtractDir::default(),
         }
     }
 
@@ -1024,37 +2747,169 @@ mod tests {
     }
 
     #[test]
-    fn test_basic_advance_search() {
-        let mut s = create_test_state_with_buf(
-            5,
-            strings_to_ls_buf(vec!["..", "foo", "frob", "bar", "baz"]),
-        );
-        s.move_cursor_to(2);
+    fn test_scroll_off() {
+        let mut state = create_test_state(5, 20);
+        state._settings.scroll_off = 2;
 
-        // current state:
-        //   ..
-        //   foo
-        // > frob
-        //   bar
-        //   baz
+        // moving down, scrolling starts when the cursor is two rows from the bottom
+        state.move_cursor(2, false);
+        assert_eq!(state.cursor_pos, 2);
+        assert_eq!(state.scroll_pos, 0);
+        state.move_cursor(1, false);
+        assert_eq!(state.cursor_pos, 2);
+        assert_eq!(state.scroll_pos, 1);
 
-        assert_eq!(s.cursor_pos, 2);
-        assert_eq!(s.scroll_pos, 0);
+        // moving up, scrolling starts when the cursor is two rows from the top
+        state.move_cursor(-1, false);
+        assert_eq!(state.cursor_pos, 2);
+        assert_eq!(state.scroll_pos, 0);
+        state.move_cursor(-1, false);
+        assert_eq!(state.cursor_pos, 1);
+        assert_eq!(state.scroll_pos, 0);
 
-        s.advance_search("b");
-        assert_eq!(s.cursor_pos, 3);
-        s.move_cursor_to_adjacent_match(1);
-        assert_eq!(s.cursor_pos, 4);
-        s.move_cursor_to_adjacent_match(1);
-        assert_eq!(s.cursor_pos, 3);
+        // at the end of the listing, the cursor can go all the way to the bottom
+        state.move_cursor_to(19);
+        assert_eq!(state.cursor_pos, 4);
+        assert_eq!(state.scroll_pos, 15);
+        state.move_cursor(-2, false);
+        assert_eq!(state.cursor_pos, 2);
+        assert_eq!(state.scroll_pos, 15);
+        state.move_cursor(-1, false);
+        assert_eq!(state.cursor_pos, 2);
+        assert_eq!(state.scroll_pos, 14);
     }
 
     #[test]
-    fn test_advance_search_wrap() {
-        let mut s = create_test_state_with_buf(
-            3,
-            strings_to_ls_buf(vec!["..", "foo", "frob", "bar", "baz"]),
-        );
+    fn test_scroll_off_small_window() {
+        // the window is too small for a margin of 3 on both sides, so the margin is reduced to 1
+        let mut state = create_test_state(4, 20);
+        state._settings.scroll_off = 3;
+
+        state.move_cursor(1, false);
+        assert_eq!(state.cursor_pos, 1);
+        assert_eq!(state.scroll_pos, 0);
+        state.move_cursor(1, false);
+        assert_eq!(state.cursor_pos, 2);
+        assert_eq!(state.scroll_pos, 0);
+        state.move_cursor(1, false);
+        assert_eq!(state.cursor_pos, 2);
+        assert_eq!(state.scroll_pos, 1);
+
+        // with a one-row window, there is no margin
+        let mut state = create_test_state(1, 20);
+        state._settings.scroll_off = 3;
+        state.move_cursor(5, false);
+        assert_eq!(state.cursor_pos, 0);
+        assert_eq!(state.scroll_pos, 5);
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        // 20 items with names at most 7 columns wide, so that the columns are 9 wide
+        let mut s = create_test_state(3, 20);
+        s._settings.grid = true;
+        s.update_main_window_dimensions(40, 3);
+        assert_eq!(s.grid_columns(), 4);
+        assert_eq!(s.grid_column_width(), 9);
+
+        // move down by one row
+        s.move_cursor(4, false);
+        assert_eq!(s.cursor_pos, 4);
+        assert_eq!(s.scroll_pos, 0);
+        s.move_cursor(4, false);
+        assert_eq!(s.cursor_pos, 8);
+        assert_eq!(s.scroll_pos, 0);
+
+        // scroll by whole rows
+        s.move_cursor(5, false);
+        assert_eq!(s.cursor_pos, 9);
+        assert_eq!(s.scroll_pos, 4);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "file 14");
+        s.move_cursor_to(19);
+        assert_eq!(s.scroll_pos, 8);
+        assert_eq!(s.cursor_pos, 11);
+
+        // cells past the last item are not valid
+        assert!(s.is_valid_screen_row(11));
+        s.move_cursor_to(0);
+        assert!(s.is_valid_screen_row(11));
+        assert!(!s.is_valid_screen_row(12));
+
+        // the cursor stays on the same item when the number of columns changes
+        s.move_cursor_to(13);
+        s.update_main_window_dimensions(20, 3);
+        assert_eq!(s.grid_columns(), 2);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "file 14");
+        assert_eq!(s.scroll_pos % 2, 0);
+
+        // with the preview, there's only half of the width for the listing
+        s.set_preview_enabled(true);
+        assert_eq!(s.grid_columns(), 1);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "file 14");
+    }
+
+    #[test]
+    fn test_move_cursor_to_screen_row() {
+        let mut s = create_test_state(4, 6);
+        s.move_cursor_to(5);
+        assert_eq!(s.cursor_pos, 3);
+        assert_eq!(s.scroll_pos, 2);
+
+        assert!(s.is_valid_screen_row(0));
+        assert!(s.is_valid_screen_row(3));
+        assert!(!s.is_valid_screen_row(4));
+
+        // the row is relative to the top of the window, so it's offset by the scroll position
+        s.move_cursor_to_screen_row(1);
+        assert_eq!(s.cursor_pos, 1);
+        assert_eq!(s.scroll_pos, 2);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 3);
+
+        s.move_cursor_to_screen_row(4);
+        assert_eq!(s.cursor_pos, 1);
+
+        // fewer items than rows
+        let mut s = create_test_state(10, 3);
+        assert!(s.is_valid_screen_row(2));
+        assert!(!s.is_valid_screen_row(3));
+        s.move_cursor_to_screen_row(5);
+        assert_eq!(s.cursor_pos, 0);
+        s.move_cursor_to_screen_row(2);
+        assert_eq!(s.cursor_pos, 2);
+    }
+
+    #[test]
+    fn test_basic_advance_search() {
+        let mut s = create_test_state_with_buf(
+            5,
+            strings_to_ls_buf(vec!["..", "foo", "frob", "bar", "baz"]),
+        );
+        s.move_cursor_to(2);
+
+        // current state:
+        //   ..
+        //   foo
+        // > frob
+        //   bar
+        //   baz
+
+        assert_eq!(s.cursor_pos, 2);
+        assert_eq!(s.scroll_pos, 0);
+
+        s.advance_search("b");
+        assert_eq!(s.cursor_pos, 3);
+        s.move_cursor_to_adjacent_match(1);
+        assert_eq!(s.cursor_pos, 4);
+        s.move_cursor_to_adjacent_match(1);
+        assert_eq!(s.cursor_pos, 3);
+    }
+
+    #[test]
+    fn test_advance_search_wrap() {
+        let mut s = create_test_state_with_buf(
+            3,
+            strings_to_ls_buf(vec!["..", "foo", "frob", "bar", "baz"]),
+        );
         s.move_cursor_to(4);
 
         // current state: ('|' shows the window position)
@@ -1085,6 +2940,195 @@ mod tests {
         assert_eq!(s.cursor_pos, 0);
     }
 
+    #[test]
+    fn test_no_wrap() {
+        let mut s = create_test_state(4, 5);
+        s._settings.no_wrap = true;
+
+        // with wrapping disabled, the UI passes wrap=false to move_cursor, and the cursor stops at
+        // the last item when moving down past it
+        for _ in 0..10 {
+            s.move_cursor(1, !s.settings().no_wrap);
+        }
+        assert_eq!(s.cursor_pos, 3);
+        assert_eq!(s.scroll_pos, 1);
+
+        // and at the first item when moving up past it
+        for _ in 0..10 {
+            s.move_cursor(-1, !s.settings().no_wrap);
+        }
+        assert_eq!(s.cursor_pos, 0);
+        assert_eq!(s.scroll_pos, 0);
+
+        // the same with jumps that would go past the ends
+        s.move_cursor(7, !s.settings().no_wrap);
+        assert_eq!(s.cursor_pos + s.scroll_pos, 4);
+        s.move_cursor(-7, !s.settings().no_wrap);
+        assert_eq!(s.cursor_pos + s.scroll_pos, 0);
+
+        // with wrapping, moving past the last item goes back to the first one
+        s._settings.no_wrap = false;
+        s.move_cursor_to(4);
+        s.move_cursor(1, !s.settings().no_wrap);
+        assert_eq!(s.cursor_pos, 0);
+        assert_eq!(s.scroll_pos, 0);
+    }
+
+    #[test]
+    fn test_advance_search_no_wrap() {
+        let mut s = create_test_state_with_buf(
+            5,
+            strings_to_ls_buf(vec!["..", "foo", "frob", "bar", "baz"]),
+        );
+        s._settings.no_wrap = true;
+
+        s.advance_search("b");
+        assert_eq!(s.cursor_pos, 3);
+        s.move_cursor_to_adjacent_match(-1);
+        assert_eq!(s.cursor_pos, 3);
+        s.move_cursor_to_adjacent_match(1);
+        assert_eq!(s.cursor_pos, 4);
+        s.move_cursor_to_adjacent_match(1);
+        assert_eq!(s.cursor_pos, 4);
+
+        // same with filter search, where only the matches are visible
+        s.set_filter_search(true);
+        s.move_cursor_to_adjacent_match(1);
+        assert_eq!(s.cursor_pos, 1);
+        s.move_cursor_to_adjacent_match(-1);
+        assert_eq!(s.cursor_pos, 0);
+        s.move_cursor_to_adjacent_match(-1);
+        assert_eq!(s.cursor_pos, 0);
+    }
+
+    #[test]
+    fn test_unique_prefix_match() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        for folder in ["Documents", "docker", "dotfiles", "my-dotfiles"] {
+            std::fs::create_dir_all(tmp.join(folder)).unwrap();
+        }
+        std::fs::write(tmp.join("download.txt"), "").unwrap();
+
+        let mut s = create_test_state_with_buf(
+            10,
+            strings_to_ls_buf(vec![
+                PathBuf::from(".."),
+                tmp.join("Documents"),
+                tmp.join("docker"),
+                tmp.join("dotfiles"),
+                tmp.join("my-dotfiles"),
+                tmp.join("download.txt"),
+            ]),
+        );
+        s._settings.gap_search_mode = GapSearchMode::NormalSearchAnywhere;
+
+        assert_eq!(s.unique_prefix_match(), None);
+
+        s.advance_search("doc");
+        assert_eq!(s.unique_prefix_match(), None);
+        s.advance_search("u");
+        assert_eq!(s.unique_prefix_match(), Some("Documents".to_string()));
+
+        s.clear_search();
+        s.advance_search("dot");
+        // 'my-dotfiles' matches, but doesn't start with the query
+        assert_eq!(s.num_matching_items(), 2);
+        assert_eq!(s.unique_prefix_match(), Some("dotfiles".to_string()));
+
+        // smart case: uppercase query is case sensitive
+        s.clear_search();
+        s.advance_search("Do");
+        assert_eq!(s.unique_prefix_match(), Some("Documents".to_string()));
+
+        // files are never returned
+        s.clear_search();
+        s.advance_search("dow");
+        assert_eq!(s.unique_prefix_match(), None);
+    }
+
+    #[test]
+    fn test_erase_search_word() {
+        let mut s = create_test_state_with_buf(
+            10,
+            strings_to_ls_buf(vec!["..", "foo-bar", "foo-baz", "frob", "qux"]),
+        );
+
+        s.advance_search("foo-baz");
+        assert_eq!(s.num_matching_items(), 1);
+        assert_eq!(s.cursor_pos, 2);
+
+        s.erase_search_word();
+        assert_eq!(s.search_string(), "foo-");
+        assert_eq!(s.num_matching_items(), 2);
+        assert_eq!(s.cursor_pos, 2);
+
+        // trailing separators are erased along with the word before them
+        s.erase_search_word();
+        assert!(!s.is_searching());
+        assert_eq!(s.cursor_pos, 2);
+
+        s.advance_search("f.. ");
+        s.erase_search_word();
+        assert!(!s.is_searching());
+
+        // erasing with an empty search does nothing
+        s.erase_search_word();
+        assert!(!s.is_searching());
+        assert_eq!(s.cursor_pos, 2);
+    }
+
+    #[test]
+    fn test_search_history() {
+        let mut s = create_test_state_with_buf(
+            10,
+            strings_to_ls_buf(vec!["..", "foo", "bar", "baz"]),
+        );
+
+        // nothing to recall yet
+        s.recall_search(true);
+        assert!(!s.is_searching());
+
+        s.advance_search("fo");
+        s.clear_search();
+        s.advance_search("ba");
+        s.clear_search();
+        // consecutive identical searches are saved only once
+        s.advance_search("ba");
+        s.clear_search();
+        s.advance_search("baz");
+        s.clear_search();
+        assert_eq!(s.search_history, vec!["baz", "ba", "fo"]);
+
+        s.recall_search(true);
+        assert_eq!(s.search_string(), "baz");
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "baz");
+        s.recall_search(true);
+        assert_eq!(s.search_string(), "ba");
+        s.recall_search(true);
+        assert_eq!(s.search_string(), "fo");
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "foo");
+        // stop at the oldest search
+        s.recall_search(true);
+        assert_eq!(s.search_string(), "fo");
+
+        s.recall_search(false);
+        assert_eq!(s.search_string(), "ba");
+        s.recall_search(false);
+        s.recall_search(false);
+        assert!(!s.is_searching());
+        // the cursor stays on the last match
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "baz");
+
+        // editing a recalled search starts again from the most recent search
+        s.recall_search(true);
+        s.recall_search(true);
+        s.erase_search_char();
+        assert_eq!(s.search_string(), "b");
+        s.recall_search(true);
+        assert_eq!(s.search_string(), "baz");
+    }
+
     #[test]
     fn test_advance_and_erase_search_with_cursor_on_match() {
         let mut s = create_test_state_with_buf(
@@ -1589,4 +3633,1277 @@ mod tests {
         assert_eq!(s.cursor_pos, 3);
     }
 
+    #[test]
+    fn test_search_target() {
+        assert_eq!(search_target("main.rs", SearchScope::Name), (0, "main.rs"));
+        assert_eq!(search_target("main.rs", SearchScope::Extension), (5, "rs"));
+        assert_eq!(search_target("main.rs", SearchScope::Stem), (0, "main"));
+        assert_eq!(search_target("archive.tar.gz", SearchScope::Extension), (12, "gz"));
+        assert_eq!(search_target("archive.tar.gz", SearchScope::Stem), (0, "archive.tar"));
+        // dotfiles and names without a dot have no extension
+        assert_eq!(search_target(".bashrc", SearchScope::Extension), (7, ""));
+        assert_eq!(search_target(".bashrc", SearchScope::Stem), (0, ".bashrc"));
+        assert_eq!(search_target("..", SearchScope::Stem), (0, ".."));
+        // with recursive search, only the last component of the relative path is searched
+        let sep = std::path::MAIN_SEPARATOR;
+        let path = format!("src{}ui{}mod.rs", sep, sep);
+        assert_eq!(search_target(&path, SearchScope::Name), (7, "mod.rs"));
+        assert_eq!(search_target(&path, SearchScope::Extension), (11, "rs"));
+        assert_eq!(search_target(&path, SearchScope::Stem), (7, "mod"));
+    }
+
+    #[test]
+    fn test_search_scope() {
+        let mut s = create_test_state_with_buf(
+            10,
+            strings_to_ls_buf(vec!["..", "rsync", "main.rs", "lib.rs", "notes.txt"]),
+        );
+
+        s.set_search_scope(SearchScope::Extension);
+        s.advance_search("rs");
+        assert_eq!(s.visible_match_indices(), vec![2, 3]);
+        // the match locations are relative to the whole name
+        assert_eq!(s.get_match_locations_at_cursor_pos(2), Some(&vec![(5, 6), (6, 7)]));
+
+        s.set_search_scope(SearchScope::Stem);
+        assert_eq!(s.visible_match_indices(), vec![1]);
+        assert_eq!(s.get_match_locations_at_cursor_pos(1), Some(&vec![(0, 1), (1, 2)]));
+
+        s.set_search_scope(SearchScope::Name);
+        assert_eq!(s.visible_match_indices(), vec![1]);
+    }
+
+    #[test]
+    fn test_fold_diacritics() {
+        assert_eq!(fold_diacritics("abc", true), ("abc".to_string(), vec![0..1, 1..2, 2..3]));
+        // precomposed: 'é' is two bytes
+        assert_eq!(fold_diacritics("Caf\u{e9}s", true), ("Cafes".to_string(), vec![0..1, 1..2, 2..3, 3..5, 5..6]));
+        // decomposed: the combining accent belongs to the 'e' before it
+        assert_eq!(fold_diacritics("Cafe\u{301}s", false), ("cafes".to_string(), vec![0..1, 1..2, 2..3, 3..6, 6..7]));
+        assert_eq!(fold_diacritics("\u{c5}ngstr\u{f6}m", false).0, "angstrom");
+        assert_eq!(fold_diacritics("", false), (String::new(), vec![]));
+    }
+
+    #[test]
+    fn test_ignore_diacritics() {
+        let composed = "caf\u{e9}s";
+        let decomposed = "cafe\u{301}s";
+        let mut s = create_test_state_with_buf(
+            10,
+            strings_to_ls_buf(vec!["..", composed, decomposed, "cafes"]),
+        );
+
+        // only the exact spelling matches by default
+        s.advance_search("cafe");
+        assert_eq!(s.visible_match_indices(), vec![2, 3]);
+        s.clear_search();
+
+        s._settings.ignore_diacritics = true;
+        s.advance_search("cafe");
+        assert_eq!(s.visible_match_indices(), vec![1, 2, 3]);
+        // the match locations cover the whole accented characters of the original names
+        assert_eq!(s.get_match_locations_at_cursor_pos(1), Some(&vec![(0, 1), (1, 2), (2, 3), (3, 5)]));
+        assert_eq!(s.get_match_locations_at_cursor_pos(2), Some(&vec![(0, 1), (1, 2), (2, 3), (3, 6)]));
+        assert_eq!(s.get_match_locations_at_cursor_pos(3), Some(&vec![(0, 1), (1, 2), (2, 3), (3, 4)]));
+        s.clear_search();
+
+        // diacritics in the search are ignored as well, in either form
+        for query in [composed, decomposed].iter() {
+            s.advance_search(query);
+            assert_eq!(s.visible_match_indices(), vec![1, 2, 3]);
+            s.clear_search();
+        }
+
+        // without case sensitivity, 'É' matches 'e'
+        s.set_case_sensitive(CaseSensitiveMode::IgnoreCase);
+        s.advance_search("CAF\u{c9}S");
+        assert_eq!(s.visible_match_indices(), vec![1, 2, 3]);
+        s.clear_search();
+        s.set_case_sensitive(CaseSensitiveMode::CaseSensitive);
+        s.advance_search("CAF\u{c9}S");
+        assert!(s.visible_match_indices().is_empty());
+    }
+
+    #[test]
+    fn test_skim_fuzzy_algo() {
+        let mut s = create_test_state_with_buf(
+            10,
+            strings_to_ls_buf(vec!["..", "dxoxc", "my_docs", "notes"]),
+        );
+        s.cursor_pos = 0;
+
+        // the cursor goes to the first match with the substring matching
+        s._settings.gap_search_mode = GapSearchMode::GapSearchAnywhere;
+        s.advance_search("doc");
+        assert_eq!(s.visible_match_indices(), vec![1, 2]);
+        assert_eq!(s.cursor_pos, 1);
+        s.clear_search();
+
+        // and to the best match with skim, even though the other one comes first
+        s._settings.fuzzy_algo = FuzzyAlgo::Skim;
+        s.move_cursor_to(0);
+        s.advance_search("doc");
+        assert_eq!(s.visible_match_indices(), vec![1, 2]);
+        assert_eq!(s.cursor_pos, 2);
+        assert_eq!(s.get_match_locations_at_cursor_pos(2), Some(&vec![(3, 4), (4, 5), (5, 6)]));
+        assert_eq!(s.get_match_locations_at_cursor_pos(1), Some(&vec![(0, 1), (2, 3), (4, 5)]));
+    }
+
+    #[test]
+    fn test_file_name_fitting_width() {
+        let long_name = "ä".repeat(5000) + "end";
+        let entry = CustomDirEntry::from(Path::new(&long_name));
+        assert_eq!(entry.file_name_fitting_width(3), "äää");
+        assert_eq!(entry.file_name_fitting_width(3), "äää");
+        // the cache is replaced when the width changes
+        assert_eq!(entry.file_name_fitting_width(5), "äääää");
+        assert_eq!(entry.file_name_fitting_width(10000), long_name);
+        assert_eq!(entry.file_name_fitting_width(0), "");
+
+        // wide characters are not cut in half
+        let entry = CustomDirEntry::from(Path::new("日本語"));
+        assert_eq!(entry.file_name_fitting_width(5), "日本");
+        assert_eq!(entry.file_name_fitting_width(6), "日本語");
+        // the cached name is not shared with clones that are drawn with another width
+        let clone = entry.clone();
+        assert_eq!(clone.file_name_fitting_width(2), "日");
+        assert_eq!(entry.file_name_fitting_width(6), "日本語");
+    }
+
+    #[test]
+    fn test_preview_text_lines() {
+        assert_eq!(preview_text_lines(b"foo\nbar\n\tbaz", 10), vec!["foo", "bar", "    baz"]);
+        assert_eq!(preview_text_lines(b"foo\nbar\nbaz", 2), vec!["foo", "bar"]);
+        assert!(preview_text_lines(b"", 10).is_empty());
+        // control characters are not printed as is
+        assert_eq!(
+            preview_text_lines(b"a\x1b[2J\rb\x7f\xc2\x9b\r\n", 10),
+            vec!["a\u{fffd}[2J\u{fffd}b\u{fffd}\u{fffd}"]
+        );
+    }
+
+    #[test]
+    fn test_preview_text_lines_binary() {
+        assert_eq!(preview_text_lines(b"foo\0bar", 10), vec![BINARY_PREVIEW_MSG]);
+        assert_eq!(preview_text_lines(&[0xff, 0xfe, 0x41], 10), vec![BINARY_PREVIEW_MSG]);
+    }
+
+    #[test]
+    fn test_is_text_file() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::write(tmp.join("text"), "hello\nworld\n").unwrap();
+        std::fs::write(tmp.join("binary"), b"\x7fELF\0\0\x01").unwrap();
+        std::fs::write(tmp.join("empty"), "").unwrap();
+
+        assert!(is_text_file(&tmp.join("text")));
+        assert!(!is_text_file(&tmp.join("binary")));
+        assert!(is_text_file(&tmp.join("empty")));
+    }
+
+    #[test]
+    fn test_preview_text_lines_truncated_multibyte() {
+        // 'ä' is two bytes in UTF-8, simulate the read being cut off in the middle of it
+        let bytes = "aä".as_bytes();
+        assert_eq!(preview_text_lines(&bytes[..2], 10), vec!["a"]);
+    }
+
+    #[test]
+    fn test_top_bottom_while_searching() {
+        let mut s = create_test_state_with_buf(
+            3,
+            strings_to_ls_buf(vec!["..", "bar", "foo", "baz", "frob", "qux"]),
+        );
+        s.advance_search("f");
+
+        // current state: ('|' shows the window position)
+        //   ..
+        //   bar
+        // > foo  |
+        //   baz  |
+        //   frob |
+        //   qux
+
+        s.move_cursor_to_top_or_bottom(false);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 4);
+        s.move_cursor_to_top_or_bottom(true);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 2);
+
+        // with no matches, move to the top / bottom of the whole listing
+        s.advance_search("x");
+        assert_eq!(s.num_matching_items(), 0);
+        s.move_cursor_to_top_or_bottom(false);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 5);
+        s.move_cursor_to_top_or_bottom(true);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 0);
+    }
+
+    #[test]
+    fn test_top_bottom_while_searching_with_filter() {
+        let mut s = create_test_state_with_buf(
+            10,
+            strings_to_ls_buf(vec!["..", "bar", "foo", "baz", "frob", "qux"]),
+        );
+        s._settings.filter_search = true;
+        s.advance_search("b");
+
+        // state should now be
+        // > bar
+        //   baz
+
+        s.move_cursor_to_top_or_bottom(false);
+        assert_eq!(s.cursor_pos, 1);
+        s.move_cursor_to_top_or_bottom(true);
+        assert_eq!(s.cursor_pos, 0);
+    }
+
+    #[test]
+    fn test_move_cursor_by_matches() {
+        let mut s = create_test_state_with_buf(
+            3,
+            strings_to_ls_buf(vec!["..", "a1", "b1", "a2", "a3", "b2", "a4", "a5"]),
+        );
+        s.advance_search("a");
+        assert_eq!(s.visible_match_indices(), vec![1, 3, 4, 6, 7]);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 1);
+
+        s.move_cursor_by_matches(2);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 4);
+        s.move_cursor_by_matches(2);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 7);
+
+        // stop at the last match
+        s.move_cursor_by_matches(2);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 7);
+
+        s.move_cursor_by_matches(-3);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 3);
+
+        // stop at the first match
+        s.move_cursor_by_matches(-100);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 1);
+    }
+
+    #[test]
+    fn test_move_cursor_by_matches_no_matches() {
+        let mut s = create_test_state_with_buf(
+            3,
+            strings_to_ls_buf(vec!["..", "a1", "b1", "a2"]),
+        );
+        s.move_cursor_to(2);
+        s.advance_search("x");
+        s.move_cursor_by_matches(2);
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 2);
+    }
+
+    #[test]
+    fn test_num_folders_and_files() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder1")).unwrap();
+        std::fs::create_dir_all(tmp.join("folder2")).unwrap();
+        std::fs::write(tmp.join("file1"), "").unwrap();
+
+        let s = create_test_state_with_buf(
+            10,
+            strings_to_ls_buf(vec![
+                PathBuf::from(".."),
+                tmp.join("folder1"),
+                tmp.join("folder2"),
+                tmp.join("file1"),
+                tmp.join("nonexistent"),
+            ]),
+        );
+
+        assert_eq!(s.num_folders(), 2);
+        assert_eq!(s.num_files(), 2);
+        assert_eq!(s.num_total_items(), 5);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_broken_symlinks() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::os::unix::fs::symlink("folder", tmp.join("link-to-folder")).unwrap();
+        std::os::unix::fs::symlink("nonexistent", tmp.join("dangling")).unwrap();
+        std::os::unix::fs::symlink("cycle2", tmp.join("cycle1")).unwrap();
+        std::os::unix::fs::symlink("cycle1", tmp.join("cycle2")).unwrap();
+
+        let entries: Vec<CustomDirEntry> = ["folder", "link-to-folder", "dangling", "cycle1"]
+            .iter()
+            .map(|name| CustomDirEntry::from(tmp.join(name).as_path()))
+            .collect();
+
+        assert!(entries[0].symlink_target.is_none());
+        assert!(!entries[0].is_broken_symlink());
+
+        assert_eq!(entries[1].symlink_target, Some(PathBuf::from("folder")));
+        assert!(entries[1].is_dir());
+        assert!(!entries[1].is_broken_symlink());
+
+        assert!(!entries[2].is_dir());
+        assert!(entries[2].is_broken_symlink());
+
+        assert!(!entries[3].is_dir());
+        assert!(entries[3].is_broken_symlink());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_dir_entries_with_unreadable_metadata() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::fs::write(tmp.join("file"), "").unwrap();
+        // reading the metadata of a dangling symlink fails
+        std::os::unix::fs::symlink("nonexistent", tmp.join("dangling")).unwrap();
+
+        let mut entries = read_dir_entries(&tmp).unwrap();
+        entries.sort_by_key(|e| e.file_name_checked());
+
+        let names: Vec<String> = entries.iter().map(|e| e.file_name_checked()).collect();
+        assert_eq!(names, vec!["dangling", "file", "folder"]);
+        assert!(entries[0].metadata().is_none());
+        assert!(entries[1].metadata().is_some());
+        assert!(entries[2].is_dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_broken_symlink_details() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::write(tmp.join("file"), "").unwrap();
+        std::os::unix::fs::symlink("nonexistent", tmp.join("dangling")).unwrap();
+        std::os::unix::fs::symlink("file", tmp.join("link")).unwrap();
+
+        let mut entries = read_dir_entries(&tmp).unwrap();
+        entries.sort_by_key(|e| e.file_name_checked());
+        let settings = TereSettings::default();
+        assert_eq!(entry_details(&entries[0], &settings), "broken link -> nonexistent");
+        // working links show the details of the target
+        let details = entry_details(&entries[2], &settings);
+        assert!(details.starts_with('l'), "{}", details);
+    }
+
+    #[test]
+    fn test_lazy_metadata() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::fs::write(tmp.join("file"), "").unwrap();
+
+        let mut entries = read_dir_entries(&tmp).unwrap();
+        entries.sort_by_key(|e| e.file_name_checked());
+        // read the metadata of 'file' only
+        assert!(entries[0].metadata().is_some());
+
+        std::fs::remove_dir_all(&tmp).unwrap();
+
+        // the metadata of 'file' was cached, while the metadata of 'folder' can't be read any more
+        assert!(entries[0].metadata().is_some());
+        assert!(entries[1].metadata().is_none());
+        // the file type doesn't depend on the metadata
+        assert!(!entries[0].is_dir());
+        assert!(entries[1].is_dir());
+    }
+
+    #[test]
+    fn test_listing_does_not_read_metadata() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::fs::write(tmp.join("file"), "").unwrap();
+
+        let mut s = create_test_state(4, 1);
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+        s.change_dir(".").unwrap();
+
+        // the folders were sorted first using only the file type from reading the folder
+        let items = &s.ls_output_buf.all_items;
+        assert_eq!(items[1].file_name_checked(), "folder");
+        assert_eq!(items[2].file_name_checked(), "file");
+        // skip '..', whose file type is not known
+        assert!(items.iter().skip(1).all(|item| item._metadata.get().is_none()));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_drive_list() {
+        let _cwd = crate::CwdGuard::lock();
+        let mut s = create_test_state(4, 1);
+        s.current_path = PathBuf::from("/");
+        let cwd = std::env::current_dir().unwrap();
+
+        // pretend that '/' is the only drive
+        s.change_dir_with_entries(Path::new(""), vec![CustomDirEntry::from(Path::new("/"))])
+            .unwrap();
+        assert!(is_drive_list(&s.current_path));
+        assert_eq!(std::env::current_dir().unwrap(), cwd);
+        // the cursor is on the drive we came from, and it can be entered
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "/");
+        assert_eq!(s.change_dir_target("").0, PathBuf::from("/"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_drive_list_is_parent_of_drive_root() {
+        let mut s = create_test_state(4, 1);
+        let root: PathBuf = std::env::current_dir().unwrap().ancestors().last().unwrap().into();
+        s.current_path = root.clone();
+
+        assert_eq!(s.change_dir_target("..").0, PathBuf::new());
+        let drives: Vec<PathBuf> = list_drives().iter().map(|d| d.path().clone()).collect();
+        assert!(drives.contains(&root), "{:?}", drives);
+    }
+
+    #[test]
+    fn test_read_dir_entries_recursive() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("a").join("b").join("c")).unwrap();
+        std::fs::write(tmp.join("a").join("b").join("file"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(tmp.join("a"), tmp.join("link")).unwrap();
+
+        let names = |depth, exclude: &[String]| {
+            let mut names: Vec<PathBuf> = read_dir_entries_recursive(&tmp, depth, exclude, &AtomicBool::new(false))
+                .unwrap()
+                .iter()
+                .map(|e| PathBuf::from(e.file_name_checked()))
+                .collect();
+            names.retain(|n| n != Path::new("link"));
+            names.sort();
+            names
+        };
+        let ab = Path::new("a").join("b");
+        assert_eq!(names(0, &[]), vec![PathBuf::from("a")]);
+        assert_eq!(names(1, &[]), vec![PathBuf::from("a"), ab.clone()]);
+        // the symlink to 'a' is listed, but not followed
+        assert_eq!(names(5, &[]), vec![PathBuf::from("a"), ab.clone(), ab.join("c"), ab.join("file")]);
+        // the excluded folders are listed, but not read
+        assert_eq!(names(5, &["b".to_string()]), vec![PathBuf::from("a"), ab.clone()]);
+
+        // the reading can be cancelled
+        assert!(matches!(
+            read_dir_entries_recursive(&tmp, 5, &[], &AtomicBool::new(true)),
+            Err(e) if e.kind() == ErrorKind::Interrupted
+        ));
+        // also without recursive search, e.g. when a prefetched listing is not needed any more
+        assert!(matches!(
+            read_listing_entries(&tmp, &ListingOptions::default(), &AtomicBool::new(true)),
+            Err(e) if e.kind() == ErrorKind::Interrupted
+        ));
+    }
+
+    #[test]
+    fn test_gitignore() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("target")).unwrap();
+        std::fs::create_dir_all(tmp.join("sub")).unwrap();
+        for file in ["a.log", "b.txt", "target/x", "sub/c.log", "sub/d.txt"] {
+            std::fs::write(tmp.join(file), "").unwrap();
+        }
+        std::fs::write(tmp.join(".gitignore"), "*.log\ntarget/\n").unwrap();
+
+        let names = |recursive_depth| {
+            let options = ListingOptions { recursive_depth, gitignore: true, exclude: Vec::new() };
+            let mut names: Vec<PathBuf> = read_listing_entries(&tmp, &options, &AtomicBool::new(false))
+                .unwrap()
+                .iter()
+                .map(|e| PathBuf::from(e.file_name_checked()))
+                .collect();
+            names.sort();
+            names
+        };
+
+        // not a git repository, so nothing is ignored
+        assert_eq!(names(None).len(), 5);
+
+        std::fs::create_dir_all(tmp.join(".git")).unwrap();
+        let expected: Vec<PathBuf> = [".git", ".gitignore", "b.txt", "sub"].iter().map(PathBuf::from).collect();
+        assert_eq!(names(None), expected);
+        let mut expected = expected;
+        expected.push(Path::new("sub").join("d.txt"));
+        assert_eq!(names(Some(1)), expected);
+
+        // the repository is found from a subfolder too
+        let mut s = create_test_state(10, 1);
+        s.current_path = tmp.join("sub");
+        s._settings.gitignore = true;
+        s.update_ls_output_buf().unwrap();
+        let names: Vec<String> = s.ls_output_buf.all_items.iter().map(|e| e.file_name_checked()).collect();
+        assert_eq!(names, vec!["..", "d.txt"]);
+    }
+
+    #[test]
+    fn test_recursive_search() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        std::fs::create_dir_all(tmp.join("sub").join("deeper")).unwrap();
+        std::fs::write(tmp.join("sub").join("deeper").join("target.txt"), "").unwrap();
+        std::fs::write(tmp.join("top.txt"), "").unwrap();
+
+        let mut s = create_test_state(10, 1);
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+        s._settings.recursive_search = true;
+        s._settings.recursive_search_depth = 5;
+        s._settings.max_depth = 5;
+        s.change_dir(".").unwrap();
+        assert_eq!(s.num_total_items(), 5);
+        assert!(!s.depth_limit_reached());
+
+        // the shared depth limit takes precedence over the depth of the recursive search
+        s._settings.max_depth = 0;
+        s.change_dir(".").unwrap();
+        assert_eq!(s.num_total_items(), 3);
+        assert!(s.depth_limit_reached());
+        s._settings.max_depth = 1;
+        s.change_dir(".").unwrap();
+        assert_eq!(s.num_total_items(), 4);
+        assert!(s.depth_limit_reached());
+        s._settings.max_depth = 5;
+        s.change_dir(".").unwrap();
+
+        // the search matches the file name, not the folders leading to it
+        s.advance_search("t");
+        assert_eq!(s.num_matching_items(), 2);
+        s.advance_search("a");
+        assert_eq!(s.num_matching_items(), 1);
+        let target = tmp.join("sub").join("deeper").join("target.txt");
+        assert_eq!(s.path_of_item_under_cursor(), Some(target));
+        let name_start = "sub/deeper/".len();
+        assert_eq!(
+            s.get_match_locations_at_cursor_pos(s.cursor_pos),
+            Some(&vec![(name_start, name_start + 1), (name_start + 1, name_start + 2)]),
+        );
+
+        // the file filter matches the file name too
+        s.clear_search();
+        s.set_file_filter(Some(crate::settings::parse_glob("ta*").unwrap()));
+        s.update_ls_output_buf().unwrap();
+        let names: Vec<PathBuf> = s.visible_items().iter().map(|e| PathBuf::from(e.file_name_checked())).collect();
+        let deeper = Path::new("sub").join("deeper");
+        let expected = vec!["..".into(), "sub".into(), deeper.clone(), deeper.join("target.txt")];
+        assert_eq!(names, expected);
+        s.set_file_filter(None);
+        s.update_ls_output_buf().unwrap();
+
+        // entering a folder goes straight to it
+        s.clear_search();
+        s.advance_search("d");
+        s.change_dir("").unwrap();
+        assert_eq!(s.current_path, tmp.join("sub").join("deeper"));
+    }
+
+    #[test]
+    fn test_modified_filter() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        std::fs::create_dir_all(tmp.join("old.d")).unwrap();
+        let week_ago = SystemTime::now() - std::time::Duration::from_secs(7 * 24 * 60 * 60);
+        for name in ["a_new", "b_old", "c_new", "d_old"] {
+            let file = std::fs::File::create(tmp.join(name)).unwrap();
+            if name.ends_with("old") {
+                file.set_modified(week_ago).unwrap();
+            }
+        }
+
+        let mut s = create_test_state(10, 10);
+        s._settings.modified_window = crate::settings::DEFAULT_MODIFIED_WINDOW;
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+        s.change_dir(".").unwrap();
+        let names = |s: &TereAppState| -> Vec<String> {
+            s.visible_items().iter().map(|e| e.file_name_checked()).collect()
+        };
+        assert_eq!(names(&s), vec!["..", "old.d", "a_new", "b_old", "c_new", "d_old"]);
+
+        // the cursor stays on the item if it's still shown
+        s.move_cursor_to_filename("c_new");
+        s.set_modified_filter(true);
+        s.update_ls_output_buf().unwrap();
+        // folders are always shown
+        assert_eq!(names(&s), vec!["..", "old.d", "a_new", "c_new"]);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "c_new");
+
+        // clearing the filter shows everything again
+        s.set_modified_filter(false);
+        s.update_ls_output_buf().unwrap();
+        assert_eq!(names(&s), vec!["..", "old.d", "a_new", "b_old", "c_new", "d_old"]);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "c_new");
+
+        // a longer window includes the old files too
+        s._settings.modified_window = std::time::Duration::from_secs(8 * 24 * 60 * 60);
+        s.set_modified_filter(true);
+        s.update_ls_output_buf().unwrap();
+        assert_eq!(names(&s), vec!["..", "old.d", "a_new", "b_old", "c_new", "d_old"]);
+    }
+
+    #[test]
+    fn test_exclude() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        std::fs::create_dir_all(tmp.join("node_modules").join("pkg")).unwrap();
+        std::fs::create_dir_all(tmp.join("src")).unwrap();
+        std::fs::write(tmp.join("src").join("main.rs"), "").unwrap();
+        // only folders are excluded
+        std::fs::write(tmp.join("target"), "").unwrap();
+
+        let mut s = create_test_state(10, 10);
+        s._settings.exclude = vec!["node_modules".into(), "target".into()];
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+        s.change_dir(".").unwrap();
+        let names = |s: &TereAppState| -> Vec<String> {
+            s.visible_items().iter().map(|e| e.file_name_checked()).collect()
+        };
+        assert_eq!(names(&s), vec!["..", "src", "target"]);
+        assert_eq!(s.num_excluded(), 1);
+
+        s.move_cursor_to_filename("src");
+        s.set_show_excluded(true);
+        s.update_ls_output_buf().unwrap();
+        assert_eq!(names(&s), vec!["..", "node_modules", "src", "target"]);
+        assert_eq!(s.num_excluded(), 0);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "src");
+        s.set_show_excluded(false);
+        s.update_ls_output_buf().unwrap();
+
+        // with recursive search, the excluded folders are not read at all
+        s._settings.recursive_search = true;
+        s._settings.recursive_search_depth = 5;
+        s._settings.max_depth = 5;
+        s.change_dir(".").unwrap();
+        let main_rs = Path::new("src").join("main.rs").to_string_lossy().to_string();
+        assert_eq!(names(&s), vec!["..", "src", &main_rs, "target"]);
+        assert_eq!(s.num_excluded(), 1);
+
+        s.set_show_excluded(true);
+        s.update_ls_output_buf().unwrap();
+        let pkg = Path::new("node_modules").join("pkg").to_string_lossy().to_string();
+        assert_eq!(names(&s), vec!["..", "node_modules", &pkg, "src", &main_rs, "target"]);
+    }
+
+    #[test]
+    fn test_save_changed_prefs() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let prefs_file = tmp.join("config").join("prefs.json");
+
+        let mut s = create_test_state(10, 10);
+        s._settings.prefs_file = Some(prefs_file.clone());
+        s._settings.sort_mode = SortMode::Created;
+        s.initial_prefs = s.settings().pref_args();
+
+        // nothing is written if nothing was changed
+        s.on_exit().unwrap();
+        assert!(!prefs_file.exists());
+
+        // the preferences saved earlier are kept, and the ones given at startup are not saved
+        let mut earlier = Prefs::new();
+        earlier.insert("numbers".to_string(), vec!["--numbers".to_string()]);
+        earlier.insert("preview".to_string(), vec!["--preview".to_string()]);
+        write_prefs(&prefs_file, &earlier).unwrap();
+        s._settings.preview_enabled = true;
+        s._settings.listing_mode = ListingMode::FoldersOnly;
+        s.on_exit().unwrap();
+        let prefs = read_prefs(&prefs_file).unwrap();
+        let names: Vec<_> = prefs.keys().map(|k| k.as_str()).collect();
+        assert_eq!(names, vec!["listing-mode", "numbers", "preview"]);
+        assert_eq!(prefs["listing-mode"], vec!["--folders-only"]);
+        assert_eq!(prefs["preview"], vec!["--preview"]);
+
+        // turning a preference off is saved too
+        s.initial_prefs = s.settings().pref_args();
+        s._settings.preview_enabled = false;
+        s.on_exit().unwrap();
+        assert_eq!(read_prefs(&prefs_file).unwrap()["preview"], vec!["--no-preview"]);
+    }
+
+    #[test]
+    fn test_change_dir_parent_restores_cursor() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        for i in 0..10 {
+            std::fs::create_dir_all(tmp.join(format!("folder{}", i)).join("child")).unwrap();
+        }
+
+        let mut s = create_test_state(4, 1);
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+
+        s.change_dir("folder7").unwrap();
+        assert_eq!(s.current_path, tmp.join("folder7"));
+        s.change_dir("..").unwrap();
+        assert_eq!(s.current_path, tmp);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "folder7");
+        // the folder is below the window, so we've scrolled down
+        assert_eq!(s.cursor_pos_to_visible_item_index(s.cursor_pos), 8);
+        assert!(s.scroll_pos > 0);
+
+        // going up from the starting folder, which is not in the history
+        s.change_dir("folder7/child").unwrap();
+        s.change_dir("..").unwrap();
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "child");
+
+        // if the folder is filtered out of the parent listing, the cursor goes to the top
+        s.change_dir("..").unwrap();
+        s.change_dir("folder3").unwrap();
+        s._settings.listing_mode = ListingMode::FilesOnly;
+        s.change_dir("..").unwrap();
+        assert_eq!(s.cursor_pos, 0);
+        assert_eq!(s.scroll_pos, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "archives")]
+    fn test_change_dir_into_archive() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        archive::tests::write_test_zip(
+            &tmp.join("files.zip"),
+            &[("folder/inner.txt", "hello"), ("top.txt", "")],
+        );
+
+        let mut s = create_test_state(10, 1);
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+        s.change_dir(".").unwrap();
+        s.move_cursor_to_filename("files.zip");
+        assert!(s.is_file_under_cursor());
+        assert!(s.is_archive_under_cursor());
+
+        s.change_dir("files.zip").unwrap();
+        assert_eq!(s.current_path, tmp.join("files.zip"));
+        // the working directory is the folder that contains the archive
+        assert_eq!(std::env::current_dir().unwrap(), tmp.canonicalize().unwrap());
+        let names: Vec<_> = s.visible_items().iter().map(|e| e.file_name_checked()).collect();
+        assert_eq!(names, vec!["..", "folder", "top.txt"]);
+        assert_eq!(s.preview_item_under_cursor(10), vec!["inner.txt"]);
+
+        s.change_dir("folder").unwrap();
+        assert_eq!(s.current_path, tmp.join("files.zip/folder"));
+        s.update_ls_output_buf().unwrap();
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "inner.txt");
+        let extracted = s.real_path_of_item_under_cursor().unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&extracted).unwrap(), "hello");
+        assert!(!extracted.starts_with(&tmp));
+
+        // a folder that doesn't exist in the archive is an error, not a move to an ancestor
+        assert!(s.change_dir(tmp.join("files.zip/missing").to_str().unwrap()).is_err());
+
+        // going up from the archive returns to the folder that contains it
+        s.change_dir("..").unwrap();
+        s.change_dir("..").unwrap();
+        assert_eq!(s.current_path, tmp);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "files.zip");
+    }
+
+    #[test]
+    fn test_change_dir_cursor_memory() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        std::fs::create_dir_all(tmp.join("a")).unwrap();
+        std::fs::create_dir_all(tmp.join("b")).unwrap();
+        for i in 0..6 {
+            std::fs::write(tmp.join("a").join(format!("file{}", i)), "").unwrap();
+        }
+
+        let mut s = create_test_state(10, 1);
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+
+        s.change_dir("a").unwrap();
+        s.move_cursor_to(4);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "file3");
+
+        s.change_dir("../b").unwrap();
+        s.change_dir("../a").unwrap();
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "file3");
+
+        // if items have been removed, the cursor is clamped to the end of the listing
+        for i in 2..6 {
+            std::fs::remove_file(tmp.join("a").join(format!("file{}", i))).unwrap();
+        }
+        s.change_dir("../b").unwrap();
+        s.change_dir("../a").unwrap();
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "file1");
+    }
+
+    #[test]
+    fn test_change_dir_search_memory() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        std::fs::create_dir_all(tmp.join("a")).unwrap();
+        std::fs::create_dir_all(tmp.join("b")).unwrap();
+        std::fs::write(tmp.join("a").join("foo"), "").unwrap();
+        std::fs::write(tmp.join("a").join("bar"), "").unwrap();
+
+        let mut s = create_test_state(10, 1);
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+        s._settings.remember_search = true;
+
+        s.change_dir("a").unwrap();
+        s.advance_search("ba");
+        s.change_dir("../b").unwrap();
+        assert!(!s.is_searching());
+        s.change_dir("../a").unwrap();
+        assert_eq!(s.search_string(), "ba");
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "bar");
+
+        // a search that doesn't match anything any more is restored too
+        std::fs::remove_file(tmp.join("a").join("bar")).unwrap();
+        s.change_dir("../b").unwrap();
+        s.change_dir("../a").unwrap();
+        assert_eq!(s.search_string(), "ba");
+        assert_eq!(s.num_matching_items(), 0);
+
+        // clearing the search forgets it
+        s.clear_search();
+        s.change_dir("../b").unwrap();
+        s.change_dir("../a").unwrap();
+        assert!(!s.is_searching());
+    }
+
+    #[test]
+    fn test_format_header_full() {
+        let home = Path::new("/home/user");
+        let path = Path::new("/home/user/projects/tere");
+        assert_eq!(
+            format_header_with_home(path, Some(home), 5, HeaderFormat::Full),
+            "/home/user/projects/tere",
+        );
+    }
+
+    #[test]
+    fn test_format_header_home_tilde() {
+        let home = Some(Path::new("/home/user"));
+        let fmt = |path| format_header_with_home(Path::new(path), home, 100, HeaderFormat::HomeTilde);
+        assert_eq!(fmt("/home/user/projects/tere"), "~/projects/tere");
+        assert_eq!(fmt("/home/user"), "~");
+        assert_eq!(fmt("/home/username"), "/home/username");
+        assert_eq!(fmt("/usr/share"), "/usr/share");
+        assert_eq!(fmt("/"), "/");
+
+        // the home folder is not replaced if it's the root
+        assert_eq!(
+            format_header_with_home(Path::new("/usr"), Some(Path::new("/")), 100, HeaderFormat::HomeTilde),
+            "/usr",
+        );
+        assert_eq!(
+            format_header_with_home(Path::new("/home/user"), None, 100, HeaderFormat::HomeTilde),
+            "/home/user",
+        );
+    }
+
+    #[test]
+    fn test_format_header_shortened() {
+        let home = Some(Path::new("/home/user"));
+        let fmt = |path, width| format_header_with_home(Path::new(path), home, width, HeaderFormat::Shortened);
+
+        // no need to shorten if the path fits
+        assert_eq!(fmt("/home/user/projects/vendor/tere", 100), "~/projects/vendor/tere");
+        // shorten from the left, only as much as needed
+        assert_eq!(fmt("/home/user/projects/vendor/tere", 16), "~/p/vendor/tere");
+        assert_eq!(fmt("/home/user/projects/vendor/tere", 10), "~/p/v/tere");
+        // the last folder is never shortened
+        assert_eq!(fmt("/home/user/projects/vendor/tere", 5), "~/p/v/tere");
+        assert_eq!(fmt("/home/user/.config/tere", 5), "~/.c/tere");
+        assert_eq!(fmt("/usr/share/doc", 5), "/u/s/doc");
+        assert_eq!(fmt("/home/user/äö/tere", 5), "~/ä/tere");
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let rel = |path, base| relative_path(Path::new(path), Path::new(base));
+        assert_eq!(rel("/foo/bar/baz", "/foo"), Path::new("bar/baz"));
+        assert_eq!(rel("/foo", "/foo/bar/baz"), Path::new("../.."));
+        assert_eq!(rel("/foo/qux", "/foo/bar/baz"), Path::new("../../qux"));
+        assert_eq!(rel("/foo", "/foo"), Path::new("."));
+        assert_eq!(rel("/", "/foo"), Path::new(".."));
+    }
+
+    #[test]
+    fn test_path_to_copy() {
+        let mut s = create_test_state_with_buf(10, strings_to_ls_buf(vec!["..", "foo", "bar"]));
+        s.current_path = "/home/user/projects".into();
+        s.initial_path = "/home/user".into();
+
+        // the cursor is on '..', copy the current folder
+        assert_eq!(s.path_to_copy(), Path::new("/home/user/projects"));
+        s._settings.copy_path_format = CopyPathFormat::Relative;
+        assert_eq!(s.path_to_copy(), Path::new("projects"));
+
+        s.move_cursor(1, false);
+        assert_eq!(s.path_to_copy(), Path::new("projects/foo"));
+        s._settings.copy_path_format = CopyPathFormat::Absolute;
+        assert_eq!(s.path_to_copy(), Path::new("/home/user/projects/foo"));
+    }
+
+    #[test]
+    fn test_path_to_print() {
+        let mut s = create_test_state(10, 1);
+        s.initial_path = "/home/user".into();
+        let print = |s: &TereAppState, path: &str| s.path_to_print(Path::new(path));
+
+        assert_eq!(print(&s, "/home/user/projects"), Path::new("/home/user/projects"));
+
+        // the base is an ancestor of the selected path
+        s._settings.relative_to = Some(".".into());
+        assert_eq!(print(&s, "/home/user/projects/foo"), Path::new("projects/foo"));
+        assert_eq!(print(&s, "/home/user"), Path::new("."));
+        // the base is unrelated to the selected path, or below it
+        assert_eq!(print(&s, "/tmp/foo"), Path::new("/tmp/foo"));
+        assert_eq!(print(&s, "/home"), Path::new("/home"));
+        assert_eq!(print(&s, "/home/username"), Path::new("/home/username"));
+
+        // a relative base is relative to the starting folder
+        s._settings.relative_to = Some("projects".into());
+        assert_eq!(print(&s, "/home/user/projects/foo"), Path::new("foo"));
+        s._settings.relative_to = Some("..".into());
+        assert_eq!(print(&s, "/home/other"), Path::new("other"));
+        s._settings.relative_to = Some("/tmp".into());
+        assert_eq!(print(&s, "/tmp/foo/bar"), Path::new("foo/bar"));
+        assert_eq!(print(&s, "/home/user/foo"), Path::new("/home/user/foo"));
+    }
+
+    #[test]
+    fn test_change_dir_deleted() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        std::fs::create_dir_all(tmp.join("foo").join("bar")).unwrap();
+
+        let mut s = create_test_state(10, 1);
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+        assert_eq!(s.change_dir("foo/bar").unwrap(), None);
+
+        // the current folder and its parent disappear
+        std::fs::remove_dir_all(tmp.join("foo")).unwrap();
+
+        let msg = s.change_dir(".").unwrap().unwrap();
+        assert_eq!(s.current_path, tmp);
+        assert!(msg.contains("no longer exists"), "{}", msg);
+        assert_eq!(s.num_total_items(), 1); // just '..'
+
+        // if only the target folder has been deleted, it's an error as usual
+        std::fs::create_dir(tmp.join("baz")).unwrap();
+        s.change_dir(".").unwrap();
+        std::fs::remove_dir(tmp.join("baz")).unwrap();
+        assert!(s.change_dir("baz").is_err());
+        assert_eq!(s.current_path, tmp);
+    }
+
+    #[test]
+    fn test_list_folder_with_file_filter() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("src.d")).unwrap();
+        for name in ["main.rs", "lib.rs", "README.md", "image.png"] {
+            std::fs::write(tmp.join(name), "").unwrap();
+        }
+        let names = |settings: &TereSettings| -> Vec<String> {
+            list_folder(&tmp, settings)
+                .unwrap()
+                .iter()
+                .map(|e| e.file_name_checked())
+                .collect()
+        };
+
+        let mut settings = TereSettings {
+            file_filter: Some(crate::settings::parse_glob("*.rs").unwrap()),
+            ..Default::default()
+        };
+        // folders are always shown
+        assert_eq!(names(&settings), vec!["src.d", "lib.rs", "main.rs"]);
+
+        settings.file_filter = Some(crate::settings::parse_glob("*.{md,png}").unwrap());
+        assert_eq!(names(&settings), vec!["src.d", "image.png", "README.md"]);
+
+        // the filter composes with the listing mode
+        settings.listing_mode = ListingMode::FilesOnly;
+        assert_eq!(names(&settings), vec!["image.png", "README.md"]);
+    }
+
+    #[test]
+    fn test_change_dir_target_does_not_change_state() {
+        let _cwd = crate::CwdGuard::lock();
+        let mut s = create_test_state(10, 3);
+        s.advance_search("1");
+        let (target, message) = s.change_dir_target("/nonexistent/tere-test-folder");
+        assert_eq!(target, PathBuf::from("/nonexistent/tere-test-folder"));
+        assert_eq!(message, None);
+
+        // a failed read leaves the previous listing and search in place
+        assert!(s.change_dir("/nonexistent/tere-test-folder").is_err());
+        assert_eq!(s.search_string(), "1");
+        assert_eq!(s.num_total_items(), 3);
+    }
+
+    #[test]
+    fn test_footer_template() {
+        let fill = |template: &str| {
+            template.parse::<FooterTemplate>().map(|t| t.fill(|name| name.to_uppercase()))
+        };
+        assert_eq!(fill("{cursor}/{total} ({dirs}d {files}f)").unwrap(), "CURSOR/TOTAL (DIRSd FILESf)");
+        assert_eq!(fill("").unwrap(), "");
+        assert_eq!(fill("{{size}} {{{size}}}").unwrap(), "{size} {SIZE}");
+
+        assert_eq!(
+            fill("{cursor} {nope}").unwrap_err(),
+            "unknown placeholder '{nope}', the placeholders are {cursor}, {total}, {dirs}, {files}, {size}, {match}, {matches}"
+        );
+        assert_eq!(fill("{cursor").unwrap_err(), "'{' without a matching '}'");
+        assert_eq!(fill("cursor}").unwrap_err(), "'}' without a matching '{'");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0, SizeFormat::Binary), "0 B");
+        assert_eq!(format_size(1023, SizeFormat::Binary), "1023 B");
+        assert_eq!(format_size(1024, SizeFormat::Binary), "1.0 KiB");
+        assert_eq!(format_size(1536, SizeFormat::Binary), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024, SizeFormat::Binary), "5.0 GiB");
+
+        assert_eq!(format_size(999, SizeFormat::Decimal), "999 B");
+        assert_eq!(format_size(1024, SizeFormat::Decimal), "1.0 kB");
+        assert_eq!(format_size(1500, SizeFormat::Decimal), "1.5 kB");
+        assert_eq!(format_size(2_500_000_000, SizeFormat::Decimal), "2.5 GB");
+
+        assert_eq!(format_size(0, SizeFormat::Bytes), "0 B");
+        assert_eq!(format_size(5 * 1024 * 1024, SizeFormat::Bytes), "5242880 B");
+    }
+
+    #[test]
+    fn test_format_time() {
+        use chrono::TimeZone;
+        use std::time::Duration;
+
+        let time: SystemTime = chrono::Local.with_ymd_and_hms(2024, 5, 1, 13, 45, 30).unwrap().into();
+        let now = time + Duration::from_secs(2 * 60 * 60 + 59);
+
+        assert_eq!(format_time(time, now, &TimeStyle::Iso), "2024-05-01 13:45");
+        let style = TimeStyle::Format("%d.%m.%Y %H:%M:%S".to_string());
+        assert_eq!(format_time(time, now, &style), "01.05.2024 13:45:30");
+
+        let relative = |secs| format_time(time, time + Duration::from_secs(secs), &TimeStyle::Relative);
+        assert_eq!(relative(0), "just now");
+        assert_eq!(relative(59), "just now");
+        assert_eq!(relative(60), "1m ago");
+        assert_eq!(relative(2 * 60 * 60 + 59), "2h ago");
+        assert_eq!(relative(3 * 24 * 60 * 60), "3d ago");
+        assert_eq!(relative(14 * 24 * 60 * 60), "2w ago");
+        assert_eq!(relative(90 * 24 * 60 * 60), "3mo ago");
+        assert_eq!(relative(800 * 24 * 60 * 60), "2y ago");
+        assert_eq!(format_time(now, time, &TimeStyle::Relative), "in 2h");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_format_mode() {
+        assert_eq!(format_mode(0o100644, false), "-rw-r--r--");
+        assert_eq!(format_mode(0o040755, false), "drwxr-xr-x");
+        assert_eq!(format_mode(0o040755, true), "lrwxr-xr-x");
+        assert_eq!(format_mode(0o041777, false), "drwxrwxrwt");
+        assert_eq!(format_mode(0o104755, false), "-rwsr-xr-x");
+        assert_eq!(format_mode(0o102644, false), "-rw-r-Sr--");
+    }
+
+    #[test]
+    fn test_page_up_down_at_boundaries() {
+        // paging without wrapping stops exactly at the first or last item, with the listing
+        // scrolled as far as possible, no matter where the page jump would have ended up
+        for win_h in [1usize, 2, 5, 10] {
+            // the same page size as in the UI
+            let page = isize::try_from(win_h.saturating_sub(1).max(1)).unwrap();
+            for n_items in [1, 2, 5, 9, 10, 11, 25] {
+                for scroll_off in [0, 3] {
+                    for start in 0..n_items {
+                        let mut s = create_test_state(win_h, n_items);
+                        s._settings.scroll_off = scroll_off;
+                        s.move_cursor_to(start);
+
+                        s.move_cursor(-page, false);
+                        let expected = start.saturating_sub(page as usize);
+                        assert_eq!(s.cursor_pos + s.scroll_pos, expected);
+                        if expected == 0 {
+                            assert_eq!((s.cursor_pos, s.scroll_pos), (0, 0));
+                        }
+
+                        s.move_cursor_to(start);
+                        s.move_cursor(page, false);
+                        let expected = (start + page as usize).min(n_items - 1);
+                        assert_eq!(s.cursor_pos + s.scroll_pos, expected);
+                        if expected == n_items - 1 {
+                            // the last item is on the last row of the window
+                            assert_eq!(s.cursor_pos, n_items.min(win_h) - 1);
+                        }
+                    }
+                }
+            }
+        }
+
+        // huge jumps don't overflow
+        let mut s = create_test_state(5, 10);
+        s.move_cursor(isize::MAX, false);
+        assert_eq!(s.cursor_pos + s.scroll_pos, 9);
+        s.move_cursor(isize::MIN, false);
+        assert_eq!(s.cursor_pos + s.scroll_pos, 0);
+    }
+
+    #[test]
+    fn test_move_cursor_zero_height_window() {
+        // the main window has no rows if the terminal is very short
+        let mut s = create_test_state(0, 5);
+        s.move_cursor(1, false);
+        s.move_cursor(-1, true);
+        s.move_cursor_to(4);
+        assert_eq!(s.scroll_pos + s.cursor_pos, 4);
+    }
+
+    #[test]
+    fn test_group_separator() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("d1")).unwrap();
+        std::fs::create_dir_all(tmp.join("d2")).unwrap();
+        for name in ["f1", "f2", "f3"] {
+            std::fs::write(tmp.join(name), "").unwrap();
+        }
+        let buf: LsBufType = ["d1", "d2", "f1", "f2", "f3"]
+            .iter()
+            .map(|name| CustomDirEntry::from(tmp.join(name).as_path()))
+            .collect::<Vec<_>>()
+            .into();
+        let mut s = create_test_state_with_buf(4, buf);
+        assert_eq!(s.separator_screen_row(), None);
+        s._settings.group_separator = true;
+
+        // the separator is drawn on row 2, and the files below it
+        assert_eq!(s.separator_screen_row(), Some(2));
+        assert_eq!(s.screen_row_of_cursor_pos(1), 1);
+        assert_eq!(s.screen_row_of_cursor_pos(2), 3);
+        assert_eq!(s.cursor_pos_at_screen_row(2), None);
+        assert_eq!(s.cursor_pos_at_screen_row(3), Some(2));
+        assert!(s.is_valid_screen_row(2));
+        assert!(!s.is_valid_screen_row(3));
+
+        // the cursor skips the separator
+        s.move_cursor(2, false);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "f1");
+        assert_eq!(s.scroll_pos, 0);
+
+        // the separator takes up a row, so we have to scroll earlier
+        s.move_cursor(1, false);
+        assert_eq!(s.get_item_under_cursor().unwrap().file_name_checked(), "f2");
+        assert_eq!(s.scroll_pos, 1);
+        assert_eq!(s.separator_screen_row(), Some(1));
+        s.move_cursor(1, false);
+        assert_eq!(s.scroll_pos, 2);
+        assert_eq!(s.separator_screen_row(), None);
+
+        // the footer counts are not affected
+        assert_eq!(s.num_visible_items(), 5);
+    }
+
+    #[test]
+    fn test_total_size() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let _cwd = crate::CwdGuard::lock();
+        std::fs::create_dir_all(tmp.join("folder").join("inner")).unwrap();
+        std::fs::write(tmp.join("a"), "hello").unwrap();
+        std::fs::write(tmp.join("b"), "world!").unwrap();
+        std::fs::write(tmp.join("folder").join("c"), "123").unwrap();
+        std::fs::write(tmp.join("folder").join("inner").join("d"), "4567").unwrap();
+
+        let mut s = create_test_state(10, 1);
+        s.current_path = tmp.clone();
+        s.history = HistoryTree::from_abs_path(&tmp);
+        s.update_ls_output_buf().unwrap();
+        assert_eq!(s.total_size(), None);
+
+        s._settings.show_total_size = true;
+        s.change_dir(".").unwrap();
+        // the folders are not counted
+        assert_eq!(s.total_size(), Some(11));
+
+        assert_eq!(dir_size_recursive(&tmp, 2, &[]), (18, false));
+        assert_eq!(dir_size_recursive(&tmp.join("nonexistent"), 2, &[]), (0, false));
+        // the files deeper than the limit are not counted
+        assert_eq!(dir_size_recursive(&tmp, 1, &[]), (14, true));
+        assert_eq!(dir_size_recursive(&tmp, 0, &[]), (11, true));
+        // neither are the files in excluded folders
+        assert_eq!(dir_size_recursive(&tmp, 2, &["inner".to_string()]), (14, false));
+    }
+
+    #[test]
+    fn test_sibling_dir() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        for name in ["a", "b", "c"] {
+            std::fs::create_dir_all(tmp.join("parent").join(name)).unwrap();
+        }
+        std::fs::write(tmp.join("parent").join("b-file"), "").unwrap();
+        std::fs::create_dir_all(tmp.join("only").join("child")).unwrap();
+
+        let mut s = create_test_state(10, 1);
+        s.current_path = tmp.join("parent").join("b");
+        assert_eq!(s.sibling_dir(true).unwrap(), Some(tmp.join("parent").join("c")));
+        assert_eq!(s.sibling_dir(false).unwrap(), Some(tmp.join("parent").join("a")));
+
+        // wrap around at the ends, unless wrapping is disabled
+        s.current_path = tmp.join("parent").join("c");
+        assert_eq!(s.sibling_dir(true).unwrap(), Some(tmp.join("parent").join("a")));
+        s._settings.no_wrap = true;
+        assert_eq!(s.sibling_dir(true).unwrap(), None);
+        assert_eq!(s.sibling_dir(false).unwrap(), Some(tmp.join("parent").join("b")));
+
+        s._settings.no_wrap = false;
+        s.current_path = tmp.join("only").join("child");
+        assert_eq!(s.sibling_dir(true).unwrap(), None);
+    }
+
+    #[test]
+    fn test_change_dir_error_contains_path() {
+        let _cwd = crate::CwdGuard::lock();
+        let mut s = create_test_state(10, 3);
+        let err = s.change_dir("/nonexistent/tere-test-folder").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().contains("'/nonexistent/tere-test-folder'"), "{}", err);
+        assert_eq!(s.current_path, PathBuf::from("/"));
+    }
+
+    #[test]
+    fn test_path_of_item_under_cursor() {
+        let mut s = create_test_state_with_buf(10, strings_to_ls_buf(vec!["..", "foo", "bar"]));
+        s.current_path = "/some/folder".into();
+
+        assert_eq!(s.path_of_item_under_cursor(), Some(PathBuf::from("/some")));
+        s.move_cursor(1, false);
+        assert_eq!(s.path_of_item_under_cursor(), Some(PathBuf::from("/some/folder/foo")));
+        s.move_cursor(1, false);
+        assert_eq!(s.path_of_item_under_cursor(), Some(PathBuf::from("/some/folder/bar")));
+
+        let s = create_test_state_with_buf(10, strings_to_ls_buf(Vec::<&str>::new()));
+        assert_eq!(s.path_of_item_under_cursor(), None);
+        assert!(!s.is_file_under_cursor());
+    }
+
 }
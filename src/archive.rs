@@ -0,0 +1,268 @@
+//! Browsing the contents of zip files as if they were folders. Inside an archive, the current path
+//! is a virtual path like `/some/folder/file.zip/inner/folder`, which is split into the path of
+//! the archive file and the path inside it. The archive is only read, never modified. This needs
+//! the `archives` feature, and without it, archives are just files.
+
+use std::io::{Error as IOError, ErrorKind, Result as IOResult};
+use std::path::{Path, PathBuf};
+
+use super::CustomDirEntry;
+
+/// Return true if `path` is named like an archive that can be browsed. This doesn't check whether
+/// the file exists.
+pub fn is_archive(path: &Path) -> bool {
+    cfg!(feature = "archives") && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// If `path` is an archive or inside one, split it into the path of the archive file and the path
+/// inside the archive, which is empty for the archive itself.
+pub fn split_archive_path(path: &Path) -> Option<(&Path, &Path)> {
+    let archive = path.ancestors().find(|p| is_archive(p) && p.is_file())?;
+    // ok to unwrap, the archive is an ancestor of the path
+    Some((archive, path.strip_prefix(archive).unwrap()))
+}
+
+/// The names of the folders (and the file) that make up a path inside an archive
+#[cfg(feature = "archives")]
+fn inner_components(inner: &Path) -> Vec<String> {
+    inner
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(feature = "archives")]
+fn open_zip(archive: &Path) -> IOResult<zip::ZipArchive<std::fs::File>> {
+    zip::ZipArchive::new(std::fs::File::open(archive)?).map_err(zip_error)
+}
+
+#[cfg(feature = "archives")]
+fn zip_error(e: zip::result::ZipError) -> IOError {
+    match e {
+        zip::result::ZipError::Io(e) => e,
+        e => IOError::new(ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+/// List the contents of the folder `inner` inside the zip file `archive`. Zip files don't always
+/// have entries for folders, so the folders are also inferred from the paths of the files in them.
+#[cfg(feature = "archives")]
+pub fn read_archive_entries(archive: &Path, inner: &Path) -> IOResult<Vec<CustomDirEntry>> {
+    let zip = open_zip(archive)?;
+    let folder = inner_components(inner);
+    let mut folder_exists = folder.is_empty();
+    // the names of the items in the folder, and whether they are folders
+    let mut children = std::collections::BTreeMap::new();
+    for name in zip.file_names() {
+        let parts: Vec<&str> = name.split('/').filter(|p| !p.is_empty()).collect();
+        // skip paths that would point outside of the archive
+        if parts.iter().any(|p| *p == "." || *p == "..")
+            || parts.len() < folder.len()
+            || parts[..folder.len()] != folder[..]
+        {
+            continue;
+        }
+        if parts.len() == folder.len() {
+            // this is the entry of the folder itself, unless it's a file with the same path
+            folder_exists |= name.ends_with('/');
+            continue;
+        }
+        folder_exists = true;
+        let is_dir = parts.len() > folder.len() + 1 || name.ends_with('/');
+        *children.entry(parts[folder.len()]).or_insert(false) |= is_dir;
+    }
+
+    if !folder_exists {
+        return Err(IOError::new(
+            ErrorKind::NotFound,
+            format!("No folder '{}' in the archive", inner.display()),
+        ));
+    }
+    let folder_path = archive.join(inner);
+    Ok(children
+        .into_iter()
+        .map(|(name, is_dir)| CustomDirEntry {
+            _path: folder_path.join(name),
+            _file_type: None,
+            // the path doesn't exist on the file system, so there's no metadata
+            _metadata: std::cell::OnceCell::from(None),
+            symlink_target: None,
+            _file_name: name.into(),
+            _archive_is_dir: Some(is_dir),
+            _fitted_name: std::cell::RefCell::new(None),
+        })
+        .collect())
+}
+
+#[cfg(not(feature = "archives"))]
+pub fn read_archive_entries(_archive: &Path, _inner: &Path) -> IOResult<Vec<CustomDirEntry>> {
+    Err(IOError::new(ErrorKind::Unsupported, "tere was built without support for archives"))
+}
+
+/// The private temporary folder that the files inside archives are extracted to. The folder has a
+/// random name and it's only accessible by the current user. It's created when the first file is
+/// extracted, and removed with everything in it when this is dropped.
+#[derive(Default)]
+pub struct ExtractDir {
+    #[cfg(feature = "archives")]
+    dir: std::cell::OnceCell<tempfile::TempDir>,
+}
+
+impl ExtractDir {
+    /// Extract the file `inner` from the zip file `archive` to the temporary folder, and return
+    /// the path of the extracted file.
+    #[cfg(feature = "archives")]
+    pub fn extract(&self, archive: &Path, inner: &Path) -> IOResult<PathBuf> {
+        let mut zip = open_zip(archive)?;
+        let parts = inner_components(inner);
+        let mut file = zip.by_name(&parts.join("/")).map_err(zip_error)?;
+
+        let dir = match self.dir.get() {
+            Some(dir) => dir,
+            None => {
+                let mut builder = tempfile::Builder::new();
+                builder.prefix("tere-archives-");
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    builder.permissions(std::fs::Permissions::from_mode(0o700));
+                }
+                let dir = builder.tempdir()?;
+                self.dir.get_or_init(|| dir)
+            }
+        };
+        let mut dest = dir.path().join(archive.file_name().unwrap_or_default());
+        dest.extend(&parts);
+        // ok to unwrap, the path has at least the temporary folder as its parent
+        std::fs::create_dir_all(dest.parent().unwrap())?;
+        // The same file may have been extracted earlier. Never write through an existing path,
+        // even though nobody else can create files in the folder.
+        match std::fs::remove_file(&dest) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let mut out = std::fs::OpenOptions::new().write(true).create_new(true).open(&dest)?;
+        std::io::copy(&mut file, &mut out)?;
+        Ok(dest)
+    }
+
+    #[cfg(not(feature = "archives"))]
+    pub fn extract(&self, _archive: &Path, _inner: &Path) -> IOResult<PathBuf> {
+        Err(IOError::new(ErrorKind::Unsupported, "tere was built without support for archives"))
+    }
+
+    /// Don't remove `path` on exit if it was extracted to the temporary folder, so that it can be
+    /// used by whatever gets the path from tere. The folder is left in place then.
+    #[cfg(feature = "archives")]
+    pub fn keep(&mut self, path: &Path) {
+        if self.dir.get().is_some_and(|dir| path.starts_with(dir.path())) {
+            // ok to unwrap, the folder was just checked to exist. Its path is not needed, since
+            // it's a part of `path`.
+            let _ = self.dir.take().unwrap().keep();
+        }
+    }
+
+    #[cfg(not(feature = "archives"))]
+    pub fn keep(&mut self, _path: &Path) {}
+}
+
+#[cfg(all(test, feature = "archives"))]
+pub mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Write a zip file with the given files (with '/' as the separator) and their contents
+    pub fn write_test_zip(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, contents) in files {
+            if name.ends_with('/') {
+                zip.add_directory(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            } else {
+                zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+                zip.write_all(contents.as_bytes()).unwrap();
+            }
+        }
+        zip.finish().unwrap();
+    }
+
+    fn listing(archive: &Path, inner: &str) -> Vec<(String, bool)> {
+        read_archive_entries(archive, Path::new(inner))
+            .unwrap()
+            .iter()
+            .map(|e| (e.file_name_checked(), e.is_dir()))
+            .collect()
+    }
+
+    #[test]
+    fn test_read_archive_entries() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let archive = tmp.join("test.ZIP");
+        write_test_zip(
+            &archive,
+            &[
+                ("file.txt", "hello"),
+                ("empty/", ""),
+                // there's no entry for the folder 'a', only for its contents
+                ("a/b/c.txt", "world"),
+                ("a/d.txt", ""),
+                ("../evil.txt", ""),
+            ],
+        );
+
+        assert_eq!(
+            listing(&archive, ""),
+            vec![("a".into(), true), ("empty".into(), true), ("file.txt".into(), false)]
+        );
+        assert_eq!(listing(&archive, "a"), vec![("b".into(), true), ("d.txt".into(), false)]);
+        assert_eq!(listing(&archive, "empty"), vec![]);
+        let entries = read_archive_entries(&archive, Path::new("a/b")).unwrap();
+        assert_eq!(entries[0].path(), &archive.join("a/b/c.txt"));
+        assert!(read_archive_entries(&archive, Path::new("nothing")).is_err());
+        assert!(read_archive_entries(&archive, Path::new("file.txt")).is_err());
+        assert!(read_archive_entries(&tmp.join("missing.zip"), Path::new("")).is_err());
+
+        assert_eq!(split_archive_path(&archive), Some((archive.as_path(), Path::new(""))));
+        assert_eq!(
+            split_archive_path(&archive.join("a/b")),
+            Some((archive.as_path(), Path::new("a/b")))
+        );
+        assert_eq!(split_archive_path(&tmp), None);
+        // a folder named like an archive is just a folder
+        std::fs::create_dir_all(tmp.join("folder.zip")).unwrap();
+        assert_eq!(split_archive_path(&tmp.join("folder.zip")), None);
+
+        let extract_dir = ExtractDir::default();
+        let extracted = extract_dir.extract(&archive, Path::new("a/b/c.txt")).unwrap();
+        assert_eq!(extracted.file_name().unwrap(), "c.txt");
+        assert_eq!(std::fs::read_to_string(&extracted).unwrap(), "world");
+        assert!(extract_dir.extract(&archive, Path::new("a/nothing.txt")).is_err());
+        // extracting again replaces the file, without writing through it
+        std::fs::write(&extracted, "changed").unwrap();
+        assert_eq!(extract_dir.extract(&archive, Path::new("a/b/c.txt")).unwrap(), extracted);
+        assert_eq!(std::fs::read_to_string(&extracted).unwrap(), "world");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let folder = extracted.ancestors().nth(4).unwrap();
+            assert!(folder.file_name().unwrap().to_str().unwrap().starts_with("tere-archives-"));
+            assert_eq!(folder.metadata().unwrap().permissions().mode() & 0o777, 0o700);
+        }
+
+        // the folder is removed on drop, unless the file is kept
+        let other = extract_dir.extract(&archive, Path::new("file.txt")).unwrap();
+        drop(extract_dir);
+        assert!(!extracted.exists());
+        assert!(!other.exists());
+        let mut extract_dir = ExtractDir::default();
+        let extracted = extract_dir.extract(&archive, Path::new("file.txt")).unwrap();
+        extract_dir.keep(&tmp);
+        extract_dir.keep(&extracted);
+        drop(extract_dir);
+        assert_eq!(std::fs::read_to_string(&extracted).unwrap(), "hello");
+        std::fs::remove_dir_all(extracted.ancestors().nth(2).unwrap()).unwrap();
+    }
+}
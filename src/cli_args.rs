@@ -1,9 +1,9 @@
 use clap::{Command, Arg, ArgAction};
 use crate::ui::{Action, ActionContext};
-use crate::settings::SortMode;
+use crate::settings::{CopyPathFormat, CursorStyle, EnterOnFile, FuzzyAlgo, HeaderFormat, Layout, PageScroll, SearchScope, SizeFormat, SortMode};
 use strum::IntoEnumIterator;
 
-/// The CLI options for tere
+// The CLI options for tere
 
 macro_rules! case_sensitive_template {
     ($help_text:tt, $x:tt, $y:tt) => {
@@ -27,9 +27,44 @@ macro_rules! gap_search_mode_template {
 
 pub fn get_cli_args() -> Command {
     Command::new(env!("CARGO_PKG_NAME"))
-        .version(env!("CARGO_PKG_VERSION"))
+        // the version includes the git commit hash if built from a git checkout, see build.rs
+        .version(env!("TERE_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
-        //.author(env!("CARGO_PKG_AUTHORS")) // TODO: rest of these https://stackoverflow.com/a/27841363
+        .long_about(concat!(
+            env!("CARGO_PKG_DESCRIPTION"),
+            ". Browse folders by typing to search, and print the final folder on exit, so that your shell can cd to it. See ",
+            env!("CARGO_PKG_REPOSITORY"),
+            " for setup instructions.",
+        ))
+        .arg(Arg::new("path")
+             .action(ArgAction::Set)
+             .help("The folder to start browsing from")
+             .long_help("The folder to start browsing from. Relative paths are resolved against the current working directory. If not provided, or if the folder can't be opened, start from the current working directory.")
+             .value_name("PATH")
+            )
+        .arg(Arg::new("list-json")
+             .action(ArgAction::SetTrue)
+             .long("list-json")
+             .help("Print the contents of the folder as JSON and exit")
+             .long_help("Print the contents of the folder given as PATH (or the current folder) as a JSON array and exit, without starting the interactive UI. Each item has the fields 'name', 'is_dir', 'size' (in bytes), 'mtime' (modification time in seconds since the Unix epoch) and 'symlink_target'. The listing is filtered and sorted according to the other options, such as --folders-only and --sort. The parent folder '..' is not included.")
+            )
+        .arg(Arg::new("print-shell-integration")
+             .action(ArgAction::Set)
+             .long("print-shell-integration")
+             .value_name("SHELL")
+             .value_parser(["bash", "zsh", "fish"])
+             .help("Print the shell function that changes to the folder printed by tere, and exit")
+             .long_help("Print the shell function that runs tere and changes to the folder that it prints on exit, and exit. To set it up, add 'eval \"$(tere --print-shell-integration bash)\"' to ~/.bashrc, 'eval \"$(tere --print-shell-integration zsh)\"' to ~/.zshrc, or 'tere --print-shell-integration fish | source' to ~/.config/fish/config.fish. The other options are ignored.")
+            )
+        .arg(Arg::new("keys")
+             .action(ArgAction::Set)
+             .long("keys")
+             .help("Press these keys instead of reading them from the keyboard, then exit")
+             .long_help("Instead of reading the keys from the keyboard, press the given keys one after another, and then exit and print the current folder as if Esc was pressed. The keys are separated by spaces, and they are written in the same way as in the --map option, for example 'd o c enter ctrl-s'. Use 'space' for the space key. This is meant for testing and scripting. The user interface is still drawn, so stderr has to be a terminal.")
+             .value_name("KEYS")
+             .overrides_with("keys")
+            )
         .arg(Arg::new("filter-search")
              .action(ArgAction::SetTrue)
              .long("filter-search")
@@ -37,7 +72,7 @@ pub fn get_cli_args() -> Command {
              .short('f')
              .help("Show only items matching the search in listing")
              .long_help("Show only items matching the current search query in the listing. This overrides the --no-filter-search option. You can toggle the filtering with the keyboard shortcut Alt-f by default.")
-             .overrides_with_all(&["filter-search", "no-filter-search"])
+             .overrides_with_all(["filter-search", "no-filter-search"])
             )
         .arg(Arg::new("no-filter-search")
              .action(ArgAction::SetTrue)
@@ -46,7 +81,62 @@ pub fn get_cli_args() -> Command {
              .short('F')
              .help("Show all items in the listing even when searching (default)")
              .long_help("Show all items in the listing even when searching (default). This overrides the --filter-search option. You can toggle the filtering with the keyboard shortcut Alt-f by default.")
-             .overrides_with_all(&["filter-search", "no-filter-search"])
+             .overrides_with_all(["filter-search", "no-filter-search"])
+            )
+        .arg(Arg::new("gitignore")
+             .action(ArgAction::SetTrue)
+             .long("gitignore")
+             .help("Hide the items that are ignored by git")
+             .long_help("Hide the files and folders that are ignored by git in the listing, i.e. the ones matched by the .gitignore files of the repository, .git/info/exclude, or the global gitignore file, like ripgrep and fd do. Outside of git repositories, nothing is hidden. This overrides the --no-gitignore option.")
+             .overrides_with_all(["gitignore", "no-gitignore"])
+            )
+        .arg(Arg::new("no-gitignore")
+             .action(ArgAction::SetTrue)
+             .long("no-gitignore")
+             .help("Show the items that are ignored by git (default)")
+             .long_help("Show all files and folders, even if they are ignored by git. This is the default. This overrides the --gitignore option.")
+             .overrides_with_all(["gitignore", "no-gitignore"])
+            )
+        .arg(Arg::new("exclude")
+             .action(ArgAction::Append)
+             .long("exclude")
+             .help("Hide the folders with this name, like 'node_modules,target'")
+             .long_help("Hide the folders with the given name everywhere, for example '--exclude node_modules' or '--exclude .git,target'. Unlike --gitignore, this only compares the names of the folders, and works outside of git repositories too. With recursive search, the excluded folders are not searched, and they are not counted in the total size either. The number of hidden folders is shown in the footer. This option can be given multiple times, and multiple names can be given as a comma-separated list. You can show the excluded folders temporarily with the keyboard shortcut Alt-i by default.")
+             .value_name("NAME,...")
+            )
+        .arg(Arg::new("recursive-search")
+             .action(ArgAction::SetTrue)
+             .long("recursive-search")
+             .help("List and search the subfolders of the current folder too")
+             .long_help("List the contents of the subfolders of the current folder too, so that items deeper in the folder tree can be found by searching. The items are shown with their path relative to the current folder, and the search matches their names. Entering a folder goes straight to it, and pressing Enter on a file goes to the folder that contains it. The subfolders are read in the background, and reading them can be cancelled with Esc. Symlinks to folders are not followed. See also --recursive-search-depth. This overrides the --no-recursive-search option. You can toggle the recursive search with the keyboard shortcut Alt-a by default.")
+             .overrides_with_all(["recursive-search", "no-recursive-search"])
+            )
+        .arg(Arg::new("no-recursive-search")
+             .action(ArgAction::SetTrue)
+             .long("no-recursive-search")
+             .help("List and search only the current folder (default)")
+             .long_help("List and search only the contents of the current folder. This is the default. This overrides the --recursive-search option.")
+             .overrides_with_all(["recursive-search", "no-recursive-search"])
+            )
+        .arg(Arg::new("recursive-search-depth")
+             .action(ArgAction::Set)
+             .long("recursive-search-depth")
+             .help("How many levels of subfolders to list with --recursive-search")
+             .long_help("With recursive search, list the subfolders of the current folder down to this many levels. For example, with 1, only the contents of the folders in the current folder are listed in addition to the current folder. This can't be more than --max-depth. The default is 5.")
+             .value_name("N")
+             .value_parser(clap::value_parser!(usize))
+             .default_value("5")
+             .overrides_with("recursive-search-depth")
+            )
+        .arg(Arg::new("max-depth")
+             .action(ArgAction::Set)
+             .long("max-depth")
+             .help("How many levels of subfolders any recursive operation goes down at most")
+             .long_help("Limit how many levels of subfolders are read by the operations that go through the subfolders of the current folder, i.e. the recursive search (see also --recursive-search-depth) and computing the total size of the folder. This keeps them from taking forever on huge folder trees. When some subfolders were left out because of the limit, it's shown in the footer. The default is 20.")
+             .value_name("N")
+             .value_parser(clap::value_parser!(usize))
+             .default_value("20")
+             .overrides_with("max-depth")
             )
         .arg(Arg::new("folders-only")
              .action(ArgAction::SetTrue)
@@ -54,8 +144,8 @@ pub fn get_cli_args() -> Command {
              //.visible_alias("fo") //TODO: consider
              .short('d')
              .help("Show only folders in the listing")
-             .long_help("Show only folders (and symlinks pointing to folders) in the listing. This overrides the --no-folders-only option.")
-             .overrides_with_all(&["folders-only", "no-folders-only"])
+             .long_help("Show only folders (and symlinks pointing to folders) in the listing. This overrides the --no-folders-only and --files-only options. You can change which items are shown with the keyboard shortcut Alt-t by default.")
+             .overrides_with_all(["folders-only", "no-folders-only", "files-only"])
             )
         .arg(Arg::new("no-folders-only")
              .action(ArgAction::SetTrue)
@@ -63,8 +153,225 @@ pub fn get_cli_args() -> Command {
              //.visible_alias("nfo") //TODO: consider
              .short('D')
              .help("Show files and folders in the listing (default)")
-             .long_help("Show both files and folders in the listing. This is the default view mode. This overrides the --folders-only option.")
-             .overrides_with_all(&["folders-only", "no-folders-only"])
+             .long_help("Show both files and folders in the listing. This is the default view mode. This overrides the --folders-only and --files-only options.")
+             .overrides_with_all(["folders-only", "no-folders-only", "files-only"])
+            )
+        .arg(Arg::new("files-only")
+             .action(ArgAction::SetTrue)
+             .long("files-only")
+             .help("Show only files in the listing")
+             .long_help("Show only files (and symlinks pointing to files) in the listing. The parent folder '..' is still shown. This overrides the --folders-only and --no-folders-only options. You can change which items are shown with the keyboard shortcut Alt-t by default.")
+             .overrides_with_all(["folders-only", "no-folders-only", "files-only"])
+            )
+        .arg(Arg::new("filter")
+             .action(ArgAction::Set)
+             .long("filter")
+             .help("Show only files matching a glob pattern")
+             .long_help("Show only the files whose name matches this glob pattern, for example '*.rs' or '*.{jpg,png}'. Folders are always shown, so that you can still navigate. Unlike the search, the filter stays in place when changing folders. You can set the filter at runtime by typing the pattern as a search and pressing Ctrl-g, and clear it by pressing Ctrl-g when not searching.")
+             .value_name("PATTERN")
+             .overrides_with("filter")
+            )
+        .arg(Arg::new("modified-within")
+             .action(ArgAction::Set)
+             .long("modified-within")
+             .help("Show only files modified within a time window, like '2h' or '3d'")
+             .long_help("Show only the files that have been modified within this time window. The window is a number followed by a unit, which is one of s (seconds), m (minutes), h (hours), d (days) or w (weeks), for example '30m' or '2d'. Folders are always shown, so that you can still navigate. The filter can be turned on and off with the keyboard shortcut Alt-m by default, and the window given here is used when turning it on. If this option is not given, the window is one day.")
+             .value_name("DURATION")
+             .overrides_with("modified-within")
+            )
+        .arg(Arg::new("preview")
+             .action(ArgAction::SetTrue)
+             .long("preview")
+             .short('p')
+             .help("Show a preview of the item under the cursor")
+             .long_help("Show a preview pane next to the listing. For folders, the preview shows the contents of the folder, and for files, it shows the first lines of the file. This overrides the --no-preview option. You can toggle the preview with the keyboard shortcut Alt-p by default.")
+             .overrides_with_all(["preview", "no-preview"])
+            )
+        .arg(Arg::new("no-preview")
+             .action(ArgAction::SetTrue)
+             .long("no-preview")
+             .short('P')
+             .help("Don't show the preview pane (default)")
+             .long_help("Don't show the preview pane. This is the default. This overrides the --preview option.")
+             .overrides_with_all(["preview", "no-preview"])
+            )
+        .arg(Arg::new("grid")
+             .action(ArgAction::SetTrue)
+             .long("grid")
+             .help("Show the items in multiple columns")
+             .long_help("Show the items in as many columns as fit in the window, like 'ls'. The items are ordered row by row. Up and down move the cursor between rows, and if there's more than one column, the left and right arrow keys move the cursor between columns instead of changing the folder. This overrides the --no-grid option.")
+             .overrides_with_all(["grid", "no-grid"])
+            )
+        .arg(Arg::new("no-grid")
+             .action(ArgAction::SetTrue)
+             .long("no-grid")
+             .help("Show the items in a single column (default)")
+             .long_help("Show the items in a single column. This is the default. This overrides the --grid option.")
+             .overrides_with_all(["grid", "no-grid"])
+            )
+        .arg(Arg::new("details")
+             .action(ArgAction::SetTrue)
+             .long("details")
+             .help("Show the permissions, owner, size and modification time of the item under the cursor")
+             .long_help("Show details of the item under the cursor in the info line at the bottom of the screen: the permissions, owner and group (only on Linux and macOS), the size and the modification time. Messages are shown in the same place, and they hide the details until the cursor is moved. This overrides the --no-details option.")
+             .overrides_with_all(["details", "no-details"])
+            )
+        .arg(Arg::new("no-details")
+             .action(ArgAction::SetTrue)
+             .long("no-details")
+             .help("Don't show details of the item under the cursor (default)")
+             .long_help("Don't show details of the item under the cursor. This is the default. This overrides the --details option.")
+             .overrides_with_all(["details", "no-details"])
+            )
+        .arg(Arg::new("size-format")
+             .action(ArgAction::Set)
+             .long("size-format")
+             .help("How file sizes are shown")
+             .long_help("Choose how file sizes are shown in the details and the total size of a folder. 'binary' uses powers of 1024, like '1.5 KiB', 'decimal' uses powers of 1000, like '1.5 kB', and 'bytes' shows the exact number of bytes. The default is 'binary'.")
+             .value_name("'binary', 'decimal' or 'bytes'")
+             .value_parser(clap::builder::EnumValueParser::<SizeFormat>::new())
+             .hide_possible_values(true)
+             .default_value("binary")
+             .overrides_with("size-format")
+            )
+        .arg(Arg::new("time-style")
+             .action(ArgAction::Set)
+             .long("time-style")
+             .help("How modification times are shown")
+             .long_help("Choose how the modification time is shown in the details. 'iso' shows the local date and time like '2024-05-01 13:45', and 'relative' shows how long ago the item was modified, like '2h ago'. Anything else is a format string with strftime-style directives, for example '%d.%m.%Y %H:%M'. The default is 'iso'.")
+             .value_name("'iso', 'relative' or FORMAT")
+             .default_value("iso")
+             .overrides_with("time-style")
+            )
+        .arg(Arg::new("group-separator")
+             .action(ArgAction::SetTrue)
+             .long("group-separator")
+             .help("Leave an empty row between the folders and the files")
+             .long_help("Leave an empty row between the last folder and the first file in the listing, to make it easier to see where the files start. The cursor skips over the empty row. The row is not shown in the grid layout. This overrides the --no-group-separator option.")
+             .overrides_with_all(["group-separator", "no-group-separator"])
+            )
+        .arg(Arg::new("no-group-separator")
+             .action(ArgAction::SetTrue)
+             .long("no-group-separator")
+             .help("Don't leave an empty row between the folders and the files (default)")
+             .long_help("Show the files right after the folders in the listing. This is the default. This overrides the --group-separator option.")
+             .overrides_with_all(["group-separator", "no-group-separator"])
+            )
+        .arg(Arg::new("total-size")
+             .action(ArgAction::SetTrue)
+             .long("total-size")
+             .help("Show the total size of the files in the current folder")
+             .long_help("Show the summed size of the files in the current folder next to the number of files and folders in the footer. Subfolders are not included, because that would require reading all of them. To get the total size including subfolders, press Alt-z (by default), which computes it in the background. This overrides the --no-total-size option.")
+             .overrides_with_all(["total-size", "no-total-size"])
+            )
+        .arg(Arg::new("no-total-size")
+             .action(ArgAction::SetTrue)
+             .long("no-total-size")
+             .help("Don't show the total size of the files in the current folder (default)")
+             .long_help("Don't show the total size of the files in the current folder. This is the default. This overrides the --total-size option.")
+             .overrides_with_all(["total-size", "no-total-size"])
+            )
+        .arg(Arg::new("icons")
+             .action(ArgAction::SetTrue)
+             .long("icons")
+             .help("Show an icon for each item, using a Nerd Font")
+             .long_help("Show an icon in front of each item in the listing, based on whether it's a folder and on the file extension. The icons are glyphs from Nerd Fonts, so they only show up correctly if the terminal uses a font patched with them. The icons can be changed with --icon-map. This overrides the --no-icons option.")
+             .overrides_with_all(["icons", "no-icons"])
+            )
+        .arg(Arg::new("no-icons")
+             .action(ArgAction::SetTrue)
+             .long("no-icons")
+             .help("Don't show icons for the items (default)")
+             .long_help("Don't show icons in front of the items in the listing. This is the default. This overrides the --icons option.")
+             .overrides_with_all(["icons", "no-icons"])
+            )
+        .arg(Arg::new("icon-map")
+             .action(ArgAction::Append)
+             .long("icon-map")
+             .help("Change the icons shown with --icons, like 'rs:R,folder:D'")
+             .long_help("Change the icons that are shown with the --icons option. The mappings are of the form 'extension:icon', for example 'rs:R' to show an 'R' in front of Rust files. Use 'folder:icon' to change the icon of folders, and 'file:icon' to change the icon of files that don't have an icon of their own. This option can be given multiple times, and multiple mappings can be given as a comma-separated list.")
+             .value_name("EXT:ICON,...")
+            )
+        .arg(Arg::new("numbers")
+             .action(ArgAction::SetTrue)
+             .long("numbers")
+             .help("Show row numbers, and jump to rows by typing their number")
+             .long_help("Show the number of each row in the listing, counting from the top of the window. Typing a number moves the cursor to that row, so you can enter a folder by typing its number and pressing Enter. In this mode, digits can't be used for searching. This overrides the --no-numbers option. You can toggle the row numbers with the keyboard shortcut Alt-n by default.")
+             .overrides_with_all(["numbers", "no-numbers"])
+            )
+        .arg(Arg::new("no-numbers")
+             .action(ArgAction::SetTrue)
+             .long("no-numbers")
+             .help("Don't show row numbers (default)")
+             .long_help("Don't show row numbers, and use digits for searching as usual. This is the default. This overrides the --numbers option.")
+             .overrides_with_all(["numbers", "no-numbers"])
+            )
+        .arg(Arg::new("pick-file")
+             .action(ArgAction::SetTrue)
+             .long("pick-file")
+             .help("Print the path of the selected file on exit")
+             .long_help("Use tere as a file picker: pressing Enter (or any key mapped to ChangeDir) on a file selects it and exits, and the full path of the file is printed instead of the current folder. Navigating folders works as usual. Note that the output can't be used with 'cd', so this is meant to be used in scripts, not with the shell function from the README. This is the same as '--enter-on-file select'. This overrides the --no-pick-file and --enter-on-file options.")
+             .overrides_with_all(["pick-file", "no-pick-file", "enter-on-file"])
+            )
+        .arg(Arg::new("no-pick-file")
+             .action(ArgAction::SetTrue)
+             .long("no-pick-file")
+             .help("Don't select files on Enter (default)")
+             .long_help("Don't select files when pressing Enter on them, and always print the current folder on exit. This is the default. This overrides the --pick-file and --enter-on-file options.")
+             .overrides_with_all(["pick-file", "no-pick-file", "enter-on-file"])
+            )
+        .arg(Arg::new("enter-on-file")
+             .action(ArgAction::Set)
+             .long("enter-on-file")
+             .help("What to do when pressing Enter on a file")
+             .long_help("Choose what happens when pressing Enter (or any key mapped to ChangeDir) on a file: 'nothing' stays in the current folder and shows a message, 'select' exits and prints the path of the file (like --pick-file), and 'open' opens the file like Alt-o (see --open-command). The default is 'nothing'. This overrides the --pick-file and --no-pick-file options.")
+             .value_name("'nothing', 'select' or 'open'")
+             .value_parser(clap::builder::EnumValueParser::<EnterOnFile>::new())
+             .hide_possible_values(true)
+             .default_value("nothing")
+             .overrides_with_all(["pick-file", "no-pick-file", "enter-on-file"])
+            )
+        .arg(Arg::new("confirm-quit")
+             .action(ArgAction::SetTrue)
+             .long("confirm-quit")
+             .help("Ask for confirmation before exiting")
+             .long_help("When pressing a key that exits tere (Esc or Alt-q by default), ask 'Quit? y/n' in the footer first. Press 'y' or Enter to exit, or any other key to continue browsing. Esc still clears the search as usual, and Ctrl-c always exits without asking. This overrides the --no-confirm-quit option.")
+             .overrides_with_all(["confirm-quit", "no-confirm-quit"])
+            )
+        .arg(Arg::new("no-confirm-quit")
+             .action(ArgAction::SetTrue)
+             .long("no-confirm-quit")
+             .help("Exit immediately when pressing a key that exits (default)")
+             .long_help("Exit without asking for confirmation. This is the default. This overrides the --confirm-quit option.")
+             .overrides_with_all(["confirm-quit", "no-confirm-quit"])
+            )
+        .arg(Arg::new("allow-mkdir")
+             .action(ArgAction::SetTrue)
+             .long("allow-mkdir")
+             .help("Allow creating folders")
+             .long_help("Allow creating a new folder in the current folder (with F7 by default). The name of the folder is asked in the footer, and the cursor is moved to the new folder. This is off by default, because it changes the file system. This overrides the --no-allow-mkdir option.")
+             .overrides_with_all(["allow-mkdir", "no-allow-mkdir"])
+            )
+        .arg(Arg::new("no-allow-mkdir")
+             .action(ArgAction::SetTrue)
+             .long("no-allow-mkdir")
+             .help("Don't allow creating folders (default)")
+             .long_help("Don't allow creating folders, so that tere never changes the file system. This is the default. This overrides the --allow-mkdir option.")
+             .overrides_with_all(["allow-mkdir", "no-allow-mkdir"])
+            )
+        .arg(Arg::new("wrap")
+             .action(ArgAction::SetTrue)
+             .long("wrap")
+             .help("Wrap the cursor around at the top and bottom of the listing (default)")
+             .long_help("When moving the cursor up at the top of the listing, jump to the bottom, and vice versa. This is the default. This overrides the --no-wrap option.")
+             .overrides_with_all(["wrap", "no-wrap"])
+            )
+        .arg(Arg::new("no-wrap")
+             .action(ArgAction::SetTrue)
+             .long("no-wrap")
+             .help("Don't wrap the cursor around at the top and bottom of the listing")
+             .long_help("Stop the cursor at the top and bottom of the listing, instead of wrapping around to the other end. This only affects moving the cursor up or down by one item (or to the previous or next match when searching). This overrides the --wrap option.")
+             .overrides_with_all(["wrap", "no-wrap"])
             )
         .arg(Arg::new("case-sensitive")
              .action(ArgAction::SetTrue)
@@ -76,7 +383,7 @@ pub fn get_cli_args() -> Command {
                      "ignore-case",
                      "smart-case"
             ))
-             .overrides_with_all(&["ignore-case", "smart-case", "case-sensitive"])
+             .overrides_with_all(["ignore-case", "smart-case", "case-sensitive"])
             )
         .arg(Arg::new("ignore-case")
              .action(ArgAction::SetTrue)
@@ -88,7 +395,7 @@ pub fn get_cli_args() -> Command {
                      "case-sensitive",
                      "smart-case"
                      ))
-             .overrides_with_all(&["ignore-case", "smart-case", "case-sensitive"])
+             .overrides_with_all(["ignore-case", "smart-case", "case-sensitive"])
             )
         .arg(Arg::new("smart-case")
              .action(ArgAction::SetTrue)
@@ -100,7 +407,21 @@ pub fn get_cli_args() -> Command {
                      "case-sensitive",
                      "ignore-case"
                      ))
-             .overrides_with_all(&["ignore-case", "smart-case", "case-sensitive"])
+             .overrides_with_all(["ignore-case", "smart-case", "case-sensitive"])
+            )
+        .arg(Arg::new("ignore-diacritics")
+             .action(ArgAction::SetTrue)
+             .long("ignore-diacritics")
+             .help("Ignore accents and other diacritics when searching")
+             .long_help("Ignore diacritics, such as accents, in the search and in the names of the items, so that for example 'cafe' matches 'café' and 'Cafe' matches 'Café'. This works for both precomposed characters and characters followed by combining marks. This overrides the --no-ignore-diacritics option.")
+             .overrides_with_all(["ignore-diacritics", "no-ignore-diacritics"])
+            )
+        .arg(Arg::new("no-ignore-diacritics")
+             .action(ArgAction::SetTrue)
+             .long("no-ignore-diacritics")
+             .help("Match diacritics exactly when searching (default)")
+             .long_help("Don't ignore diacritics when searching, so that 'cafe' doesn't match 'café'. This is the default. This overrides the --ignore-diacritics option.")
+             .overrides_with_all(["ignore-diacritics", "no-ignore-diacritics"])
             )
         .arg(Arg::new("gap-search")
              .action(ArgAction::SetTrue)
@@ -113,7 +434,7 @@ pub fn get_cli_args() -> Command {
                      "normal-search",
                      "normal-search-anywhere",
                      ))
-             .overrides_with_all(&["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
+             .overrides_with_all(["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
             )
         .arg(Arg::new("gap-search-anywhere")
              .action(ArgAction::SetTrue)
@@ -126,13 +447,13 @@ pub fn get_cli_args() -> Command {
                      "normal-search",
                      "normal-search-anywhere",
                      ))
-             .overrides_with_all(&["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
+             .overrides_with_all(["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
             )
         // DEPRECATED in favor of normal-search, this is here only for backward compatibility
         .arg(Arg::new("no-gap-search")
              .action(ArgAction::SetTrue)
              .long("no-gap-search")
-             .overrides_with_all(&["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
+             .overrides_with_all(["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
              .hide(true)
             )
         .arg(Arg::new("normal-search")
@@ -146,7 +467,7 @@ pub fn get_cli_args() -> Command {
                      "gap-search-anywhere",
                      "normal-search-anywhere",
                      ))
-             .overrides_with_all(&["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
+             .overrides_with_all(["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
             )
         .arg(Arg::new("normal-search-anywhere")
              .action(ArgAction::SetTrue)
@@ -159,7 +480,7 @@ pub fn get_cli_args() -> Command {
                      "gap-search-anywhere",
                      "normal-search",
                      ))
-             .overrides_with_all(&["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
+             .overrides_with_all(["gap-search", "gap-search-anywhere", "normal-search", "normal-search-anywhere", "no-gap-search"])
             )
         .arg(Arg::new("map")
              .action(ArgAction::Append)
@@ -167,11 +488,12 @@ pub fn get_cli_args() -> Command {
              .short('m')
              .help("Map one or more keyboard shortcuts. See full help (with --help) for further details.")
              .long_help(format!(
-"Add one or more keyboard shortcut mappings. The basic syntax is of the form 'key-combination:action' or 'key-combination:context:action', see examples below. This option can be provided multiple times, and multiple mappings can be created by a comma-separated list of mappings. If the same key combination (with the same context) is provided multiple times, the previous mappings are overridden. Use the action 'None' to remove a previously added mapping or one of the default mappings.
+"Add one or more keyboard shortcut mappings. The basic syntax is of the form 'key-combination:action' or 'key-combination:context:action', see examples below. This option can be provided multiple times, and multiple mappings can be created by a comma-separated list of mappings. Several key combinations can be mapped to the same action at once by separating them with spaces, like 'q alt-q:Exit'. If the same key combination (with the same context) is provided multiple times, the previous mappings are overridden. A mapping without a context is an error if the same key combination is mapped to other actions in both the Searching and NotSearching contexts, because it would never be used. Use the action 'None' to remove a previously added mapping or one of the default mappings.
 
 Examples:
 
-    -m ctrl-x:Exit - Exit tere by typing ctrl-x
+    -m ctrl-x:Exit - Exit tere by typing ctrl-x (key combinations can also be written with plus signs, like ctrl+x)
+    -m 'q alt-q:NotSearching:Exit' - Exit tere by typing either q or alt-q when not searching
     -m ctrl-h:ChangeDirParent,ctrl-j:CursorDown,ctrl-k:CursorUp,ctrl-l:ChangeDir - Navigate using Control + hjkl in addition to the default Alt + hjkl
     -m 1:NotSearching:CursorTop - Move the cursor to the top of the listing by typing '1', but only if not already searching (so you can still search for filenames that contain '1')
     -m esc:NotSearching:ExitWithoutCd,enter:ChangeDirAndExit - Map Escape to exiting with error, and map Enter to select the directory under the cursor and exit
@@ -215,6 +537,229 @@ justify_and_indent(
              .default_value("name")
              .overrides_with("sort")
             )
+        .arg(Arg::new("search-scope")
+             .action(ArgAction::Set)
+             .long("search-scope")
+             .help("Which part of the names to search")
+             .long_help("Choose which part of the item names the search is matched against: the whole 'name', only the 'extension' (for example, type 'rs' to find '.rs' files), or the 'stem', which is the name without the extension. You can change the search scope with the keyboard shortcut Alt-x by default.")
+             .value_name("'name', 'extension', or 'stem'")
+             .value_parser(clap::builder::EnumValueParser::<SearchScope>::new())
+             .hide_possible_values(true)
+             .default_value("name")
+             .overrides_with("search-scope")
+            )
+        .arg(Arg::new("fuzzy-algo")
+             .action(ArgAction::Set)
+             .long("fuzzy-algo")
+             .help("The algorithm for matching the search")
+             .long_help("Choose how the search is matched against the item names. 'substr' (the default) finds the search as a substring of the names, or with gaps between the characters, as set by the gap search options. 'skim' uses the fuzzy matching algorithm of the skim fuzzy finder, which allows gaps anywhere and gives each match a score, so that for example consecutive characters and characters at the start of words are preferred. The cursor moves to the match with the best score. The gap search options have no effect with 'skim'.")
+             .value_name("'substr' or 'skim'")
+             .value_parser(clap::builder::EnumValueParser::<FuzzyAlgo>::new())
+             .hide_possible_values(true)
+             .default_value("substr")
+             .overrides_with("fuzzy-algo")
+            )
+        .arg(Arg::new("header-format")
+             .action(ArgAction::Set)
+             .long("header-format")
+             .help("How to show the current path in the header")
+             .long_help("Choose how to show the current path at the top of the screen. 'full' shows the full path, 'home-tilde' replaces the home folder with '~', and 'shortened' also shortens folder names to their first letter, starting from the left, until the path fits on the screen (for example '~/p/v/tere'). If the path still doesn't fit, the beginning of it is cut off.")
+             .value_name("'full', 'home-tilde', or 'shortened'")
+             .value_parser(clap::builder::EnumValueParser::<HeaderFormat>::new())
+             .hide_possible_values(true)
+             .default_value("full")
+             .overrides_with("header-format")
+            )
+        .arg(Arg::new("header-parent-style")
+             .action(ArgAction::Set)
+             .long("header-parent-style")
+             .help("How the parent folders are drawn in the header")
+             .long_help("Choose how the parent folders of the current folder are drawn in the header, so that the name of the current folder stands out. 'dim' draws them dimmed, 'bold' draws them bold and underlined like the current folder, and a color name draws them in that color. The possible colors are the same as for --match-color. If colors are disabled with the NO_COLOR environment variable, a color falls back to 'dim'. The default is 'dim'.")
+             .value_name("'dim', 'bold' or COLOR")
+             .default_value("dim")
+             .overrides_with("header-parent-style")
+            )
+        .arg(Arg::new("footer-format")
+             .action(ArgAction::Set)
+             .long("footer-format")
+             .value_name("TEMPLATE")
+             .help("Customize the counts in the footer, like '{cursor}/{total} ({dirs}d {files}f)'")
+             .long_help("Customize the counts shown at the right edge of the footer when not searching. The placeholders {cursor} (the position of the cursor), {total} (the number of items), {dirs}, {files}, {size} (the total size, if it's shown), {match} and {matches} are replaced by their values. For example, '{cursor}/{total} ({dirs}d {files}f)'. Write '{{' and '}}' for literal braces. By default, the counts are shown like '3 folders, 5 files - 2 / 9'. See also --search-footer-format.")
+             .overrides_with("footer-format")
+            )
+        .arg(Arg::new("search-footer-format")
+             .action(ArgAction::Set)
+             .long("search-footer-format")
+             .value_name("TEMPLATE")
+             .help("Customize the counts in the footer while searching, like '{match}/{matches}'")
+             .long_help("Customize the counts shown at the right edge of the footer while searching. The placeholders are the same as in --footer-format, with {match} being the number of the match under the cursor, and {matches} the number of matches. The default is '{match} / {matches} / {total}'.")
+             .overrides_with("search-footer-format")
+            )
+        .arg(Arg::new("copy-path-format")
+             .action(ArgAction::Set)
+             .long("copy-path-format")
+             .help("Copy absolute or relative paths to the clipboard")
+             .long_help("Choose whether copying the path of the item under the cursor to the clipboard (with Alt-y by default) copies the absolute path, or the path relative to the folder where tere was started.")
+             .value_name("'absolute' or 'relative'")
+             .value_parser(clap::builder::EnumValueParser::<CopyPathFormat>::new())
+             .hide_possible_values(true)
+             .default_value("absolute")
+             .overrides_with("copy-path-format")
+            )
+        .arg(Arg::new("relative")
+             .action(ArgAction::SetTrue)
+             .long("relative")
+             .help("Print the selected path relative to the folder where tere was started")
+             .long_help("On exit, print the selected path relative to the folder where tere was started, instead of the absolute path. If the selected path is not inside that folder, the absolute path is printed. This only affects the output, not the navigation. This overrides the --relative-to and --no-relative options.")
+             .overrides_with_all(["relative", "relative-to", "no-relative"])
+            )
+        .arg(Arg::new("relative-to")
+             .action(ArgAction::Set)
+             .long("relative-to")
+             .help("Print the selected path relative to BASE")
+             .long_help("On exit, print the selected path relative to the folder BASE, instead of the absolute path. If BASE is a relative path, it's relative to the folder where tere was started. If the selected path is not inside BASE, the absolute path is printed. This only affects the output, not the navigation. This overrides the --relative and --no-relative options.")
+             .value_name("BASE")
+             .overrides_with_all(["relative", "relative-to", "no-relative"])
+            )
+        .arg(Arg::new("no-relative")
+             .action(ArgAction::SetTrue)
+             .long("no-relative")
+             .help("Print the absolute path of the selected folder (default)")
+             .long_help("On exit, print the absolute path of the selected folder or file. This is the default. This overrides the --relative and --relative-to options.")
+             .overrides_with_all(["relative", "relative-to", "no-relative"])
+            )
+        .arg(Arg::new("page-scroll")
+             .action(ArgAction::Set)
+             .long("page-scroll")
+             .help("Whether Page Up and Page Down move by a full or half screen")
+             .long_help("Choose how far the cursor moves when pressing Page Up or Page Down: 'full' moves by one screenful (minus one row, so that the item at the edge stays visible), and 'half' moves by half a screenful. Ctrl-u and Ctrl-d always move by half a screenful, like in Vim. The default is 'full'.")
+             .value_name("'full' or 'half'")
+             .value_parser(clap::builder::EnumValueParser::<PageScroll>::new())
+             .hide_possible_values(true)
+             .default_value("full")
+             .overrides_with("page-scroll")
+            )
+        .arg(Arg::new("layout")
+             .action(ArgAction::Set)
+             .long("layout")
+             .help("How the header, listing, info line and footer are arranged")
+             .long_help("Choose how the different parts of the UI are arranged on the screen. 'classic' shows the header at the top, then the listing, and the info line and the footer with the search at the bottom. 'compact' leaves out the info line to make room for one more item, and shows the messages in the footer instead of the number of items. 'top-search' shows the footer with the search and the info line right below the header, followed by the listing. The default is 'classic'.")
+             .value_name("'classic', 'compact' or 'top-search'")
+             .value_parser(clap::builder::EnumValueParser::<Layout>::new())
+             .hide_possible_values(true)
+             .default_value("classic")
+             .overrides_with("layout")
+            )
+        .arg(Arg::new("match-color")
+             .action(ArgAction::Set)
+             .long("match-color")
+             .help("Color of the characters that match the search")
+             .long_help("Show the characters that match the current search in this color, in addition to underlining them. Possible colors are black, red, green, yellow, blue, magenta, cyan, white and grey, and the darker versions dark_red, dark_green, dark_yellow, dark_blue, dark_magenta, dark_cyan and dark_grey. The default is 'none', which means that the matching characters are only underlined.")
+             .value_name("COLOR or 'none'")
+             .default_value("none")
+             .overrides_with("match-color")
+            )
+        .arg(Arg::new("warning-color")
+             .action(ArgAction::Set)
+             .long("warning-color")
+             .help("Color of the search query when nothing matches it")
+             .long_help("Show the search query in the footer in this color when no items match it. The possible colors are the same as for --match-color. Use 'none' to show the query normally. The default is red.")
+             .value_name("COLOR or 'none'")
+             .default_value("red")
+             .overrides_with("warning-color")
+            )
+        .arg(Arg::new("cursor-style")
+             .action(ArgAction::Set)
+             .long("cursor-style")
+             .help("How to mark the item under the cursor")
+             .long_help("Choose how the item under the cursor is marked: 'reverse' draws the whole row in the cursor color, 'arrow' draws a '>' in front of the item, and 'bar' draws a vertical bar in front of the item. With 'arrow' and 'bar', the other items are indented by one column so that the names stay aligned. The default is 'reverse'.")
+             .value_name("'reverse', 'arrow' or 'bar'")
+             .value_parser(clap::builder::EnumValueParser::<CursorStyle>::new())
+             .hide_possible_values(true)
+             .default_value("reverse")
+             .overrides_with("cursor-style")
+            )
+        .arg(Arg::new("cursor-color")
+             .action(ArgAction::Set)
+             .long("cursor-color")
+             .help("Color of the cursor")
+             .long_help("The background color of the row under the cursor with '--cursor-style reverse', or the color of the marker with the other cursor styles. The possible colors are the same as for --match-color. With 'none', the row under the cursor is drawn in reverse video using the colors of the terminal, and the marker in the default text color. The default is grey.")
+             .value_name("COLOR or 'none'")
+             .default_value("grey")
+             .overrides_with("cursor-color")
+            )
+        .arg(Arg::new("prefetch")
+             .action(ArgAction::SetTrue)
+             .long("prefetch")
+             .help("Read the folder under the cursor in the background (default)")
+             .long_help("When the cursor stays on a folder for a moment, start reading its contents in the background, so that entering it is faster, especially on slow drives. A listing read this way is used only if the folder is entered within a few seconds. Folders are not read in advance with recursive search. This is the default. This overrides the --no-prefetch option.")
+             .overrides_with_all(["prefetch", "no-prefetch"])
+            )
+        .arg(Arg::new("no-prefetch")
+             .action(ArgAction::SetTrue)
+             .long("no-prefetch")
+             .help("Only read folders when entering them")
+             .long_help("Don't read the folder under the cursor in the background, only read folders when entering them. This overrides the --prefetch option.")
+             .overrides_with_all(["prefetch", "no-prefetch"])
+            )
+        .arg(Arg::new("row-fill")
+             .action(ArgAction::SetTrue)
+             .long("row-fill")
+             .help("Pad the rows of the listing to the full width (default)")
+             .long_help("Pad the rows of the listing with spaces to the full width of the listing, so that the cursor row is highlighted from edge to edge. This is the default. This overrides the --no-row-fill option.")
+             .overrides_with_all(["row-fill", "no-row-fill"])
+            )
+        .arg(Arg::new("no-row-fill")
+             .action(ArgAction::SetTrue)
+             .long("no-row-fill")
+             .help("Don't pad the rows of the listing with spaces")
+             .long_help("Don't pad the rows of the listing with spaces to the full width. The rest of each row is erased instead, so that selecting names with the mouse doesn't copy trailing spaces. The highlight of the cursor row only extends one column past the name. This overrides the --row-fill option.")
+             .overrides_with_all(["row-fill", "no-row-fill"])
+            )
+        .arg(Arg::new("align-extensions")
+             .action(ArgAction::SetTrue)
+             .long("align-extensions")
+             .help("Show the file extensions aligned to the right edge of the listing")
+             .long_help("Draw the extensions of files in a different color at the right edge of the listing (or of the column in the grid layout), so that files of the same type are easy to pick out. The extension is the part of the name after the last dot, so for example the extension of 'archive.tar.gz' is '.gz', and dotfiles like '.bashrc' have no extension. If the name doesn't fit in the row, it's drawn as usual. This doesn't affect searching. This overrides the --no-align-extensions option.")
+             .overrides_with_all(["align-extensions", "no-align-extensions"])
+            )
+        .arg(Arg::new("no-align-extensions")
+             .action(ArgAction::SetTrue)
+             .long("no-align-extensions")
+             .help("Show the file extensions as part of the names (default)")
+             .long_help("Draw the extensions of files as part of the names, like the rest of the name. This is the default. This overrides the --align-extensions option.")
+             .overrides_with_all(["align-extensions", "no-align-extensions"])
+            )
+        .arg(Arg::new("scroll-off")
+             .action(ArgAction::Set)
+             .long("scroll-off")
+             .help("Keep at least this many items visible above and below the cursor")
+             .long_help("Start scrolling the listing when the cursor is this many items away from the top or bottom of the window, so that the items around the cursor stay visible (like the 'scrolloff' option in Vim). If the window is too small, the margin is reduced so that the cursor can still move. The default is 0, which means that the listing only scrolls when the cursor moves past the edge of the window.")
+             .value_name("N")
+             .value_parser(clap::value_parser!(usize))
+             .default_value("0")
+             .overrides_with("scroll-off")
+            )
+        .arg(Arg::new("padding")
+             .action(ArgAction::Set)
+             .long("padding")
+             .help("Leave this many empty columns on both sides of the listing")
+             .long_help("Leave this many empty columns on both sides of the listing, so that the names don't touch the edges of the window, and the long names are cut before the right edge. With the preview, the padding on the right is between the listing and the preview. The header and the footer are not affected. The default is 0.")
+             .value_name("N")
+             .value_parser(clap::value_parser!(usize))
+             .default_value("0")
+             .overrides_with("padding")
+            )
+        .arg(Arg::new("search-debounce")
+             .action(ArgAction::Set)
+             .long("search-debounce")
+             .help("In large folders, wait this many milliseconds for more typing before searching")
+             .long_help("In folders with at least 10000 items, the characters typed in quick succession are collected, and the search is updated only once the typing pauses for this many milliseconds. The footer shows '…' while there are characters waiting. The automatic change to the only matching folder only happens after the search is updated. In smaller folders, the search is always updated immediately. The default is 50, and 0 disables the waiting.")
+             .value_name("MS")
+             .value_parser(clap::value_parser!(u64))
+             .default_value("50")
+             .overrides_with("search-debounce")
+            )
         .arg(Arg::new("autocd-timeout")
              .action(ArgAction::Set)
              .long("autocd-timeout")
@@ -224,6 +769,122 @@ justify_and_indent(
              .value_name("TIMEOUT or 'off'")
              .overrides_with("autocd-timeout")
             )
+        .arg(Arg::new("autocd-prefix")
+             .action(ArgAction::SetTrue)
+             .long("autocd-prefix")
+             .help("Also auto-cd if the search is the start of only one folder name")
+             .long_help("In addition to changing to the only matching folder, automatically change to a folder if the search query is the start of its name and no other folder or file name starts with the query, even if other items match the search elsewhere in their names. Files are never entered automatically. This uses the same timeout as --autocd-timeout, and has no effect if it is 'off'. This overrides the --no-autocd-prefix option.")
+             .overrides_with_all(["autocd-prefix", "no-autocd-prefix"])
+            )
+        .arg(Arg::new("no-autocd-prefix")
+             .action(ArgAction::SetTrue)
+             .long("no-autocd-prefix")
+             .help("Only auto-cd if there's only one match (default)")
+             .long_help("Only change to a folder automatically if it's the only item that matches the search. This is the default. This overrides the --autocd-prefix option.")
+             .overrides_with_all(["autocd-prefix", "no-autocd-prefix"])
+            )
+        .arg(Arg::new("autocd-discard-keys")
+             .action(ArgAction::SetTrue)
+             .long("autocd-discard-keys")
+             .help("Ignore the keys pressed while waiting for auto-cd")
+             .long_help("Ignore the keys that are pressed during the --autocd-timeout, i.e. while the only match is highlighted before changing to it. This prevents keys that were meant for the old folder from ending up in the search of the new folder. Keys pressed before or after the timeout are not affected. This overrides the --no-autocd-discard-keys option.")
+             .overrides_with_all(["autocd-discard-keys", "no-autocd-discard-keys"])
+            )
+        .arg(Arg::new("no-autocd-discard-keys")
+             .action(ArgAction::SetTrue)
+             .long("no-autocd-discard-keys")
+             .help("Keep the keys pressed while waiting for auto-cd (default)")
+             .long_help("Keep the keys that are pressed while waiting for the --autocd-timeout, and handle them in the new folder after changing to it, so that you can type ahead. This is the default. This overrides the --autocd-discard-keys option.")
+             .overrides_with_all(["autocd-discard-keys", "no-autocd-discard-keys"])
+            )
+        .arg(Arg::new("continuous-search")
+             .action(ArgAction::SetTrue)
+             .long("continuous-search")
+             .help("Keep searching after entering a folder from a search")
+             .long_help("After entering a folder while searching (either with Enter or automatically when there's only one match), stay in search mode in the new folder, so that you can keep typing the next folder name. For example, typing 'doc<Enter>mus<Enter>' would go to 'Documents/Music'. While in search mode, keys like Space and Backspace are used for searching instead of navigation, even if the search is empty. Press Esc to exit search mode, and Esc again to exit tere. This overrides the --no-continuous-search option.")
+             .overrides_with_all(["continuous-search", "no-continuous-search"])
+            )
+        .arg(Arg::new("no-continuous-search")
+             .action(ArgAction::SetTrue)
+             .long("no-continuous-search")
+             .help("Exit search mode after entering a folder (default)")
+             .long_help("Clear the search and exit search mode after entering a folder. This is the default. This overrides the --continuous-search option.")
+             .overrides_with_all(["continuous-search", "no-continuous-search"])
+            )
+        .arg(Arg::new("bell-on-no-match")
+             .action(ArgAction::SetTrue)
+             .long("bell-on-no-match")
+             .help("Ring the terminal bell when a typed character leaves no matches")
+             .long_help("Ring the terminal bell when typing a character makes the search match no items, so that you notice right away that the search can't be extended. Depending on the terminal, the bell is a sound or a flash of the screen. This overrides the --no-bell-on-no-match option.")
+             .overrides_with_all(["bell-on-no-match", "no-bell-on-no-match"])
+            )
+        .arg(Arg::new("no-bell-on-no-match")
+             .action(ArgAction::SetTrue)
+             .long("no-bell-on-no-match")
+             .help("Don't ring the bell when the search matches nothing (default)")
+             .long_help("Don't ring the terminal bell when the search doesn't match any items. This is the default. This overrides the --bell-on-no-match option.")
+             .overrides_with_all(["bell-on-no-match", "no-bell-on-no-match"])
+            )
+        .arg(Arg::new("remember-search")
+             .action(ArgAction::SetTrue)
+             .long("remember-search")
+             .help("Restore the last search when coming back to a folder")
+             .long_help("Remember the search that was active when leaving a folder, and apply it again when coming back to the same folder later in the same session. If the search doesn't match anything any more, the listing just shows that there are no matches. Clearing the search with Esc also forgets it. This overrides the --no-remember-search option.")
+             .overrides_with_all(["remember-search", "no-remember-search"])
+            )
+        .arg(Arg::new("no-remember-search")
+             .action(ArgAction::SetTrue)
+             .long("no-remember-search")
+             .help("Start with an empty search in every folder (default)")
+             .long_help("Don't restore searches when coming back to a folder. This is the default. This overrides the --remember-search option.")
+             .overrides_with_all(["remember-search", "no-remember-search"])
+            )
+        .arg(Arg::new("autocd-min-chars")
+             .action(ArgAction::Set)
+             .long("autocd-min-chars")
+             .help("Only auto-cd when the search is at least this many characters long")
+             .long_help("Don't change to a folder automatically until the search query is at least this many characters long, even if only one item matches a shorter query. This applies to both the single match and the --autocd-prefix cases. The default is 1, which means that auto-cd can happen after typing any character.")
+             .value_name("N")
+             .value_parser(clap::value_parser!(usize))
+             .default_value("1")
+             .overrides_with("autocd-min-chars")
+            )
+        .arg(Arg::new("info-timeout")
+             .action(ArgAction::Set)
+             .long("info-timeout")
+             .help("Clear info messages after this many seconds. Use 'off' to disable (default).")
+             .long_help("Clear informational messages (such as 'No matches') from the info line after this many seconds. If the value is 'off', the messages stay visible until they are replaced by another message. See also --error-timeout.")
+             .default_value("off")
+             .value_name("SECONDS or 'off'")
+             .allow_hyphen_values(true)
+             .overrides_with("info-timeout")
+            )
+        .arg(Arg::new("error-timeout")
+             .action(ArgAction::Set)
+             .long("error-timeout")
+             .help("Clear error messages after this many seconds. Use 'off' to disable (default).")
+             .long_help("Clear error messages from the info line after this many seconds. If the value is 'off', the messages stay visible until they are replaced by another message. This is separate from --info-timeout, so that errors can be shown for longer than other messages.")
+             .default_value("off")
+             .value_name("SECONDS or 'off'")
+             .allow_hyphen_values(true)
+             .overrides_with("error-timeout")
+            )
+        .arg(Arg::new("open-command")
+             .action(ArgAction::Set)
+             .long("open-command")
+             .help("Command for opening files with Alt-o")
+             .long_help("The command to use for opening the file under the cursor (with Alt-o by default), for example 'less' or 'bat --paging=always'. The path of the file is added to the end of the command. If not given or empty, text files are opened in $VISUAL or $EDITOR, and other files with the default application of the system (using 'xdg-open' on Linux, 'open' on macOS, and 'start' on Windows).")
+             .value_name("COMMAND")
+             .overrides_with("open-command")
+            )
+        .arg(Arg::new("file-manager-command")
+             .action(ArgAction::Set)
+             .long("file-manager-command")
+             .help("Command for opening the current folder in a file manager with Alt-e")
+             .long_help("The command to use for opening the current folder in a file manager (with Alt-e by default), for example 'nautilus' or 'thunar'. The path of the folder is added to the end of the command. The command is started in the background, and tere keeps running. If not given or empty, the default file manager of the system is used (using 'xdg-open' on Linux, 'open' on macOS, and 'explorer' on Windows).")
+             .value_name("COMMAND")
+             .overrides_with("file-manager-command")
+            )
         .arg(Arg::new("history-file")
              .action(ArgAction::Set)
              .long("history-file")
@@ -231,6 +892,39 @@ justify_and_indent(
              .long_help("Save a history of visited folders in this file in JSON format. Should be an absolute path. Set to empty to disable saving history. If not provided, defaults to '$CACHE_DIR/tere/history.json', where $CACHE_DIR is the cache directory, i.e. $XDG_CACHE_HOME or ~/.cache. Note that the history file reveals parts of your folder structure if it can be read by someone else.")
              .value_name("FILE or ''")
             )
+        .arg(Arg::new("save-prefs")
+             .action(ArgAction::SetTrue)
+             .long("save-prefs")
+             .help("Remember the view preferences changed with keyboard shortcuts")
+             .long_help("When exiting, save the preferences that were changed with keyboard shortcuts during the session, such as the sort order, showing only folders or files, the preview, the row numbers and the search modes, and use them on the next start. The preferences are saved in '$CONFIG_DIR/tere/prefs.json', where $CONFIG_DIR is the config directory, i.e. $XDG_CONFIG_HOME or ~/.config. Options given on the command line take precedence over the saved preferences. The search itself and the file filters are not saved. This overrides the --no-save-prefs option.")
+             .overrides_with_all(["save-prefs", "no-save-prefs"])
+            )
+        .arg(Arg::new("no-save-prefs")
+             .action(ArgAction::SetTrue)
+             .long("no-save-prefs")
+             .help("Don't save or use the view preferences from earlier sessions (default)")
+             .long_help("Don't save the preferences changed during the session, and don't use the saved preferences. This is the default. This overrides the --save-prefs option.")
+             .overrides_with_all(["save-prefs", "no-save-prefs"])
+            )
+        .arg(Arg::new("inline")
+             .action(ArgAction::Set)
+             .long("inline")
+             .help("Draw the UI below the cursor instead of taking over the whole screen")
+             .long_help("Instead of switching to the alternate screen, reserve this many rows below the cursor for the UI, and leave the rest of the terminal as it is. The rows are cleared on exit, so the shell prompt continues where tere was started. If ROWS is left out, 15 rows are used, and if the terminal is shorter than that, the whole terminal is used. Note that the path to start from must then come before this option, or after '--'. This overrides the --no-inline option.")
+             .value_name("ROWS")
+             .num_args(0..=1)
+             .require_equals(false)
+             .default_missing_value("15")
+             .value_parser(clap::value_parser!(u16).range(1..))
+             .overrides_with_all(["inline", "no-inline"])
+            )
+        .arg(Arg::new("no-inline")
+             .action(ArgAction::SetTrue)
+             .long("no-inline")
+             .help("Use the whole screen for the UI (default)")
+             .long_help("Draw the UI on the alternate screen, which takes up the whole terminal. This is the default. This overrides the --inline option.")
+             .overrides_with_all(["inline", "no-inline"])
+            )
         .arg(Arg::new("mouse")
              .action(ArgAction::Set)
              .long("mouse")
@@ -240,7 +934,14 @@ justify_and_indent(
              .value_parser(clap::builder::PossibleValuesParser::new(["on", "off"]))
              .hide_possible_values(true)
              .default_value("off")
-             .overrides_with("mouse")
+             .overrides_with_all(["mouse", "no-mouse"])
+            )
+        .arg(Arg::new("no-mouse")
+             .action(ArgAction::SetTrue)
+             .long("no-mouse")
+             .help("Disable mouse navigation (default)")
+             .long_help("Disable mouse navigation. This is the same as --mouse=off, and overrides the --mouse option. Useful if mouse capture interferes with selecting text in your terminal.")
+             .overrides_with_all(["mouse", "no-mouse"])
             )
 }
 
@@ -5,12 +5,25 @@ pub enum TereError {
     Clap(clap::Error),
     SerdeJson(serde_json::error::Error),
 
-    // This is raised when the user wants to exit tere without changing the folder. A bit of a hack
-    // to define this here but this is simple enough.
-    ExitWithoutCd(String),
-
     // The user cancelled the first-run prompt
     FirstRunPromptCancelled(String),
+
+    // There's no terminal for drawing the UI or reading the keys. The string says what's missing.
+    NotATerminal(String),
+}
+
+impl std::fmt::Display for TereError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Clap(e) => write!(f, "{}", e),
+            Self::SerdeJson(e) => write!(f, "{}", e),
+            Self::FirstRunPromptCancelled(msg) => write!(f, "{}", msg),
+            Self::NotATerminal(reason) => {
+                write!(f, "must be run in an interactive terminal ({})", reason)
+            }
+        }
+    }
 }
 
 impl From<std::io::Error> for TereError {
@@ -30,3 +43,63 @@ impl From<serde_json::error::Error> for TereError {
         Self::SerdeJson(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{EventSource, TereTui};
+    use crate::TereAppState;
+    use crossterm::{event::Event, Result as CTResult};
+    use std::time::Duration;
+
+    /// A terminal that fails to report its size or to read the events, e.g. because it was closed
+    struct BrokenTerminal {
+        size_works: bool,
+    }
+
+    impl EventSource for BrokenTerminal {
+        fn size(&self) -> CTResult<(u16, u16)> {
+            if self.size_works {
+                Ok((80, 24))
+            } else {
+                Err(std::io::Error::other("failed to query the terminal size"))
+            }
+        }
+
+        fn poll(&mut self, _timeout: Duration) -> CTResult<bool> {
+            Err(std::io::Error::other("failed to read the events"))
+        }
+
+        fn read(&mut self) -> CTResult<Option<Event>> {
+            Err(std::io::Error::other("failed to read the events"))
+        }
+
+        fn is_terminal(&self) -> bool {
+            false
+        }
+
+        fn enable_raw_mode(&mut self) -> CTResult<()> {
+            Ok(())
+        }
+    }
+
+    fn init_ui(size_works: bool) -> Result<TereTui<'static>, TereError> {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec!["tere", "--history-file=", "--no-prefetch"]);
+        let (settings, warnings) = crate::TereSettings::parse_cli_args(&m).unwrap();
+        let state = TereAppState::init(settings, &warnings).unwrap();
+        TereTui::init(state, Box::new(std::io::sink()), Box::new(BrokenTerminal { size_works }))
+    }
+
+    #[test]
+    fn test_terminal_error_is_handled() {
+        // the size is needed for drawing the UI in the first place
+        let err = init_ui(false).err().unwrap();
+        assert!(matches!(err, TereError::Io(_)));
+        assert_eq!(err.to_string(), "failed to query the terminal size");
+
+        let err = init_ui(true).unwrap().main_event_loop().unwrap_err();
+        assert!(matches!(err, TereError::Io(_)));
+        assert_eq!(err.to_string(), "failed to read the events");
+    }
+}
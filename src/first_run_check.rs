@@ -1,4 +1,4 @@
-/// This module contains functionality for checking if the app is being run for the first time.
+//! This module contains functionality for checking if the app is being run for the first time.
 
 use std::path::PathBuf;
 use std::io::Stderr;
@@ -38,8 +38,12 @@ pub fn check_first_run_with_prompt(
     // Earlier I also had the idea to write the current version of the app to a `version` file in
     // the cache folder, which would signify that the app has been run before, but for now the
     // history file is enough.
+    //
+    // The keys given with `--keys` don't come from the terminal, so there's no one to answer the
+    // prompt, and the prompt is not shown.
     if hist_file.is_none() // user passed empty history file
         || PathBuf::from(hist_file.as_ref().unwrap()).try_exists().unwrap_or(false) // history file exists
+        || settings.scripted_keys.is_some()
     {
         Ok(())
     } else {
@@ -123,4 +127,18 @@ mod tests {
         // incorrectly formatted in the README
         get_formatted_first_run_message(100);
     }
+
+    #[test]
+    fn test_no_prompt_with_scripted_keys() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let m = crate::cli_args::get_cli_args().get_matches_from(vec![
+            "tere",
+            "--history-file", tmp_dir.path().join("history.json").to_str().unwrap(),
+            "--keys", "esc",
+        ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        // this would fail or wait for a key press if the prompt was shown, because there's no
+        // terminal in the tests
+        assert!(check_first_run_with_prompt(&settings, &mut std::io::stderr()).is_ok());
+    }
 }
@@ -0,0 +1,108 @@
+//! Icons for the items in the listing, shown with the `--icons` option. The icons are glyphs from
+//! Nerd Fonts (https://www.nerdfonts.com), so they need a patched font to show up correctly. To
+//! add an icon for a file type, add its extension to `EXTENSION_ICONS`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The icon of folders. This can be overridden with the name 'folder' in `--icon-map`.
+pub const FOLDER_ICON: &str = "\u{f07b}"; // nf-fa-folder
+
+/// The icon of files that don't have an icon of their own. This can be overridden with the name
+/// 'file' in `--icon-map`.
+pub const FILE_ICON: &str = "\u{f15b}"; // nf-fa-file
+
+/// Icons for files by their extension. The extensions are lowercase.
+const EXTENSION_ICONS: &[(&str, &str)] = &[
+    // source code
+    ("c", "\u{e61e}"),
+    ("cpp", "\u{e61d}"),
+    ("css", "\u{e749}"),
+    ("go", "\u{e627}"),
+    ("h", "\u{e61e}"),
+    ("hpp", "\u{e61d}"),
+    ("html", "\u{e736}"),
+    ("java", "\u{e738}"),
+    ("js", "\u{e74e}"),
+    ("py", "\u{e606}"),
+    ("rs", "\u{e7a8}"),
+    ("sh", "\u{f489}"),
+    ("ts", "\u{e628}"),
+    ("vim", "\u{e62b}"),
+    // data and configuration
+    ("ini", "\u{e615}"),
+    ("json", "\u{e60b}"),
+    ("lock", "\u{f023}"),
+    ("toml", "\u{e615}"),
+    ("yaml", "\u{e615}"),
+    ("yml", "\u{e615}"),
+    // documents
+    ("md", "\u{e609}"),
+    ("pdf", "\u{f1c1}"),
+    ("txt", "\u{f0f6}"),
+    // images, audio and video
+    ("gif", "\u{f1c5}"),
+    ("jpeg", "\u{f1c5}"),
+    ("jpg", "\u{f1c5}"),
+    ("png", "\u{f1c5}"),
+    ("svg", "\u{f1c5}"),
+    ("flac", "\u{f001}"),
+    ("mp3", "\u{f001}"),
+    ("ogg", "\u{f001}"),
+    ("wav", "\u{f001}"),
+    ("mkv", "\u{f008}"),
+    ("mp4", "\u{f008}"),
+    ("webm", "\u{f008}"),
+    // archives
+    ("7z", "\u{f1c6}"),
+    ("gz", "\u{f1c6}"),
+    ("tar", "\u{f1c6}"),
+    ("xz", "\u{f1c6}"),
+    ("zip", "\u{f1c6}"),
+];
+
+/// The icon of an item. The mappings in `overrides` take precedence over the built-in icons. Its
+/// keys are lowercase extensions, or 'folder' and 'file' for the icons of folders and other files.
+pub fn icon_for<'a>(file_name: &str, is_dir: bool, overrides: &'a HashMap<String, String>) -> &'a str {
+    let icon = |name: &str, default: &'a str| overrides.get(name).map_or(default, String::as_str);
+    if is_dir {
+        return icon("folder", FOLDER_ICON);
+    }
+    let default = icon("file", FILE_ICON);
+    match Path::new(file_name).extension() {
+        Some(ext) => {
+            let ext = ext.to_string_lossy().to_lowercase();
+            let builtin = EXTENSION_ICONS.iter().find(|(e, _)| *e == ext).map(|(_, icon)| *icon);
+            icon(&ext, builtin.unwrap_or(default))
+        }
+        None => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_for() {
+        let no_overrides = HashMap::new();
+        assert_eq!(icon_for("src", true, &no_overrides), FOLDER_ICON);
+        assert_eq!(icon_for("main.rs", false, &no_overrides), "\u{e7a8}");
+        assert_eq!(icon_for("IMAGE.PNG", false, &no_overrides), "\u{f1c5}");
+        assert_eq!(icon_for("Makefile", false, &no_overrides), FILE_ICON);
+        assert_eq!(icon_for("notes.unknown", false, &no_overrides), FILE_ICON);
+        // folders get the folder icon even if the name looks like a file
+        assert_eq!(icon_for("config.json", true, &no_overrides), FOLDER_ICON);
+
+        let overrides: HashMap<String, String> = [("rs", "R"), ("folder", "D"), ("file", "F"), ("xyz", "X")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(icon_for("main.rs", false, &overrides), "R");
+        assert_eq!(icon_for("src", true, &overrides), "D");
+        assert_eq!(icon_for("Makefile", false, &overrides), "F");
+        assert_eq!(icon_for("notes.unknown", false, &overrides), "F");
+        assert_eq!(icon_for("data.XYZ", false, &overrides), "X");
+        assert_eq!(icon_for("Cargo.toml", false, &overrides), "\u{e615}");
+    }
+}
@@ -0,0 +1,59 @@
+//! The folder navigation logic of `tere`, separate from the terminal UI. The `tere` binary is a
+//! thin layer on top of this library that reads the keyboard and draws the screen.
+//!
+//! The state of the navigation is in [`TereAppState`]: the current folder, its listing (as
+//! [`CustomDirEntry`] items), the cursor, the search and the history. It's configured with
+//! [`TereSettings`], which are parsed from the same command line arguments as the binary takes.
+//! The search is done with a [`Matcher`](matcher::Matcher), which can also be used on its own.
+//!
+//! ```
+//! use tere::{cli_args, TereAppState, TereSettings};
+//!
+//! let args = cli_args::get_cli_args().get_matches_from(vec!["tere", "--history-file="]);
+//! let (settings, warnings) = TereSettings::parse_cli_args(&args).unwrap();
+//! let mut state = TereAppState::init(settings, &warnings).unwrap();
+//!
+//! // the listing starts with the parent folder
+//! assert_eq!(state.visible_items()[0].file_name_checked(), "..");
+//! state.change_dir("..").unwrap();
+//! ```
+
+pub mod app_state;
+pub mod cli_args;
+pub mod error;
+pub mod matcher;
+pub mod settings;
+
+mod icons;
+
+// The terminal UI is only public for the binary
+#[doc(hidden)]
+pub mod ui;
+
+pub use app_state::{CustomDirEntry, TereAppState};
+pub use error::TereError;
+pub use settings::TereSettings;
+
+/// Changing the folder in the app state changes the working directory of the process, which is
+/// shared by all tests. The tests that do it hold this guard, so that they don't run at the same
+/// time, and the working directory is changed back to the crate folder when the test ends.
+#[cfg(test)]
+pub(crate) struct CwdGuard {
+    _lock: std::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl CwdGuard {
+    pub(crate) fn lock() -> Self {
+        static CWD_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        // a test that panicked while holding the lock doesn't affect the others
+        Self { _lock: CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner()) }
+    }
+}
+
+#[cfg(test)]
+impl Drop for CwdGuard {
+    fn drop(&mut self) {
+        let _ = std::env::set_current_dir(env!("CARGO_MANIFEST_DIR"));
+    }
+}
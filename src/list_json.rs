@@ -0,0 +1,93 @@
+//! This module contains functionality for printing the contents of a folder as JSON, without
+//! starting the UI. This is meant for using `tere` in scripts.
+
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use serde_json::{json, Value};
+
+use crate::app_state::{list_folder, with_path_context, CustomDirEntry};
+use crate::error::TereError;
+use crate::settings::TereSettings;
+
+/// Print the contents of the folder given on the command line (or the current folder) as a JSON
+/// array to stdout. The listing is filtered and sorted the same way as in the interactive mode.
+pub fn print_listing_json(settings: &TereSettings) -> Result<(), TereError> {
+    let path = settings
+        .start_path
+        .as_deref()
+        .unwrap_or_else(|| Path::new("."));
+    let listing = listing_json(path, settings)?;
+    match writeln!(std::io::stdout(), "{}", serde_json::to_string_pretty(&listing)?) {
+        // the output was piped to a program that exited early (e.g. 'head'), that's fine
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        res => Ok(res?),
+    }
+}
+
+fn listing_json(path: &Path, settings: &TereSettings) -> Result<Value, TereError> {
+    Ok(Value::Array(
+        list_folder(path, settings)
+            .map_err(|e| with_path_context(e, path))?
+            .iter()
+            .map(entry_to_json)
+            .collect(),
+    ))
+}
+
+/// Convert a directory entry to a JSON object. If the metadata of the entry can't be read, the
+/// size and modification time are null.
+fn entry_to_json(entry: &CustomDirEntry) -> Value {
+    let size = entry.metadata().map(|m| m.len());
+    let mtime = entry
+        .metadata()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+
+    json!({
+        "name": entry.file_name_checked(),
+        "is_dir": entry.is_dir(),
+        "size": size,
+        "mtime": mtime,
+        "symlink_target": entry.symlink_target.as_ref().map(|t| t.to_string_lossy()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ListingMode;
+
+    #[test]
+    fn test_listing_json() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::fs::write(tmp.join("b-file"), "hello").unwrap();
+        std::fs::write(tmp.join("a-file"), "").unwrap();
+
+        let mut settings = TereSettings::default();
+        let all = listing_json(&tmp, &settings).unwrap();
+        settings.listing_mode = ListingMode::FilesOnly;
+        let files_only = listing_json(&tmp, &settings).unwrap();
+
+        let names: Vec<&str> = all
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        // folders first, then files sorted by name
+        assert_eq!(names, vec!["folder", "a-file", "b-file"]);
+
+        assert_eq!(all[0]["is_dir"], json!(true));
+        assert_eq!(all[2]["is_dir"], json!(false));
+        assert_eq!(all[2]["size"], json!(5));
+        assert!(all[2]["mtime"].as_u64().unwrap() > 0);
+        assert_eq!(all[2]["symlink_target"], Value::Null);
+
+        assert_eq!(files_only.as_array().unwrap().len(), 2);
+    }
+}
@@ -1,98 +1,213 @@
 use crossterm::{cursor, execute, terminal};
-use std::io::Write;
+use std::ffi::OsString;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
 
 //TODO: rustfmt
 //TODO: clippy
 
-mod cli_args;
+use tere::{app_state, cli_args, error, settings, ui};
 
-mod settings;
-use settings::TereSettings;
+use settings::{default_prefs_file, read_prefs, remove_invalid_prefs, TereSettings};
 
-mod app_state;
 use app_state::TereAppState;
 
 mod first_run_check;
 use first_run_check::check_first_run_with_prompt;
 
-mod ui;
-use ui::TereTui;
+mod list_json;
+use list_json::print_listing_json;
+
+mod shell_integration;
+use shell_integration::print_shell_integration;
+
+use ui::{EventSource, ExitReason, ScriptedEvents, TereTui, TerminalEvents};
 
-mod error;
 use error::TereError;
 
 mod panic_guard;
 use panic_guard::GuardWithHook;
 
+use ui::inline_region::{enter_inline_region, leave_inline_region};
+
+/// The exit code when the user exits without selecting a folder, e.g. with Ctrl-c. Errors use the
+/// exit code 1, and invalid command line arguments 2.
+const EXIT_CODE_ABORTED: i32 = 130;
+
+/// The exit code for an error from parsing the command line arguments, the same that clap uses
+/// when exiting by itself: 0 for '--help' and '--version', and 2 for invalid arguments.
+fn clap_exit_code(err: &clap::Error) -> i32 {
+    if err.use_stderr() {
+        2
+    } else {
+        0
+    }
+}
+
+/// Print an error message to stderr and exit with the exit code 1.
+fn exit_with_error(err: TereError) -> ! {
+    eprintln!("{}: {}", env!("CARGO_PKG_NAME"), err);
+    std::process::exit(1);
+}
+
+/// Prepare for reading `events` and drawing the UI to stderr. If the events are read from the
+/// terminal, this checks that stderr is a terminal too, so that a file or a pipe is not filled with
+/// escape sequences, and enables raw mode. The keys given with `--keys` don't need a terminal.
+fn start_terminal(events: &mut dyn EventSource, stderr_is_terminal: bool) -> Result<(), TereError> {
+    if !events.is_terminal() {
+        return Ok(());
+    }
+    if !stderr_is_terminal {
+        return Err(TereError::NotATerminal("stderr is not a terminal".into()));
+    }
+    events
+        .enable_raw_mode()
+        .map_err(|e| TereError::NotATerminal(format!("can't read the keyboard: {}", e)))
+}
+
 fn main() -> Result<(), TereError> {
-    let cli_args = cli_args::get_cli_args()
-        .try_get_matches()
-        .unwrap_or_else(|err| {
-            // custom error handling: clap writes '--help' and '--version'
-            // to stdout by default, but we want to print those to stderr
-            // as well to not interfere with the intended behavior of tere
-            eprint!("{}", err);
-            std::process::exit(1);
-        });
+    let get_matches = |args: Vec<OsString>| {
+        cli_args::get_cli_args()
+            .try_get_matches_from(args)
+            .unwrap_or_else(|err| {
+                // custom error handling: clap writes '--help' and '--version'
+                // to stdout by default, but we want to print those to stderr
+                // as well to not interfere with the intended behavior of tere
+                eprint!("{}", err);
+                std::process::exit(clap_exit_code(&err));
+            })
+    };
+    let mut cli_args = get_matches(std::env::args_os().collect());
 
+    if let Some(shell) = cli_args.get_one::<String>("print-shell-integration") {
+        // Non-interactive mode, the other options don't matter
+        return print_shell_integration(shell).or_else(|e| exit_with_error(e));
+    }
+
+    // The saved preferences are parsed as if they were given before the other arguments, so that
+    // the arguments given on the command line take precedence.
+    let mut prefs_warnings = vec![];
+    if let (true, Some(prefs_file)) = (cli_args.get_flag("save-prefs"), default_prefs_file()) {
+        let mut prefs = read_prefs(&prefs_file).unwrap_or_else(|e| exit_with_error(e.into()));
+        prefs_warnings = remove_invalid_prefs(&mut prefs);
+        let mut args = std::env::args_os();
+        let args_with_prefs = args
+            .next()
+            .into_iter()
+            .chain(prefs.into_values().flatten().map(OsString::from))
+            .chain(args)
+            .collect();
+        cli_args = get_matches(args_with_prefs);
+    }
+
+    if cli_args.get_flag("list-json") {
+        // Non-interactive mode, print the listing without starting the UI
+        return TereSettings::parse_cli_args(&cli_args)
+            .and_then(|(settings, _)| print_listing_json(&settings))
+            .or_else(|err| match err {
+                TereError::Clap(e) => e.exit(),
+                e => exit_with_error(e),
+            });
+    }
+
+    let (settings, mut warnings) = TereSettings::parse_cli_args(&cli_args).unwrap_or_else(|err| match err {
+        // Print pretty error message if the error was in arg parsing
+        TereError::Clap(e) => e.exit(),
+        e => exit_with_error(e),
+    });
+    warnings.extend(prefs_warnings);
+
+    let mut events: Box<dyn EventSource> = match &settings.scripted_keys {
+        Some(keys) => Box::new(ScriptedEvents::from_keys(keys)),
+        None => Box::new(TerminalEvents),
+    };
+    if let Err(err) = start_terminal(events.as_mut(), std::io::stderr().is_terminal()) {
+        exit_with_error(err);
+    }
     //TODO: should this alternate screen etc initialization (and teardown) be done by the UI?
     //Now the mouse capture enabling (which is kind of similar) is handled there.
-    execute!(std::io::stderr(), terminal::EnterAlternateScreen)?;
-    let res: Result<std::path::PathBuf, TereError> = {
+    // With --inline, the UI is drawn in some rows below the cursor instead of the alternate screen.
+    // The UI moves the region if needed, so the row where it starts is shared with it.
+    let inline_rows = settings.inline_rows;
+    let inline_top = Arc::new(AtomicU16::new(0));
+    let res: Result<ExitReason, TereError> = {
         // Use guards to ensure that we disable raw mode, show the cursor and leave the alternate
         // screen, even in the event of a panic. We are using unwrap quite liberally here, but the
         // guards should ensure that everything is handled correctly in the very unlikely event
         // that terminal modification calls fail.
-        let _guard = GuardWithHook::new(|| {
-            execute!(std::io::stderr(), terminal::LeaveAlternateScreen).unwrap()
+        let raw_mode = events.is_terminal();
+        let _guard = GuardWithHook::new(move || {
+            if raw_mode {
+                terminal::disable_raw_mode().unwrap()
+            }
         });
 
-        execute!(std::io::stderr(), cursor::Hide).unwrap();
-        {
-            let _guard = GuardWithHook::new(|| execute!(std::io::stderr(), cursor::Show).unwrap());
+        match inline_rows {
+            Some(rows) => enter_inline_region(&mut std::io::stderr(), rows)
+                .map(|top| inline_top.store(top, Ordering::Relaxed)),
+            None => execute!(std::io::stderr(), terminal::EnterAlternateScreen),
+        }
+        .map_err(TereError::from)
+        .and_then(|_| {
+            let guard_inline_top = inline_top.clone();
+            let _guard = GuardWithHook::new(move || match inline_rows {
+                Some(_) => {
+                    let top = guard_inline_top.load(Ordering::Relaxed);
+                    leave_inline_region(&mut std::io::stderr(), top).unwrap()
+                }
+                None => execute!(std::io::stderr(), terminal::LeaveAlternateScreen).unwrap(),
+            });
 
-            terminal::enable_raw_mode().unwrap();
-            {
-                let _guard = GuardWithHook::new(|| terminal::disable_raw_mode().unwrap());
+            execute!(std::io::stderr(), cursor::Hide).unwrap();
+            let _guard = GuardWithHook::new(|| execute!(std::io::stderr(), cursor::Show).unwrap());
 
-                // We are now inside the alternate screen, with the cursor hidden and raw mode
-                // enabled. We can finally actually run the application.
+            // We are now inside the alternate screen (or the inline region), with the cursor hidden and raw mode
+            // enabled. We can finally actually run the application.
 
-                let mut stderr = std::io::stderr();
+            let mut stderr = std::io::stderr();
 
-                stderr
-                    .flush()
-                    .map_err(TereError::from)
-                    .and_then(|_| TereSettings::parse_cli_args(&cli_args))
-                    .and_then(|(settings, warnings)| {
-                        check_first_run_with_prompt(&settings, &mut stderr)?;
-                        Ok((settings, warnings))
+            stderr
+                .flush()
+                .map_err(TereError::from)
+                .and_then(|_| {
+                    check_first_run_with_prompt(&settings, &mut stderr)?;
+                    Ok((settings, warnings))
+                })
+                .and_then(|(settings, warnings)| TereAppState::init(settings, &warnings))
+                .and_then(|state| TereTui::init(state, Box::new(&mut stderr), events))
+                .map(|mut ui| {
+                    ui.set_inline_top(inline_top);
+                    ui
+                })
+                // actually run the app and return the final path, if any
+                .and_then(|mut ui| {
+                    Ok(match ui.main_event_loop()? {
+                        ExitReason::Selected(path) => ExitReason::Selected(ui.path_to_print(&path)),
+                        reason => reason,
                     })
-                    .and_then(|(settings, warnings)| TereAppState::init(settings, &warnings))
-                    .and_then(|state| TereTui::init(state, &mut stderr))
-                    // actually run the app and return the final path
-                    .and_then(|mut ui| ui.main_event_loop())
-            }
-        }
+                })
+        })
     };
 
     // Check if there was an error
     let final_path = match res {
         Err(err) => {
             match err {
-                // Print pretty error message if the error was in arg parsing
-                TereError::Clap(e) => e.exit(),
-
-                TereError::ExitWithoutCd(msg) | TereError::FirstRunPromptCancelled(msg) => {
+                TereError::FirstRunPromptCancelled(msg) => {
                     eprintln!("{}", msg);
-                    std::process::exit(1);
+                    std::process::exit(EXIT_CODE_ABORTED);
                 }
 
-                // exit in case of any other error
-                e => return Err(e),
+                // exit in case of any other error, now that the terminal has been restored
+                e => exit_with_error(e),
             }
         }
-        Ok(path) => path,
+        Ok(ExitReason::Selected(path)) => path,
+        Ok(ExitReason::Aborted) => {
+            eprintln!("{}: Exited without changing folder", env!("CARGO_PKG_NAME"));
+            std::process::exit(EXIT_CODE_ABORTED);
+        }
     };
 
     // No error, print cwd, as returned by the app state
@@ -100,3 +215,69 @@ fn main() -> Result<(), TereError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Events that should be read from a terminal that can't be put in raw mode, like when stdin
+    /// is redirected and there's no controlling terminal either
+    struct NoKeyboard;
+
+    impl EventSource for NoKeyboard {
+        fn size(&self) -> crossterm::Result<(u16, u16)> {
+            Ok((80, 24))
+        }
+
+        fn poll(&mut self, _timeout: std::time::Duration) -> crossterm::Result<bool> {
+            unreachable!("the events are not read without a terminal")
+        }
+
+        fn read(&mut self) -> crossterm::Result<Option<crossterm::event::Event>> {
+            unreachable!("the events are not read without a terminal")
+        }
+
+        fn is_terminal(&self) -> bool {
+            true
+        }
+
+        fn enable_raw_mode(&mut self) -> crossterm::Result<()> {
+            Err(std::io::Error::other("No such device or address"))
+        }
+    }
+
+    #[test]
+    fn test_not_a_terminal() {
+        let err = start_terminal(&mut NoKeyboard, false).unwrap_err();
+        assert!(matches!(err, TereError::NotATerminal(_)));
+        assert_eq!(
+            err.to_string(),
+            "must be run in an interactive terminal (stderr is not a terminal)",
+        );
+
+        let err = start_terminal(&mut NoKeyboard, true).unwrap_err();
+        assert!(matches!(err, TereError::NotATerminal(_)));
+        assert_eq!(
+            err.to_string(),
+            "must be run in an interactive terminal (can't read the keyboard: No such device or address)",
+        );
+
+        // the keys given with --keys don't need a terminal at all
+        let mut events = ScriptedEvents::from_keys(&[]);
+        assert!(start_terminal(&mut events, false).is_ok());
+    }
+
+    #[test]
+    fn test_cli_args_exit_code() {
+        let exit_code = |args: &[&str]| {
+            let err = cli_args::get_cli_args()
+                .try_get_matches_from(args)
+                .unwrap_err();
+            clap_exit_code(&err)
+        };
+        assert_eq!(exit_code(&["tere", "--bogus"]), 2);
+        assert_eq!(exit_code(&["tere", "--sort", "nope"]), 2);
+        assert_eq!(exit_code(&["tere", "--help"]), 0);
+        assert_eq!(exit_code(&["tere", "--version"]), 0);
+    }
+}
@@ -0,0 +1,156 @@
+//! Matching the search query against the names of the items. The algorithm is chosen with the
+//! `--fuzzy-algo` option. Each algorithm implements the `Matcher` trait, which tells which
+//! characters of a name matched and how good the match is, so that the matching can be tested
+//! without drawing anything.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use regex::Regex;
+use std::fmt::Write as _;
+
+use crate::settings::{FuzzyAlgo, GapSearchMode};
+
+pub trait Matcher {
+    /// Match the query against `target`. Returns None if it doesn't match. Otherwise, returns the
+    /// score of the match, higher being better, and the byte offsets of the characters of `target`
+    /// that matched the query, in increasing order.
+    fn find(&self, target: &str) -> Option<(i64, Vec<usize>)>;
+}
+
+/// Create a matcher for `query` with the given algorithm. The query and the targets are compared
+/// as they are, so they should already be lowercased for a case insensitive search.
+pub fn new_matcher(algo: FuzzyAlgo, query: &str, gap_search_mode: &GapSearchMode) -> Box<dyn Matcher> {
+    match algo {
+        FuzzyAlgo::Substr => Box::new(SubstrMatcher::new(query, gap_search_mode)),
+        FuzzyAlgo::Skim => Box::new(SkimMatcher::new(query)),
+    }
+}
+
+/// The built-in matching, which finds the query as a substring of the name, or with gaps between
+/// its characters depending on the gap search mode. All matches have the same score.
+pub struct SubstrMatcher {
+    ptn: Regex,
+}
+
+impl SubstrMatcher {
+    pub fn new(query: &str, gap_search_mode: &GapSearchMode) -> Self {
+        let mut regex_str = "".to_string();
+        if gap_search_mode == &GapSearchMode::NormalSearch {
+            let _ = write!(regex_str, "^({})", regex::escape(query));
+        } else if gap_search_mode == &GapSearchMode::NormalSearchAnywhere {
+            let _ = write!(regex_str, "({})", regex::escape(query));
+        } else {
+            // enable gap search. Add '^' to the regex to match only from the start if applicable.
+            if gap_search_mode == &GapSearchMode::GapSearchFromStart {
+                regex_str.push('^');
+            }
+            regex_str.push_str(
+                &query
+                    .chars()
+                    .map(|c| format!("({})", regex::escape(&c.to_string())))
+                    .collect::<Vec<String>>()
+                    .join(".*?"),
+            );
+        }
+
+        // ok to unwrap, we have escaped the regex above
+        Self {
+            ptn: Regex::new(&regex_str).unwrap(),
+        }
+    }
+}
+
+impl Matcher for SubstrMatcher {
+    fn find(&self, target: &str) -> Option<(i64, Vec<usize>)> {
+        let mut capture_locations = self.ptn.capture_locations();
+        self.ptn.captures_read(&mut capture_locations, target)?;
+        // have to do it this way using range because capture_locations has no iter() method
+        let positions = (1..capture_locations.len())
+            .filter_map(|i| capture_locations.get(i))
+            .flat_map(|(start, end)| target[start..end].char_indices().map(move |(i, _)| start + i))
+            .collect();
+        Some((0, positions))
+    }
+}
+
+/// Fuzzy matching with the algorithm of the skim fuzzy finder. The characters of the query can be
+/// anywhere in the name, and the score is higher for example for consecutive characters and
+/// characters at the start of words. The gap search mode has no effect.
+pub struct SkimMatcher {
+    matcher: SkimMatcherV2,
+    query: String,
+}
+
+impl SkimMatcher {
+    pub fn new(query: &str) -> Self {
+        Self {
+            // The case is handled by the caller
+            matcher: SkimMatcherV2::default().respect_case(),
+            query: query.to_string(),
+        }
+    }
+}
+
+impl Matcher for SkimMatcher {
+    fn find(&self, target: &str) -> Option<(i64, Vec<usize>)> {
+        let (score, char_indices) = self.matcher.fuzzy_indices(target, &self.query)?;
+        // The indices are character indices, convert them to byte offsets
+        let mut char_starts = target.char_indices().map(|(i, _)| i);
+        let mut prev = 0;
+        let positions = char_indices
+            .into_iter()
+            .filter_map(|i| {
+                let pos = char_starts.nth(i - prev);
+                prev = i + 1;
+                pos
+            })
+            .collect();
+        Some((score, positions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substr_matcher() {
+        let m = SubstrMatcher::new("ab", &GapSearchMode::NormalSearchAnywhere);
+        assert_eq!(m.find("xaby"), Some((0, vec![1, 2])));
+        assert_eq!(m.find("axb"), None);
+
+        let m = SubstrMatcher::new("ab", &GapSearchMode::NormalSearch);
+        assert_eq!(m.find("abc"), Some((0, vec![0, 1])));
+        assert_eq!(m.find("xab"), None);
+
+        let m = SubstrMatcher::new("ab", &GapSearchMode::GapSearchFromStart);
+        assert_eq!(m.find("axxb"), Some((0, vec![0, 3])));
+        assert_eq!(m.find("xab"), None);
+
+        let m = SubstrMatcher::new("äb", &GapSearchMode::GapSearchAnywhere);
+        assert_eq!(m.find("xäxb"), Some((0, vec![1, 4])));
+
+        // the empty query matches everything
+        let m = SubstrMatcher::new("", &GapSearchMode::GapSearchFromStart);
+        assert_eq!(m.find("abc"), Some((0, vec![])));
+    }
+
+    #[test]
+    fn test_skim_matcher() {
+        let m = SkimMatcher::new("dt");
+        let (_, positions) = m.find("desktop").unwrap();
+        assert_eq!(positions, vec![0, 4]);
+        // the positions are byte offsets
+        let (_, positions) = m.find("ädt").unwrap();
+        assert_eq!(positions, vec![2, 3]);
+        assert_eq!(m.find("td"), None);
+        // the case is not ignored, the caller lowercases the names if needed
+        assert_eq!(m.find("DT"), None);
+
+        // consecutive characters and word starts score higher
+        let m = SkimMatcher::new("doc");
+        let (good, _) = m.find("documents").unwrap();
+        let (bad, _) = m.find("xdxoxc").unwrap();
+        assert!(good > bad);
+    }
+}
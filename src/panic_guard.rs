@@ -1,6 +1,6 @@
 use std::sync::{Arc, Mutex};
 
-type PanicHookType = (dyn for<'r, 's> Fn(&'r std::panic::PanicInfo<'s>) + Send + Sync + 'static);
+type PanicHookType = dyn Fn(&std::panic::PanicHookInfo<'_>) + Send + Sync + 'static;
 
 /// Custom scopeguard-like struct that wraps a panic hook function and a callback ("cleanup")
 /// function, and in the case of a panic, calls the callback *before* the wrapped panic hook (i.e.
@@ -94,6 +94,30 @@ mod tests {
         assert_eq!(*called.lock().unwrap(), 1);
     }
 
+    #[test]
+    fn test_callback_called_on_error_return() {
+        let _m = TEST_MUTEX.lock().unwrap();
+
+        let called = Arc::new(Mutex::new(0));
+
+        fn failing_operation() -> Result<(), std::io::Error> {
+            Err(std::io::Error::other("test"))
+        }
+
+        let run = |called: Arc<Mutex<usize>>| -> Result<(), std::io::Error> {
+            let _guard = GuardWithHook::new(move || {
+                *called.lock().unwrap() += 1;
+            });
+            failing_operation()?;
+            unreachable!("the operation above should have failed");
+        };
+
+        assert!(run(called.clone()).is_err());
+        // guard was dropped when returning early with '?', so the callback should have been
+        // called exactly once
+        assert_eq!(*called.lock().unwrap(), 1);
+    }
+
     #[test]
     fn test_callback_called_once_only_panic() {
         let _m = TEST_MUTEX.lock().unwrap();
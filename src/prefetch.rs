@@ -0,0 +1,172 @@
+//! Reading the listings of folders in the background before they are entered, so that entering
+//! them feels instant even on a slow disk. The UI starts prefetching the folder under the cursor
+//! when the cursor has stayed on it for a moment, and changing to the folder then uses the
+//! prefetched listing (or waits for the reading that is already in progress) instead of reading
+//! the folder again.
+
+use std::collections::HashMap;
+use std::io::Result as IOResult;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{read_listing_entries, CustomDirEntry, ListingOptions};
+
+/// Prefetched listings older than this are not used, because the folder might have changed since
+pub const PREFETCH_MAX_AGE: Duration = Duration::from_secs(5);
+
+/// The maximum number of folders to keep prefetched. The oldest one is dropped to make room.
+pub const PREFETCH_CACHE_SIZE: usize = 8;
+
+pub type ListingReceiver = Receiver<IOResult<Vec<CustomDirEntry>>>;
+
+struct Prefetched {
+    started: Instant,
+    options: ListingOptions,
+    // The result is sent here by the thread that reads the folder
+    receiver: ListingReceiver,
+    // Set to stop the reading, e.g. with recursive search, when the listing is not needed any more
+    cancel: Arc<AtomicBool>,
+}
+
+impl Prefetched {
+    fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+pub struct PrefetchCache {
+    entries: HashMap<PathBuf, Prefetched>,
+}
+
+impl PrefetchCache {
+    /// True if the listing of `path` with `options` is being read or was read recently
+    pub fn contains(&self, path: &Path, options: &ListingOptions) -> bool {
+        self.entries
+            .get(path)
+            .is_some_and(|p| &p.options == options && p.started.elapsed() < PREFETCH_MAX_AGE)
+    }
+
+    /// Start reading the listing of `path` in a background thread, unless it's already cached
+    pub fn start(&mut self, path: PathBuf, options: ListingOptions) {
+        if self.contains(&path, &options) {
+            return;
+        }
+        self.entries.retain(|_, p| {
+            let keep = p.started.elapsed() < PREFETCH_MAX_AGE;
+            if !keep {
+                p.cancel();
+            }
+            keep
+        });
+        if self.entries.len() >= PREFETCH_CACHE_SIZE {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, p)| p.started)
+                .map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest.and_then(|oldest| self.entries.remove(&oldest)) {
+                oldest.cancel();
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let thread_path = path.clone();
+        let thread_options = options.clone();
+        let thread_cancel = Arc::clone(&cancel);
+        std::thread::spawn(move || {
+            // if the receiver is gone, the listing is not needed any more
+            sender
+                .send(read_listing_entries(&thread_path, &thread_options, &thread_cancel))
+                .ok();
+        });
+        let prefetched = Prefetched { started: Instant::now(), options, receiver, cancel };
+        // the folder may have been prefetched with other options before
+        if let Some(replaced) = self.entries.insert(path, prefetched) {
+            replaced.cancel();
+        }
+    }
+
+    /// Remove the listing of `path` from the cache and return the channel where it's received, if
+    /// it was read with the same options recently enough, and the flag for cancelling the reading.
+    /// The listing may still be being read.
+    pub fn take(
+        &mut self,
+        path: &Path,
+        options: &ListingOptions,
+    ) -> Option<(ListingReceiver, Arc<AtomicBool>)> {
+        if !self.contains(path, options) {
+            return None;
+        }
+        self.entries.remove(path).map(|p| (p.receiver, p.cancel))
+    }
+}
+
+impl Drop for PrefetchCache {
+    fn drop(&mut self) {
+        for prefetched in self.entries.values() {
+            prefetched.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefetch_cache() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::fs::write(tmp.join("file"), "").unwrap();
+
+        let options = ListingOptions::default();
+        let mut cache = PrefetchCache::default();
+        assert!(cache.take(&tmp, &options).is_none());
+
+        cache.start(tmp.clone(), options.clone());
+        assert!(cache.contains(&tmp, &options));
+        // the listing is only used with the same options
+        let other_options = ListingOptions { gitignore: true, ..options.clone() };
+        assert!(!cache.contains(&tmp, &other_options));
+        assert!(cache.take(&tmp, &other_options).is_none());
+
+        let (receiver, cancel) = cache.take(&tmp, &options).unwrap();
+        assert!(!cancel.load(Ordering::Relaxed));
+        let entries = receiver.recv().unwrap().unwrap();
+        let mut names: Vec<_> = entries.iter().map(|e| e.file_name_checked()).collect();
+        names.sort();
+        assert_eq!(names, vec!["file", "folder"]);
+        // the listing is used only once
+        assert!(cache.take(&tmp, &options).is_none());
+
+        // old listings are not used, and their reading is cancelled
+        cache.start(tmp.clone(), options.clone());
+        let prefetched = cache.entries.get_mut(&tmp).unwrap();
+        prefetched.started = Instant::now().checked_sub(PREFETCH_MAX_AGE).unwrap();
+        let expired_cancel = Arc::clone(&prefetched.cancel);
+        assert!(cache.take(&tmp, &options).is_none());
+        assert!(!expired_cancel.load(Ordering::Relaxed));
+        cache.start(tmp.join("other"), options.clone());
+        assert!(expired_cancel.load(Ordering::Relaxed));
+
+        // the size of the cache is bounded, and the reading of the dropped listings is cancelled
+        let first_cancel = Arc::clone(&cache.entries[&tmp.join("other")].cancel);
+        for i in 0..PREFETCH_CACHE_SIZE + 2 {
+            cache.start(tmp.join(i.to_string()), options.clone());
+        }
+        assert_eq!(cache.entries.len(), PREFETCH_CACHE_SIZE);
+        assert!(first_cancel.load(Ordering::Relaxed));
+
+        // dropping the cache cancels the rest
+        let last_cancel = Arc::clone(&cache.entries[&tmp.join((PREFETCH_CACHE_SIZE + 1).to_string())].cancel);
+        assert!(!last_cancel.load(Ordering::Relaxed));
+        drop(cache);
+        assert!(last_cancel.load(Ordering::Relaxed));
+    }
+}
@@ -1,31 +1,33 @@
 /// Module for managing the settings (command line arguments) of the app
+use chrono::format::{Item, StrftimeItems};
 use clap::{error::ErrorKind as ClapErrorKind, ArgMatches, Error as ClapError};
 use crokey::key;
 use crossterm::event::KeyEvent;
-use std::collections::HashMap;
+use globset::{Glob, GlobMatcher};
+use crossterm::style::Color;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::fmt;
-use std::path::PathBuf;
+use std::io::{ErrorKind, Result as IOResult};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 use strum_macros::EnumIter;
 
+use crate::app_state::FooterTemplate;
 use crate::error::TereError;
 use crate::ui::{Action, ActionContext};
 
 //TODO: config file?
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Default)]
 pub enum CaseSensitiveMode {
     IgnoreCase,
     CaseSensitive,
+    #[default]
     SmartCase,
 }
 
-impl Default for CaseSensitiveMode {
-    fn default() -> Self {
-        Self::SmartCase
-    }
-}
-
 impl fmt::Display for CaseSensitiveMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let text = match self {
@@ -37,20 +39,15 @@ impl fmt::Display for CaseSensitiveMode {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Default)]
 pub enum GapSearchMode {
     NormalSearch,
     NormalSearchAnywhere,
+    #[default]
     GapSearchFromStart,
     GapSearchAnywhere,
 }
 
-impl Default for GapSearchMode {
-    fn default() -> Self {
-        Self::GapSearchFromStart
-    }
-}
-
 impl fmt::Display for GapSearchMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let text = match self {
@@ -63,19 +60,47 @@ impl fmt::Display for GapSearchMode {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, EnumIter, clap::ValueEnum)]
-pub enum SortMode {
+/// The algorithm that matches the search query against the item names
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum FuzzyAlgo {
+    /// Find the query as a substring, or with gaps depending on the gap search mode
+    #[default]
+    Substr,
+    /// The fuzzy matching algorithm of skim, which ranks the matches by their score
+    Skim,
+}
+
+/// Which part of the item names the search is matched against
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum SearchScope {
+    /// The whole name
+    #[default]
     Name,
-    Created,
-    Modified,
+    /// Only the extension, i.e. the part after the last '.'
+    Extension,
+    /// The name without the extension
+    Stem,
 }
 
-impl Default for SortMode {
-    fn default() -> Self {
-        Self::Name
+impl fmt::Display for SearchScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            SearchScope::Name      => "name",
+            SearchScope::Extension => "ext",
+            SearchScope::Stem      => "stem",
+        };
+        write!(f, "{}", text)
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, EnumIter, clap::ValueEnum)]
+pub enum SortMode {
+    #[default]
+    Name,
+    Created,
+    Modified,
+}
+
 impl fmt::Display for SortMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let text = match self {
@@ -87,44 +112,479 @@ impl fmt::Display for SortMode {
     }
 }
 
+/// How to show the current path in the header
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum HeaderFormat {
+    /// The full path
+    #[default]
+    Full,
+    /// The full path, with the home folder replaced by '~'
+    HomeTilde,
+    /// Like home-tilde, but shorten folder names if the path doesn't fit on the screen
+    Shortened,
+}
+
+/// How the parent folders of the current folder are drawn in the header. The current folder is
+/// always drawn bold and underlined.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum HeaderParentStyle {
+    #[default]
+    Dim,
+    /// The same as the current folder
+    Bold,
+    Color(Color),
+}
+
+/// Whether to copy absolute paths or paths relative to the folder where tere was started
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum CopyPathFormat {
+    #[default]
+    Absolute,
+    Relative,
+}
+
+/// How far Page Up and Page Down move the cursor
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum PageScroll {
+    /// One screenful, minus one row so that the previous edge stays visible
+    #[default]
+    Full,
+    /// Half a screenful, like Ctrl-u / Ctrl-d in Vim
+    Half,
+}
+
+/// How the header, the listing, the info line and the footer are arranged on the screen
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum Layout {
+    /// The header at the top, then the listing, and the info line and the footer at the bottom
+    #[default]
+    Classic,
+    /// Like classic, but without the info line. Messages are shown in the footer instead.
+    Compact,
+    /// The header, the footer with the search and the info line at the top, then the listing
+    TopSearch,
+}
+
+/// How file sizes are shown
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum SizeFormat {
+    /// Powers of 1024, like `1.5 KiB`
+    #[default]
+    Binary,
+    /// Powers of 1000, like `1.5 kB`
+    Decimal,
+    /// The exact number of bytes, like `1536 B`
+    Bytes,
+}
+
+/// How modification times are shown
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub enum TimeStyle {
+    /// The local date and time, like `2024-05-01 13:45`. This is the same as
+    /// `ls --time-style=long-iso`.
+    #[default]
+    Iso,
+    /// How long ago, like `2h ago`
+    Relative,
+    /// A custom strftime-style format, like `%d.%m.%Y %H:%M`
+    Format(String),
+}
+
+/// What to do when trying to enter a file
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum EnterOnFile {
+    /// Stay in the current folder, and show a message
+    #[default]
+    Nothing,
+    /// Exit and print the path of the file
+    Select,
+    /// Open the file with the open command
+    Open,
+}
+
+/// How to mark the item under the cursor
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, clap::ValueEnum)]
+pub enum CursorStyle {
+    /// Draw the whole row in the cursor color
+    #[default]
+    Reverse,
+    /// Draw an arrow in front of the item
+    Arrow,
+    /// Draw a bar in front of the item
+    Bar,
+}
+
+impl CursorStyle {
+    /// The marker drawn in front of the item under the cursor. The other items are indented by the
+    /// width of the marker, so that the names stay aligned.
+    pub fn marker(&self) -> Option<&'static str> {
+        match self {
+            CursorStyle::Reverse => None,
+            CursorStyle::Arrow => Some(">"),
+            CursorStyle::Bar => Some("▌"),
+        }
+    }
+}
+
+/// Which types of items to show in the listing
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ListingMode {
+    #[default]
+    All,
+    FoldersOnly,
+    FilesOnly,
+}
+
+impl fmt::Display for ListingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ListingMode::All         => "files and folders",
+            ListingMode::FoldersOnly => "folders only",
+            ListingMode::FilesOnly   => "files only",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// The time window used by the modified-within filter if `--modified-within` is not given
+pub const DEFAULT_MODIFIED_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[derive(Default)]
 pub struct TereSettings {
-    /// If true, show only folders, not files in the listing
-    pub folders_only: bool,
+    /// The folder to start browsing from. If None, start from the current working directory.
+    pub start_path: Option<PathBuf>,
+
+    /// Whether to show files, folders, or both in the listing
+    pub listing_mode: ListingMode,
+    /// If not None, show only the files whose name matches this glob pattern. Folders are always
+    /// shown.
+    pub file_filter: Option<GlobMatcher>,
+    /// If true, hide the items that are ignored by git
+    pub gitignore: bool,
+    /// The names of the folders to hide from the listing
+    pub exclude: Vec<String>,
+    /// If true, show the folders in `exclude` anyway
+    pub show_excluded: bool,
+    /// If true, show only the files modified within `modified_window`. Folders are always shown.
+    pub modified_filter: bool,
+    /// The time window for `modified_filter`
+    pub modified_window: Duration,
     /// If true, show only items matching the search in listing
     pub filter_search: bool,
+    /// If true, list and search the subfolders of the current folder too
+    pub recursive_search: bool,
+    /// How many levels of subfolders to list with recursive search
+    pub recursive_search_depth: usize,
+    /// How many levels of subfolders any recursive operation reads at most
+    pub max_depth: usize,
 
     pub case_sensitive: CaseSensitiveMode,
 
+    /// If true, remove diacritics from the search and the names before matching them
+    pub ignore_diacritics: bool,
+
     pub sort_mode: SortMode,
 
+    pub search_scope: SearchScope,
+
+    pub fuzzy_algo: FuzzyAlgo,
+
+    pub header_format: HeaderFormat,
+
+    pub header_parent_style: HeaderParentStyle,
+
+    /// If not None, the format of the counts in the footer when not searching
+    pub footer_format: Option<FooterTemplate>,
+    /// If not None, the format of the counts in the footer while searching
+    pub search_footer_format: Option<FooterTemplate>,
+
+    pub copy_path_format: CopyPathFormat,
+
+    /// The folder that the printed path is relative to, relative to the folder where the app was
+    /// started. If None, the absolute path is printed.
+    pub relative_to: Option<PathBuf>,
+
+    pub page_scroll: PageScroll,
+
+    pub layout: Layout,
+
+    pub size_format: SizeFormat,
+
+    pub time_style: TimeStyle,
+
     pub autocd_timeout: Option<u64>,
 
+    /// If true, also change dir automatically if the search is the start of only one folder name
+    pub autocd_prefix: bool,
+
+    /// Don't change dir automatically unless the search is at least this many characters long
+    pub autocd_min_chars: usize,
+
+    /// If true, ignore the keys pressed while waiting for the autocd timeout
+    pub autocd_discard_keys: bool,
+
+    /// If true, keep searching after changing dir from a search, until the search is cleared
+    pub continuous_search: bool,
+
+    /// If true, ring the terminal bell when a typed character leaves the search without matches
+    pub bell_on_no_match: bool,
+
+    /// Clear info messages after this many seconds. None means that they are never cleared.
+    pub info_timeout: Option<u64>,
+    /// Clear error messages after this many seconds. None means that they are never cleared.
+    pub error_timeout: Option<u64>,
+
     pub history_file: Option<PathBuf>,
 
+    /// If not None, save the preferences changed at runtime to this file on exit
+    pub prefs_file: Option<PathBuf>,
+
     /// whether to allow matches with gaps in them, and if we have to match from beginning
     pub gap_search_mode: GapSearchMode,
 
     pub mouse_enabled: bool,
 
+    /// If not None, draw the UI in this many rows below the cursor instead of the alternate screen
+    pub inline_rows: Option<u16>,
+
+    /// If true, show the items in multiple columns if they fit
+    pub grid: bool,
+
+    /// If true, show the permissions, owner and size of the item under the cursor in the info
+    /// window when there's no other message
+    pub show_details: bool,
+
+    /// If true, show the summed size of the files in the current folder in the footer
+    pub show_total_size: bool,
+
+    /// If true, restore the last search used in a folder when coming back to it
+    pub remember_search: bool,
+
+    /// If true, leave an empty row between the folders and the files in the listing
+    pub group_separator: bool,
+
+    /// If true, show a preview of the item under the cursor next to the listing
+    pub preview_enabled: bool,
+
+    /// If true, show an icon in front of each item
+    pub icons: bool,
+
+    /// Icons that replace the built-in ones, by lowercase extension, or 'folder' or 'file'
+    pub icon_map: HashMap<String, String>,
+
+    /// If true, show row numbers next to the items, and use digits for jumping to rows instead of
+    /// searching
+    pub number_mode: bool,
+
+    /// What happens when trying to enter a file, e.g. by pressing Enter on it
+    pub enter_on_file: EnterOnFile,
+
+    /// If true, ask for confirmation before exiting with the `Exit` action
+    pub confirm_quit: bool,
+
+    /// If true, folders can be created with the `CreateFolder` action
+    pub allow_mkdir: bool,
+
+    /// The command for opening files. If None, text files are opened in $VISUAL or $EDITOR, and
+    /// other files with the default application of the system.
+    pub open_command: Option<String>,
+
+    /// The command for opening the current folder in a file manager. If None, the default file
+    /// manager of the system is used.
+    pub file_manager_command: Option<String>,
+
+    /// If true, the cursor stops at the top and bottom of the listing instead of wrapping around
+    pub no_wrap: bool,
+
+    /// The minimum number of items to keep visible above and below the cursor when scrolling
+    pub scroll_off: usize,
+
+    /// How long to wait for more characters before updating the search in large folders. None
+    /// if the search is always updated immediately.
+    pub search_debounce: Option<Duration>,
+
+    /// The number of empty columns on both sides of the listing
+    pub padding: usize,
+
+    /// The text color of the characters that match the search. If None, the matching characters
+    /// are only underlined.
+    pub match_color: Option<Color>,
+
+    /// The color of the search query in the footer when nothing matches it. If None, the query is
+    /// drawn normally.
+    pub warning_color: Option<Color>,
+
+    /// How to mark the item under the cursor
+    pub cursor_style: CursorStyle,
+
+    /// The background color of the cursor row with the reverse cursor style, or the color of the
+    /// marker with the other styles. If None, the cursor row is drawn in the reverse video of the
+    /// terminal, and the marker in the default text color.
+    pub cursor_color: Option<Color>,
+
+    /// If true, the folder under the cursor is not read in the background before entering it
+    pub no_prefetch: bool,
+
+    /// If true, the rows of the listing are not padded with spaces to the full width, and the
+    /// cursor row is highlighted only a bit past the name
+    pub no_row_fill: bool,
+
+    /// If true, the extensions of files are drawn in a different color at the right edge of the
+    /// listing
+    pub align_extensions: bool,
+
     pub keymap: HashMap<(KeyEvent, ActionContext), Action>,
+
+    /// The keys given with --keys, which are pressed instead of reading keys from the terminal
+    pub scripted_keys: Option<Vec<KeyEvent>>,
 }
 
-pub type DeprecationWarnings = Vec<&'static str>;
+/// Warnings to show when starting, e.g. about deprecated options
+pub type Warnings = Vec<String>;
 
 impl TereSettings {
-    pub fn parse_cli_args(args: &ArgMatches) -> Result<(Self, DeprecationWarnings), TereError> {
+    pub fn parse_cli_args(args: &ArgMatches) -> Result<(Self, Warnings), TereError> {
         let mut ret = Self::default();
         let mut warnings = vec![];
 
+        ret.start_path = args.get_one::<String>("path").map(PathBuf::from);
+
         if args.get_flag("folders-only") {
-            ret.folders_only = true;
+            ret.listing_mode = ListingMode::FoldersOnly;
+        } else if args.get_flag("files-only") {
+            ret.listing_mode = ListingMode::FilesOnly;
+        }
+
+        ret.file_filter = args
+            .get_one::<String>("filter")
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| {
+                parse_glob(pattern).map_err(|e| {
+                    ClapError::raw(
+                        ClapErrorKind::InvalidValue,
+                        format!("Invalid pattern for 'filter': {}\n", e),
+                    )
+                })
+            })
+            .transpose()?;
+
+        ret.modified_window = DEFAULT_MODIFIED_WINDOW;
+        if let Some(window) = args.get_one::<String>("modified-within") {
+            ret.modified_window = parse_duration(window)
+                .filter(|d| !d.is_zero())
+                .ok_or_else(|| {
+                    ClapError::raw(
+                        ClapErrorKind::InvalidValue,
+                        format!("Invalid value for 'modified-within': '{}'\n", window),
+                    )
+                })?;
+            ret.modified_filter = true;
         }
 
         if args.get_flag("filter-search") {
             ret.filter_search = true;
         }
 
+        if let Some(exclude_args) = args.get_many::<String>("exclude") {
+            ret.exclude = exclude_args
+                .flat_map(|arg| arg.split(','))
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect();
+        }
+
+        if args.get_flag("gitignore") {
+            ret.gitignore = true;
+        }
+
+        if args.get_flag("recursive-search") {
+            ret.recursive_search = true;
+        }
+        ret.recursive_search_depth = args
+            .get_one::<usize>("recursive-search-depth")
+            .copied()
+            .unwrap_or_default();
+        ret.max_depth = args
+            .get_one::<usize>("max-depth")
+            .copied()
+            .unwrap_or_default();
+
+        if args.get_flag("details") {
+            ret.show_details = true;
+        }
+
+        if args.get_flag("total-size") {
+            ret.show_total_size = true;
+        }
+
+        if args.get_flag("remember-search") {
+            ret.remember_search = true;
+        }
+
+        if args.get_flag("group-separator") {
+            ret.group_separator = true;
+        }
+
+        if args.get_flag("no-prefetch") {
+            ret.no_prefetch = true;
+        }
+
+        if args.get_flag("no-row-fill") {
+            ret.no_row_fill = true;
+        }
+
+        if args.get_flag("align-extensions") {
+            ret.align_extensions = true;
+        }
+
+        if args.get_flag("grid") {
+            ret.grid = true;
+        }
+
+        if args.get_flag("preview") {
+            ret.preview_enabled = true;
+        }
+
+        if args.get_flag("numbers") {
+            ret.number_mode = true;
+        }
+
+        ret.enter_on_file = if args.get_flag("pick-file") {
+            EnterOnFile::Select
+        } else {
+            args.get_one::<EnterOnFile>("enter-on-file")
+                .copied()
+                .unwrap_or_default()
+        };
+
+        if args.get_flag("confirm-quit") {
+            ret.confirm_quit = true;
+        }
+
+        if args.get_flag("allow-mkdir") {
+            ret.allow_mkdir = true;
+        }
+
+        if args.get_flag("no-wrap") {
+            ret.no_wrap = true;
+        }
+
+        if args.get_flag("autocd-prefix") {
+            ret.autocd_prefix = true;
+        }
+
+        if args.get_flag("continuous-search") {
+            ret.continuous_search = true;
+        }
+
+        if args.get_flag("bell-on-no-match") {
+            ret.bell_on_no_match = true;
+        }
+
+        if args.get_flag("ignore-diacritics") {
+            ret.ignore_diacritics = true;
+        }
+
         if args.get_flag("case-sensitive") {
             ret.case_sensitive = CaseSensitiveMode::CaseSensitive;
         } else if args.get_flag("ignore-case") {
@@ -142,31 +602,27 @@ impl TereSettings {
         } else if args.get_flag("normal-search-anywhere") {
             ret.gap_search_mode = GapSearchMode::NormalSearchAnywhere;
         } else if args.get_flag("no-gap-search") {
-            warnings.push("The option 'no-gap-search' has been renamed to 'normal-search', please use that instead.");
+            warnings.push("The option 'no-gap-search' has been renamed to 'normal-search', please use that instead.".into());
             ret.gap_search_mode = GapSearchMode::NormalSearch;
         }
 
-        ret.autocd_timeout = match args
-            .get_many::<String>("autocd-timeout")
-            // ok to unwrap because autocd-timeout has a default value which is always present
-            .unwrap()
-            .map(|v| v.as_str())
-            .last()
-            .unwrap()
-        {
-            "off" => None,
-            x => u64::from_str(x)
-                .map_err(|_| {
-                    // We don't want to pass the App all the way here, so create raw error
-                    // NOTE: We don't call error.format(app) anywhere now, but it doesn't seem to
-                    // make a difference for this error type.
-                    ClapError::raw(
-                        ClapErrorKind::InvalidValue,
-                        format!("Invalid value for 'autocd-timeout': '{}'\n", x),
-                    )
-                })?
-                .into(),
-        };
+        ret.autocd_timeout = parse_timeout_arg(args, "autocd-timeout")?;
+        ret.autocd_min_chars = args
+            .get_one::<usize>("autocd-min-chars")
+            .copied()
+            .unwrap_or_default();
+        ret.autocd_discard_keys = args.get_flag("autocd-discard-keys");
+        ret.open_command = args
+            .get_one::<String>("open-command")
+            .filter(|cmd| !cmd.trim().is_empty())
+            .cloned();
+        ret.file_manager_command = args
+            .get_one::<String>("file-manager-command")
+            .filter(|cmd| !cmd.trim().is_empty())
+            .cloned();
+
+        ret.info_timeout = parse_timeout_arg(args, "info-timeout")?;
+        ret.error_timeout = parse_timeout_arg(args, "error-timeout")?;
 
         if let Some(hist_file) = args.get_one::<String>("history-file") {
             ret.history_file = if hist_file.is_empty() {
@@ -175,15 +631,22 @@ impl TereSettings {
                 Some(PathBuf::from(hist_file))
             }
         } else {
-            ret.history_file = dirs::cache_dir()
+            ret.history_file = cache_dir()
                 .map(|path| path.join(env!("CARGO_PKG_NAME")).join("history.json"));
         }
 
-        // ok to unwrap, because mouse has the default value of 'off'
-        if args.get_one::<String>("mouse").unwrap() == "on" {
+        if args.get_flag("save-prefs") {
+            ret.prefs_file = default_prefs_file();
+        }
+
+        // ok to unwrap, because mouse has the default value of 'off'. If --no-mouse is given
+        // after --mouse, the default value is used.
+        if !args.get_flag("no-mouse") && args.get_one::<String>("mouse").unwrap() == "on" {
             ret.mouse_enabled = true;
         }
 
+        ret.inline_rows = args.get_one::<u16>("inline").copied();
+
         if let Some(false) = args.get_one::<bool>("clear-default-keymap") {
             ret.keymap = DEFAULT_KEYMAP
                 .iter()
@@ -204,6 +667,26 @@ impl TereSettings {
                 }
             }
         }
+        check_keymap_conflicts(&ret.keymap)?;
+
+        ret.icons = args.get_flag("icons");
+        if let Some(icon_map_args) = args.get_many::<String>("icon-map") {
+            for mapping in icon_map_args.flat_map(|arg| arg.split(',')) {
+                match mapping.split_once(':') {
+                    Some((name, icon)) if !name.is_empty() && !icon.is_empty() => {
+                        ret.icon_map.insert(name.to_lowercase(), icon.to_string());
+                    }
+                    _ => return Err(ClapError::raw(
+                        ClapErrorKind::InvalidValue,
+                        format!("Invalid icon mapping: '{}'\n", mapping),
+                    ).into()),
+                }
+            }
+        }
+
+        if let Some(keys_arg) = args.get_one::<String>("keys") {
+            ret.scripted_keys = Some(parse_keys_arg(keys_arg)?);
+        }
 
         if !ret.keymap.values().any(|a| a == &Action::Exit) {
             return Err(ClapError::raw(
@@ -217,103 +700,509 @@ impl TereSettings {
             .cloned()
             .unwrap_or_default();
 
-        Ok((ret, warnings))
-    }
-}
-
-fn parse_keymap_arg(arg: &str) -> Result<Vec<(KeyEvent, ActionContext, Action)>, ClapError> {
-    let mappings = arg.split(',');
-    let mut ret = Vec::new();
+        ret.search_scope = args
+            .get_one::<SearchScope>("search-scope")
+            .copied()
+            .unwrap_or_default();
 
-    fn parsekey_to_clap(mapping: &str, err: crokey::ParseKeyError) -> ClapError {
-        ClapError::raw(
-            ClapErrorKind::InvalidValue,
-            format!("Error parsing key combination '{}': {}\n", mapping, err),
-        )
-    }
+        ret.fuzzy_algo = args
+            .get_one::<FuzzyAlgo>("fuzzy-algo")
+            .copied()
+            .unwrap_or_default();
 
-    fn strum_to_clap(mapping: &str, attempted_value: &str, ctx_or_action: &str) -> ClapError {
-        ClapError::raw(
-            ClapErrorKind::InvalidValue,
-            format!(
-                "Error parsing key mapping '{}': invalid {} '{}'\n",
-                mapping, ctx_or_action, attempted_value,
-            ),
-        )
-    }
+        ret.header_format = args
+            .get_one::<HeaderFormat>("header-format")
+            .copied()
+            .unwrap_or_default();
 
-    for mapping in mappings {
-        if mapping.is_empty() {
-            return Err(ClapError::raw(
-                ClapErrorKind::InvalidValue,
-                format!("Invalid mapping: '{}'\n", arg),
-            ));
-        }
+        ret.footer_format = parse_footer_template_arg(args, "footer-format")?;
+        ret.search_footer_format = parse_footer_template_arg(args, "search-footer-format")?;
 
-        //TODO: what if I want to map colon? see how crokey does the hyphen parsing
-        let parts: Vec<&str> = mapping.split(':').collect();
-        let (k, c, a) = match parts[..] {
-            [keys, action] => (
-                crokey::parse(keys).map_err(|e| parsekey_to_clap(mapping, e))?,
-                ActionContext::None,
-                Action::from_str(action).map_err(|_| strum_to_clap(mapping, action, "action"))?
-            ),
-            [keys, ctx, action] => (
-                crokey::parse(keys).map_err(|e| parsekey_to_clap(mapping, e))?,
-                ActionContext::from_str(ctx).map_err(|_| strum_to_clap(mapping, ctx, "context"))?,
-                Action::from_str(action).map_err(|_| strum_to_clap(mapping, action, "action"))?
-            ),
-            _ => return Err(ClapError::raw(
-                    ClapErrorKind::InvalidValue,
-                    format!("Keyboard mapping is not of the form 'key-combination:action' or 'key-combination:context:action': '{}'\n", &mapping),
-                    ))
+        // ok to unwrap, because the argument has a default value
+        ret.header_parent_style = match args.get_one::<String>("header-parent-style").unwrap().as_str() {
+            "dim" => HeaderParentStyle::Dim,
+            "bold" => HeaderParentStyle::Bold,
+            // 'none' means no separate style, like 'bold'
+            _ => parse_color_arg(args, "header-parent-style")?
+                .map_or(HeaderParentStyle::Bold, HeaderParentStyle::Color),
         };
 
-        ret.push((k, c, a));
-    }
+        ret.copy_path_format = args
+            .get_one::<CopyPathFormat>("copy-path-format")
+            .copied()
+            .unwrap_or_default();
 
-    Ok(ret)
-}
+        ret.relative_to = if args.get_flag("relative") {
+            Some(PathBuf::from("."))
+        } else {
+            args.get_one::<String>("relative-to").map(PathBuf::from)
+        };
 
-// NOTE: can't create a const hashmap (without an extra dependency like phf), so just using a slice
-// of tuples.
-pub const DEFAULT_KEYMAP: &[(KeyEvent, ActionContext, Action)] = &[
+        ret.page_scroll = args
+            .get_one::<PageScroll>("page-scroll")
+            .copied()
+            .unwrap_or_default();
 
-    (key!(enter),    ActionContext::None, Action::ChangeDir),
-    (key!(right),    ActionContext::None, Action::ChangeDir),
-    (key!(alt-down), ActionContext::None, Action::ChangeDir),
-    (key!(alt-l),    ActionContext::None, Action::ChangeDir),
-    (key!(space), ActionContext::NotSearching, Action::ChangeDir),
+        ret.layout = args
+            .get_one::<Layout>("layout")
+            .copied()
+            .unwrap_or_default();
 
-    (key!(left),   ActionContext::None, Action::ChangeDirParent),
-    (key!(alt-up), ActionContext::None, Action::ChangeDirParent),
-    (key!(alt-h),  ActionContext::None, Action::ChangeDirParent),
-    (key!('-'),       ActionContext::NotSearching, Action::ChangeDirParent),
-    (key!(backspace), ActionContext::NotSearching, Action::ChangeDirParent),
+        ret.size_format = args
+            .get_one::<SizeFormat>("size-format")
+            .copied()
+            .unwrap_or_default();
 
-    (key!('~'),        ActionContext::None, Action::ChangeDirHome),
-    (key!(ctrl-home),  ActionContext::None, Action::ChangeDirHome),
-    (key!(ctrl-alt-h), ActionContext::None, Action::ChangeDirHome),
+        if let Some(style) = args.get_one::<String>("time-style") {
+            ret.time_style = parse_time_style(style).ok_or_else(|| {
+                ClapError::raw(
+                    ClapErrorKind::InvalidValue,
+                    format!("Invalid value for 'time-style': '{}'\n", style),
+                )
+            })?;
+        }
 
-    (key!('/'),        ActionContext::None, Action::ChangeDirRoot),
-    (key!(alt-r),      ActionContext::None, Action::ChangeDirRoot),
+        ret.match_color = parse_color_arg(args, "match-color")?;
+        ret.warning_color = parse_color_arg(args, "warning-color")?;
 
-    (key!(alt-enter),  ActionContext::None, Action::ChangeDirAndExit),
-    (key!(ctrl-space), ActionContext::None, Action::ChangeDirAndExit),
+        ret.cursor_style = args
+            .get_one::<CursorStyle>("cursor-style")
+            .copied()
+            .unwrap_or_default();
+        ret.cursor_color = parse_color_arg(args, "cursor-color")?;
 
-    (key!(up),    ActionContext::None, Action::CursorUp),
-    (key!(alt-k), ActionContext::None, Action::CursorUp),
+        ret.scroll_off = args
+            .get_one::<usize>("scroll-off")
+            .copied()
+            .unwrap_or_default();
 
-    (key!(down),  ActionContext::None, Action::CursorDown),
-    (key!(alt-j), ActionContext::None, Action::CursorDown),
+        ret.search_debounce = args
+            .get_one::<u64>("search-debounce")
+            .filter(|ms| **ms > 0)
+            .map(|ms| Duration::from_millis(*ms));
 
-    (key!(pageup),  ActionContext::None, Action::CursorUpScreen),
-    (key!(alt-u),   ActionContext::None, Action::CursorUpScreen),
-    (key!(ctrl-u),  ActionContext::None, Action::CursorUpScreen),
+        ret.padding = args
+            .get_one::<usize>("padding")
+            .copied()
+            .unwrap_or_default();
 
-    (key!(pagedown), ActionContext::None, Action::CursorDownScreen),
-    (key!(alt-d),    ActionContext::None, Action::CursorDownScreen),
-    (key!(ctrl-d),   ActionContext::None, Action::CursorDownScreen),
+        Ok((ret, warnings))
+    }
+
+    /// The preferences that can be changed with keyboard shortcuts while the app is running. Each
+    /// one is given as the command line arguments that select its current value, so that they
+    /// can be saved with `--save-prefs` and parsed like any other arguments on the next start.
+    pub fn pref_args(&self) -> Prefs {
+        fn flag(name: &str) -> Vec<String> {
+            vec![format!("--{}", name)]
+        }
+        fn value<T: clap::ValueEnum>(name: &str, value: &T) -> Vec<String> {
+            // ok to unwrap, because none of the values are skipped
+            let value = value.to_possible_value().unwrap();
+            vec![format!("--{}", name), value.get_name().to_string()]
+        }
+
+        let listing_mode = match self.listing_mode {
+            ListingMode::All => "no-folders-only",
+            ListingMode::FoldersOnly => "folders-only",
+            ListingMode::FilesOnly => "files-only",
+        };
+        let case_sensitive = match self.case_sensitive {
+            CaseSensitiveMode::IgnoreCase => "ignore-case",
+            CaseSensitiveMode::CaseSensitive => "case-sensitive",
+            CaseSensitiveMode::SmartCase => "smart-case",
+        };
+        let gap_search = match self.gap_search_mode {
+            GapSearchMode::NormalSearch => "normal-search",
+            GapSearchMode::NormalSearchAnywhere => "normal-search-anywhere",
+            GapSearchMode::GapSearchFromStart => "gap-search",
+            GapSearchMode::GapSearchAnywhere => "gap-search-anywhere",
+        };
+        let toggle = |on: bool, name: &str| {
+            flag(&if on { name.to_string() } else { format!("no-{}", name) })
+        };
+
+        vec![
+            ("sort", value("sort", &self.sort_mode)),
+            ("search-scope", value("search-scope", &self.search_scope)),
+            ("listing-mode", flag(listing_mode)),
+            ("case-sensitive", flag(case_sensitive)),
+            ("gap-search", flag(gap_search)),
+            ("filter-search", toggle(self.filter_search, "filter-search")),
+            ("recursive-search", toggle(self.recursive_search, "recursive-search")),
+            ("preview", toggle(self.preview_enabled, "preview")),
+            ("numbers", toggle(self.number_mode, "numbers")),
+        ]
+        .into_iter()
+        .map(|(name, args)| (name.to_string(), args))
+        .collect()
+    }
+}
+
+/// Saved preferences, from the name of the preference to the command line arguments that select
+/// its value
+pub type Prefs = BTreeMap<String, Vec<String>>;
+
+/// The file where the preferences are saved with `--save-prefs`
+pub fn default_prefs_file() -> Option<PathBuf> {
+    config_dir().map(|path| path.join(env!("CARGO_PKG_NAME")).join("prefs.json"))
+}
+
+/// The folder for configuration files: `$XDG_CONFIG_HOME`, or `~/.config` if it's not set
+fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config", dirs::config_dir)
+}
+
+/// The folder for cached data, like the history: `$XDG_CACHE_HOME`, or `~/.cache` if it's not set
+fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache", dirs::cache_dir)
+}
+
+/// Find a base folder like the XDG base directory specification says. The environment variable
+/// `var` is used if it's set to an absolute path. Otherwise, the folder `home_subdir` in the home
+/// folder is used on unix, and the usual folder of the platform elsewhere. The usual folder of the
+/// platform (e.g. `~/Library/Caches` on macOS) is still used on unix if only it has our files, so
+/// that the files saved by older versions are not lost.
+fn xdg_dir(var: &str, home_subdir: &str, platform_dir: impl FnOnce() -> Option<PathBuf>) -> Option<PathBuf> {
+    match std::env::var_os(var).map(PathBuf::from) {
+        Some(path) if path.is_absolute() => Some(path),
+        _ if cfg!(unix) => {
+            let has_our_files =
+                |dir: &Option<PathBuf>| dir.as_ref().is_some_and(|dir| dir.join(env!("CARGO_PKG_NAME")).exists());
+            let home_dir = dirs::home_dir().map(|home| home.join(home_subdir));
+            let platform_dir = platform_dir();
+            if !has_our_files(&home_dir) && has_our_files(&platform_dir) {
+                platform_dir
+            } else {
+                home_dir
+            }
+        }
+        _ => platform_dir(),
+    }
+}
+
+/// Read the saved preferences. A missing file means that nothing has been saved yet.
+pub fn read_prefs(path: &Path) -> IOResult<Prefs> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Prefs::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Remove the saved preferences whose arguments can't be parsed anymore, for example because an
+/// option has been removed in a newer version, and return a warning for each of them. The other
+/// preferences can still be used, so this is not an error.
+pub fn remove_invalid_prefs(prefs: &mut Prefs) -> Warnings {
+    let mut warnings = vec![];
+    prefs.retain(|name, args| {
+        let matches = crate::cli_args::get_cli_args()
+            .try_get_matches_from(std::iter::once(env!("CARGO_PKG_NAME")).chain(args.iter().map(String::as_str)));
+        if matches.is_err() {
+            warnings.push(format!("Ignoring the invalid saved preference '{}': {}", name, args.join(" ")));
+        }
+        matches.is_ok()
+    });
+    warnings
+}
+
+/// Write the preferences to a file, creating its folder if needed
+pub fn write_prefs(path: &Path, prefs: &Prefs) -> IOResult<()> {
+    if let Some(parent_dir) = path.parent() {
+        std::fs::DirBuilder::new().recursive(true).create(parent_dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(prefs)?)
+}
+
+/// Parse a footer template argument, checking that it only uses known placeholders
+fn parse_footer_template_arg(args: &ArgMatches, name: &str) -> Result<Option<FooterTemplate>, ClapError> {
+    args.get_one::<String>(name)
+        .map(|template| {
+            FooterTemplate::from_str(template).map_err(|e| {
+                ClapError::raw(
+                    ClapErrorKind::InvalidValue,
+                    format!("Invalid value for '{}': {}\n", name, e),
+                )
+            })
+        })
+        .transpose()
+}
+
+/// Parse a timeout argument, which is either a non-negative integer or 'off' (which is converted
+/// to None). The argument must have a default value.
+fn parse_timeout_arg(args: &ArgMatches, name: &str) -> Result<Option<u64>, ClapError> {
+    match args
+        .get_many::<String>(name)
+        // ok to unwrap because the timeout arguments have a default value which is always present
+        .unwrap()
+        .map(|v| v.as_str())
+        .next_back()
+        .unwrap()
+    {
+        "off" => Ok(None),
+        x => u64::from_str(x)
+            .map_err(|_| {
+                // We don't want to pass the App all the way here, so create raw error
+                // NOTE: We don't call error.format(app) anywhere now, but it doesn't seem to
+                // make a difference for this error type.
+                ClapError::raw(
+                    ClapErrorKind::InvalidValue,
+                    format!("Invalid value for '{}': '{}'\n", name, x),
+                )
+            })
+            .map(Some),
+    }
+}
+
+/// Parse a key combination like `ctrl-x`. The keys can also be separated with plus signs, like
+/// `ctrl+x`, in which case the plus signs are converted to hyphens before passing the combination
+/// to crokey. A plus sign at the start, or one following another plus sign, is the '+' key itself.
+fn parse_key_combination(keys: &str) -> Result<KeyEvent, crokey::ParseKeyError> {
+    let mut normalized = String::with_capacity(keys.len());
+    let mut prev: Option<char> = None;
+    let mut chars = keys.chars().peekable();
+    while let Some(c) = chars.next() {
+        let is_separator = c == '+'
+            && prev.is_some_and(|p| p != '+' && p != '-')
+            && chars.peek().is_some();
+        normalized.push(if is_separator { '-' } else { c });
+        prev = Some(if is_separator { '-' } else { c });
+    }
+    crokey::parse(&normalized)
+}
+
+/// Parse a glob pattern for filtering files, such as '*.rs'
+pub fn parse_glob(pattern: &str) -> Result<GlobMatcher, globset::Error> {
+    Glob::new(pattern).map(|glob| glob.compile_matcher())
+}
+
+const DURATION_UNITS: [(char, u64); 5] = [
+    ('w', 7 * 24 * 60 * 60),
+    ('d', 24 * 60 * 60),
+    ('h', 60 * 60),
+    ('m', 60),
+    ('s', 1),
+];
+
+/// Parse a duration like '30m', '12h' or '2d'. The unit is one of s, m, h, d or w.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let unit = text.chars().last()?;
+    let (_, unit_secs) = DURATION_UNITS.iter().find(|(c, _)| *c == unit)?;
+    let amount = u64::from_str(&text[..text.len() - unit.len_utf8()]).ok()?;
+    amount.checked_mul(*unit_secs).map(Duration::from_secs)
+}
+
+/// Format a duration in the same format as `parse_duration`, using the largest unit that
+/// represents it exactly.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (unit, unit_secs) = DURATION_UNITS
+        .iter()
+        .find(|(_, unit_secs)| secs > 0 && secs.is_multiple_of(*unit_secs))
+        .unwrap_or(&('s', 1));
+    format!("{}{}", secs / unit_secs, unit)
+}
+
+/// Parse a time style: 'iso', 'relative', or a strftime-style format like '%Y-%m-%d %H:%M'. Like
+/// with `ls`, the format may start with a '+'. Formats without any '%' directives are rejected, so
+/// that misspelled style names are not taken as formats.
+pub fn parse_time_style(text: &str) -> Option<TimeStyle> {
+    match text {
+        "iso" => Some(TimeStyle::Iso),
+        "relative" => Some(TimeStyle::Relative),
+        format => {
+            let format = format.strip_prefix('+').unwrap_or(format);
+            let valid = format.contains('%')
+                && !StrftimeItems::new(format).any(|item| item == Item::Error);
+            valid.then(|| TimeStyle::Format(format.to_string()))
+        }
+    }
+}
+
+/// Parse a color given on the command line. The color can be one of the named terminal colors, or
+/// 'none' for no color.
+fn parse_color_arg(args: &ArgMatches, name: &str) -> Result<Option<Color>, ClapError> {
+    // ok to unwrap because the color arguments have a default value
+    match args.get_one::<String>(name).unwrap().as_str() {
+        "none" => Ok(None),
+        x => Color::try_from(x).map(Some).map_err(|_| {
+            ClapError::raw(
+                ClapErrorKind::InvalidValue,
+                format!("Invalid color for '{}': '{}'\n", name, x),
+            )
+        }),
+    }
+}
+
+/// Parse the space-separated key combinations given with --keys
+fn parse_keys_arg(arg: &str) -> Result<Vec<KeyEvent>, ClapError> {
+    arg.split_whitespace()
+        .map(|keys| {
+            parse_key_combination(keys).map_err(|e| {
+                ClapError::raw(
+                    ClapErrorKind::InvalidValue,
+                    format!("Error parsing key combination '{}' in --keys: {}\n", keys, e),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Parse the value of a `--map` option. A mapping can list several key combinations separated by
+/// spaces, which are all mapped to the same action.
+fn parse_keymap_arg(arg: &str) -> Result<Vec<(KeyEvent, ActionContext, Action)>, ClapError> {
+    let mappings = arg.split(',');
+    let mut ret = Vec::new();
+
+    fn parsekey_to_clap(mapping: &str, err: crokey::ParseKeyError) -> ClapError {
+        ClapError::raw(
+            ClapErrorKind::InvalidValue,
+            format!("Error parsing key combination '{}': {}\n", mapping, err),
+        )
+    }
+
+    fn strum_to_clap(mapping: &str, attempted_value: &str, ctx_or_action: &str) -> ClapError {
+        ClapError::raw(
+            ClapErrorKind::InvalidValue,
+            format!(
+                "Error parsing key mapping '{}': invalid {} '{}'\n",
+                mapping, ctx_or_action, attempted_value,
+            ),
+        )
+    }
+
+    for mapping in mappings {
+        if mapping.trim().is_empty() {
+            return Err(ClapError::raw(
+                ClapErrorKind::InvalidValue,
+                format!("Invalid mapping: '{}'\n", arg),
+            ));
+        }
+
+        //TODO: what if I want to map colon? see how crokey does the hyphen parsing
+        let parts: Vec<&str> = mapping.split(':').collect();
+        let (keys, c, a) = match parts[..] {
+            [keys, action] => (
+                keys,
+                ActionContext::None,
+                Action::from_str(action).map_err(|_| strum_to_clap(mapping, action, "action"))?
+            ),
+            [keys, ctx, action] => (
+                keys,
+                ActionContext::from_str(ctx).map_err(|_| strum_to_clap(mapping, ctx, "context"))?,
+                Action::from_str(action).map_err(|_| strum_to_clap(mapping, action, "action"))?
+            ),
+            _ => return Err(ClapError::raw(
+                    ClapErrorKind::InvalidValue,
+                    format!("Keyboard mapping is not of the form 'key-combination:action' or 'key-combination:context:action': '{}'\n", &mapping),
+                    ))
+        };
+
+        if keys.trim().is_empty() {
+            return Err(ClapError::raw(
+                ClapErrorKind::InvalidValue,
+                format!("No key combination given in the mapping '{}'\n", mapping),
+            ));
+        }
+        for key_str in keys.split_whitespace() {
+            let k = parse_key_combination(key_str).map_err(|e| parsekey_to_clap(mapping, e))?;
+            ret.push((k, c.clone(), a.clone()));
+        }
+    }
+
+    Ok(ret)
+}
+
+/// Check that the keymap, with the mappings of all the `--map` options applied, has no conflicting
+/// mappings. A key that is mapped to some action without a context conflicts with the mappings
+/// of the same key to other actions both when searching and when not searching, because then the
+/// mapping without a context is never used.
+fn check_keymap_conflicts(keymap: &HashMap<(KeyEvent, ActionContext), Action>) -> Result<(), ClapError> {
+    let mut conflicts: Vec<String> = keymap
+        .iter()
+        .filter(|((_, c), _)| *c == ActionContext::None)
+        .filter_map(|((k, _), a)| {
+            let searching = keymap.get(&(*k, ActionContext::Searching))?;
+            let not_searching = keymap.get(&(*k, ActionContext::NotSearching))?;
+            (searching != a || not_searching != a).then(|| {
+                format!(
+                    "'{}' is mapped to {}, but also to {} when searching and to {} when not searching",
+                    crokey::KeyEventFormat::default().to_string(*k),
+                    a,
+                    searching,
+                    not_searching,
+                )
+            })
+        })
+        .collect();
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+    conflicts.sort();
+    Err(ClapError::raw(
+        ClapErrorKind::InvalidValue,
+        format!("Conflicting key mappings: {}\n", conflicts.join(", ")),
+    ))
+}
+
+// NOTE: can't create a const hashmap (without an extra dependency like phf), so just using a slice
+// of tuples.
+pub const DEFAULT_KEYMAP: &[(KeyEvent, ActionContext, Action)] = &[
+
+    (key!(enter),    ActionContext::None, Action::ChangeDir),
+    (key!(right),    ActionContext::None, Action::ChangeDir),
+    (key!(alt-down), ActionContext::None, Action::ChangeDir),
+    (key!(alt-l),    ActionContext::None, Action::ChangeDir),
+    (key!(space), ActionContext::NotSearching, Action::ChangeDir),
+    (key!(tab),   ActionContext::Searching,    Action::ChangeDir), // accept the highlighted match
+
+    (key!(left),   ActionContext::None, Action::ChangeDirParent),
+    (key!(alt-up), ActionContext::None, Action::ChangeDirParent),
+    (key!(alt-h),  ActionContext::None, Action::ChangeDirParent),
+    (key!('-'),       ActionContext::NotSearching, Action::ChangeDirParent),
+    (key!(backspace), ActionContext::NotSearching, Action::ChangeDirParent),
+
+    (key!('~'),        ActionContext::None, Action::ChangeDirHome),
+    (key!(ctrl-home),  ActionContext::None, Action::ChangeDirHome),
+    (key!(ctrl-alt-h), ActionContext::None, Action::ChangeDirHome),
+
+    (key!('/'),        ActionContext::None, Action::ChangeDirRoot),
+    (key!(alt-r),      ActionContext::None, Action::ChangeDirRoot),
+
+    (key!(alt-'-'),    ActionContext::None, Action::ChangeDirPrevious),
+
+    (key!(alt-b),      ActionContext::None, Action::ChangeDirAncestor),
+
+    (key!(alt-right),  ActionContext::None, Action::ChangeDirNextSibling),
+    (key!(alt-left),   ActionContext::None, Action::ChangeDirPreviousSibling),
+
+    (key!(alt-enter),  ActionContext::None, Action::ChangeDirAndExit),
+    (key!(ctrl-space), ActionContext::None, Action::ChangeDirAndExit),
+
+    (key!(alt-w),  ActionContext::None, Action::AcceptCurrentDir),
+    (key!(ctrl-o), ActionContext::None, Action::SelectAndExit),
+
+    (key!(alt-o),  ActionContext::None, Action::OpenFile),
+    (key!(alt-e),  ActionContext::None, Action::OpenFileManager),
+    (key!(alt-y),  ActionContext::None, Action::CopyPath),
+    (key!(f7),     ActionContext::None, Action::CreateFolder),
+
+    (key!(up),    ActionContext::None, Action::CursorUp),
+    (key!(alt-k), ActionContext::None, Action::CursorUp),
+
+    (key!(down),  ActionContext::None, Action::CursorDown),
+    (key!(alt-j), ActionContext::None, Action::CursorDown),
+
+    (key!(left),  ActionContext::Grid, Action::CursorLeft),
+    (key!(right), ActionContext::Grid, Action::CursorRight),
+
+    (key!(pageup),  ActionContext::None, Action::CursorUpScreen),
+    (key!(alt-u),   ActionContext::None, Action::CursorUpScreen),
+    (key!(ctrl-u),  ActionContext::None, Action::CursorUpHalfScreen),
+
+    (key!(pagedown), ActionContext::None, Action::CursorDownScreen),
+    (key!(alt-d),    ActionContext::None, Action::CursorDownScreen),
+    (key!(ctrl-d),   ActionContext::None, Action::CursorDownHalfScreen),
 
     (key!(home),        ActionContext::None, Action::CursorTop),
     (key!(alt-g),       ActionContext::None, Action::CursorTop), // like vim 'gg'
@@ -322,15 +1211,46 @@ pub const DEFAULT_KEYMAP: &[(KeyEvent, ActionContext, Action)] = &[
 
     (key!(backspace), ActionContext::Searching, Action::EraseSearchChar),
 
-    (key!(esc), ActionContext::Searching, Action::ClearSearch),
+    (key!(ctrl-w), ActionContext::Searching, Action::EraseSearchWord),
+
+    (key!(esc),    ActionContext::Searching, Action::ClearSearch),
+    (key!(ctrl-u), ActionContext::Searching, Action::ClearSearch),
+
+    (key!(ctrl-p), ActionContext::None, Action::RecallPreviousSearch),
+    (key!(ctrl-n), ActionContext::None, Action::RecallNextSearch),
 
     (key!(alt-f),  ActionContext::None, Action::ChangeFilterSearchMode),
     (key!(alt-c),  ActionContext::None, Action::ChangeCaseSensitiveMode),
     (key!(ctrl-f), ActionContext::None, Action::ChangeGapSearchMode),
     (key!(alt-s),  ActionContext::None, Action::ChangeSortMode),
+    (key!(alt-x),  ActionContext::None, Action::ChangeSearchScope),
+
+    (key!(alt-t),  ActionContext::None, Action::ChangeListingMode),
+
+    (key!(alt-a),  ActionContext::None, Action::ToggleRecursiveSearch),
+
+    (key!(alt-p),  ActionContext::None, Action::TogglePreview),
+
+    (key!(alt-n),  ActionContext::None, Action::ToggleNumberMode),
+    (key!('0'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('1'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('2'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('3'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('4'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('5'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('6'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('7'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('8'), ActionContext::NumberMode, Action::TypeRowNumber),
+    (key!('9'), ActionContext::NumberMode, Action::TypeRowNumber),
+
+    (key!(ctrl-g), ActionContext::None, Action::SetFileFilter),
+    (key!(alt-m),  ActionContext::None, Action::ToggleModifiedFilter),
+    (key!(alt-i),  ActionContext::None, Action::ToggleExcluded),
 
     (key!(ctrl-r), ActionContext::None, Action::RefreshListing),
 
+    (key!(alt-z),  ActionContext::None, Action::ComputeTotalSize),
+
     (key!('?'), ActionContext::None, Action::Help),
     (key!(shift-'?'), ActionContext::None, Action::Help),
 
@@ -383,401 +1303,1923 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_keymap_arg2() {
-        let m = parse_keymap_arg("ctrl-x:Exit,ctrl-j:NotSearching:CursorUp").unwrap();
-        assert_eq!(m.len(), 2);
-        assert_eq!(m[0].0, key!(ctrl-x));
-        assert_eq!(m[0].1, ActionContext::None);
-        assert_eq!(m[0].2, Action::Exit);
-        assert_eq!(m[1].0, key!(ctrl-j));
-        assert_eq!(m[1].1, ActionContext::NotSearching);
-        assert_eq!(m[1].2, Action::CursorUp);
-    }
+    fn test_parse_keymap_arg2() {
+        let m = parse_keymap_arg("ctrl-x:Exit,ctrl-j:NotSearching:CursorUp").unwrap();
+        assert_eq!(m.len(), 2);
+        assert_eq!(m[0].0, key!(ctrl-x));
+        assert_eq!(m[0].1, ActionContext::None);
+        assert_eq!(m[0].2, Action::Exit);
+        assert_eq!(m[1].0, key!(ctrl-j));
+        assert_eq!(m[1].1, ActionContext::NotSearching);
+        assert_eq!(m[1].2, Action::CursorUp);
+    }
+
+    #[test]
+    fn test_parse_keymap_arg_multiple_keys() {
+        let m = parse_keymap_arg("q alt-q:Exit,  up  ctrl+p :Searching:CursorUp").unwrap();
+        assert_eq!(m, vec![
+            (key!(q), ActionContext::None, Action::Exit),
+            (key!(alt-q), ActionContext::None, Action::Exit),
+            (key!(up), ActionContext::Searching, Action::CursorUp),
+            (key!(ctrl-p), ActionContext::Searching, Action::CursorUp),
+        ]);
+
+        assert!(parse_keymap_arg(" :Exit").is_err());
+        assert!(parse_keymap_arg("q xyzzy:Exit").is_err());
+    }
+
+    #[test]
+    fn test_parse_key_combination_with_plus() {
+        assert_eq!(parse_key_combination("ctrl+u").unwrap(), key!(ctrl-u));
+        assert_eq!(parse_key_combination("alt+k").unwrap(), key!(alt-k));
+        assert_eq!(parse_key_combination("ctrl+alt+h").unwrap(), key!(ctrl-alt-h));
+        assert_eq!(parse_key_combination("ctrl-x").unwrap(), key!(ctrl-x));
+        // the plus key itself
+        assert_eq!(parse_key_combination("+").unwrap(), key!('+'));
+        assert_eq!(parse_key_combination("ctrl++").unwrap(), key!(ctrl-'+'));
+        assert_eq!(parse_key_combination("ctrl-+").unwrap(), key!(ctrl-'+'));
+
+        let m = parse_keymap_arg("ctrl+x:Exit,alt+j:NotSearching:CursorDown").unwrap();
+        assert_eq!(m[0].0, key!(ctrl-x));
+        assert_eq!(m[1].0, key!(alt-j));
+        assert_eq!(m[1].1, ActionContext::NotSearching);
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option1() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-x:Exit",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.keymap.get(&(key!(ctrl-x), ActionContext::None)), Some(&Action::Exit));
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option2() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-x:Exit,ctrl-y:ClearSearch",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.keymap.get(&(key!(ctrl-x), ActionContext::None)), Some(&Action::Exit));
+        assert_eq!(settings.keymap.get(&(key!(ctrl-y), ActionContext::None)), Some(&Action::ClearSearch));
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option3() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-x:Exit,ctrl-x:ClearSearch", // repeated mapping
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.keymap.get(&(key!(ctrl-x), ActionContext::None)), Some(&Action::ClearSearch));
+    }
+
+    #[test]
+    fn test_keyboard_mapping_conflicts() {
+        let parse = |args: &[&str]| {
+            let m = crate::cli_args::get_cli_args().get_matches_from([&["foo"], args].concat());
+            TereSettings::parse_cli_args(&m).map(|(settings, _)| settings)
+        };
+
+        // backspace is mapped to other actions both when searching and when not searching by
+        // default, so a mapping without a context would never be used
+        let err = parse(&["-m", "backspace:CursorUp"]).err().unwrap().to_string();
+        assert!(
+            err.contains("'Backspace' is mapped to CursorUp, but also to EraseSearchChar when searching and to ChangeDirParent when not searching"),
+            "{}",
+            err,
+        );
+
+        // the mappings of all --map options are checked together
+        let err = parse(&["-m", "ctrl-x:Searching:Exit", "-m", "ctrl-x:NotSearching:ClearSearch", "-m", "ctrl-x:CursorUp"]);
+        assert!(err.is_err());
+        let settings = parse(&["-m", "backspace:CursorUp", "-m", "backspace:Searching:None"]).unwrap();
+        assert_eq!(settings.keymap.get(&(key!(backspace), ActionContext::NotSearching)), Some(&Action::ChangeDirParent));
+        assert_eq!(settings.keymap.get(&(key!(backspace), ActionContext::None)), Some(&Action::CursorUp));
+
+        // the same action in every context is not a conflict
+        assert!(parse(&["-m", "ctrl-x:Searching:Exit", "-m", "ctrl-x:NotSearching:Exit", "-m", "ctrl-x:Exit"]).is_ok());
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option4() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                // provide option multiple times
+                "-m", "ctrl-x:Exit",
+                "-m", "ctrl-x:ClearSearch",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.keymap.get(&(key!(ctrl-x), ActionContext::None)), Some(&Action::ClearSearch));
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option_wrong1() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-x:Exxit", // incorrect action
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option_wrong2() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-:Exit", // inccorect mapping
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option_wrong3() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-x:Wrong:Exit", // incorrect context
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option_wrong4() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-x::Exit", // Incorrect syntax
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option_wrong5() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-x", // missing mapping and/or context
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_keyboard_mapping_cli_option_wrong6() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "ctrl-x:", // missing mapping and/or context
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_unmap1() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-h), ActionContext::None)), Some(&Action::ChangeDirParent));
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-j), ActionContext::None)), Some(&Action::CursorDown));
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-k), ActionContext::None)), Some(&Action::CursorUp));
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-l), ActionContext::None)), Some(&Action::ChangeDir));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "alt-h:None,alt-j:None,alt-k:None,alt-l:None",
+            ]);
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-h), ActionContext::None)), None);
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-j), ActionContext::None)), None);
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-k), ActionContext::None)), None);
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-l), ActionContext::None)), None);
+    }
+
+    #[test]
+    fn test_unmap2() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::NotSearching)), Some(&Action::Exit));
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::Searching)), Some(&Action::ClearSearch));
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::None)), None);
+
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(backspace), ActionContext::Searching)), Some(&Action::EraseSearchChar));
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(backspace), ActionContext::NotSearching)), Some(&Action::ChangeDirParent));
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(backspace), ActionContext::None)), None);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "-m", "esc:Searching:None",
+                "-m", "backspace:None", // this shouldn't affect any of the mappings since they are context-dependent
+                "-m", "backspace:None:None", // this shouldn't affect any of the mappings since they are context-dependent
+            ]);
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::NotSearching)), Some(&Action::Exit));
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::Searching)), None);
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::None)), None);
+    }
+
+    #[test]
+    fn test_clear_default_keymap() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--clear-default-keymap",
+                "--map", "ctrl-x:Exit",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert!(settings.keymap.len() == 1);
+    }
+
+    #[test]
+    fn test_empty_keymap_is_error() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--clear-default-keymap",
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_unmap_exit_is_error() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--map", "esc:NotSearching:None,alt-q:None",
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_start_path() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.start_path, None);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--folders-only",
+                "some/folder",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.start_path, Some(PathBuf::from("some/folder")));
+        assert_eq!(settings.listing_mode, ListingMode::FoldersOnly);
+    }
+
+    #[test]
+    fn test_gitignore_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.gitignore);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--gitignore",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.gitignore);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--gitignore",
+                "--no-gitignore",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.gitignore);
+    }
+
+    #[test]
+    fn test_exclude() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.exclude.is_empty());
+        assert!(!settings.show_excluded);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--exclude",
+                "node_modules",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.exclude, vec!["node_modules"]);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--exclude",
+                "node_modules",
+                "--exclude=.git,,target",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.exclude, vec!["node_modules", ".git", "target"]);
+    }
+
+    #[test]
+    fn test_recursive_search() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.recursive_search);
+        assert_eq!(settings.recursive_search_depth, 5);
+        assert_eq!(settings.max_depth, 20);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--recursive-search",
+                "--recursive-search-depth", "2",
+                "--max-depth", "1",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.recursive_search);
+        assert_eq!(settings.recursive_search_depth, 2);
+        assert_eq!(settings.max_depth, 1);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--recursive-search",
+                "--no-recursive-search",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.recursive_search);
+    }
+
+    #[test]
+    fn test_filter_search_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert!(!settings.filter_search);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--filter-search",
+                "--no-filter-search",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert!(!settings.filter_search);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--filter-search",
+                "--no-filter-search",
+                "--filter-search",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert!(settings.filter_search);
+    }
+
+    #[test]
+    fn test_folders_only_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.listing_mode, ListingMode::All);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--folders-only",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.listing_mode, ListingMode::FoldersOnly);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--folders-only",
+                "--no-folders-only",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.listing_mode, ListingMode::All);
+    }
+
+    #[test]
+    fn test_files_only_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--files-only",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.listing_mode, ListingMode::FilesOnly);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--files-only",
+                "--folders-only",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.listing_mode, ListingMode::FoldersOnly);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--folders-only",
+                "--files-only",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.listing_mode, ListingMode::FilesOnly);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--files-only",
+                "--no-folders-only",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.listing_mode, ListingMode::All);
+    }
+
+    #[test]
+    fn test_case_sensitive_mode_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.case_sensitive, CaseSensitiveMode::SmartCase);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--case-sensitive",
+                "--ignore-case",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.case_sensitive, CaseSensitiveMode::IgnoreCase);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--case-sensitive",
+                "--ignore-case",
+                "--case-sensitive",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.case_sensitive, CaseSensitiveMode::CaseSensitive);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--case-sensitive",
+                "--ignore-case",
+                "--case-sensitive",
+                "--smart-case",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.case_sensitive, CaseSensitiveMode::SmartCase);
+    }
+
+    #[test]
+    fn test_gap_search_mode_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.gap_search_mode, GapSearchMode::GapSearchFromStart);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--gap-search",
+                "--gap-search-anywhere",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.gap_search_mode, GapSearchMode::GapSearchAnywhere);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--gap-search",
+                "--gap-search-anywhere",
+                "--normal-search-anywhere",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.gap_search_mode, GapSearchMode::NormalSearchAnywhere);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--gap-search",
+                "--gap-search-anywhere",
+                "--normal-search-anywhere",
+                "--gap-search",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.gap_search_mode, GapSearchMode::GapSearchFromStart);
+    }
+
+    #[test]
+    fn test_no_gap_search_deprecated() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--no-gap-search"
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!warnings.is_empty());
+        assert!(settings.gap_search_mode == GapSearchMode::NormalSearch);
+    }
+
+    #[test]
+    fn test_sort_mode_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.sort_mode, SortMode::Name);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--sort", "created",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.sort_mode, SortMode::Created);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--sort",  "created",
+                "--sort",  "name",
+                "--sort",  "modified",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.sort_mode, SortMode::Modified);
+
+    }
+
+    #[test]
+    fn test_search_scope() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.search_scope, SearchScope::Name);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--search-scope", "stem",
+                "--search-scope", "extension",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.search_scope, SearchScope::Extension);
+    }
+
+    #[test]
+    fn test_fuzzy_algo() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.fuzzy_algo, FuzzyAlgo::Substr);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--fuzzy-algo", "skim",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.fuzzy_algo, FuzzyAlgo::Skim);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--fuzzy-algo", "skim",
+                "--fuzzy-algo", "substr",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.fuzzy_algo, FuzzyAlgo::Substr);
+    }
+
+    #[test]
+    fn test_layout() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.layout, Layout::Classic);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--layout", "top-search",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.layout, Layout::TopSearch);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--layout", "top-search",
+                "--layout", "compact",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.layout, Layout::Compact);
+
+        let res = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--layout", "sideways",
+            ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_size_format() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.size_format, SizeFormat::Binary);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--size-format", "decimal",
+                "--size-format", "bytes",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.size_format, SizeFormat::Bytes);
+
+        let m = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--size-format", "octal",
+            ]);
+        assert!(m.is_err());
+    }
+
+    #[test]
+    fn test_time_style() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.time_style, TimeStyle::Iso);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--time-style", "iso",
+                "--time-style", "relative",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.time_style, TimeStyle::Relative);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--time-style", "+%d.%m. %H:%M",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.time_style, TimeStyle::Format("%d.%m. %H:%M".to_string()));
+
+        for style in ["isoo", "%Y-%m-%Q", ""] {
+            let m = crate::cli_args::get_cli_args()
+                .get_matches_from(vec![
+                    "foo",
+                    "--time-style", style,
+                ]);
+            assert!(TereSettings::parse_cli_args(&m).is_err(), "{}", style);
+        }
+    }
+
+    #[test]
+    fn test_save_prefs() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.prefs_file, None);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--no-save-prefs",
+                "--save-prefs",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.prefs_file, default_prefs_file());
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--save-prefs",
+                "--no-save-prefs",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.prefs_file, None);
+    }
+
+    #[test]
+    fn test_remove_invalid_prefs() {
+        let mut prefs: Prefs = vec![
+            ("sort", vec!["--sort", "modified"]),
+            ("numbers", vec!["--numbers"]),
+            // a value and an option that don't exist anymore
+            ("search-scope", vec!["--search-scope", "nonexistent"]),
+            ("removed", vec!["--removed-option"]),
+        ]
+        .into_iter()
+        .map(|(name, args)| (name.to_string(), args.into_iter().map(String::from).collect()))
+        .collect();
+
+        let warnings = remove_invalid_prefs(&mut prefs);
+        assert_eq!(prefs.keys().collect::<Vec<_>>(), vec!["numbers", "sort"]);
+        assert_eq!(warnings, vec![
+            "Ignoring the invalid saved preference 'removed': --removed-option",
+            "Ignoring the invalid saved preference 'search-scope': --search-scope nonexistent",
+        ]);
+    }
+
+    #[test]
+    fn test_pref_args() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--sort", "modified",
+                "--files-only",
+                "--ignore-case",
+                "--normal-search-anywhere",
+                "--preview",
+                "--numbers",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        let prefs = settings.pref_args();
+        assert_eq!(prefs["sort"], vec!["--sort", "modified"]);
+        assert_eq!(prefs["listing-mode"], vec!["--files-only"]);
+        assert_eq!(prefs["filter-search"], vec!["--no-filter-search"]);
+
+        // parsing the arguments gives the same preferences back, and later arguments override them
+        let args: Vec<String> = std::iter::once("foo".to_string())
+            .chain(prefs.values().flatten().cloned())
+            .chain(["--no-folders-only".to_string()])
+            .collect();
+        let m = crate::cli_args::get_cli_args().get_matches_from(args);
+        let (parsed, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(parsed.sort_mode, SortMode::Modified);
+        assert_eq!(parsed.case_sensitive, CaseSensitiveMode::IgnoreCase);
+        assert_eq!(parsed.gap_search_mode, GapSearchMode::NormalSearchAnywhere);
+        assert!(parsed.preview_enabled);
+        assert!(parsed.number_mode);
+        assert_eq!(parsed.listing_mode, ListingMode::All);
+        let mut expected = prefs;
+        expected.insert("listing-mode".to_string(), vec!["--no-folders-only".to_string()]);
+        assert_eq!(parsed.pref_args(), expected);
+    }
+
+    #[test]
+    fn test_xdg_dir() {
+        // use a variable of our own, so that the other tests are not affected
+        let var = "TERE_TEST_XDG_DIR";
+        let tmp = std::env::temp_dir().join("tere-test-xdg");
+        let fallback = || Some(PathBuf::from("platform"));
+
+        std::env::set_var(var, &tmp);
+        assert_eq!(xdg_dir(var, ".config", fallback), Some(tmp));
+
+        // relative paths are ignored, like the spec says
+        std::env::set_var(var, "relative/path");
+        let expected = if cfg!(unix) {
+            dirs::home_dir().map(|home| home.join(".config"))
+        } else {
+            fallback()
+        };
+        assert_eq!(xdg_dir(var, ".config", fallback), expected);
+
+        std::env::remove_var(var);
+        assert_eq!(xdg_dir(var, ".config", fallback), expected);
+
+        // the old folder of the platform is used if our files are only there
+        if cfg!(unix) {
+            let tmp_dir = tempfile::tempdir().unwrap();
+            let old = tmp_dir.path().to_path_buf();
+            let home_subdir = ".tere-test-nonexistent";
+            let expected = dirs::home_dir().map(|home| home.join(home_subdir));
+            assert_eq!(xdg_dir(var, home_subdir, || Some(old.clone())), expected);
+            std::fs::create_dir_all(old.join(env!("CARGO_PKG_NAME"))).unwrap();
+            assert_eq!(xdg_dir(var, home_subdir, || Some(old.clone())), Some(old.clone()));
+        }
+    }
+
+    #[test]
+    fn test_header_parent_style() {
+        let parse = |style: &str| {
+            let m = crate::cli_args::get_cli_args()
+                .get_matches_from(vec![
+                    "foo",
+                    "--header-parent-style", style,
+                ]);
+            TereSettings::parse_cli_args(&m).map(|(settings, _)| settings.header_parent_style)
+        };
+        let m = crate::cli_args::get_cli_args().get_matches_from(vec!["foo"]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.header_parent_style, HeaderParentStyle::Dim);
+
+        assert_eq!(parse("bold").unwrap(), HeaderParentStyle::Bold);
+        assert_eq!(parse("none").unwrap(), HeaderParentStyle::Bold);
+        assert_eq!(parse("dark_grey").unwrap(), HeaderParentStyle::Color(Color::DarkGrey));
+        assert!(parse("not-a-color").is_err());
+    }
+
+    #[test]
+    fn test_icons() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.icons);
+        assert!(settings.icon_map.is_empty());
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--no-icons",
+                "--icons",
+                "--icon-map", "RS:R,folder:D",
+                "--icon-map", "rs:r",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.icons);
+        assert_eq!(settings.icon_map.len(), 2);
+        assert_eq!(settings.icon_map["rs"], "r");
+        assert_eq!(settings.icon_map["folder"], "D");
+
+        for mapping in ["rs", "rs:", ":R", "rs:R,"] {
+            let m = crate::cli_args::get_cli_args()
+                .get_matches_from(vec![
+                    "foo",
+                    "--icon-map", mapping,
+                ]);
+            assert!(TereSettings::parse_cli_args(&m).is_err(), "{}", mapping);
+        }
+    }
+
+    #[test]
+    fn test_bell_on_no_match() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.bell_on_no_match);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--bell-on-no-match",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.bell_on_no_match);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--bell-on-no-match",
+                "--no-bell-on-no-match",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.bell_on_no_match);
+    }
+
+    #[test]
+    fn test_ignore_diacritics() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.ignore_diacritics);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--ignore-diacritics",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.ignore_diacritics);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--ignore-diacritics",
+                "--no-ignore-diacritics",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.ignore_diacritics);
+    }
+
+    #[test]
+    fn test_no_prefetch() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.no_prefetch);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--no-prefetch",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.no_prefetch);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--no-prefetch",
+                "--prefetch",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.no_prefetch);
+    }
+
+    #[test]
+    fn test_no_row_fill() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.no_row_fill);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--no-row-fill",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.no_row_fill);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--no-row-fill",
+                "--row-fill",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.no_row_fill);
+    }
+
+    #[test]
+    fn test_align_extensions() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.align_extensions);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--align-extensions",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.align_extensions);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--align-extensions",
+                "--no-align-extensions",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.align_extensions);
+    }
+
+    #[test]
+    fn test_allow_mkdir() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.allow_mkdir);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--allow-mkdir",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.allow_mkdir);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--allow-mkdir",
+                "--no-allow-mkdir",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.allow_mkdir);
+    }
+
+    #[test]
+    fn test_relative() {
+        let m = crate::cli_args::get_cli_args().get_matches_from(vec!["foo"]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.relative_to, None);
+
+        let m = crate::cli_args::get_cli_args().get_matches_from(vec!["foo", "--relative"]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.relative_to, Some(PathBuf::from(".")));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec!["foo", "--relative", "--relative-to", "/some/base"]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.relative_to, Some(PathBuf::from("/some/base")));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec!["foo", "--relative-to", "base", "--no-relative"]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.relative_to, None);
+    }
+
+    #[test]
+    fn test_page_scroll() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.page_scroll, PageScroll::Full);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--page-scroll", "full",
+                "--page-scroll", "half",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.page_scroll, PageScroll::Half);
+
+        let m = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--page-scroll", "quarter",
+            ]);
+        assert!(m.is_err());
+    }
+
+    #[test]
+    fn test_padding() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.padding, 0);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--padding", "2",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.padding, 2);
+
+        let m = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--padding", "x",
+            ]);
+        assert!(m.is_err());
+    }
+
+    #[test]
+    fn test_scroll_off() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.scroll_off, 0);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--scroll-off", "3",
+                "--scroll-off", "5",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.scroll_off, 5);
+
+        let m = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--scroll-off", "-1",
+            ]);
+        assert!(m.is_err());
+    }
+
+    #[test]
+    fn test_search_debounce() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.search_debounce, Some(Duration::from_millis(50)));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--search-debounce", "100",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.search_debounce, Some(Duration::from_millis(100)));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--search-debounce", "100",
+                "--search-debounce", "0",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.search_debounce, None);
+    }
+
+    #[test]
+    fn test_match_color() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.match_color, None);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--match-color", "red",
+                "--match-color", "Dark_Green",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.match_color, Some(Color::DarkGreen));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--match-color", "yellow",
+                "--match-color", "none",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.match_color, None);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--match-color", "not-a-color",
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_cursor_style() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.cursor_style, CursorStyle::Reverse);
+        assert_eq!(settings.cursor_color, Some(Color::Grey));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--cursor-style", "bar",
+                "--cursor-style", "arrow",
+                "--cursor-color", "red",
+                "--cursor-color", "dark_blue",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.cursor_style, CursorStyle::Arrow);
+        assert_eq!(settings.cursor_color, Some(Color::DarkBlue));
+
+        let m = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--cursor-style", "underline",
+            ]);
+        assert!(m.is_err());
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--cursor-color", "none",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.cursor_color, None);
+    }
+
+    #[test]
+    fn test_modified_within() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.modified_filter);
+        assert_eq!(settings.modified_window, DEFAULT_MODIFIED_WINDOW);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--modified-within", "2h",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.modified_filter);
+        assert_eq!(settings.modified_window, Duration::from_secs(2 * 60 * 60));
+
+        for invalid in ["2", "h", "0d", "1.5d", "2y"] {
+            let m = crate::cli_args::get_cli_args()
+                .get_matches_from(vec![
+                    "foo",
+                    "--modified-within", invalid,
+                ]);
+            assert!(TereSettings::parse_cli_args(&m).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_footer_format() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.footer_format.is_none());
+        assert!(settings.search_footer_format.is_none());
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--footer-format", "{cursor}/{total}",
+                "--search-footer-format", "{match}/{matches}",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.footer_format, Some(FooterTemplate::from_str("{cursor}/{total}").unwrap()));
+        assert_eq!(settings.search_footer_format, Some(FooterTemplate::from_str("{match}/{matches}").unwrap()));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--footer-format", "{cursor}/{count}",
+            ]);
+        let err = TereSettings::parse_cli_args(&m).err().unwrap();
+        assert!(err.to_string().contains("Invalid value for 'footer-format': unknown placeholder '{count}'"), "{}", err);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        for text in ["30s", "90m", "1h", "36h", "2d", "3w"] {
+            assert_eq!(format_duration(parse_duration(text).unwrap()), text);
+        }
+        assert_eq!(format_duration(Duration::from_secs(120)), "2m");
+        assert_eq!(format_duration(Duration::from_secs(14 * 24 * 60 * 60)), "2w");
+    }
+
+    #[test]
+    fn test_confirm_quit_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.confirm_quit);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--confirm-quit",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.confirm_quit);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--confirm-quit",
+                "--no-confirm-quit",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.confirm_quit);
+    }
+
+    #[test]
+    fn test_inline() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.inline_rows, None);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--inline",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.inline_rows, Some(15));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--inline", "8",
+                "some/path",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.inline_rows, Some(8));
+        assert_eq!(settings.start_path, Some("some/path".into()));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--inline=8",
+                "--no-inline",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.inline_rows, None);
+
+        let res = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--inline=0",
+            ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_file_filter() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.file_filter.is_none());
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--filter", "*.rs",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        let filter = settings.file_filter.unwrap();
+        assert!(filter.is_match("main.rs"));
+        assert!(!filter.is_match("main.rs.bak"));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--filter", "*.rs",
+                "--filter", "",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.file_filter.is_none());
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--filter", "a{b",
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
+    }
+
+    #[test]
+    fn test_warning_color() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.warning_color, Some(Color::Red));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--warning-color", "magenta",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.warning_color, Some(Color::Magenta));
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--warning-color", "none",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.warning_color, None);
+    }
+
+    #[test]
+    fn test_preview_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert!(!settings.preview_enabled);
 
-    #[test]
-    fn test_keyboard_mapping_cli_option1() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-x:Exit",
+                "--preview",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
         assert!(warnings.is_empty());
-        assert_eq!(settings.keymap.get(&(key!(ctrl-x), ActionContext::None)), Some(&Action::Exit));
-    }
+        assert!(settings.preview_enabled);
 
-    #[test]
-    fn test_keyboard_mapping_cli_option2() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-x:Exit,ctrl-y:ClearSearch",
+                "--preview",
+                "--no-preview",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
         assert!(warnings.is_empty());
-        assert_eq!(settings.keymap.get(&(key!(ctrl-x), ActionContext::None)), Some(&Action::Exit));
-        assert_eq!(settings.keymap.get(&(key!(ctrl-y), ActionContext::None)), Some(&Action::ClearSearch));
+        assert!(!settings.preview_enabled);
     }
 
     #[test]
-    fn test_keyboard_mapping_cli_option3() {
+    fn test_numbers_override() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-x:Exit,ctrl-x:ClearSearch", // repeated mapping
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert_eq!(settings.keymap.get(&(key!(ctrl-x), ActionContext::None)), Some(&Action::ClearSearch));
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.number_mode);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--numbers",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.number_mode);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--numbers",
+                "--no-numbers",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.number_mode);
     }
 
     #[test]
-    fn test_keyboard_mapping_cli_option4() {
+    fn test_pick_file_override() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                // provide option multiple times
-                "-m", "ctrl-x:Exit",
-                "-m", "ctrl-x:ClearSearch",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
         assert!(warnings.is_empty());
-        assert_eq!(settings.keymap.get(&(key!(ctrl-x), ActionContext::None)), Some(&Action::ClearSearch));
-    }
+        assert_eq!(settings.enter_on_file, EnterOnFile::Nothing);
 
-    #[test]
-    fn test_keyboard_mapping_cli_option_wrong1() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-x:Exxit", // incorrect action
+                "--pick-file",
             ]);
-        assert!(TereSettings::parse_cli_args(&m).is_err());
-    }
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.enter_on_file, EnterOnFile::Select);
 
-    #[test]
-    fn test_keyboard_mapping_cli_option_wrong2() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-:Exit", // inccorect mapping
+                "--pick-file",
+                "--no-pick-file",
             ]);
-        assert!(TereSettings::parse_cli_args(&m).is_err());
-    }
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(settings.enter_on_file, EnterOnFile::Nothing);
 
-    #[test]
-    fn test_keyboard_mapping_cli_option_wrong3() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-x:Wrong:Exit", // incorrect context
+                "--pick-file",
+                "--enter-on-file", "open",
             ]);
-        assert!(TereSettings::parse_cli_args(&m).is_err());
-    }
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.enter_on_file, EnterOnFile::Open);
 
-    #[test]
-    fn test_keyboard_mapping_cli_option_wrong4() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-x::Exit", // Incorrect syntax
+                "--enter-on-file", "open",
+                "--pick-file",
             ]);
-        assert!(TereSettings::parse_cli_args(&m).is_err());
-    }
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.enter_on_file, EnterOnFile::Select);
 
-    #[test]
-    fn test_keyboard_mapping_cli_option_wrong5() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-x", // missing mapping and/or context
+                "--enter-on-file", "open",
+                "--no-pick-file",
             ]);
-        assert!(TereSettings::parse_cli_args(&m).is_err());
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.enter_on_file, EnterOnFile::Nothing);
+
+        let m = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--enter-on-file", "delete",
+            ]);
+        assert!(m.is_err());
     }
 
     #[test]
-    fn test_keyboard_mapping_cli_option_wrong6() {
+    fn test_continuous_search_override() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "ctrl-x:", // missing mapping and/or context
             ]);
-        assert!(TereSettings::parse_cli_args(&m).is_err());
-    }
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.continuous_search);
 
-    #[test]
-    fn test_unmap1() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
+                "--continuous-search",
             ]);
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-h), ActionContext::None)), Some(&Action::ChangeDirParent));
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-j), ActionContext::None)), Some(&Action::CursorDown));
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-k), ActionContext::None)), Some(&Action::CursorUp));
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-l), ActionContext::None)), Some(&Action::ChangeDir));
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.continuous_search);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "alt-h:None,alt-j:None,alt-k:None,alt-l:None",
+                "--continuous-search",
+                "--no-continuous-search",
             ]);
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-h), ActionContext::None)), None);
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-j), ActionContext::None)), None);
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-k), ActionContext::None)), None);
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(alt-l), ActionContext::None)), None);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.continuous_search);
     }
 
     #[test]
-    fn test_unmap2() {
+    fn test_details_override() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
             ]);
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::NotSearching)), Some(&Action::Exit));
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::Searching)), Some(&Action::ClearSearch));
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::None)), None);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.show_details);
 
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(backspace), ActionContext::Searching)), Some(&Action::EraseSearchChar));
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(backspace), ActionContext::NotSearching)), Some(&Action::ChangeDirParent));
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(backspace), ActionContext::None)), None);
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--details",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.show_details);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "-m", "esc:Searching:None",
-                "-m", "backspace:None", // this shouldn't affect any of the mappings since they are context-dependent
-                "-m", "backspace:None:None", // this shouldn't affect any of the mappings since they are context-dependent
+                "--details",
+                "--no-details",
             ]);
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::NotSearching)), Some(&Action::Exit));
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::Searching)), None);
-        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.keymap.get(&(key!(esc), ActionContext::None)), None);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.show_details);
     }
 
     #[test]
-    fn test_clear_default_keymap() {
+    fn test_parse_keys_arg() {
+        assert_eq!(
+            parse_keys_arg("d o  space enter ctrl+x").unwrap(),
+            vec![key!(d), key!(o), key!(space), key!(enter), key!(ctrl-x)],
+        );
+        assert_eq!(parse_keys_arg("").unwrap(), vec![]);
+        assert!(parse_keys_arg("j foo-bar").is_err());
+
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--clear-default-keymap",
-                "--map", "ctrl-x:Exit",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert!(settings.keymap.len() == 1);
-    }
+        assert_eq!(TereSettings::parse_cli_args(&m).unwrap().0.scripted_keys, None);
 
-    #[test]
-    fn test_empty_keymap_is_error() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--clear-default-keymap",
+                "--keys",
+                "j enter",
             ]);
-        assert!(TereSettings::parse_cli_args(&m).is_err());
+        assert_eq!(
+            TereSettings::parse_cli_args(&m).unwrap().0.scripted_keys,
+            Some(vec![key!(j), key!(enter)]),
+        );
     }
 
     #[test]
-    fn test_unmap_exit_is_error() {
+    fn test_total_size_override() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--map", "esc:NotSearching:None,alt-q:None",
             ]);
-        assert!(TereSettings::parse_cli_args(&m).is_err());
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.show_total_size);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--total-size",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.show_total_size);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--total-size",
+                "--no-total-size",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.show_total_size);
     }
 
     #[test]
-    fn test_filter_search_override() {
+    fn test_remember_search_override() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert!(!settings.filter_search);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.remember_search);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--filter-search",
-                "--no-filter-search",
+                "--remember-search",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert!(!settings.filter_search);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.remember_search);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--filter-search",
-                "--no-filter-search",
-                "--filter-search",
+                "--remember-search",
+                "--no-remember-search",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert!(settings.filter_search);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.remember_search);
     }
 
     #[test]
-    fn test_folders_only_override() {
+    fn test_group_separator_override() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert!(!settings.folders_only);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.group_separator);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--folders-only",
+                "--group-separator",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert!(settings.folders_only);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.group_separator);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--folders-only",
-                "--no-folders-only",
+                "--group-separator",
+                "--no-group-separator",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert!(!settings.folders_only);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.group_separator);
     }
 
     #[test]
-    fn test_case_sensitive_mode_override() {
+    fn test_autocd_discard_keys() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert_eq!(settings.case_sensitive, CaseSensitiveMode::SmartCase);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.autocd_discard_keys);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--case-sensitive",
-                "--ignore-case",
+                "--autocd-discard-keys",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert_eq!(settings.case_sensitive, CaseSensitiveMode::IgnoreCase);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.autocd_discard_keys);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--case-sensitive",
-                "--ignore-case",
-                "--case-sensitive",
+                "--autocd-discard-keys",
+                "--no-autocd-discard-keys",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert_eq!(settings.case_sensitive, CaseSensitiveMode::CaseSensitive);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.autocd_discard_keys);
+    }
 
+    #[test]
+    fn test_autocd_min_chars() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--case-sensitive",
-                "--ignore-case",
-                "--case-sensitive",
-                "--smart-case",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert_eq!(settings.case_sensitive, CaseSensitiveMode::SmartCase);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.autocd_min_chars, 1);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--autocd-min-chars", "2",
+                "--autocd-min-chars", "3",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert_eq!(settings.autocd_min_chars, 3);
+
+        let m = crate::cli_args::get_cli_args()
+            .try_get_matches_from(vec![
+                "foo",
+                "--autocd-min-chars", "x",
+            ]);
+        assert!(m.is_err());
     }
 
     #[test]
-    fn test_gap_search_mode_override() {
+    fn test_autocd_prefix_override() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert_eq!(settings.gap_search_mode, GapSearchMode::GapSearchFromStart);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.autocd_prefix);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--gap-search",
-                "--gap-search-anywhere",
+                "--autocd-prefix",
             ]);
-        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(warnings.is_empty());
-        assert_eq!(settings.gap_search_mode, GapSearchMode::GapSearchAnywhere);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(settings.autocd_prefix);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--gap-search",
-                "--gap-search-anywhere",
-                "--normal-search-anywhere",
+                "--autocd-prefix",
+                "--no-autocd-prefix",
+            ]);
+        let (settings, _) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(!settings.autocd_prefix);
+    }
+
+    #[test]
+    fn test_wrap_override() {
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
         assert!(warnings.is_empty());
-        assert_eq!(settings.gap_search_mode, GapSearchMode::NormalSearchAnywhere);
+        assert!(!settings.no_wrap);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--gap-search",
-                "--gap-search-anywhere",
-                "--normal-search-anywhere",
-                "--gap-search",
+                "--no-wrap",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
         assert!(warnings.is_empty());
-        assert_eq!(settings.gap_search_mode, GapSearchMode::GapSearchFromStart);
-    }
+        assert!(settings.no_wrap);
 
-    #[test]
-    fn test_no_gap_search_deprecated() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--no-gap-search"
+                "--no-wrap",
+                "--wrap",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
-        assert!(!warnings.is_empty());
-        assert!(settings.gap_search_mode == GapSearchMode::NormalSearch);
+        assert!(warnings.is_empty());
+        assert!(!settings.no_wrap);
     }
 
     #[test]
-    fn test_sort_mode_override() {
+    fn test_message_timeouts() {
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
         assert!(warnings.is_empty());
-        assert_eq!(settings.sort_mode, SortMode::Name);
+        assert_eq!(settings.info_timeout, None);
+        assert_eq!(settings.error_timeout, None);
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--sort", "created",
+                "--info-timeout", "3",
+                "--error-timeout", "10",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
         assert!(warnings.is_empty());
-        assert_eq!(settings.sort_mode, SortMode::Created);
+        assert_eq!(settings.info_timeout, Some(3));
+        assert_eq!(settings.error_timeout, Some(10));
 
         let m = crate::cli_args::get_cli_args()
             .get_matches_from(vec![
                 "foo",
-                "--sort",  "created",
-                "--sort",  "name",
-                "--sort",  "modified",
+                "--info-timeout", "3",
+                "--info-timeout", "off",
             ]);
         let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
         assert!(warnings.is_empty());
-        assert_eq!(settings.sort_mode, SortMode::Modified);
+        assert_eq!(settings.info_timeout, None);
 
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--error-timeout", "-1",
+            ]);
+        assert!(TereSettings::parse_cli_args(&m).is_err());
     }
 
     #[test]
@@ -811,6 +3253,25 @@ mod tests {
         assert!(warnings.is_empty());
         assert!(!settings.mouse_enabled);
 
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--mouse",  "on",
+                "--no-mouse",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert!(!settings.mouse_enabled);
+
+        let m = crate::cli_args::get_cli_args()
+            .get_matches_from(vec![
+                "foo",
+                "--no-mouse",
+                "--mouse",  "on",
+            ]);
+        let (settings, warnings) = TereSettings::parse_cli_args(&m).unwrap();
+        assert!(warnings.is_empty());
+        assert!(settings.mouse_enabled);
     }
 
 }
@@ -0,0 +1,88 @@
+//! This module contains the shell functions that make the shell `cd` to the folder printed by
+//! `tere`, for printing them with `--print-shell-integration`. The UI is drawn to stderr and only
+//! the final folder is printed to stdout, so the functions capture stdout and leave stderr alone.
+
+use std::io::{ErrorKind, Write};
+
+use crate::error::TereError;
+
+const POSIX_FUNCTION: &str = r#"tere() {
+    local result=$(command tere "$@")
+    [ -n "$result" ] && cd -- "$result"
+}
+"#;
+
+const FISH_FUNCTION: &str = r#"function tere
+    set --local result (command tere $argv)
+    [ -n "$result" ] && cd -- "$result"
+end
+"#;
+
+/// The shell code that defines the `tere` function for `shell`, along with a comment on how to use
+/// it. The shell is one of the values accepted by `--print-shell-integration`.
+fn shell_integration(shell: &str) -> String {
+    let (rc_file, function) = match shell {
+        "bash" => ("~/.bashrc", POSIX_FUNCTION),
+        "zsh" => ("~/.zshrc", POSIX_FUNCTION),
+        "fish" => ("~/.config/fish/config.fish", FISH_FUNCTION),
+        _ => unreachable!("unsupported shell '{}'", shell),
+    };
+    let eval = match shell {
+        "fish" => format!("tere --print-shell-integration {} | source", shell),
+        _ => format!("eval \"$(tere --print-shell-integration {})\"", shell),
+    };
+    format!(
+        "# Change to the folder that tere prints when it exits. To use this, add the line\n#     {}\n# to {}\n{}",
+        eval, rc_file, function,
+    )
+}
+
+/// Print the shell integration for `shell` to stdout
+pub fn print_shell_integration(shell: &str) -> Result<(), TereError> {
+    match write!(std::io::stdout(), "{}", shell_integration(shell)) {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        res => Ok(res?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_integration() {
+        assert_eq!(
+            shell_integration("bash"),
+            r#"# Change to the folder that tere prints when it exits. To use this, add the line
+#     eval "$(tere --print-shell-integration bash)"
+# to ~/.bashrc
+tere() {
+    local result=$(command tere "$@")
+    [ -n "$result" ] && cd -- "$result"
+}
+"#
+        );
+        assert_eq!(
+            shell_integration("zsh"),
+            r#"# Change to the folder that tere prints when it exits. To use this, add the line
+#     eval "$(tere --print-shell-integration zsh)"
+# to ~/.zshrc
+tere() {
+    local result=$(command tere "$@")
+    [ -n "$result" ] && cd -- "$result"
+}
+"#
+        );
+        assert_eq!(
+            shell_integration("fish"),
+            r#"# Change to the folder that tere prints when it exits. To use this, add the line
+#     tere --print-shell-integration fish | source
+# to ~/.config/fish/config.fish
+function tere
+    set --local result (command tere $argv)
+    [ -n "$result" ] && cd -- "$result"
+end
+"#
+        );
+    }
+}
@@ -15,24 +15,53 @@ pub enum Action {
     ChangeDirParent,
     ChangeDirHome,
     ChangeDirRoot,
+    ChangeDirPrevious,
+    ChangeDirAncestor,
+    ChangeDirNextSibling,
+    ChangeDirPreviousSibling,
     ChangeDirAndExit,
+    AcceptCurrentDir,
+    SelectAndExit,
+    OpenFile,
+    OpenFileManager,
+    CopyPath,
+    CreateFolder,
 
     CursorUp,
     CursorDown,
+    CursorLeft,
+    CursorRight,
     CursorUpScreen,
     CursorDownScreen,
+    CursorUpHalfScreen,
+    CursorDownHalfScreen,
     CursorTop,
     CursorBottom,
 
     EraseSearchChar,
+    EraseSearchWord,
     ClearSearch,
+    RecallPreviousSearch,
+    RecallNextSearch,
 
     ChangeFilterSearchMode,
     ChangeCaseSensitiveMode,
     ChangeGapSearchMode,
     ChangeSortMode,
+    ChangeSearchScope,
+    ChangeListingMode,
+    ToggleRecursiveSearch,
+
+    TogglePreview,
+    ToggleNumberMode,
+    TypeRowNumber,
+
+    SetFileFilter,
+    ToggleModifiedFilter,
+    ToggleExcluded,
 
     RefreshListing,
+    ComputeTotalSize,
 
     Help,
 
@@ -49,24 +78,53 @@ impl Action {
             Self::ChangeDirParent => "Go to the parent directory",
             Self::ChangeDirHome => "Go to the home directory",
             Self::ChangeDirRoot => "Go to the root directory",
+            Self::ChangeDirPrevious => "Go back to the previously visited directory, like 'cd -'",
+            Self::ChangeDirAncestor => "Select a parent directory in the header and go there",
+            Self::ChangeDirNextSibling => "Go to the next directory in the parent directory",
+            Self::ChangeDirPreviousSibling => "Go to the previous directory in the parent directory",
             Self::ChangeDirAndExit => "Enter the directory under the cursor and exit",
+            Self::AcceptCurrentDir => "Exit and print the path of the current directory, even if the cursor is on a file",
+            Self::SelectAndExit => "Exit and print the path of the item under the cursor",
+            Self::OpenFile => "Open the file under the cursor in an editor or other program",
+            Self::OpenFileManager => "Open the current directory in the file manager",
+            Self::CopyPath => "Copy the path of the item under the cursor to the clipboard",
+            Self::CreateFolder => "Create a new directory in the current directory (requires --allow-mkdir)",
 
             Self::CursorUp => "Move the cursor up by one step",
             Self::CursorDown => "Move the cursor down by one step",
+            Self::CursorLeft => "Move the cursor left by one column in the grid layout",
+            Self::CursorRight => "Move the cursor right by one column in the grid layout",
             Self::CursorUpScreen => "Move the cursor up by one screenful",
             Self::CursorDownScreen => "Move the cursor down by one screenful",
+            Self::CursorUpHalfScreen => "Move the cursor up by half a screenful",
+            Self::CursorDownHalfScreen => "Move the cursor down by half a screenful",
             Self::CursorTop => "Move the cursor to the first item in the listing",
             Self::CursorBottom => "Move the cursor to the last item in the listing",
 
             Self::EraseSearchChar => "Erase one character from the search",
+            Self::EraseSearchWord => "Erase the last word from the search",
             Self::ClearSearch => "Clear the search",
+            Self::RecallPreviousSearch => "Replace the search with the previous one from the search history",
+            Self::RecallNextSearch => "Replace the search with the next one from the search history",
 
             Self::ChangeFilterSearchMode => "Toggle the filter-search mode",
             Self::ChangeCaseSensitiveMode => "Change the case-sensitive mode",
             Self::ChangeGapSearchMode => "Change the gap-search mode",
             Self::ChangeSortMode => "Change the sorting mode",
+            Self::ChangeSearchScope => "Cycle between searching the whole name, the extension, or the name without the extension",
+            Self::ChangeListingMode => "Cycle between showing files and folders, folders only, or files only",
+            Self::ToggleRecursiveSearch => "Search the subdirectories of the current directory too, or only the current directory",
+
+            Self::TogglePreview => "Show or hide the preview pane",
+            Self::ToggleNumberMode => "Show or hide row numbers, and jump to a row by typing its number",
+            Self::TypeRowNumber => "Add the digit that was typed to the number of the row to jump to",
+
+            Self::SetFileFilter => "Use the search as a glob pattern for filtering files, or clear the filter if not searching",
+            Self::ToggleModifiedFilter => "Show only recently modified files, or all files",
+            Self::ToggleExcluded => "Show or hide the directories excluded with --exclude",
 
             Self::RefreshListing => "Refresh the directory listing",
+            Self::ComputeTotalSize => "Compute the total size of the current directory, including subdirectories",
 
             Self::Help => "Show the help screen",
 
@@ -89,6 +147,12 @@ pub enum ActionContext {
 
     /// This shortcut only applies when not searching
     NotSearching,
+
+    /// This shortcut only applies in the grid layout, when there's more than one column
+    Grid,
+
+    /// This shortcut only applies when the row numbers are shown
+    NumberMode,
 }
 
 impl ActionContext {
@@ -98,6 +162,8 @@ impl ActionContext {
             Self::None => "This mapping applies if no other context applies. This is the behavior if no context is specified: the mapping 'key-combination:action' is equivalent to 'key-combination:None:action'.",
             Self::Searching => "This mapping only applies while searching (at least one search character has been given).",
             Self::NotSearching => "This mapping only applies while not searching.",
+            Self::Grid => "This mapping only applies in the grid layout (see --grid), when there's more than one column. It takes precedence over the other contexts.",
+            Self::NumberMode => "This mapping only applies while the row numbers are shown (see --numbers). It takes precedence over the other contexts.",
         }
     }
 
@@ -108,6 +174,8 @@ impl ActionContext {
             Self::None => "no context",
             Self::Searching => "when searching",
             Self::NotSearching => "when not searching",
+            Self::Grid => "in the grid layout",
+            Self::NumberMode => "when showing row numbers",
         }
     }
 }
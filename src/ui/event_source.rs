@@ -0,0 +1,149 @@
+//! This module contains the sources of terminal events that the UI reacts to. Normally the events
+//! are read from the terminal, but they can also be given in advance with the `--keys` option or
+//! in tests, so that the navigation logic can be tested (or scripted) without anyone typing.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crossterm::{
+    event::{poll, read, Event, KeyEvent},
+    terminal,
+    Result as CTResult,
+};
+
+pub trait EventSource {
+    /// The size of the terminal as (columns, rows). The events include `Resize` events when this
+    /// changes.
+    fn size(&self) -> CTResult<(u16, u16)>;
+
+    /// Return true if an event is available within `timeout`, so that `read` won't block.
+    fn poll(&mut self, timeout: Duration) -> CTResult<bool>;
+
+    /// Wait for the next event. Returns None if there will be no more events, in which case the
+    /// UI should exit.
+    fn read(&mut self) -> CTResult<Option<Event>>;
+
+    /// True if the events are read from the terminal, which then has to be put in raw mode with
+    /// `enable_raw_mode` before reading them
+    fn is_terminal(&self) -> bool;
+
+    /// Put the terminal in raw mode, so that the keys are read as they are pressed. This fails if
+    /// there's no terminal to read the keys from, for example when stdin is redirected and there's
+    /// no controlling terminal either.
+    fn enable_raw_mode(&mut self) -> CTResult<()>;
+}
+
+/// Read the events from the terminal with crossterm. This never runs out of events.
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn size(&self) -> CTResult<(u16, u16)> {
+        terminal::size()
+    }
+
+    fn poll(&mut self, timeout: Duration) -> CTResult<bool> {
+        poll(timeout)
+    }
+
+    fn read(&mut self) -> CTResult<Option<Event>> {
+        read().map(Some)
+    }
+
+    fn is_terminal(&self) -> bool {
+        true
+    }
+
+    fn enable_raw_mode(&mut self) -> CTResult<()> {
+        terminal::enable_raw_mode()
+    }
+}
+
+/// A fixed sequence of events. Each event is considered to happen only after the previous one has
+/// been handled, so `poll` never reports events that arrived while the UI was busy. Instead, it
+/// just waits until the timeout has passed.
+pub struct ScriptedEvents {
+    events: VecDeque<Event>,
+    // If this is None, the size of the actual terminal is used, if there is one
+    size: Option<(u16, u16)>,
+}
+
+/// The size used for scripted events if no size is given and there's no terminal
+const SCRIPTED_DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+impl ScriptedEvents {
+    pub fn new(events: Vec<Event>) -> Self {
+        Self {
+            events: events.into(),
+            size: None,
+        }
+    }
+
+    /// Key presses, e.g. from the `--keys` option
+    pub fn from_keys(keys: &[KeyEvent]) -> Self {
+        Self::new(keys.iter().map(|k| Event::Key(*k)).collect())
+    }
+
+    /// Pretend that the terminal has the given size, so that no terminal is needed at all. The
+    /// `Resize` events in the script change the size when they are read.
+    #[cfg(test)]
+    pub fn with_size(mut self, w: u16, h: u16) -> Self {
+        self.size = Some((w, h));
+        self
+    }
+}
+
+impl EventSource for ScriptedEvents {
+    fn size(&self) -> CTResult<(u16, u16)> {
+        match self.size {
+            Some(size) => Ok(size),
+            // the keys given with --keys don't need a terminal, e.g. when the output is redirected
+            None => Ok(terminal::size().unwrap_or(SCRIPTED_DEFAULT_SIZE)),
+        }
+    }
+
+    fn poll(&mut self, timeout: Duration) -> CTResult<bool> {
+        std::thread::sleep(timeout);
+        Ok(false)
+    }
+
+    fn read(&mut self) -> CTResult<Option<Event>> {
+        let event = self.events.pop_front();
+        if let (Some(size), Some(Event::Resize(w, h))) = (&mut self.size, &event) {
+            *size = (*w, *h);
+        }
+        Ok(event)
+    }
+
+    fn is_terminal(&self) -> bool {
+        false
+    }
+
+    fn enable_raw_mode(&mut self) -> CTResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crokey::key;
+
+    #[test]
+    fn test_scripted_events() {
+        let mut events = ScriptedEvents::from_keys(&[key!(j), key!(enter)]).with_size(80, 24);
+        assert_eq!(events.size().unwrap(), (80, 24));
+        assert!(!events.poll(Duration::from_secs(0)).unwrap());
+        assert_eq!(events.read().unwrap(), Some(Event::Key(key!(j))));
+        assert_eq!(events.read().unwrap(), Some(Event::Key(key!(enter))));
+        assert_eq!(events.read().unwrap(), None);
+        assert_eq!(events.read().unwrap(), None);
+    }
+
+    #[test]
+    fn test_scripted_resize() {
+        let mut events = ScriptedEvents::new(vec![Event::Resize(40, 10)]).with_size(80, 24);
+        assert_eq!(events.size().unwrap(), (80, 24));
+        assert_eq!(events.read().unwrap(), Some(Event::Resize(40, 10)));
+        assert_eq!(events.size().unwrap(), (40, 10));
+    }
+}
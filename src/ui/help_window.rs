@@ -242,6 +242,7 @@ mod tests {
 
             let key_combos: Vec<_> = parts[2]
                 .replace("if not searching,", "").replace("if searching", "")
+                .replace("in the grid layout,", "").replace("when showing row numbers,", "")
                 .replace("<kbd>", "").replace("</kbd>", "")
                 .replace('+', "-")
                 .replace('↑', "up").replace('↓', "down").replace('←', "left").replace('→', "right")
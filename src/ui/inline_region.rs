@@ -0,0 +1,45 @@
+//! Drawing the UI in a region of rows below the cursor instead of on the alternate screen, with the
+//! `--inline` option. The region is made the scrolling region of the terminal, and origin mode is
+//! enabled, so that the cursor positions used by the UI are relative to the top of the region. This
+//! way the UI can draw as if the region was the whole terminal.
+
+use crossterm::{cursor, queue, style::Print, terminal, Result as CTResult};
+use std::io::Write;
+
+/// Reserve `rows` rows for the UI, starting from the line of the cursor if it's empty, or the next
+/// line otherwise. If the terminal is shorter than `rows`, the whole terminal is used. The terminal
+/// is scrolled up if there isn't enough room below the cursor. Returns the terminal row where the
+/// region starts.
+pub fn enter_inline_region<W: Write>(w: &mut W, rows: u16) -> CTResult<u16> {
+    let (_, height) = terminal::size()?;
+    let rows = rows.clamp(1, height.max(1));
+    let (column, row) = cursor::position()?;
+    let start = if column == 0 { row } else { row.saturating_add(1) };
+
+    // Printing newlines scrolls the terminal when we're at the bottom
+    let newlines = usize::from(start - row) + usize::from(rows - 1);
+    queue!(w, Print("\r\n".repeat(newlines)))?;
+    let top = start.min(height.saturating_sub(rows));
+    set_inline_region(w, top, rows)?;
+    Ok(top)
+}
+
+/// Make the `rows` rows starting from the terminal row `top` the region that the UI is drawn in,
+/// by setting the scrolling region and enabling origin mode. This also moves the cursor to the top
+/// left corner of the region.
+pub fn set_inline_region<W: Write>(w: &mut W, top: u16, rows: u16) -> CTResult<()> {
+    queue!(w, Print(format!("\x1b[{};{}r\x1b[?6h", top + 1, top + rows)))?;
+    w.flush()
+}
+
+/// Restore the normal scrolling and cursor positioning, and clear the region that started from the
+/// terminal row `top`. The cursor is left at the start of the region.
+pub fn leave_inline_region<W: Write>(w: &mut W, top: u16) -> CTResult<()> {
+    queue!(
+        w,
+        Print("\x1b[?6l\x1b[r"),
+        cursor::MoveTo(0, top),
+        terminal::Clear(terminal::ClearType::FromCursorDown),
+    )?;
+    w.flush()
+}
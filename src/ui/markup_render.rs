@@ -1,6 +1,6 @@
-/// This module contains some functions to do some extremely basic rendering of markdown to
-/// `StyledContent` compatible with crossterm. Because the rendering is so simple, we are doing it
-/// ourselves instead of a heavy, full-fledged markdown crate, at least for now.
+//! This module contains some functions to do some extremely basic rendering of markdown to
+//! `StyledContent` compatible with crossterm. Because the rendering is so simple, we are doing it
+//! ourselves instead of a heavy, full-fledged markdown crate, at least for now.
 
 use crossterm::style::{StyledContent, Stylize};
 
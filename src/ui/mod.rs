@@ -1,17 +1,31 @@
 mod action;
+mod event_source;
 pub mod help_window;
+pub mod inline_region;
 pub mod markup_render;
 
 use std::convert::TryFrom;
-use std::fmt::Write as _;
-use std::io::{Stderr, Write};
-use std::path::PathBuf;
+use std::fmt::{self, Write as _};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::app_state::{TereAppState, NO_MATCHES_MSG};
+use crate::app_state::{
+    dir_size_recursive, entry_details, format_size, is_drive_list, is_text_file,
+    read_listing_entries, split_archive_path, CustomDirEntry, TereAppState, GRID_COLUMN_GAP,
+    NO_MATCHES_MSG,
+};
 use crate::error::TereError;
-use crate::settings::{CaseSensitiveMode, GapSearchMode, SortMode};
+use crate::settings::{
+    format_duration, parse_glob, CaseSensitiveMode, EnterOnFile, GapSearchMode, HeaderParentStyle, Layout, PageScroll, SearchScope, SortMode, ListingMode, TereSettings,
+};
 pub use action::{Action, ActionContext};
+pub use event_source::{EventSource, ScriptedEvents, TerminalEvents};
 use help_window::get_formatted_help_text;
+use inline_region::{enter_inline_region, leave_inline_region, set_inline_region};
 
 use crossterm::{
     execute,
@@ -20,7 +34,6 @@ use crossterm::{
     cursor,
     style::{self, Stylize, Attribute},
     event::{
-        read as read_event,
         Event,
         KeyEvent,
         MouseEvent,
@@ -31,43 +44,344 @@ use crossterm::{
         EnableMouseCapture,
         DisableMouseCapture,
     },
+    Command,
     Result as CTResult,
 };
 
 use dirs::home_dir;
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 const HEADER_SIZE: usize = 1;
 const INFO_WIN_SIZE: usize = 1;
 const FOOTER_SIZE: usize = 1;
 
+/// Drawn between the listing and the preview pane
+const PREVIEW_SEPARATOR: &str = "│ ";
+
+/// Frames of the spinner shown while reading a slow folder, and the time between them. The spinner
+/// is only shown if reading the folder takes longer than one frame.
+const SPINNER_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Start reading the folder under the cursor in the background when the cursor has stayed on it
+/// for this long
+const PREFETCH_DELAY: Duration = Duration::from_millis(200);
+
+/// With --no-row-fill, the number of columns highlighted on the cursor row after the name
+const NO_ROW_FILL_CURSOR_MARGIN: usize = 1;
+
+/// Resize events that arrive within this time of each other are handled together, so that the
+/// screen is redrawn only once when the window is resized by dragging
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(30);
+
+/// The search is only debounced (see --search-debounce) in folders with at least this many items.
+/// In smaller folders, updating the search is fast enough to do for every character.
+const SEARCH_DEBOUNCE_MIN_ITEMS: usize = 10_000;
+
+/// How the user exited the main event loop
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The user exited normally, and this folder (or file, see `--enter-on-file` and `SelectAndExit`)
+    /// should be printed so that the shell can cd to it
+    Selected(PathBuf),
+    /// The user exited without selecting anything (with Ctrl-c by default), nothing should be
+    /// printed
+    Aborted,
+}
+
 /// This struct is responsible for drawing an app state object to a stderr stream (confusingly
 /// called 'window' for historical reasons) that the UI is written to. Currently it somewhat
 /// conflates application logic with the UI.
 pub struct TereTui<'a> {
-    window: &'a Stderr,
+    window: Box<dyn Write + 'a>,
     app_state: TereAppState,
+    // Where the key presses and other events come from. This is the terminal, unless the keys are
+    // given with --keys.
+    events: Box<dyn EventSource>,
+    // If this is Some, the info message will be cleared at this time.
+    info_msg_clear_time: Option<Instant>,
+    // What is currently drawn on each row of the main window, so that rows that haven't changed
+    // don't have to be redrawn. None means that the contents of the row are unknown.
+    drawn_rows: Vec<Option<RowContents>>,
+    // The row number typed so far in number mode
+    number_input: String,
+    // The path of the item that was under the cursor when the info message was last set. With
+    // --details, the message is replaced by the details when the cursor moves to another item.
+    info_msg_item: Option<PathBuf>,
+    // True if the search mode stays active even though the search is empty, because we changed
+    // dir from a search with the continuous search option enabled.
+    continuous_search_active: bool,
+    // The folder whose total size (including subfolders) is being computed in the background, and
+    // the channel for receiving the result.
+    total_size_receiver: Option<(PathBuf, mpsc::Receiver<(u64, bool)>)>,
+    // The last computed total size and whether it was cut short by the depth limit, and the
+    // folder it was computed for
+    recursive_total_size: Option<(PathBuf, (u64, bool))>,
+    // False if colors are disabled with the NO_COLOR environment variable
+    use_color: bool,
+    // The system clipboard, created when something is copied for the first time. It's kept alive
+    // while the app is running, because on some platforms the clipboard contents are lost when
+    // it's dropped.
+    clipboard: Option<arboard::Clipboard>,
+    // The terminal row where the UI starts with --inline. The drawing is relative to it anyway (see
+    // `main`), but mouse events are reported in terminal coordinates. The region is moved when the
+    // terminal is resized or a file is opened, and `main` needs to know where it is on exit.
+    inline_top: Arc<AtomicU16>,
+    // An event that was read while skipping a burst of resize events. It's handled next.
+    pending_event: Option<Event>,
+}
+
+/// Everything that affects how a row of the main window is drawn. If this is the same as the last
+/// time the row was drawn, there's no need to draw it again.
+#[derive(PartialEq)]
+struct RowContents {
+    file_name: Option<String>,
+    is_dir: bool,
+    symlink_target: Option<PathBuf>,
+    is_broken_symlink: bool,
+    underline_locs: Vec<usize>,
+    highlight: bool,
+    width: usize,
+    columns: usize,
+    preview_enabled: bool,
+    number_prefix: String,
+    icon_prefix: String,
+    screen_row: usize,
+}
+
+/// Return the grapheme clusters of `text` (along with their byte offsets) that fit within
+/// `max_width` columns of the terminal. This takes into account that some characters, such as CJK
+/// characters and emoji, take up two columns.
+fn graphemes_fitting_width(text: &str, max_width: usize) -> Vec<(usize, &str)> {
+    let mut total_width = 0;
+    UnicodeSegmentation::grapheme_indices(text, true)
+        .take_while(|(_, g)| {
+            total_width += g.width();
+            total_width <= max_width
+        })
+        .collect()
+}
+
+/// The byte offset of the dot that starts the extension of a file name, i.e. the last dot. The dot
+/// of a dotfile like `.bashrc` doesn't start an extension, and neither does a dot at the end.
+fn extension_start(name: &str) -> Option<usize> {
+    name.rfind('.').filter(|i| *i > 0 && i + 1 < name.len())
+}
+
+/// A command that sets the foreground and background colors. If colors are disabled, it doesn't
+/// output any colors, and highlighted text is shown in reverse video instead.
+struct SetColors {
+    fg: style::Color,
+    bg: style::Color,
+    highlight: bool,
+    use_color: bool,
+}
+
+impl Command for SetColors {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if self.use_color {
+            style::SetForegroundColor(self.fg).write_ansi(f)?;
+            style::SetBackgroundColor(self.bg).write_ansi(f)
+        } else if self.highlight {
+            style::SetAttribute(Attribute::Reverse).write_ansi(f)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> CTResult<()> {
+        if self.use_color {
+            style::SetForegroundColor(self.fg).execute_winapi()?;
+            style::SetBackgroundColor(self.bg).execute_winapi()
+        } else if self.highlight {
+            style::SetAttribute(Attribute::Reverse).execute_winapi()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The OSC 52 escape sequence, which asks the terminal to copy `text` to the clipboard.
+fn osc52_copy_sequence(text: &str) -> String {
+    const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in text.as_bytes().chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_CHARS[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    format!("\x1b]52;c;{}\x07", encoded)
+}
+
+/// The program and arguments of a command for opening something, e.g. a file or a folder. The
+/// `configured` command is split on whitespace, and if it's not given, the platform default is
+/// used, which is `windows_default` on Windows.
+fn launcher_command(configured: Option<&str>, windows_default: &[&str]) -> Vec<String> {
+    match configured {
+        Some(cmd) => cmd.split_whitespace().map(String::from).collect(),
+        None if cfg!(target_os = "macos") => vec!["open".into()],
+        None if cfg!(windows) => windows_default.iter().map(|s| s.to_string()).collect(),
+        None => vec!["xdg-open".into()],
+    }
+}
+
+/// A summary of the version and the build of `tere`, and the current values of the settings that
+/// can differ from the command line arguments, because they are read from the preferences file or
+/// changed while running, for bug reports.
+fn version_info(settings: &TereSettings) -> String {
+    let mut info = format!("tere {}", env!("TERE_VERSION"));
+    let commit_date = env!("TERE_COMMIT_DATE");
+    if !commit_date.is_empty() {
+        let _ = write!(info, " from {}", commit_date);
+    }
+    let _ = write!(
+        info,
+        " - {} build for {}-{} - prefs file: ",
+        if cfg!(debug_assertions) { "debug" } else { "release" },
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+    );
+    match &settings.prefs_file {
+        Some(path) => info.push_str(&path.display().to_string()),
+        None => info.push_str("none"),
+    }
+    info.push_str(" - settings:");
+    for arg in settings.pref_args().values().flatten() {
+        let _ = write!(info, " {}", arg);
+    }
+    info
+}
+
+/// Truncate `text` from the left, so that the end of the string fits within `max_width` columns.
+fn truncate_left_to_width(text: &str, max_width: usize) -> String {
+    let mut total_width = 0;
+    let mut graphemes: Vec<&str> = UnicodeSegmentation::graphemes(text, true)
+        .rev()
+        .take_while(|g| {
+            total_width += g.width();
+            total_width <= max_width
+        })
+        .collect();
+    graphemes.reverse();
+    graphemes.concat()
 }
 
-/// Return the current terminal size as a pair of `(usize, usize)` instead of `(u16, 16)` as
-/// is done by crossterm.
-fn terminal_size_usize() -> CTResult<(usize, usize)> {
-    let (w, h): (u16, u16) = terminal::size()?;
-    Ok((w as usize, h as usize))
+/// A path component shown in the header, which can be clicked or selected to go to that folder
+#[derive(Debug, PartialEq, Eq)]
+struct Breadcrumb {
+    /// The byte range of the component in the header text
+    range: std::ops::Range<usize>,
+    path: PathBuf,
 }
 
-// Dimensions (width, height) of main window
-pub fn main_window_size() -> CTResult<(usize, usize)> {
-    let (w, h) = terminal_size_usize()?;
-    Ok((
-        w as usize,
-        (h as usize).saturating_sub(HEADER_SIZE + INFO_WIN_SIZE + FOOTER_SIZE),
-    ))
+/// Fit the header text into `max_width` columns, and find the breadcrumb of each path component in
+/// it. The header is `path` formatted with `format_header`, so its components correspond to the
+/// ancestors of `path`, counting from the right. If the header is too wide, folder names in the
+/// middle are replaced by '…', and if that's not enough, the beginning is cut off.
+fn layout_header(header: &str, path: &Path, max_width: usize) -> (String, Vec<Breadcrumb>) {
+    let mut pieces: Vec<&str> = header.split(std::path::is_separator).collect();
+    let ends_with_separator = pieces.len() > 1 && pieces.last() == Some(&"");
+    if ends_with_separator {
+        // the header is only the root, like '/' or 'C:\'
+        pieces.pop();
+    }
+    let ancestors: Vec<&Path> = path.ancestors().collect();
+    let piece = |i: usize| {
+        let ancestor = ancestors.get(pieces.len() - 1 - i).map(|p| p.to_path_buf());
+        (pieces[i], ancestor)
+    };
+
+    let join = |shown: &[(&str, Option<PathBuf>)]| {
+        let mut text = String::new();
+        let mut crumbs = vec![];
+        for (i, (name, path)) in shown.iter().enumerate() {
+            if i > 0 {
+                text.push(std::path::MAIN_SEPARATOR);
+            }
+            let start = text.len();
+            text.push_str(name);
+            // The root folder on unix has an empty name, use the separator after it instead
+            let end = if name.is_empty() { start + std::path::MAIN_SEPARATOR.len_utf8() } else { text.len() };
+            if let Some(path) = path {
+                crumbs.push(Breadcrumb { range: start..end, path: path.clone() });
+            }
+        }
+        if ends_with_separator {
+            text.push(std::path::MAIN_SEPARATOR);
+        }
+        (text, crumbs)
+    };
+
+    let all: Vec<_> = (0..pieces.len()).map(piece).collect();
+    let (mut text, mut crumbs) = join(&all);
+
+    // Keep the first component and as many components from the end as possible. The '…' goes to
+    // the deepest hidden folder.
+    if text.width() > max_width && pieces.len() > 2 {
+        for n_kept in (1..=pieces.len() - 2).rev() {
+            let first_kept = pieces.len() - n_kept;
+            let shown: Vec<_> = std::iter::once(piece(0))
+                .chain(std::iter::once(("…", piece(first_kept - 1).1)))
+                .chain((first_kept..pieces.len()).map(piece))
+                .collect();
+            (text, crumbs) = join(&shown);
+            if text.width() <= max_width {
+                break;
+            }
+        }
+    }
+
+    if text.width() > max_width {
+        let truncated = truncate_left_to_width(&text, max_width);
+        let cut = text.len() - truncated.len();
+        crumbs = crumbs
+            .into_iter()
+            .filter(|c| c.range.end > cut)
+            .map(|c| Breadcrumb { range: c.range.start.max(cut) - cut..c.range.end - cut, path: c.path })
+            .collect();
+        text = truncated;
+    }
+
+    (text, crumbs)
 }
 
 impl<'a> TereTui<'a> {
-    pub fn init(app_state: TereAppState, window: &'a mut Stderr) -> Result<Self, TereError> {
-        let mut ret = Self { window, app_state };
+    pub fn init(
+        app_state: TereAppState,
+        window: Box<dyn Write + 'a>,
+        events: Box<dyn EventSource>,
+    ) -> Result<Self, TereError> {
+        let info_msg_clear_time = app_state
+            .settings()
+            .info_timeout
+            .map(|t| Instant::now() + Duration::from_secs(t));
+        // See https://no-color.org
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let mut ret = Self {
+            window,
+            app_state,
+            events,
+            info_msg_clear_time,
+            drawn_rows: vec![],
+            number_input: String::new(),
+            info_msg_item: None,
+            continuous_search_active: false,
+            total_size_receiver: None,
+            recursive_total_size: None,
+            use_color: !no_color,
+            clipboard: None,
+            inline_top: Arc::new(AtomicU16::new(0)),
+            pending_event: None,
+        };
 
         if ret.app_state.settings().mouse_enabled {
             execute!(ret.window, EnableMouseCapture)?;
@@ -75,15 +389,127 @@ impl<'a> TereTui<'a> {
 
         ret.update_main_window_dimensions()?;
         ret.update_header()?;
+        ret.info_msg_item = ret.app_state.path_of_item_under_cursor();
         ret.redraw_all_windows()?;
         Ok(ret)
     }
 
+    /// Return the current terminal size as a pair of `(usize, usize)` instead of `(u16, 16)` as
+    /// is done by crossterm.
+    fn terminal_size_usize(&self) -> CTResult<(usize, usize)> {
+        let (w, h): (u16, u16) = self.events.size()?;
+        // with --inline, only part of the terminal is used
+        let h = self.app_state.settings().inline_rows.map_or(h, |rows| h.min(rows));
+        Ok((w as usize, h as usize))
+    }
+
+    /// Set the terminal row that the UI starts from with --inline. The UI updates it when it moves
+    /// the region.
+    pub fn set_inline_top(&mut self, inline_top: Arc<AtomicU16>) {
+        self.inline_top = inline_top;
+    }
+
+    /// With --inline, fit the region that the UI is drawn in to the current size of the terminal.
+    /// The region stays where it is, unless it doesn't fit in the terminal any more.
+    fn update_inline_region(&mut self) -> CTResult<()> {
+        if let Some(rows) = self.app_state.settings().inline_rows {
+            let (_, height) = self.events.size()?;
+            let rows = rows.clamp(1, height.max(1));
+            let top = self.inline_top.load(Ordering::Relaxed).min(height.saturating_sub(rows));
+            self.inline_top.store(top, Ordering::Relaxed);
+            // the terminal may have reset the scrolling region when it was resized
+            set_inline_region(&mut self.window, top, rows)?;
+        }
+        Ok(())
+    }
+
+    /// Update the UI for a new terminal size. The windows still need to be redrawn afterwards.
+    fn on_resize(&mut self) -> CTResult<()> {
+        self.update_inline_region()?;
+        self.update_main_window_dimensions()
+    }
+
+    // Dimensions (width, height) of main window
+    fn main_window_size(&self) -> CTResult<(usize, usize)> {
+        let (w, h) = self.terminal_size_usize()?;
+        let info_win_size = if self.app_state.settings().layout == Layout::Compact {
+            0
+        } else {
+            INFO_WIN_SIZE
+        };
+        Ok((
+            w,
+            h.saturating_sub(HEADER_SIZE + info_win_size + FOOTER_SIZE),
+        ))
+    }
+
+    /// The first row of the main window
+    fn main_window_top(&self) -> usize {
+        match self.app_state.settings().layout {
+            Layout::Classic | Layout::Compact => HEADER_SIZE,
+            Layout::TopSearch => HEADER_SIZE + FOOTER_SIZE + INFO_WIN_SIZE,
+        }
+    }
+
+    /// The row that the footer is drawn on
+    fn footer_win_row(&self) -> CTResult<usize> {
+        let (_, h) = self.terminal_size_usize()?;
+        Ok(match self.app_state.settings().layout {
+            Layout::Classic | Layout::Compact => h.saturating_sub(FOOTER_SIZE),
+            Layout::TopSearch => HEADER_SIZE,
+        })
+    }
+
+    /// The row that the info line is drawn on, or None if the layout has no info line
+    fn info_win_row(&self) -> CTResult<Option<usize>> {
+        let (_, h) = self.terminal_size_usize()?;
+        Ok(match self.app_state.settings().layout {
+            Layout::Classic => Some(h.saturating_sub(FOOTER_SIZE + INFO_WIN_SIZE)),
+            Layout::Compact => None,
+            Layout::TopSearch => Some(HEADER_SIZE + FOOTER_SIZE),
+        })
+    }
+
+    /// The path to print on exit when `path` is selected, according to the settings
+    pub fn path_to_print(&self, path: &Path) -> PathBuf {
+        self.app_state.path_to_print(path)
+    }
+
     /// Get the current (logical) path to be printed on exit.
     pub fn current_path(&self) -> PathBuf {
+        if is_drive_list(&self.app_state.current_path) {
+            // The drive list can't be cd'd to, so use the drive that we came from instead
+            return std::env::current_dir().unwrap_or_default();
+        }
+        // An archive can't be cd'd to either, so use the folder that contains it
+        if let Some((archive, _)) = split_archive_path(&self.app_state.current_path) {
+            return archive.parent().unwrap_or(archive).to_path_buf();
+        }
         self.app_state.current_path.clone()
     }
 
+    /// The number of columns available for the listing in the main window. This is the full width
+    /// of the window, unless the preview pane is shown, minus the padding on both sides.
+    fn listing_width(&self) -> usize {
+        self.app_state.listing_width()
+    }
+
+    /// The terminal column where the listing starts, i.e. the width of the padding on its left
+    fn listing_x(&self) -> usize {
+        self.app_state.settings().padding
+    }
+
+    /// The terminal column after the padding on the right of the listing, where the preview starts
+    fn listing_end(&self) -> usize {
+        self.listing_x() + self.listing_width() + self.app_state.settings().padding
+    }
+
+    /// A command for setting the colors, see `SetColors`. If `highlight` is true and colors are
+    /// disabled, the text is shown in reverse video.
+    fn colors(&self, fg: style::Color, bg: style::Color, highlight: bool) -> SetColors {
+        SetColors { fg, bg, highlight, use_color: self.use_color }
+    }
+
     /// Queue up a command to clear a given row (starting from 0). Must be executed/flushed
     /// separately.
     fn queue_clear_row(&mut self, row: usize) -> CTResult<()> {
@@ -95,28 +521,200 @@ impl<'a> TereTui<'a> {
     }
 
     fn redraw_header(&mut self) -> CTResult<()> {
-        //TODO: what to do if window is narrower than path?
-        // add "..." to beginning? or collapse folder names? make configurable?
-        // at least, truncate towards the left instead of to the right
+        self.draw_header(None)
+    }
+
+    /// The header text fitted to the window, and the breadcrumbs in it
+    fn header_layout(&self) -> CTResult<(String, Vec<Breadcrumb>)> {
+        // The header message is already shortened according to the header format, but it can still
+        // be too wide for the window.
+        let (max_x, _) = self.main_window_size()?;
+        Ok(layout_header(&self.app_state.header_msg, &self.app_state.current_path, max_x))
+    }
 
-        let (max_x, _) = main_window_size()?;
+    /// Draw the header, with the breadcrumb at index `selected` highlighted, if any.
+    fn draw_header(&mut self, selected: Option<usize>) -> CTResult<()> {
+        let (header_msg, crumbs) = self.header_layout()?;
+        let selected = selected
+            .and_then(|i| crumbs.get(i))
+            .map_or(0..0, |c| c.range.clone());
 
-        let header_graphemes: Vec<String> =
-            UnicodeSegmentation::graphemes(self.app_state.header_msg.as_str(), true)
-                .map(String::from)
-                .collect();
-        let n_skip = header_graphemes.len().saturating_sub(max_x as usize);
-        let header_msg = header_graphemes[n_skip..].join("");
+        // The parent folders are everything before the last breadcrumb. If the beginning of the
+        // header is cut off, it's cut from them first.
+        let parent_end = crumbs.last().map_or(0, |c| c.range.start);
+        let parent_style = match self.app_state.settings().header_parent_style {
+            // without colors, fall back to the default style
+            HeaderParentStyle::Color(_) if !self.use_color => HeaderParentStyle::Dim,
+            style => style,
+        };
+
+        let mut bounds = vec![0, parent_end, selected.start, selected.end, header_msg.len()];
+        bounds.sort_unstable();
+        bounds.dedup();
 
-        // must use variable here b/c can't borrow 'self' twice in execute!() below
-        let mut win = self.window;
         self.queue_clear_row(0)?;
-        execute!(
-            win,
-            cursor::MoveTo(0, 0),
-            style::SetAttribute(Attribute::Reset),
-            style::Print(&header_msg.bold().underlined()),
-        )
+        queue!(self.window, cursor::MoveTo(0, 0), style::SetAttribute(Attribute::Reset))?;
+        for piece in bounds.windows(2) {
+            let (start, end) = (piece[0], piece[1]);
+            let text = header_msg[start..end].stylize();
+            let text = match parent_style {
+                HeaderParentStyle::Dim if end <= parent_end => text.dim(),
+                HeaderParentStyle::Color(color) if end <= parent_end => text.with(color),
+                _ => text.bold().underlined(),
+            };
+            let text = if selected.start <= start && end <= selected.end { text.reverse() } else { text };
+            queue!(self.window, style::Print(text))?;
+        }
+        self.window.flush()
+    }
+
+    /// Change to the folder of a breadcrumb in the header
+    fn change_dir_to_breadcrumb(&mut self, crumb: &Breadcrumb) -> CTResult<()> {
+        match crumb.path.to_str() {
+            Some(path) => self.change_dir(path).map(|_| ()),
+            None => self.error_message(&format!("can't open '{}'", crumb.path.display())),
+        }
+    }
+
+    /// Select a folder in the header with the arrow keys, and go there.
+    fn breadcrumb_loop(&mut self) -> CTResult<()> {
+        let mut selected = match self.header_layout()?.1.len() {
+            // we're at the root or the header is empty, there's nowhere to go
+            0 | 1 => return Ok(()),
+            n => n - 2, // start from the parent folder
+        };
+        self.info_message("Use ←/→ or h/l to select a folder. Press Enter to go there, or Esc to cancel.")?;
+
+        loop {
+            self.draw_header(Some(selected))?;
+            let event = match self.events.read()? {
+                Some(event) => event,
+                None => break,
+            };
+            match event {
+                Event::Key(k) => match k.code {
+                    KeyCode::Left | KeyCode::Char('h') => selected = selected.saturating_sub(1),
+                    KeyCode::Right | KeyCode::Char('l') => {
+                        let n = self.header_layout()?.1.len();
+                        selected = (selected + 1).min(n.saturating_sub(1));
+                    }
+                    KeyCode::Enter => {
+                        self.info_message("")?;
+                        if let Some(crumb) = self.header_layout()?.1.get(selected) {
+                            return self.change_dir_to_breadcrumb(crumb);
+                        }
+                        return self.redraw_header();
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => break,
+                    KeyCode::Char('c') if k.modifiers == KeyModifiers::CONTROL => break,
+                    _ => {}
+                },
+
+                Event::Resize(_, _) => {
+                    self.on_resize()?;
+                    self.redraw_all_windows()?;
+                    let n = self.header_layout()?.1.len();
+                    selected = selected.min(n.saturating_sub(1));
+                }
+
+                _ => {}
+            }
+        }
+
+        self.info_message("")?;
+        self.redraw_header()
+    }
+
+    /// Ask for confirmation in the footer before exiting. Returns true if the user pressed 'y' or
+    /// Enter, and false if any other key was pressed.
+    fn confirm_quit(&mut self) -> CTResult<bool> {
+        loop {
+            let footer_win_row = self.footer_win_row()?;
+            self.queue_clear_row(footer_win_row)?;
+            execute!(
+                self.window,
+                cursor::MoveTo(0, u16::try_from(footer_win_row).unwrap_or(u16::MAX)),
+                style::SetAttribute(Attribute::Reset),
+                style::Print("Quit? y/n".bold()),
+            )?;
+
+            match self.events.read()? {
+                // no more events, e.g. all keys given with --keys have been handled
+                None => return Ok(true),
+                Some(Event::Key(k)) => {
+                    let confirmed = matches!(k.code, KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter);
+                    if !confirmed {
+                        self.redraw_footer()?;
+                    }
+                    return Ok(confirmed);
+                }
+                Some(Event::Resize(_, _)) => {
+                    self.on_resize()?;
+                    self.redraw_all_windows()?;
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    /// Ask for a line of text in the footer, after `prompt`. Returns None if the input was
+    /// cancelled with Esc or Ctrl-c.
+    fn prompt_text(&mut self, prompt: &str) -> CTResult<Option<String>> {
+        let mut text = String::new();
+        let result = loop {
+            let footer_win_row = self.footer_win_row()?;
+            self.queue_clear_row(footer_win_row)?;
+            execute!(
+                self.window,
+                cursor::MoveTo(0, u16::try_from(footer_win_row).unwrap_or(u16::MAX)),
+                style::SetAttribute(Attribute::Reset),
+                style::Print(format!("{}: ", prompt).bold()),
+                style::Print(text.as_str().bold()),
+            )?;
+
+            match self.events.read()? {
+                // no more events, e.g. all keys given with --keys have been handled
+                None => break None,
+                Some(Event::Key(k)) => match k.code {
+                    KeyCode::Enter => break Some(text),
+                    KeyCode::Esc => break None,
+                    KeyCode::Char('c') if k.modifiers == KeyModifiers::CONTROL => break None,
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    KeyCode::Char(c) if !k.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                        text.push(c)
+                    }
+                    _ => {}
+                },
+                Some(Event::Resize(_, _)) => {
+                    self.on_resize()?;
+                    self.redraw_all_windows()?;
+                }
+                Some(_) => {}
+            }
+        };
+        self.redraw_footer()?;
+        Ok(result)
+    }
+
+    /// Ask for a name and create a folder with it in the current folder, if it's allowed with
+    /// --allow-mkdir
+    fn create_folder(&mut self) -> CTResult<()> {
+        if !self.app_state.settings().allow_mkdir {
+            return self.error_message("Creating folders is not allowed (see --allow-mkdir)");
+        }
+        let name = match self.prompt_text("new folder")? {
+            Some(name) if !name.is_empty() => name,
+            _ => return Ok(()),
+        };
+        match self.app_state.create_folder(&name) {
+            Ok(()) => {
+                self.redraw_main_window()?;
+                self.info_message(&format!("Created folder '{}'", name))
+            }
+            Err(e) => self.error_message(&e.to_string()),
+        }
     }
 
     fn update_header(&mut self) -> CTResult<()> {
@@ -125,56 +723,333 @@ impl<'a> TereTui<'a> {
         self.redraw_header()
     }
 
+    /// The text of the info line: either the info message, or the details of the item under the
+    /// cursor if there's no message. The second value is true in the latter case.
+    fn info_text(&self) -> (String, bool) {
+        // Broken symlinks are explained even if the details are not shown otherwise
+        let show_details = self.app_state.info_msg.is_empty()
+            && (self.app_state.settings().show_details
+                || self.app_state.get_item_under_cursor().is_some_and(|item| item.is_broken_symlink()));
+        if show_details {
+            let details = self
+                .app_state
+                .get_item_under_cursor()
+                .map(|item| entry_details(item, self.app_state.settings()))
+                .unwrap_or_default();
+            (details, true)
+        } else {
+            (self.app_state.info_msg.clone(), false)
+        }
+    }
+
     fn redraw_info_window(&mut self) -> CTResult<()> {
-        let (w, h) = terminal_size_usize()?;
-        let info_win_row = h - FOOTER_SIZE - INFO_WIN_SIZE;
+        let info_win_row = match self.info_win_row()? {
+            Some(row) => row,
+            // the messages are shown in the footer instead
+            None => return self.redraw_footer(),
+        };
+        let (w, _) = self.terminal_size_usize()?;
 
         self.queue_clear_row(info_win_row)?;
-        let mut win = self.window;
-        let msg = UnicodeSegmentation::graphemes(self.app_state.info_msg.as_str(), true)
-            .take(w)
-            .collect::<Vec<&str>>()
-            .as_slice()
-            .concat();
+
+        let (msg, is_details) = self.info_text();
+        let msg = graphemes_fitting_width(&msg, w)
+            .iter()
+            .map(|(_, g)| *g)
+            .collect::<String>();
 
         execute!(
-            win,
+            self.window,
             cursor::MoveTo(0, u16::try_from(info_win_row).unwrap_or(u16::MAX)),
             style::SetAttribute(Attribute::Reset),
-            style::Print(msg.bold()),
+            style::Print(if is_details { msg.stylize() } else { msg.bold() }),
         )
     }
 
+    /// If the cursor has moved to another item since the info message was set, clear the message
+    /// and show the details of the new item instead. If the details are not enabled, this is only
+    /// done for broken symlinks, to show where they point to.
+    fn update_details(&mut self) -> CTResult<()> {
+        let item = self.app_state.path_of_item_under_cursor();
+        if item != self.info_msg_item {
+            self.info_msg_item = item;
+            if self.app_state.settings().show_details
+                || self.app_state.get_item_under_cursor().is_some_and(|item| item.is_broken_symlink())
+            {
+                self.app_state.info_msg.clear();
+                self.info_msg_clear_time = None;
+            }
+            // Without an info line, the footer shows the details, and it's being redrawn anyway
+            if self.info_win_row()?.is_some() {
+                self.redraw_info_window()?;
+            }
+        }
+        Ok(())
+    }
+
     /// Set/update the current info message and redraw the info window
     fn info_message(&mut self, msg: &str) -> CTResult<()> {
-        //TODO: add thread with timeout that will clear the info message after x seconds?
-        self.app_state.info_msg = msg.to_string();
-        self.redraw_info_window()
+        self.set_info_message(msg, self.app_state.settings().info_timeout)
+    }
+
+    /// The path of the item under the cursor to select or open. Files inside archives are
+    /// extracted first, and if that fails, the error is shown and None is returned.
+    fn real_path_of_item_under_cursor(&mut self) -> CTResult<Option<PathBuf>> {
+        match self.app_state.real_path_of_item_under_cursor() {
+            Ok(path) => Ok(path),
+            Err(e) => {
+                self.error_message(&e.to_string())?;
+                Ok(None)
+            }
+        }
     }
 
     fn error_message(&mut self, msg: &str) -> CTResult<()> {
         //TODO: red color (also: make it configurable)
         let error_msg = format!("error: {}", &msg);
-        self.info_message(&error_msg)
+        self.set_info_message(&error_msg, self.app_state.settings().error_timeout)
+    }
+
+    /// Set the info message, which is cleared after `timeout` seconds, or never if it is None.
+    fn set_info_message(&mut self, msg: &str, timeout: Option<u64>) -> CTResult<()> {
+        self.app_state.info_msg = msg.to_string();
+        self.info_msg_item = self.app_state.path_of_item_under_cursor();
+        self.info_msg_clear_time = if msg.is_empty() {
+            None
+        } else {
+            timeout.map(|t| Instant::now() + Duration::from_secs(t))
+        };
+        self.redraw_info_window()
+    }
+
+    /// Wait for the next terminal event. If the info message should be cleared before that
+    /// happens, clear it while waiting. If the total size of a folder is being computed, show it
+    /// when it's ready. If the cursor stays on a folder for `PREFETCH_DELAY`, start prefetching
+    /// it. Returns None if there are no more events.
+    fn wait_for_event(&mut self) -> CTResult<Option<Event>> {
+        if let Some(event) = self.pending_event.take() {
+            return Ok(Some(event));
+        }
+        let prefetch_time = Instant::now() + PREFETCH_DELAY;
+        loop {
+            self.check_total_size()?;
+
+            let clear_timeout = self
+                .info_msg_clear_time
+                .map(|t| t.saturating_duration_since(Instant::now()));
+            let total_size_timeout = self.total_size_receiver.as_ref().map(|_| SPINNER_INTERVAL);
+            let prefetch_timeout = self
+                .app_state
+                .needs_prefetch()
+                .then(|| prefetch_time.saturating_duration_since(Instant::now()));
+            let timeout = match [clear_timeout, total_size_timeout, prefetch_timeout].iter().flatten().min() {
+                Some(timeout) => *timeout,
+                None => break,
+            };
+
+            if self.events.poll(timeout)? {
+                break;
+            }
+            if clear_timeout.is_some_and(|t| t <= timeout) {
+                self.info_message("")?;
+            }
+            if prefetch_timeout.is_some_and(|t| t <= timeout) {
+                self.app_state.prefetch_item_under_cursor();
+            }
+        }
+        self.events.read()
+    }
+
+    /// Skip the resize events that follow each other within `RESIZE_DEBOUNCE`, so that only the
+    /// last size of a burst of resizes has to be drawn. If some other event arrives, it's kept for
+    /// `wait_for_event`.
+    fn skip_resize_burst(&mut self) -> CTResult<()> {
+        while self.pending_event.is_none() && self.events.poll(RESIZE_DEBOUNCE)? {
+            match self.events.read()? {
+                Some(Event::Resize(_, _)) => {}
+                event => self.pending_event = event,
+            }
+        }
+        Ok(())
+    }
+
+    /// The action that `k` is mapped to. The mappings for the grid layout and number mode take
+    /// precedence if they apply, then the ones for searching or not searching, and finally the
+    /// ones that apply in any context.
+    fn action_for_key(&self, k: &KeyEvent, searching: bool) -> Option<Action> {
+        let settings = self.app_state.settings();
+        let mut contexts = Vec::with_capacity(4);
+        if settings.number_mode {
+            contexts.push(ActionContext::NumberMode);
+        }
+        if self.app_state.grid_columns() > 1 {
+            contexts.push(ActionContext::Grid);
+        }
+        contexts.push(if searching { ActionContext::Searching } else { ActionContext::NotSearching });
+        contexts.push(ActionContext::None);
+        contexts.into_iter().find_map(|ctx| settings.keymap.get(&(*k, ctx)).cloned())
+    }
+
+    /// True if `k` would be added to the search while searching, i.e. it's a character that's not
+    /// mapped to any action
+    fn is_search_char_key(&self, k: &KeyEvent) -> bool {
+        matches!(k.code, KeyCode::Char(_))
+            && !(k.code == KeyCode::Char('c') && k.modifiers == KeyModifiers::CONTROL)
+            && self.action_for_key(k, true).is_none()
+    }
+
+    /// In large folders, collect the characters that are typed within `search_debounce` of each
+    /// other, so that the search is updated only once for a burst of typing. Returns the
+    /// characters to add to the search, starting with `c`. If some other event arrives, it's kept
+    /// for `wait_for_event`.
+    fn debounce_search_chars(&mut self, c: char) -> CTResult<String> {
+        let mut chars = c.to_string();
+        let debounce = match self.app_state.settings().search_debounce {
+            Some(debounce) if self.app_state.num_total_items() >= SEARCH_DEBOUNCE_MIN_ITEMS => debounce,
+            _ => return Ok(chars),
+        };
+        while self.pending_event.is_none() {
+            self.draw_pending_search_chars(&chars)?;
+            if !self.events.poll(debounce)? {
+                break;
+            }
+            match self.events.read()? {
+                Some(Event::Key(k)) if self.is_search_char_key(&k) => {
+                    if let KeyCode::Char(c) = k.code {
+                        chars.push(c);
+                    }
+                }
+                event => self.pending_event = event,
+            }
+        }
+        Ok(chars)
+    }
+
+    /// Show the characters that are waiting to be added to the search after the query in the
+    /// footer, followed by '…'
+    fn draw_pending_search_chars(&mut self, chars: &str) -> CTResult<()> {
+        self.redraw_footer()?;
+        let label = if self.app_state.settings().filter_search { "filter" } else { "search" };
+        let query_width = format!("{}: {}", label, self.app_state.search_string()).width();
+        let row = self.footer_win_row()?;
+        execute!(
+            self.window,
+            cursor::MoveTo(
+                u16::try_from(query_width).unwrap_or(u16::MAX),
+                u16::try_from(row).unwrap_or(u16::MAX),
+            ),
+            style::SetAttribute(Attribute::Reset),
+            style::Print(format!("{}…", chars).bold()),
+        )
+    }
+
+    /// Start computing the total size of the current folder, including subfolders, in a
+    /// background thread. The result is shown in the footer when it's ready.
+    fn compute_total_size(&mut self) -> CTResult<()> {
+        let path = self.app_state.current_path.clone();
+        let (sender, receiver) = mpsc::channel();
+        let thread_path = path.clone();
+        let max_depth = self.app_state.settings().max_depth;
+        let exclude = self.app_state.listing_options().exclude;
+        std::thread::spawn(move || {
+            // if the receiver is gone, the result is not needed any more
+            sender.send(dir_size_recursive(&thread_path, max_depth, &exclude)).ok();
+        });
+        self.total_size_receiver = Some((path, receiver));
+        self.redraw_footer()
+    }
+
+    /// If the total size computation started by `compute_total_size` has finished, show the
+    /// result.
+    fn check_total_size(&mut self) -> CTResult<()> {
+        let result = match &self.total_size_receiver {
+            Some((path, receiver)) => match receiver.try_recv() {
+                Ok(size) => Some((path.clone(), size)),
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => None,
+            },
+            None => return Ok(()),
+        };
+        self.total_size_receiver = None;
+        self.recursive_total_size = result;
+        self.redraw_footer()
+    }
+
+    /// The total size of the current folder to show in the footer, if any
+    fn total_size_text(&self) -> Option<String> {
+        let current_path = &self.app_state.current_path;
+        if self.total_size_receiver.as_ref().is_some_and(|(path, _)| path == current_path) {
+            return Some("computing size...".to_string());
+        }
+        let size_format = self.app_state.settings().size_format;
+        match &self.recursive_total_size {
+            Some((path, (size, false))) if path == current_path => {
+                Some(format!("{} in total", format_size(*size, size_format)))
+            }
+            Some((path, (size, true))) if path == current_path => Some(format!(
+                "at least {} in total (max depth {})",
+                format_size(*size, size_format),
+                self.app_state.settings().max_depth,
+            )),
+            _ => self.app_state.total_size().map(|size| format_size(size, size_format)),
+        }
     }
 
     fn redraw_footer(&mut self) -> CTResult<()> {
-        let (w, h) = terminal_size_usize()?;
-        let footer_win_row = h - FOOTER_SIZE;
+        // The footer is redrawn every time the cursor moves, which is when the details change
+        self.update_details()?;
+
+        let (w, _) = self.terminal_size_usize()?;
+        let footer_win_row = self.footer_win_row()?;
         self.queue_clear_row(footer_win_row)?;
 
-        let mut win = self.window;
         let mut extra_msg = String::new();
 
         let _ = write!(extra_msg, "{} - ", self.app_state.settings().gap_search_mode);
         let _ = write!(extra_msg, "{} - ", self.app_state.settings().case_sensitive);
         let _ = write!(extra_msg, "sort:{} - ", self.app_state.settings().sort_mode);
+        if self.app_state.settings().search_scope != SearchScope::Name {
+            let _ = write!(extra_msg, "in:{} - ", self.app_state.settings().search_scope);
+        }
+        if self.app_state.settings().listing_mode != ListingMode::All {
+            let _ = write!(extra_msg, "{} - ", self.app_state.settings().listing_mode);
+        }
+        if self.app_state.depth_limit_reached() {
+            let depth = self.app_state.listing_options().recursive_depth.unwrap_or_default();
+            let _ = write!(extra_msg, "recursive (depth {} reached) - ", depth);
+        } else if self.app_state.settings().recursive_search {
+            let _ = write!(extra_msg, "recursive - ");
+        }
+        if self.app_state.settings().gitignore {
+            let _ = write!(extra_msg, "gitignore - ");
+        }
+        if let Some(file_filter) = &self.app_state.settings().file_filter {
+            let _ = write!(extra_msg, "files:{} - ", file_filter.glob());
+        }
+        if self.app_state.num_excluded() > 0 {
+            let _ = write!(extra_msg, "{} excluded - ", self.app_state.num_excluded());
+        }
+        if self.app_state.settings().modified_filter {
+            let _ = write!(
+                extra_msg,
+                "modified:{} - ",
+                format_duration(self.app_state.settings().modified_window)
+            );
+        }
 
         let cursor_idx = self
             .app_state
             .cursor_pos_to_visible_item_index(self.app_state.cursor_pos);
 
-        if self.app_state.is_searching() {
+        let footer_format = if self.app_state.is_searching() {
+            &self.app_state.settings().search_footer_format
+        } else {
+            &self.app_state.settings().footer_format
+        };
+        if let Some(template) = footer_format {
+            let size = self.total_size_text();
+            extra_msg.push_str(&self.app_state.format_footer_counts(template, size.as_deref()));
+        } else if self.app_state.is_searching() {
             let index_in_matches = self
                 .app_state
                 .visible_match_indices()
@@ -190,83 +1065,131 @@ impl<'a> TereTui<'a> {
                 self.app_state.num_total_items()
             );
         } else {
-            //TODO: show no. of files/folders separately? like 'n folders, n files'
+            let n_folders = self.app_state.num_folders();
+            let n_files = self.app_state.num_files();
+            let _ = write!(
+                extra_msg,
+                "{} folder{}, {} file{}",
+                n_folders,
+                if n_folders == 1 { "" } else { "s" },
+                n_files,
+                if n_files == 1 { "" } else { "s" },
+            );
+            if let Some(size) = self.total_size_text() {
+                let _ = write!(extra_msg, ", {}", size);
+            }
             let _ = write!(
                 extra_msg,
-                "{} / {}",
+                " - {} / {}",
                 cursor_idx + 1,
                 self.app_state.num_visible_items()
             );
         }
 
+        let (info_text, _) = self.info_text();
+        let extra_msg = if self.info_win_row()?.is_none() && !info_text.is_empty() {
+            // There's no info line, so the messages are shown here instead of the item counts, in
+            // the space left next to the search query
+            let query_width = "search: ".width() + self.app_state.search_string().width();
+            graphemes_fitting_width(&info_text, w.saturating_sub(query_width + 1))
+                .iter()
+                .map(|(_, g)| *g)
+                .collect::<String>()
+        } else {
+            // If the window is too narrow, cut the extra message from the left, so that the cursor
+            // position is always visible.
+            truncate_left_to_width(&extra_msg, w)
+        };
+
         // draw extra message first, so that it gets overwritten by the more important search query
         // if there is not enough space
         queue!(
-            win,
+            self.window,
             cursor::MoveTo(
-                u16::try_from(w.saturating_sub(extra_msg.len())).unwrap_or(u16::MAX),
+                u16::try_from(w.saturating_sub(extra_msg.width())).unwrap_or(u16::MAX),
                 u16::try_from(footer_win_row).unwrap_or(u16::MAX),
             ),
             style::SetAttribute(Attribute::Reset),
-            style::Print(
-                extra_msg
-                    .chars()
-                    .take(w as usize)
-                    .collect::<String>()
-                    .bold()
-            ),
+            style::Print(extra_msg.bold()),
         )?;
 
+        // Show the query in the warning color if nothing matches it
+        let query_color = match self.app_state.settings().warning_color {
+            Some(color) if self.app_state.is_searching()
+                && self.app_state.num_matching_items() == 0 => color,
+            _ => style::Color::Reset,
+        };
+
+        let search_label = if self.app_state.settings().filter_search {
+            "filter"
+        } else {
+            "search"
+        };
+        let query_colors = self.colors(query_color, style::Color::Reset, false);
+        let query = self.app_state.search_string().clone();
+
         execute!(
-            win,
+            self.window,
             cursor::MoveTo(0, u16::try_from(footer_win_row).unwrap_or(u16::MAX)),
             style::SetAttribute(Attribute::Reset),
             //TODO: prevent line wrap here
-            style::Print(
-                &format!(
-                    "{}: {}",
-                    if self.app_state.settings().filter_search {
-                        "filter"
-                    } else {
-                        "search"
-                    },
-                    self.app_state.search_string()
-                )
-                .bold()
-            ),
+            style::Print(format!("{}: ", search_label).bold()),
+            query_colors,
+            style::Print(query.bold()),
+            style::ResetColor,
         )
     }
 
+    /// Draw the item on the given row of the main window. In the grid layout, `row` is the index
+    /// of a cell in the main window, counting the cells row by row. If the separator between
+    /// folders and files is shown, the items below it are drawn one row lower.
     fn draw_main_window_row(&mut self, row: usize, highlight: bool) -> CTResult<()> {
-        let row_abs = row + HEADER_SIZE;
-        let width: usize = main_window_size()?.0;
+        let columns = self.app_state.grid_columns();
+        let screen_row = self.app_state.screen_row_of_cursor_pos(row);
+        let (_, h) = self.main_window_size()?;
+        if screen_row >= h * columns {
+            // pushed below the window by the separator
+            return Ok(());
+        }
+        let row_abs = screen_row / columns + self.main_window_top();
+        let column = screen_row % columns;
+        let is_last_column = column + 1 == columns;
+        let column_offset = column * self.app_state.grid_column_width();
+        let column_x = self.listing_x() + column_offset;
+        // the last column extends to the end of the listing
+        let width: usize = if is_last_column {
+            self.listing_width().saturating_sub(column_offset)
+        } else {
+            self.app_state.grid_column_width()
+        };
 
-        //TODO: make customizable...
+        let cursor_color = self.app_state.settings().cursor_color;
         let highlight_fg = style::Color::Black;
-        let highlight_bg = style::Color::Grey;
+        let highlight_bg = cursor_color.unwrap_or(style::Color::Reset);
         let matching_letter_bg = style::Color::DarkGrey;
+        let matching_letter_fg = self.app_state.settings().match_color.unwrap_or(style::Color::Reset);
         let symlink_color = style::Color::Cyan;
+        let broken_symlink_color = style::Color::Red;
+        let extension_color = style::Color::DarkYellow;
 
-        let item = self.app_state.get_item_at_cursor_pos(row);
+        // With the arrow and bar styles, the cursor is only a marker in front of the item, and the
+        // rest of the row is drawn like the other rows.
+        let cursor_marker = self.app_state.settings().cursor_style.marker();
+        let reverse = highlight && cursor_marker.is_none();
 
-        let text_attr = if item.map(|itm| itm.is_dir()).unwrap_or(false) {
-            Attribute::Bold
-        } else {
-            Attribute::Dim
-        };
+        // In reverse video mode, the cursor row is drawn like in no-color mode, i.e. with
+        // `Attribute::Reverse` instead of explicit colors. The attributes are reset at the end of
+        // the row, so this doesn't affect the other rows.
+        let reverse_video = reverse && cursor_color.is_none();
+        let use_color = self.use_color && !reverse_video;
+        let colors = |fg, bg, highlight| SetColors { fg, bg, highlight, use_color };
 
-        queue!(
-            self.window,
-            cursor::MoveTo(0, u16::try_from(row_abs).unwrap_or(u16::MAX)),
-            style::SetAttribute(Attribute::Reset),
-            style::ResetColor,
-            style::SetAttribute(text_attr),
-        )?;
+        let item = self.app_state.get_item_at_cursor_pos(row);
 
         let idx = self.app_state.cursor_pos_to_visible_item_index(row);
 
         // All *byte offsets* that should be underlined
-        let underline_locs = if self.app_state.is_searching()
+        let underline_locs: Vec<usize> = if self.app_state.is_searching()
             && self.app_state.visible_match_indices().contains(&idx)
         {
             self.app_state
@@ -279,29 +1202,191 @@ impl<'a> TereTui<'a> {
             vec![]
         };
 
+        // In number mode, items are prefixed with their row number, counting from 1
+        let number_prefix = if self.app_state.settings().number_mode && item.is_some() {
+            let (_, h) = self.main_window_size()?;
+            let max_digits = (h * columns).to_string().len();
+            format!("{:>w$} ", row + 1, w = max_digits)
+        } else {
+            String::new()
+        };
+
+        let icon_prefix = item.map(|itm| self.app_state.icon_prefix(itm)).unwrap_or_default();
+
+        // Only the part of the name that can fit in the row is needed. This is cached by the item,
+        // so that huge names are not processed on every redraw.
+        let fitted_name = item.map(|itm| itm.file_name_fitting_width(width));
+
+        // Skip drawing the row if it looks the same as last time
+        let contents = RowContents {
+            file_name: fitted_name.clone(),
+            is_dir: item.map(|itm| itm.is_dir()).unwrap_or(false),
+            symlink_target: item.and_then(|itm| itm.symlink_target.clone()),
+            is_broken_symlink: item.map(|itm| itm.is_broken_symlink()).unwrap_or(false),
+            underline_locs: underline_locs.clone(),
+            highlight,
+            width,
+            columns,
+            preview_enabled: self.app_state.settings().preview_enabled,
+            number_prefix: number_prefix.clone(),
+            icon_prefix: icon_prefix.clone(),
+            screen_row,
+        };
+        if let Some(Some(drawn)) = self.drawn_rows.get(row) {
+            if *drawn == contents {
+                return Ok(());
+            }
+        }
+
+        let text_attr = if item.map(|itm| itm.is_dir()).unwrap_or(false) {
+            Attribute::Bold
+        } else {
+            Attribute::Dim
+        };
+
+        let padding = self.listing_x();
+        if column == 0 && padding > 0 {
+            // Erase the padding, something else might have been drawn there. Erasing doesn't move
+            // the cursor.
+            queue!(
+                self.window,
+                cursor::MoveTo(0, u16::try_from(row_abs).unwrap_or(u16::MAX)),
+                style::SetAttribute(Attribute::Reset),
+                style::ResetColor,
+                style::Print(format!("\x1b[{}X", padding)),
+            )?;
+        }
+
+        queue!(
+            self.window,
+            cursor::MoveTo(
+                u16::try_from(column_x).unwrap_or(u16::MAX),
+                u16::try_from(row_abs).unwrap_or(u16::MAX),
+            ),
+            style::SetAttribute(Attribute::Reset),
+            style::ResetColor,
+            style::SetAttribute(text_attr),
+        )?;
+
+        let marker_width = cursor_marker.map(|m| m.width()).unwrap_or(0);
+        if let Some(marker) = cursor_marker {
+            if highlight {
+                queue!(
+                    self.window,
+                    colors(
+                        cursor_color.unwrap_or(style::Color::Reset),
+                        style::Color::Reset,
+                        false,
+                    ),
+                    style::Print(marker),
+                    style::ResetColor,
+                )?;
+            } else {
+                queue!(self.window, style::Print(" ".repeat(marker_width)))?;
+            }
+        }
+        let width = width.saturating_sub(marker_width);
+
+        if !number_prefix.is_empty() {
+            queue!(
+                self.window,
+                style::SetAttribute(Attribute::Reset),
+                colors(
+                    if reverse { highlight_fg } else { style::Color::DarkGrey },
+                    if reverse { highlight_bg } else { style::Color::Reset },
+                    reverse,
+                ),
+                style::Print(&number_prefix),
+                style::SetAttribute(text_attr),
+            )?;
+        }
+        let prefix_width = number_prefix.width();
+        let width = width.saturating_sub(prefix_width);
+
+        // The icon is drawn like the rest of the row, and the name is shortened to make room for it
+        let icon_prefix: String = graphemes_fitting_width(&icon_prefix, width)
+            .into_iter()
+            .map(|(_, g)| g)
+            .collect();
+        if !icon_prefix.is_empty() {
+            queue!(
+                self.window,
+                colors(
+                    if reverse { highlight_fg } else { style::Color::Reset },
+                    if reverse { highlight_bg } else { style::Color::Reset },
+                    reverse,
+                ),
+                style::Print(&icon_prefix),
+            )?;
+        }
+        let width = width.saturating_sub(icon_prefix.width());
+
         let item_size = if let Some(item) = item {
             // we're actually drawing an item
 
             let symlink_target = &item.symlink_target;
             let is_symlink = symlink_target.is_some();
-            let fname = item.file_name_checked();
+            let symlink_color = if item.is_broken_symlink() {
+                broken_symlink_color
+            } else {
+                symlink_color
+            };
+            let fname = fitted_name.unwrap_or_default();
 
             // Find out the grapheme clusters corresponding to the
-            // above byte offsets, and determine whether they should be underlined.
-            let letters_underlining: Vec<(&str, bool)> =
-                UnicodeSegmentation::grapheme_indices(fname.as_str(), true)
-                    // print only up to as many characters as the screen width
-                    .take(width)
+            // above byte offsets, and determine whether they should be underlined. The name is
+            // cut from the end, so the offsets are the same as in the full name.
+            let letters_underlining: Vec<(usize, &str, bool)> =
+                // print only up to as many characters as fit after the prefixes
+                graphemes_fitting_width(fname.as_str(), width)
+                    .into_iter()
                     // this contains() could probably be optimized, but shouldn't be too bad.
-                    .map(|(i, c)| (c, underline_locs.contains(&i)))
+                    .map(|(i, c)| (i, c, underline_locs.contains(&i)))
                     .collect();
+            let mut fname_width: usize = letters_underlining.iter().map(|(_, c, _)| c.width()).sum();
+
+            // With --align-extensions, the extension of a file is pushed to the right edge of the
+            // row, or in the grid layout, to the gap before the next column, if the whole name fits
+            let align_width = if is_last_column {
+                width
+            } else {
+                width.saturating_sub(GRID_COLUMN_GAP)
+            };
+            let whole_name_fits = fname.len() == item.file_name_len()
+                && letters_underlining.iter().map(|(_, c, _)| c.len()).sum::<usize>() == fname.len()
+                && fname_width <= align_width;
+            let extension_start = Some(fname.as_str())
+                .filter(|_| {
+                    self.app_state.settings().align_extensions
+                        && !item.is_dir()
+                        && !is_symlink
+                        && whole_name_fits
+                })
+                .and_then(extension_start);
 
             // queue draw actions for each (non-)underlined segment
-            for (c, underline) in &letters_underlining {
-                let (underline, fg, bg) = match (underline, highlight) {
+            for (i, c, underline) in &letters_underlining {
+                let is_extension = extension_start.is_some_and(|start| *i >= start);
+                if extension_start == Some(*i) {
+                    let gap = align_width - fname_width;
+                    queue!(
+                        self.window,
+                        style::SetAttribute(Attribute::NoUnderline),
+                        colors(
+                            style::Color::Reset,
+                            if reverse { highlight_bg } else { style::Color::Reset },
+                            reverse,
+                        ),
+                        style::Print(" ".repeat(gap)),
+                    )?;
+                    fname_width += gap;
+                }
+                let (underline, fg, bg) = match (underline, reverse) {
+                    // Matching letters have the same background on the cursor row too, so that the
+                    // match color stays legible.
                     (true, _) => (
                         Attribute::Underlined,
-                        style::Color::Reset,
+                        matching_letter_fg,
                         matching_letter_bg,
                     ),
                     (false, true) => (
@@ -313,6 +1398,8 @@ impl<'a> TereTui<'a> {
                         Attribute::NoUnderline,
                         if is_symlink {
                             symlink_color
+                        } else if is_extension {
+                            extension_color
                         } else {
                             style::Color::Reset
                         },
@@ -323,8 +1410,7 @@ impl<'a> TereTui<'a> {
                 queue!(
                     self.window,
                     style::SetAttribute(underline),
-                    style::SetBackgroundColor(bg),
-                    style::SetForegroundColor(fg),
+                    colors(fg, bg, reverse),
                     style::Print(c.to_string()),
                 )?;
 
@@ -335,17 +1421,25 @@ impl<'a> TereTui<'a> {
                 // use it for anything else.
                 //TODO: different color for target?
                 let target_text = format!(" -> {}", target.display());
+                let target_text: String =
+                    graphemes_fitting_width(&target_text, width.saturating_sub(fname_width))
+                        .iter()
+                        .map(|(_, g)| *g)
+                        .collect();
                 queue!(
                     self.window,
                     style::SetAttribute(Attribute::Reset),
-                    style::SetForegroundColor(symlink_color),
-                    style::SetBackgroundColor(if highlight { highlight_bg } else { style::Color::Reset }),
+                    colors(
+                        symlink_color,
+                        if reverse { highlight_bg } else { style::Color::Reset },
+                        reverse,
+                    ),
                     style::Print(&target_text),
                 )?;
 
-                letters_underlining.len() + UnicodeSegmentation::graphemes(target_text.as_str(), true).count()
+                fname_width + target_text.width()
             } else {
-                letters_underlining.len()
+                fname_width
             }
         } else {
             0
@@ -353,13 +1447,37 @@ impl<'a> TereTui<'a> {
 
         // color the rest of the line if applicable
         if item_size < width {
-            if highlight {
+            let mut rest = width - item_size;
+            let no_row_fill = self.app_state.settings().no_row_fill;
+            if reverse {
+                // With --no-row-fill, the highlight only extends a bit past the name, and the rest
+                // of the row is erased below
+                let fill = if no_row_fill { rest.min(NO_ROW_FILL_CURSOR_MARGIN) } else { rest };
                 queue!(
                     self.window,
                     style::SetAttribute(Attribute::Reset), // so that the rest of the line isn't underlined
-                    style::SetBackgroundColor(highlight_bg),
-                    style::Print(" ".repeat(width.saturating_sub(item_size))),
+                    colors(style::Color::Reset, highlight_bg, true),
+                    style::Print(" ".repeat(fill)),
+                )?;
+                rest -= fill;
+            }
+            if rest == 0 {
+                // the whole row is highlighted already
+            } else if self.app_state.settings().preview_enabled || !is_last_column {
+                // don't clear until the end of the line, it would erase the preview or the next
+                // columns
+                queue!(
+                    self.window,
+                    style::ResetColor,
+                    style::SetAttribute(Attribute::Reset),
                 )?;
+                if no_row_fill {
+                    // Erase the characters instead of printing spaces, so that selecting the row
+                    // with the mouse doesn't copy trailing spaces. This doesn't move the cursor.
+                    queue!(self.window, style::Print(format!("\x1b[{}X", rest)))?;
+                } else {
+                    queue!(self.window, style::Print(" ".repeat(rest)))?;
+                }
             } else {
                 queue!(
                     self.window,
@@ -370,6 +1488,26 @@ impl<'a> TereTui<'a> {
             }
         }
 
+        if is_last_column && padding > 0 {
+            // Erase the padding on the right, which is not covered by the highlight
+            let padding_x = self.listing_x() + self.listing_width();
+            queue!(
+                self.window,
+                cursor::MoveTo(
+                    u16::try_from(padding_x).unwrap_or(u16::MAX),
+                    u16::try_from(row_abs).unwrap_or(u16::MAX),
+                ),
+                style::SetAttribute(Attribute::Reset),
+                style::ResetColor,
+                style::Print(format!("\x1b[{}X", padding)),
+            )?;
+        }
+
+        if self.drawn_rows.len() <= row {
+            self.drawn_rows.resize_with(row + 1, || None);
+        }
+        self.drawn_rows[row] = Some(contents);
+
         execute!(
             self.window,
             style::ResetColor,
@@ -377,6 +1515,13 @@ impl<'a> TereTui<'a> {
         )
     }
 
+    /// Forget what has been drawn in the main window, so that all rows are drawn again on the next
+    /// redraw. This has to be called whenever the main window is drawn over by something other
+    /// than `draw_main_window_row`.
+    fn invalidate_main_window(&mut self) {
+        self.drawn_rows.clear();
+    }
+
     // redraw row 'row' (relative to the top of the main window) without highlighting
     fn unhighlight_row(&mut self, row: usize) -> CTResult<()> {
         self.draw_main_window_row(row, false)
@@ -389,8 +1534,10 @@ impl<'a> TereTui<'a> {
     }
 
     fn queue_clear_main_window(&mut self) -> CTResult<()> {
-        let (_, h) = main_window_size()?;
-        for row in HEADER_SIZE..(h + HEADER_SIZE) {
+        self.invalidate_main_window();
+        let (_, h) = self.main_window_size()?;
+        let top = self.main_window_top();
+        for row in top..(h + top) {
             self.queue_clear_row(row)?;
         }
         Ok(())
@@ -404,8 +1551,7 @@ impl<'a> TereTui<'a> {
     }
 
     fn redraw_main_window(&mut self) -> CTResult<()> {
-        let (_, max_y) = main_window_size()?;
-        let mut win = self.window;
+        let (_, max_y) = self.main_window_size()?;
 
         // are there any matches?
         let any_matches = self.app_state.num_matching_items() > 0;
@@ -414,17 +1560,74 @@ impl<'a> TereTui<'a> {
 
         // Draw entries. No need to clear the whole main window, because draw_main_window_row takes
         // care of clearing each row when applicable.
-        for row in 0..max_y {
+        for row in 0..max_y * self.app_state.grid_columns() {
             // highlight the current row under the cursor when applicable
             let highlight = self.app_state.cursor_pos == row
                 && (!is_search || (any_matches || any_visible_items));
             self.draw_main_window_row(row, highlight)?;
         }
 
-        win.flush()
+        if let Some(row) = self.app_state.separator_screen_row() {
+            let listing_width = self.listing_end();
+            let top = self.main_window_top();
+            queue!(
+                self.window,
+                cursor::MoveTo(0, u16::try_from(row + top).unwrap_or(u16::MAX)),
+                style::ResetColor,
+                style::SetAttribute(Attribute::Reset),
+                style::Print(" ".repeat(listing_width)),
+            )?;
+        }
+
+        self.redraw_preview()?;
+
+        self.window.flush()
+    }
+
+    /// Draw the preview of the item under the cursor to the right side of the main window, if the
+    /// preview is enabled.
+    fn redraw_preview(&mut self) -> CTResult<()> {
+        if !self.app_state.settings().preview_enabled {
+            return Ok(());
+        }
+
+        let (w, h) = self.main_window_size()?;
+        let listing_width = self.listing_end();
+        let preview_width = w.saturating_sub(listing_width + PREVIEW_SEPARATOR.width());
+        let lines = self.app_state.preview_item_under_cursor(h);
+        let top = self.main_window_top();
+
+        for row in 0..h {
+            let line: String = lines
+                .get(row)
+                .map(|line| {
+                    graphemes_fitting_width(line, preview_width)
+                        .iter()
+                        .map(|(_, g)| *g)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            queue!(
+                self.window,
+                cursor::MoveTo(
+                    u16::try_from(listing_width).unwrap_or(u16::MAX),
+                    u16::try_from(row + top).unwrap_or(u16::MAX),
+                ),
+                style::SetAttribute(Attribute::Reset),
+                style::ResetColor,
+                style::Print(PREVIEW_SEPARATOR),
+                style::Print(line),
+                terminal::Clear(terminal::ClearType::UntilNewLine),
+            )?;
+        }
+
+        self.window.flush()
     }
 
     fn redraw_all_windows(&mut self) -> CTResult<()> {
+        // the window might have been resized or drawn over, so redraw everything from scratch
+        self.invalidate_main_window();
         self.redraw_header()?;
         self.redraw_info_window()?;
         self.redraw_footer()?;
@@ -447,6 +1650,7 @@ impl<'a> TereTui<'a> {
         } else {
             self.unhighlight_row(old_cursor_pos)?;
             self.highlight_row(self.app_state.cursor_pos)?;
+            self.redraw_preview()?;
         }
         Ok(())
     }
@@ -455,7 +1659,20 @@ impl<'a> TereTui<'a> {
     /// message to the UI and return false.
     fn change_dir(&mut self, path: &str) -> CTResult<bool> {
         //TODO: if there are no visible items, don't do anything?
-        let res = match self.app_state.change_dir(path) {
+        let was_searching = self.is_in_search_mode();
+        let (final_path, message) = self.app_state.change_dir_target(path);
+        let entries = match self.read_dir_with_spinner(&final_path)? {
+            Some(entries) => entries,
+            None => {
+                // cancelled, keep showing the previous folder as it was
+                self.info_message(&format!("Cancelled opening '{}'", final_path.display()))?;
+                return Ok(false);
+            }
+        };
+        let res = match entries
+            .map_err(|e| crate::app_state::with_path_context(e, &final_path))
+            .and_then(|entries| self.app_state.change_dir_with_entries(&final_path, entries))
+        {
             Err(e) => {
                 if cfg!(debug_assertions) {
                     self.error_message(&format!("{:?}", e))?;
@@ -465,8 +1682,10 @@ impl<'a> TereTui<'a> {
                 false
             }
             Ok(()) => {
+                self.continuous_search_active =
+                    was_searching && self.app_state.settings().continuous_search;
                 self.update_header()?;
-                self.info_message("")?;
+                self.info_message(message.as_deref().unwrap_or(""))?;
                 true
             }
         };
@@ -475,25 +1694,147 @@ impl<'a> TereTui<'a> {
         Ok(res)
     }
 
-    fn on_search_char(&mut self, c: char) -> CTResult<()> {
-        self.app_state.advance_search(&c.to_string());
-        let n_matches = self.app_state.num_matching_items();
-        if n_matches == 1 {
-            // There's only one match, highlight it and then change dir if applicable
+    /// Read the current folder again in the background, e.g. after changing a filter, keeping the
+    /// cursor on the same item. Returns false if the reading was cancelled or failed, in which case
+    /// the listing is left as it was, and a message is shown.
+    fn refresh_listing(&mut self) -> CTResult<bool> {
+        let path = self.app_state.current_path.clone();
+        match self.read_dir_with_spinner(&path)? {
+            Some(Ok(entries)) => {
+                self.app_state.refresh_with_entries(entries);
+                self.info_message("")?;
+                Ok(true)
+            }
+            Some(Err(e)) => {
+                let e = crate::app_state::with_path_context(e, &path);
+                self.error_message(&format!("{}", e))?;
+                Ok(false)
+            }
+            None => {
+                self.info_message(&format!("Cancelled reading '{}'", path.display()))?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Read the contents of a folder in a background thread, so that a spinner can be shown if it
+    /// takes a while (e.g. on a slow network drive). Returns None if the user cancelled by pressing
+    /// Esc. Other keys pressed while waiting are ignored.
+    fn read_dir_with_spinner(
+        &mut self,
+        path: &Path,
+    ) -> CTResult<Option<std::io::Result<Vec<CustomDirEntry>>>> {
+        // Use the listing that was prefetched, or is being prefetched, if there is one
+        let (receiver, cancel) = match self.app_state.take_prefetched(path) {
+            Some(prefetched) => prefetched,
+            None => {
+                let cancel = Arc::new(AtomicBool::new(false));
+                let (sender, receiver) = mpsc::channel();
+                let thread_path = path.to_path_buf();
+                let options = self.app_state.listing_options();
+                let thread_cancel = Arc::clone(&cancel);
+                std::thread::spawn(move || {
+                    let entries = read_listing_entries(&thread_path, &options, &thread_cancel);
+                    // if the receiver is gone, the reading was cancelled and the result is not needed
+                    sender.send(entries).ok();
+                });
+                (receiver, cancel)
+            }
+        };
+
+        let mut frame = 0;
+        loop {
+            match receiver.recv_timeout(SPINNER_INTERVAL) {
+                Ok(res) => return Ok(Some(res)),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Ok(Some(Err(std::io::Error::other(
+                        "reading the folder failed unexpectedly",
+                    ))));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            while self.events.poll(Duration::from_secs(0))? {
+                if let Some(Event::Key(KeyEvent { code: KeyCode::Esc, .. })) = self.events.read()? {
+                    // stop reading subfolders with recursive search
+                    cancel.store(true, Ordering::Relaxed);
+                    return Ok(None);
+                }
+            }
+
+            self.info_message(&format!(
+                "{} Loading '{}'... Press Esc to cancel.",
+                SPINNER_FRAMES[frame],
+                path.display()
+            ))?;
+            frame = (frame + 1) % SPINNER_FRAMES.len();
+        }
+    }
+
+    /// Add a character to the search, and change dir automatically if applicable. Returns the path
+    /// of the file to select and exit with, if the only match is a file and files are picked with
+    /// Enter.
+    fn on_search_chars(&mut self, chars: &str) -> CTResult<Option<PathBuf>> {
+        self.app_state.advance_search(chars);
+
+        if self.app_state.settings().bell_on_no_match && self.app_state.num_matching_items() == 0 {
+            execute!(self.window, style::Print('\x07'))?;
+        }
+
+        // If there's only one match, or if enabled, the query is the start of only one folder
+        // name, we can change dir automatically, as long as the query is long enough
+        let long_enough = self.app_state.search_string().chars().count()
+            >= self.app_state.settings().autocd_min_chars;
+        let mut autocd = long_enough && self.app_state.num_matching_items() == 1;
+        if long_enough && !autocd && self.app_state.settings().autocd_prefix {
+            if let Some(name) = self.app_state.unique_prefix_match() {
+                autocd = self.app_state.move_cursor_to_filename(name);
+                self.redraw_main_window()?;
+            }
+        }
+
+        // A file can't be entered, so it's only selected if files are picked with Enter. Otherwise
+        // the cursor is just left on it.
+        let is_file =
+            self.app_state.is_file_under_cursor() && !self.app_state.is_archive_under_cursor();
+        if is_file && self.app_state.settings().enter_on_file != EnterOnFile::Select {
+            autocd = false;
+        }
+
+        if autocd {
+            // highlight the match and then change dir if applicable
             if let Some(timeout) = self.app_state.settings().autocd_timeout {
                 self.highlight_row_exclusive(self.app_state.cursor_pos)?;
 
-                std::thread::sleep(std::time::Duration::from_millis(timeout));
+                self.wait_for_autocd(Duration::from_millis(timeout))?;
 
-                // ignore keys that were pressed during sleep
-                while crossterm::event::poll(std::time::Duration::from_secs(0)).unwrap_or(false) {
-                    read_event()?;
+                if is_file {
+                    return self.real_path_of_item_under_cursor();
                 }
-
                 self.change_dir("")?;
             }
         }
-        self.on_matches_changed()
+        self.on_matches_changed()?;
+        Ok(None)
+    }
+
+    /// Wait for `timeout` before changing dir automatically. The keys pressed in the meantime are
+    /// handled after changing dir, unless they're discarded with `--autocd-discard-keys`.
+    fn wait_for_autocd(&mut self, timeout: Duration) -> CTResult<()> {
+        if !self.app_state.settings().autocd_discard_keys {
+            std::thread::sleep(timeout);
+            return Ok(());
+        }
+        // Only the events that arrive before the timeout are discarded, so keys typed after it
+        // are never lost
+        let deadline = Instant::now() + timeout;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            if !self.events.poll(remaining)? {
+                break;
+            }
+            self.events.read()?;
+        }
+        Ok(())
     }
 
     fn erase_search_char(&mut self) -> CTResult<()> {
@@ -502,11 +1843,27 @@ impl<'a> TereTui<'a> {
     }
 
 
+    fn erase_search_word(&mut self) -> CTResult<()> {
+        self.app_state.erase_search_word();
+        self.on_matches_changed()
+    }
+
+    /// True if the search is not empty, or if the continuous search mode is active.
+    fn is_in_search_mode(&self) -> bool {
+        self.app_state.is_searching() || self.continuous_search_active
+    }
+
     fn on_clear_search(&mut self) -> CTResult<()> {
+        self.continuous_search_active = false;
         self.app_state.clear_search();
         self.on_matches_changed()
     }
 
+    fn recall_search(&mut self, older: bool) -> CTResult<()> {
+        self.app_state.recall_search(older);
+        self.on_matches_changed()
+    }
+
     /// Things to do when the matches are possibly changed
     fn on_matches_changed(&mut self) -> CTResult<()> {
         if self.app_state.is_searching() && self.app_state.num_matching_items() == 0 {
@@ -519,7 +1876,7 @@ impl<'a> TereTui<'a> {
     }
 
     fn update_main_window_dimensions(&mut self) -> CTResult<()> {
-        let (w, h) = main_window_size()?;
+        let (w, h) = self.main_window_size()?;
         self.app_state.update_main_window_dimensions(w, h);
         Ok(())
     }
@@ -531,52 +1888,44 @@ impl<'a> TereTui<'a> {
             self.app_state.move_cursor_to_adjacent_match(dir);
             self.redraw_main_window()?;
         } else {
-            self.move_cursor(dir, true)?;
+            // in the grid layout, move by a whole row
+            let columns = isize::try_from(self.app_state.grid_columns()).unwrap_or(1);
+            self.move_cursor(dir * columns, !self.app_state.settings().no_wrap)?;
         }
         self.redraw_footer()
     }
 
+    /// Move the cursor to the previous (if `left` is true) or next item, i.e. by one column in the
+    /// grid layout
+    fn on_cursor_left_right(&mut self, left: bool) -> CTResult<()> {
+        self.move_cursor(if left { -1 } else { 1 }, !self.app_state.settings().no_wrap)?;
+        self.redraw_footer()
+    }
+
     // When scroling up or down by a screenful (i.e. 'page up' or 'page down')
-    fn on_cursor_up_down_screen(&mut self, up: bool) -> CTResult<()> {
-        if !self.app_state.is_searching() {
-            let (_, h) = main_window_size()?;
-            let delta = ((h - 1) as isize) * if up { -1 } else { 1 };
+    /// Move the cursor by a screenful (minus one row), or by half a screenful if `half` is true.
+    fn on_cursor_up_down_screen(&mut self, up: bool, half: bool) -> CTResult<()> {
+        let (_, h) = self.main_window_size()?;
+        // always move at least one row, even if the window has only one row
+        let rows = if half { h / 2 } else { h.saturating_sub(1) }.max(1);
+        let delta = (rows as isize)
+            * isize::try_from(self.app_state.grid_columns()).unwrap_or(1)
+            * if up { -1 } else { 1 };
+        if self.app_state.is_searching() {
+            // jump by a screenful of matches
+            self.app_state.move_cursor_by_matches(delta);
+            self.redraw_main_window()?;
+        } else {
             self.move_cursor(delta, false)?;
-            self.redraw_footer()?;
-        } //TODO: how to handle page up / page down while searching? jump to the next match below view?
-        Ok(())
+        }
+        self.redraw_footer()
     }
 
     // When moving the cursor to the top or bottom of the listing
     fn on_cursor_top_bottom(&mut self, top: bool) -> CTResult<()> {
-        let searching = self.app_state.is_searching();
-        let match_indices = self.app_state.visible_match_indices();
-
-        let target_idx = if !searching || match_indices.is_empty() {
-            Some(if top {
-                0
-            } else {
-                self.app_state.num_visible_items()
-            })
-        } else if searching && !match_indices.is_empty() {
-            let mut it = match_indices.iter();
-            Some(if top {
-                // OK to unwrap, we've checked that the len is > 0
-                *it.next().unwrap()
-            } else {
-                *it.last().unwrap()
-            })
-        } else {
-            None
-        };
-
-        if let Some(idx) = target_idx {
-            self.app_state.move_cursor_to(idx);
-            self.redraw_main_window()?;
-            self.redraw_footer()?;
-        }
-
-        Ok(())
+        self.app_state.move_cursor_to_top_or_bottom(top);
+        self.redraw_main_window()?;
+        self.redraw_footer()
     }
 
     fn on_go_to_home(&mut self) -> CTResult<()> {
@@ -593,15 +1942,70 @@ impl<'a> TereTui<'a> {
         Ok(())
     }
 
-    fn handle_mouse_event(&mut self, event: MouseEvent) -> CTResult<()> {
+    /// Go back to the folder that was shown before the current one. Doing this again goes back
+    /// to where we started, like 'cd -' in the shell.
+    fn on_go_to_previous(&mut self) -> CTResult<()> {
+        match self.app_state.previous_path().cloned() {
+            Some(path) => match path.to_str() {
+                Some(path) => self.change_dir(path).map(|_| ()),
+                None => self.error_message(&format!("can't open '{}'", path.display())),
+            },
+            None => self.info_message("No previous folder"),
+        }
+    }
+
+    /// Go to the next or previous folder in the parent folder
+    fn change_dir_to_sibling(&mut self, forward: bool) -> CTResult<()> {
+        match self.app_state.sibling_dir(forward) {
+            Ok(Some(path)) => match path.to_str() {
+                Some(path) => self.change_dir(path).map(|_| ()),
+                None => self.error_message(&format!("can't open '{}'", path.display())),
+            },
+            Ok(None) => self.info_message(if forward {
+                "No next folder in the parent folder"
+            } else {
+                "No previous folder in the parent folder"
+            }),
+            Err(e) => self.error_message(&e.to_string()),
+        }
+    }
+
+    fn handle_mouse_event(&mut self, mut event: MouseEvent) -> CTResult<()> {
+        event.row = match event.row.checked_sub(self.inline_top.load(Ordering::Relaxed)) {
+            Some(row) => row,
+            // above the rows used with --inline
+            None => return Ok(()),
+        };
         if event.row == 0 {
-            //TODO: change to folder by clicking on path component in header
+            // change to a folder by clicking on its name in the header
+            if event.kind == MouseEventKind::Up(MouseButton::Left) {
+                let (header_msg, crumbs) = self.header_layout()?;
+                let column = event.column as usize;
+                let clicked = crumbs.iter().find(|c| {
+                    let start = header_msg[..c.range.start].width();
+                    (start..start + header_msg[c.range.clone()].width()).contains(&column)
+                });
+                if let Some(crumb) = clicked {
+                    self.change_dir_to_breadcrumb(crumb)?;
+                }
+            }
             return Ok(());
         }
 
+        // ignore clicks on the footer and the info line
+        let (_, h) = self.main_window_size()?;
+        let row = match (event.row as usize).checked_sub(self.main_window_top()) {
+            Some(row) if row < h => row,
+            _ => return Ok(()),
+        };
+
+        let columns = self.app_state.grid_columns();
+        let listing_column = (event.column as usize).saturating_sub(self.listing_x());
+        let column = (listing_column / self.app_state.grid_column_width()).min(columns - 1);
         if let Some(entry) = self
             .app_state
-            .get_item_at_cursor_pos((event.row - 1) as usize)
+            .cursor_pos_at_screen_row(row * columns + column)
+            .and_then(|cursor_pos| self.app_state.get_item_at_cursor_pos(cursor_pos))
         {
             let fname = entry.file_name_checked();
             if event.kind == MouseEventKind::Up(MouseButton::Left) {
@@ -639,98 +2043,466 @@ impl<'a> TereTui<'a> {
     }
 
     fn cycle_sort_mode(&mut self) -> CTResult<()> {
-        self.app_state.set_sort_mode(match self.app_state.settings().sort_mode {
+        let old_mode = self.app_state.settings().sort_mode;
+        self.app_state.set_sort_mode(match old_mode {
             SortMode::Name => SortMode::Created,
             SortMode::Created => SortMode::Modified,
             SortMode::Modified => SortMode::Name,
         });
+        if !self.refresh_listing()? {
+            self.app_state.set_sort_mode(old_mode);
+        }
+        self.on_matches_changed()
+    }
+
+    fn cycle_search_scope(&mut self) -> CTResult<()> {
+        self.app_state.set_search_scope(match self.app_state.settings().search_scope {
+            SearchScope::Name => SearchScope::Extension,
+            SearchScope::Extension => SearchScope::Stem,
+            SearchScope::Stem => SearchScope::Name,
+        });
         self.on_matches_changed()
     }
 
-    pub fn main_event_loop(&mut self) -> Result<PathBuf, TereError> {
+    fn cycle_listing_mode(&mut self) -> CTResult<()> {
+        let old_mode = self.app_state.settings().listing_mode;
+        self.app_state.set_listing_mode(match old_mode {
+            ListingMode::All => ListingMode::FoldersOnly,
+            ListingMode::FoldersOnly => ListingMode::FilesOnly,
+            ListingMode::FilesOnly => ListingMode::All,
+        });
+        if !self.refresh_listing()? {
+            self.app_state.set_listing_mode(old_mode);
+        }
+        self.on_matches_changed()
+    }
 
-        let loop_result = loop {
-            match read_event()? {
-                Event::Key(k) => {
-                    let valid_ctx = if self.app_state.is_searching() {
-                        ActionContext::Searching
+    /// Use the current search as a glob pattern for filtering the files, or clear the filter if
+    /// not searching.
+    fn set_file_filter(&mut self) -> CTResult<()> {
+        let old_filter = self.app_state.settings().file_filter.clone();
+        if self.app_state.is_searching() {
+            let pattern = self.app_state.search_string().clone();
+            match parse_glob(&pattern) {
+                Ok(file_filter) => {
+                    self.app_state.set_file_filter(Some(file_filter));
+                    if self.refresh_listing()? {
+                        self.app_state.clear_search();
+                        self.info_message(&format!("Showing only files matching '{}'", pattern))?;
                     } else {
-                        ActionContext::NotSearching
-                    };
-
-                    let action = self
-                        .app_state
-                        .settings()
-                        .keymap
-                        .get(&(k, valid_ctx))
-                        // If no mapping is found with the currently applying context, look for a
-                        // mapping that applies in any context
-                        .or_else(|| {
-                            self.app_state
-                                .settings()
-                                .keymap
-                                .get(&(k, ActionContext::None))
-                        });
-
-                    if let Some(action) = action {
-                        match action {
-                            Action::ChangeDir => { self.change_dir("")?; },
-                            Action::ChangeDirParent => { self.change_dir("..")?; },
-                            Action::ChangeDirHome => self.on_go_to_home()?,
-                            Action::ChangeDirRoot => self.on_go_to_root()?,
+                        self.app_state.set_file_filter(old_filter);
+                    }
+                }
+                Err(e) => self.error_message(&format!("invalid pattern: {}", e))?,
+            }
+        } else if old_filter.is_some() {
+            self.app_state.set_file_filter(None);
+            if self.refresh_listing()? {
+                self.info_message("Showing all files")?;
+            } else {
+                self.app_state.set_file_filter(old_filter);
+            }
+        }
+        self.on_matches_changed()
+    }
 
-                            Action::ChangeDirAndExit => {
-                                if self.change_dir("")? {
-                                    break Ok(());
-                                }
-                            }
+    /// Show only the files modified within the time window given with `--modified-within`, or all
+    /// files if the filter is already on.
+    fn toggle_modified_filter(&mut self) -> CTResult<()> {
+        let modified_filter = !self.app_state.settings().modified_filter;
+        self.app_state.set_modified_filter(modified_filter);
+        if !self.refresh_listing()? {
+            self.app_state.set_modified_filter(!modified_filter);
+        } else if modified_filter {
+            let window = format_duration(self.app_state.settings().modified_window);
+            self.info_message(&format!("Showing only files modified in the last {}", window))?;
+        } else {
+            self.info_message("Showing all files")?;
+        }
+        self.on_matches_changed()
+    }
 
-                            Action::CursorUp => self.on_cursor_up_down(true)?,
+    /// Show the folders that are hidden with `--exclude`, or hide them again
+    fn toggle_excluded(&mut self) -> CTResult<()> {
+        if self.app_state.settings().exclude.is_empty() {
+            return self.info_message("No folders are excluded (see --exclude)");
+        }
+        let show_excluded = !self.app_state.settings().show_excluded;
+        self.app_state.set_show_excluded(show_excluded);
+        if !self.refresh_listing()? {
+            self.app_state.set_show_excluded(!show_excluded);
+        } else if show_excluded {
+            self.info_message("Showing the excluded folders")?;
+        } else {
+            self.info_message("Hiding the excluded folders")?;
+        }
+        self.on_matches_changed()
+    }
+
+    fn toggle_number_mode(&mut self) -> CTResult<()> {
+        self.app_state.set_number_mode(!self.app_state.settings().number_mode);
+        self.number_input.clear();
+        self.redraw_main_window()
+    }
+
+    /// Handle a digit typed in number mode. The digits typed so far form a row number, and the
+    /// cursor is moved to that row. If the number is not a valid row, start over from this digit.
+    fn on_number_char(&mut self, c: char) -> CTResult<()> {
+        let is_valid_row_number = |state: &TereAppState, input: &str| {
+            matches!(input.parse::<usize>(), Ok(n) if n > 0 && state.is_valid_screen_row(n - 1))
+        };
+
+        self.number_input.push(c);
+        if !is_valid_row_number(&self.app_state, &self.number_input) {
+            self.number_input = c.to_string();
+        }
+
+        if is_valid_row_number(&self.app_state, &self.number_input) {
+            // ok to unwrap, we just checked that the number is valid
+            let row = self.number_input.parse::<usize>().unwrap() - 1;
+            let old_cursor_pos = self.app_state.cursor_pos;
+            self.app_state.move_cursor_to_screen_row(row);
+            self.unhighlight_row(old_cursor_pos)?;
+            self.highlight_row(self.app_state.cursor_pos)?;
+            self.redraw_preview()?;
+            self.redraw_footer()?;
+        } else {
+            self.number_input.clear();
+        }
+        Ok(())
+    }
+
+    /// The command for opening the file at `path`, as a list of the program and its arguments.
+    fn open_command(&self, path: &Path) -> Vec<String> {
+        let command = self.app_state.settings().open_command.clone().or_else(|| {
+            if is_text_file(path) {
+                ["VISUAL", "EDITOR"]
+                    .iter()
+                    .filter_map(|var| std::env::var(var).ok())
+                    .find(|cmd| !cmd.trim().is_empty())
+            } else {
+                None
+            }
+        });
+        launcher_command(command.as_deref(), &["cmd", "/C", "start", "\"\""])
+    }
+
+    /// Open the file under the cursor with the open command, and wait for it to finish. The
+    /// terminal is restored to its normal state while the command is running, so that it can use
+    /// the terminal (e.g. if it's a text editor).
+    fn open_file_under_cursor(&mut self) -> CTResult<()> {
+        if !self.app_state.is_file_under_cursor() {
+            return self.error_message("Only files can be opened");
+        }
+        let path = match self.real_path_of_item_under_cursor()? {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let command = self.open_command(&path);
+        // ok to unwrap, open_command always returns at least the program
+        let (program, args) = command.split_first().unwrap();
+
+        let mouse_enabled = self.app_state.settings().mouse_enabled;
+        if mouse_enabled {
+            execute!(self.window, DisableMouseCapture)?;
+        }
+        let inline_rows = self.app_state.settings().inline_rows;
+        match inline_rows {
+            Some(_) => leave_inline_region(&mut self.window, self.inline_top.load(Ordering::Relaxed))?,
+            None => execute!(self.window, terminal::LeaveAlternateScreen)?,
+        }
+        execute!(self.window, cursor::Show)?;
+        // the terminal is not in raw mode if the keys don't come from it
+        let raw_mode = self.events.is_terminal();
+        if raw_mode {
+            terminal::disable_raw_mode()?;
+        }
+
+        // The stdout of tere is usually captured by the shell function that cd's to the final
+        // folder, so give the stderr to the command instead, which is connected to the terminal.
+        let status = std::process::Command::new(program)
+            .args(args)
+            .arg(&path)
+            .stdout(std::io::stderr())
+            .status();
+
+        if raw_mode {
+            self.events.enable_raw_mode()?;
+        }
+        match inline_rows {
+            // the command may have printed something, so start the region below it
+            Some(rows) => {
+                let top = enter_inline_region(&mut self.window, rows)?;
+                self.inline_top.store(top, Ordering::Relaxed);
+            }
+            None => execute!(self.window, terminal::EnterAlternateScreen)?,
+        }
+        execute!(self.window, cursor::Hide)?;
+        if mouse_enabled {
+            execute!(self.window, EnableMouseCapture)?;
+        }
+        self.redraw_all_windows()?;
+
+        match status {
+            Ok(status) if status.success() => self.info_message(""),
+            Ok(status) => self.error_message(&format!("'{}' exited with {}", program, status)),
+            Err(e) => self.error_message(&format!("could not run '{}': {}", program, e)),
+        }
+    }
+
+    /// The command for opening a folder in the file manager, as a list of the program and its
+    /// arguments.
+    fn file_manager_command(&self) -> Vec<String> {
+        launcher_command(self.app_state.settings().file_manager_command.as_deref(), &["explorer"])
+    }
+
+    /// Open the current folder in the file manager. Unlike with `open_file_under_cursor`, the
+    /// command is not waited for, because file managers are GUI programs that don't need the
+    /// terminal.
+    fn open_file_manager(&mut self) -> CTResult<()> {
+        let path = self.app_state.current_path.clone();
+        let command = self.file_manager_command();
+        // ok to unwrap, file_manager_command always returns at least the program
+        let (program, args) = command.split_first().unwrap();
+
+        let child = std::process::Command::new(program)
+            .args(args)
+            .arg(&path)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+
+        match child {
+            Ok(mut child) => {
+                // wait for the command in the background, so that it doesn't become a zombie
+                std::thread::spawn(move || child.wait());
+                self.info_message(&format!("Opened '{}' in the file manager", path.display()))
+            }
+            Err(e) => self.error_message(&format!("could not run '{}': {}", program, e)),
+        }
+    }
+
+    /// Copy the path of the item under the cursor to the system clipboard. If the clipboard is not
+    /// available (for example over SSH), ask the terminal to copy it using an OSC 52 escape
+    /// sequence, which is supported by many terminals.
+    fn copy_path(&mut self) -> CTResult<()> {
+        let path = self.app_state.path_to_copy().display().to_string();
+
+        let clipboard = match self.clipboard.take() {
+            Some(clipboard) => Ok(clipboard),
+            None => arboard::Clipboard::new(),
+        };
+        let copied = clipboard.and_then(|mut clipboard| {
+            clipboard.set_text(path.as_str())?;
+            self.clipboard = Some(clipboard);
+            Ok(())
+        });
+        if copied.is_err() {
+            execute!(self.window, style::Print(osc52_copy_sequence(&path)))?;
+        }
+
+        self.info_message(&format!("Copied '{}'", path))
+    }
+
+    /// Turn recursive search on or off, and read the folder again. The search is kept, so that
+    /// it's possible to first search the current folder, and then the subfolders too.
+    fn toggle_recursive_search(&mut self) -> CTResult<()> {
+        let recursive_search = !self.app_state.settings().recursive_search;
+        let query = self.app_state.search_string().clone();
+        self.app_state.set_recursive_search(recursive_search);
+        if !self.change_dir(".")? {
+            // cancelled or failed, so the listing didn't change
+            self.app_state.set_recursive_search(!recursive_search);
+            return self.redraw_footer();
+        }
+        // the search might have been restored already with --remember-search
+        if !query.is_empty() && !self.app_state.is_searching() {
+            self.app_state.advance_search(&query);
+            self.on_matches_changed()?;
+        }
+        self.info_message(if recursive_search {
+            "Searching subfolders too"
+        } else {
+            "Searching only the current folder"
+        })
+    }
+
+    /// Go to the folder that contains the file under the cursor, which is in a subfolder with
+    /// recursive search, and put the cursor on the file.
+    fn change_dir_to_file_folder(&mut self, file: &Path) -> CTResult<()> {
+        let (folder, name) = match (file.parent(), file.file_name()) {
+            (Some(folder), Some(name)) => (folder.to_path_buf(), name.to_os_string()),
+            _ => return Ok(()),
+        };
+        if self.change_dir(&folder.to_string_lossy())? {
+            self.app_state.move_cursor_to_filename(name);
+            self.redraw_main_window()?;
+            self.redraw_footer()?;
+        }
+        Ok(())
+    }
+
+    fn toggle_preview(&mut self) -> CTResult<()> {
+        self.app_state.set_preview_enabled(!self.app_state.settings().preview_enabled);
+        self.redraw_main_window()
+    }
+
+    pub fn main_event_loop(&mut self) -> Result<ExitReason, TereError> {
+
+        let loop_result = loop {
+            let event = match self.wait_for_event()? {
+                Some(event) => event,
+                // all keys given with --keys have been handled, exit and print the current folder
+                None => break ExitReason::Selected(self.current_path()),
+            };
+            match event {
+                Event::Key(k) => {
+                    let half_page = self.app_state.settings().page_scroll == PageScroll::Half;
+
+                    let action = self.action_for_key(&k, self.is_in_search_mode());
+
+                    // the row number is typed with consecutive keys
+                    if action != Some(Action::TypeRowNumber) {
+                        self.number_input.clear();
+                    }
+
+                    if let Some(action) = action {
+                        match action {
+                            Action::ChangeDir
+                                if self.app_state.is_file_under_cursor()
+                                    && !self.app_state.is_archive_under_cursor() =>
+                            {
+                                match self.app_state.settings().enter_on_file {
+                                    EnterOnFile::Select => {
+                                        if let Some(path) = self.real_path_of_item_under_cursor()? {
+                                            break ExitReason::Selected(path);
+                                        }
+                                    }
+                                    EnterOnFile::Open => self.open_file_under_cursor()?,
+                                    EnterOnFile::Nothing => match self.app_state.path_of_item_under_cursor() {
+                                        // with recursive search, the file can be in a subfolder
+                                        Some(path) if path.parent() != Some(&self.app_state.current_path) => {
+                                            self.change_dir_to_file_folder(&path)?
+                                        }
+                                        _ => self.info_message(
+                                            "This is a file, not a folder (see --enter-on-file)"
+                                        )?,
+                                    },
+                                }
+                            }
+                            Action::ChangeDir => { self.change_dir("")?; },
+                            Action::ChangeDirParent => { self.change_dir("..")?; },
+                            Action::ChangeDirHome => self.on_go_to_home()?,
+                            Action::ChangeDirRoot => self.on_go_to_root()?,
+                            Action::ChangeDirPrevious => self.on_go_to_previous()?,
+                            Action::ChangeDirAncestor => self.breadcrumb_loop()?,
+                            Action::ChangeDirNextSibling => self.change_dir_to_sibling(true)?,
+                            Action::ChangeDirPreviousSibling => self.change_dir_to_sibling(false)?,
+
+                            Action::ChangeDirAndExit => {
+                                if self.change_dir("")? {
+                                    break ExitReason::Selected(self.current_path());
+                                }
+                            }
+
+                            // like Exit, but never asks for confirmation, because the intent is clear
+                            Action::AcceptCurrentDir => break ExitReason::Selected(self.current_path()),
+
+                            Action::SelectAndExit => {
+                                if let Some(path) = self.real_path_of_item_under_cursor()? {
+                                    break ExitReason::Selected(path);
+                                }
+                            }
+
+                            Action::OpenFile => self.open_file_under_cursor()?,
+                            Action::OpenFileManager => self.open_file_manager()?,
+                            Action::CopyPath => self.copy_path()?,
+                            Action::CreateFolder => self.create_folder()?,
+
+                            Action::CursorUp => self.on_cursor_up_down(true)?,
                             Action::CursorDown => self.on_cursor_up_down(false)?,
-                            Action::CursorUpScreen => self.on_cursor_up_down_screen(true)?,
-                            Action::CursorDownScreen => self.on_cursor_up_down_screen(false)?,
+                            Action::CursorLeft => self.on_cursor_left_right(true)?,
+                            Action::CursorRight => self.on_cursor_left_right(false)?,
+                            Action::CursorUpScreen => self.on_cursor_up_down_screen(true, half_page)?,
+                            Action::CursorDownScreen => self.on_cursor_up_down_screen(false, half_page)?,
+                            Action::CursorUpHalfScreen => self.on_cursor_up_down_screen(true, true)?,
+                            Action::CursorDownHalfScreen => self.on_cursor_up_down_screen(false, true)?,
                             Action::CursorTop => self.on_cursor_top_bottom(true)?,
                             Action::CursorBottom => self.on_cursor_top_bottom(false)?,
 
                             Action::EraseSearchChar => self.erase_search_char()?,
+                            Action::EraseSearchWord => self.erase_search_word()?,
 
                             Action::ClearSearch => self.on_clear_search()?,
+                            Action::RecallPreviousSearch => self.recall_search(true)?,
+                            Action::RecallNextSearch => self.recall_search(false)?,
 
                             Action::ChangeFilterSearchMode => self.toggle_filter_search_mode()?,
                             Action::ChangeCaseSensitiveMode => self.cycle_case_sensitive_mode()?,
                             Action::ChangeGapSearchMode => self.cycle_gap_search_mode()?,
                             Action::ChangeSortMode => self.cycle_sort_mode()?,
+                            Action::ChangeSearchScope => self.cycle_search_scope()?,
+                            Action::ChangeListingMode => self.cycle_listing_mode()?,
+                            Action::ToggleRecursiveSearch => self.toggle_recursive_search()?,
+
+                            Action::TogglePreview => self.toggle_preview()?,
+                            Action::ToggleNumberMode => self.toggle_number_mode()?,
+                            Action::TypeRowNumber => {
+                                if let KeyCode::Char(c @ '0'..='9') = k.code {
+                                    self.on_number_char(c)?;
+                                }
+                            }
+
+                            Action::SetFileFilter => self.set_file_filter()?,
+                            Action::ToggleModifiedFilter => self.toggle_modified_filter()?,
+                            Action::ToggleExcluded => self.toggle_excluded()?,
 
                             Action::RefreshListing => {
-                                self.change_dir(".")?; //TODO: use 'current dir' instead of hardcoded '.' (?, see also pardir discussion elsewhere)
-                                self.info_message("Refreshed directory listing")?;
+                                //TODO: use 'current dir' instead of hardcoded '.' (?, see also pardir discussion elsewhere)
+                                // If the folder was deleted, keep the message about it instead
+                                if self.change_dir(".")? && self.app_state.info_msg.is_empty() {
+                                    self.info_message("Refreshed directory listing")?;
+                                }
                             }
 
+                            Action::ComputeTotalSize => self.compute_total_size()?,
+
                             Action::Help => self.help_view_loop()?,
 
-                            Action::Exit => break Ok(()),
-                            Action::ExitWithoutCd => {
-                                // exit with error (ctl+c by default), to avoid cd'ing
-                                let msg = format!(
-                                    "{}: Exited without changing folder",
-                                    env!("CARGO_PKG_NAME")
-                                );
-                                break Err(TereError::ExitWithoutCd(msg));
+                            Action::Exit => {
+                                if !self.app_state.settings().confirm_quit || self.confirm_quit()? {
+                                    break ExitReason::Selected(self.current_path());
+                                }
                             }
+                            // exit without printing anything (ctrl+c by default), to avoid cd'ing
+                            Action::ExitWithoutCd => break ExitReason::Aborted,
 
                             Action::None => (),
 
                         }
                     } else {
-                        // The key is not part of any mapping, advance the search if it's a char
-                        if let KeyEvent { code: KeyCode::Char(c), .. } = k {
-                            self.on_search_char(c)?;
-                        } // else { self.info_message(&format!("{:?}", k))? } // for debugging
+                        match k {
+                            // In raw mode, Ctrl-c doesn't send SIGINT. Always allow aborting with
+                            // it, even if it has been unmapped (e.g. with --clear-default-keymap).
+                            KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL, .. } => {
+                                break ExitReason::Aborted;
+                            }
+                            // The key is not part of any mapping, advance the search if it's a char
+                            KeyEvent { code: KeyCode::Char(c), .. } => {
+                                let chars = self.debounce_search_chars(c)?;
+                                if let Some(path) = self.on_search_chars(&chars)? {
+                                    break ExitReason::Selected(path);
+                                }
+                            }
+                            _ => (), // self.info_message(&format!("{:?}", k))?, // for debugging
+                        }
                     }
                 }
 
                 Event::Resize(_, _) => {
-                    self.update_main_window_dimensions()?;
+                    self.skip_resize_burst()?;
+                    self.on_resize()?;
                     self.redraw_all_windows()?;
                 }
 
@@ -751,60 +2523,76 @@ impl<'a> TereTui<'a> {
             }
         };
 
-        if self.app_state.settings().mouse_enabled {
-            execute!(self.window, DisableMouseCapture)?;
+        if let ExitReason::Selected(path) = &loop_result {
+            self.app_state.keep_extracted_file(path);
         }
-
-        self.app_state
-            .on_exit()
-            .map_err(TereError::from)
-            .and(loop_result)
-            .map(|_| self.current_path())
+        self.app_state.on_exit()?;
+        Ok(loop_result)
     }
 
     fn help_view_loop(&mut self) -> CTResult<()> {
-        self.info_message("Use ↓/↑ or j/k to scroll. Press Esc, 'q', '?' or Ctrl+c to exit help.")?;
+        const HELP_VIEW_HINT: &str =
+            "Use ↓/↑ or j/k to scroll, Page Up/Page Down or Space/b to flip pages, and 'v' to show the version. Press any other key to exit help.";
+        self.info_message(HELP_VIEW_HINT)?;
+        let mut showing_version = false;
 
         // We don't need the help view scroll state anywhere else, so not worth it to put in
         // app_state, just keep it here.
         let mut help_view_scroll: usize = 0;
 
         // drawing the help takes care of clearing the window
-        self.draw_help_view(help_view_scroll)?;
+        self.draw_help_view(&mut help_view_scroll)?;
 
         loop {
-            match read_event()? {
-                Event::Key(k) => match k.code {
-                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => {
-                        self.info_message("")?;
-                        return self.redraw_all_windows();
-                    }
-
-                    KeyCode::Char('c') if k.modifiers == KeyModifiers::CONTROL => {
-                        self.info_message("")?;
-                        return self.redraw_all_windows();
-                    }
+            let event = match self.events.read()? {
+                Some(event) => event,
+                None => {
+                    self.info_message("")?;
+                    return self.redraw_all_windows();
+                }
+            };
+            if showing_version && matches!(event, Event::Key(_)) {
+                // the version is only shown until the next key press
+                self.info_message(HELP_VIEW_HINT)?;
+                showing_version = false;
+            }
+            match event {
+                Event::Key(k) => {
+                    let (_, page) = self.main_window_size()?;
+                    match k.code {
+                        KeyCode::Char('v') => {
+                            self.info_message(&version_info(self.app_state.settings()))?;
+                            showing_version = true;
+                            continue;
+                        }
 
-                    KeyCode::Down | KeyCode::Char('j') => {
-                        help_view_scroll += 1;
-                        self.draw_help_view(help_view_scroll)?;
-                    }
+                        KeyCode::Down | KeyCode::Char('j') => help_view_scroll += 1,
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            help_view_scroll = help_view_scroll.saturating_sub(1)
+                        }
+                        KeyCode::PageDown | KeyCode::Char(' ') => help_view_scroll += page.max(1),
+                        KeyCode::PageUp | KeyCode::Char('b') => {
+                            help_view_scroll = help_view_scroll.saturating_sub(page.max(1))
+                        }
+                        KeyCode::Home | KeyCode::Char('g') => help_view_scroll = 0,
+                        // clamped to the last page when drawing
+                        KeyCode::End | KeyCode::Char('G') => help_view_scroll = usize::MAX,
 
-                    KeyCode::Up | KeyCode::Char('k') => {
-                        help_view_scroll = help_view_scroll.saturating_sub(1);
-                        self.draw_help_view(help_view_scroll)?;
+                        // any other key closes the help
+                        _ => {
+                            self.info_message("")?;
+                            return self.redraw_all_windows();
+                        }
                     }
-
-                    _ => {}
-                },
+                    self.draw_help_view(&mut help_view_scroll)?;
+                }
 
                 Event::Resize(_, _) => {
-                    self.update_main_window_dimensions()?;
+                    self.on_resize()?;
                     // Redraw all windows except for main window
                     self.redraw_header()?;
                     self.redraw_info_window()?;
-                    self.redraw_footer()?;
-                    self.draw_help_view(help_view_scroll)?;
+                    self.draw_help_view(&mut help_view_scroll)?;
                 }
 
                 _ => {}
@@ -812,26 +2600,31 @@ impl<'a> TereTui<'a> {
         }
     }
 
-    fn draw_help_view(&mut self, scroll: usize) -> CTResult<()> {
+    /// Draw the help text starting from the line `scroll`, and show which lines are visible in the
+    /// footer. The scroll position is clamped so that the last page is full.
+    fn draw_help_view(&mut self, scroll: &mut usize) -> CTResult<()> {
+        self.invalidate_main_window();
         queue!(
             self.window,
             style::SetAttribute(Attribute::Reset),
             style::ResetColor,
         )?;
 
-        let (width, height) = main_window_size()?;
+        let (width, height) = self.main_window_size()?;
         let help_text = get_formatted_help_text(width, &self.app_state.settings().keymap);
+        *scroll = (*scroll).min(help_text.len().saturating_sub(height));
+        let top = self.main_window_top();
         for (i, line) in help_text
             .iter()
-            .skip(scroll)
-            .chain(vec![vec![]].iter().cycle()) // add empty lines at the end
-            .take(height as usize)
+            .skip(*scroll)
+            .chain([vec![]].iter().cycle()) // add empty lines at the end
+            .take(height)
             .enumerate()
         {
             // Set up cursor position
             queue!(
                 self.window,
-                cursor::MoveTo(0, u16::try_from(i + HEADER_SIZE).unwrap_or(u16::MAX)),
+                cursor::MoveTo(0, u16::try_from(i + top).unwrap_or(u16::MAX)),
             )?;
 
             let mut col = 0; // manually count how many columns we're printing
@@ -841,7 +2634,7 @@ impl<'a> TereTui<'a> {
                     self.window,
                     style::PrintStyledContent(fragment.clone()),
                 )?;
-                col += UnicodeSegmentation::graphemes(fragment.content().as_str(), true).count();
+                col += fragment.content().width();
             }
 
             // Clear the rest of the row if applicable
@@ -853,8 +2646,1565 @@ impl<'a> TereTui<'a> {
             }
         }
 
-        execute!(self.window)?;
+        let position = format!(
+            "help - lines {}-{} / {}",
+            (*scroll + 1).min(help_text.len()),
+            (*scroll + height).min(help_text.len()),
+            help_text.len(),
+        );
+        let position = truncate_left_to_width(&position, width);
+        let footer_win_row = self.footer_win_row()?;
+        self.queue_clear_row(footer_win_row)?;
+        execute!(
+            self.window,
+            cursor::MoveTo(
+                u16::try_from(width.saturating_sub(position.width())).unwrap_or(u16::MAX),
+                u16::try_from(footer_win_row).unwrap_or(u16::MAX),
+            ),
+            style::Print(position.bold()),
+        )?;
 
         Ok(())
     }
 }
+
+impl<'a> Drop for TereTui<'a> {
+    /// Disable mouse capture if it was enabled in `init`. This is done on drop so that it also
+    /// happens if the main event loop exits with an error or panics.
+    fn drop(&mut self) {
+        if self.app_state.settings().mouse_enabled {
+            // We can't do anything about the error here, and the terminal is about to be reset
+            // anyway.
+            let _ = execute!(self.window, DisableMouseCapture);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fitting(text: &str, max_width: usize) -> String {
+        graphemes_fitting_width(text, max_width)
+            .iter()
+            .map(|(_, g)| *g)
+            .collect()
+    }
+
+    #[test]
+    fn test_set_colors_without_color() {
+        let ansi = |cmd: SetColors| {
+            let mut s = String::new();
+            cmd.write_ansi(&mut s).unwrap();
+            s
+        };
+        let colors = |highlight, use_color| SetColors {
+            fg: style::Color::Black,
+            bg: style::Color::Grey,
+            highlight,
+            use_color,
+        };
+
+        assert_eq!(ansi(colors(false, true)), "\x1b[38;5;0m\x1b[48;5;7m");
+        // no colors, and highlighting uses reverse video
+        assert_eq!(ansi(colors(false, false)), "");
+        assert_eq!(ansi(colors(true, false)), "\x1b[7m");
+    }
+
+    #[test]
+    fn test_osc52_copy_sequence() {
+        assert_eq!(osc52_copy_sequence(""), "\x1b]52;c;\x07");
+        assert_eq!(osc52_copy_sequence("f"), "\x1b]52;c;Zg==\x07");
+        assert_eq!(osc52_copy_sequence("fo"), "\x1b]52;c;Zm8=\x07");
+        assert_eq!(osc52_copy_sequence("foo"), "\x1b]52;c;Zm9v\x07");
+        assert_eq!(osc52_copy_sequence("/home/äö"), "\x1b]52;c;L2hvbWUvw6TDtg==\x07");
+    }
+
+    fn crumbs(header: &str, path: &str, max_width: usize) -> (String, Vec<(String, PathBuf)>) {
+        let (text, crumbs) = layout_header(header, Path::new(path), max_width);
+        let crumbs = crumbs
+            .into_iter()
+            .map(|c| (text[c.range].to_string(), c.path))
+            .collect();
+        (text, crumbs)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_layout_header() {
+        let p = PathBuf::from;
+        assert_eq!(
+            crumbs("/home/user/foo", "/home/user/foo", 80),
+            (
+                "/home/user/foo".to_string(),
+                vec![
+                    ("/".into(), p("/")),
+                    ("home".into(), p("/home")),
+                    ("user".into(), p("/home/user")),
+                    ("foo".into(), p("/home/user/foo")),
+                ]
+            ),
+        );
+        assert_eq!(crumbs("/", "/", 80), ("/".to_string(), vec![("/".into(), p("/"))]));
+
+        // the home folder is replaced by '~'
+        assert_eq!(
+            crumbs("~/foo", "/home/user/foo", 80).1,
+            vec![("~".into(), p("/home/user")), ("foo".into(), p("/home/user/foo"))],
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_layout_header_collapsed() {
+        let p = PathBuf::from;
+        // the middle components are replaced by '…', which goes to the deepest hidden folder
+        assert_eq!(
+            crumbs("/aaaa/bbbb/cccc/dddd", "/aaaa/bbbb/cccc/dddd", 16),
+            (
+                "/…/cccc/dddd".to_string(),
+                vec![
+                    ("/".into(), p("/")),
+                    ("…".into(), p("/aaaa/bbbb")),
+                    ("cccc".into(), p("/aaaa/bbbb/cccc")),
+                    ("dddd".into(), p("/aaaa/bbbb/cccc/dddd")),
+                ]
+            ),
+        );
+        assert_eq!(
+            crumbs("/aaaa/bbbb/cccc/dddd", "/aaaa/bbbb/cccc/dddd", 7).1,
+            vec![
+                ("/".into(), p("/")),
+                ("…".into(), p("/aaaa/bbbb/cccc")),
+                ("dddd".into(), p("/aaaa/bbbb/cccc/dddd")),
+            ],
+        );
+        // the first component is kept if it's not the root
+        assert_eq!(
+            crumbs("~/aaaa/bbbb/cccc", "/home/user/aaaa/bbbb/cccc", 13).0,
+            "~/…/bbbb/cccc",
+        );
+
+        // if collapsing is not enough, the beginning is cut off
+        assert_eq!(
+            crumbs("/aaaa/bbbb/cccc/dddd", "/aaaa/bbbb/cccc/dddd", 3).1,
+            vec![("ddd".into(), p("/aaaa/bbbb/cccc/dddd"))],
+        );
+    }
+
+    /// Run the UI in `path` with the given key presses, without a terminal. The UI is drawn to
+    /// `window`, and the terminal size is `size`. Returns the exit reason and the UI, so that its
+    /// state can be checked.
+    fn run_ui<'a>(
+        path: &Path,
+        args: &[&str],
+        keys: &[KeyEvent],
+        size: (u16, u16),
+        window: Box<dyn Write + 'a>,
+    ) -> (ExitReason, TereTui<'a>) {
+        let events = keys.iter().map(|k| Event::Key(*k)).collect();
+        run_ui_with_events(path, args, events, size, window)
+    }
+
+    /// Like `run_ui`, but with any terminal events instead of just key presses
+    fn run_ui_with_events<'a>(
+        path: &Path,
+        args: &[&str],
+        events: Vec<Event>,
+        size: (u16, u16),
+        window: Box<dyn Write + 'a>,
+    ) -> (ExitReason, TereTui<'a>) {
+        let events = Box::new(ScriptedEvents::new(events).with_size(size.0, size.1));
+        run_ui_with_source(path, args, events, window)
+    }
+
+    /// Like `run_ui`, but with any source of events
+    fn run_ui_with_source<'a>(
+        path: &Path,
+        args: &[&str],
+        events: Box<dyn EventSource>,
+        window: Box<dyn Write + 'a>,
+    ) -> (ExitReason, TereTui<'a>) {
+        let _cwd = crate::CwdGuard::lock();
+        let mut all_args = vec![
+            "tere",
+            path.to_str().unwrap(),
+            "--history-file=",
+            "--autocd-timeout=0",
+            // scripted events don't arrive while waiting, so the prefetch delay would slow down
+            // every test
+            "--no-prefetch",
+        ];
+        all_args.extend_from_slice(args);
+        let m = crate::cli_args::get_cli_args().get_matches_from(all_args);
+        let (settings, warnings) = crate::settings::TereSettings::parse_cli_args(&m).unwrap();
+        let state = TereAppState::init(settings, &warnings).unwrap();
+        let mut ui = TereTui::init(state, window, events).unwrap();
+        let reason = ui.main_event_loop().unwrap();
+        (reason, ui)
+    }
+
+    fn run_with_keys(path: &Path, keys: &[KeyEvent]) -> (ExitReason, TereTui<'static>) {
+        run_ui(path, &[], keys, (80, 24), Box::new(std::io::sink()))
+    }
+
+    /// Marks the cells of the screen that are covered by the wide character on their left
+    const WIDE_CHAR_CONTINUATION: char = '\0';
+
+    /// A very simple terminal emulator for checking what the UI draws. It only understands the
+    /// escape sequences that the UI uses, and ignores colors.
+    struct Screen {
+        text: Vec<Vec<char>>,
+        underlined: Vec<Vec<bool>>,
+        reversed: Vec<Vec<bool>>,
+        dimmed: Vec<Vec<bool>>,
+        // False for the cells that are blank because nothing has been printed on them or they
+        // have been erased
+        written: Vec<Vec<bool>>,
+    }
+
+    impl Screen {
+        fn from_output(output: &[u8], w: usize, h: usize) -> Self {
+            let mut screen = Self {
+                text: vec![vec![' '; w]; h],
+                underlined: vec![vec![false; w]; h],
+                reversed: vec![vec![false; w]; h],
+                dimmed: vec![vec![false; w]; h],
+                written: vec![vec![false; w]; h],
+            };
+            let (mut row, mut col, mut underline, mut reverse, mut dim) = (0, 0, false, false, false);
+            let output = String::from_utf8_lossy(output);
+            let mut chars = output.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\x1b' => match chars.next() {
+                        Some('[') => {
+                            let mut params = String::new();
+                            let cmd = loop {
+                                match chars.next() {
+                                    Some(c) if c.is_ascii_alphabetic() => break c,
+                                    Some(c) => params.push(c),
+                                    None => break ' ',
+                                }
+                            };
+                            let nums: Vec<usize> =
+                                params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+                            match cmd {
+                                'H' => {
+                                    row = nums[0].saturating_sub(1);
+                                    col = nums.get(1).map_or(0, |c| c.saturating_sub(1));
+                                }
+                                'K' => {
+                                    let start = if nums[0] == 2 { 0 } else { col };
+                                    screen.clear(row, start..w);
+                                }
+                                'X' => {
+                                    screen.clear(row, col..(col + nums[0].max(1)).min(w));
+                                }
+                                'J' if nums[0] == 2 => {
+                                    for r in 0..h {
+                                        screen.clear(r, 0..w);
+                                    }
+                                }
+                                'm' => {
+                                    let mut nums = nums.iter();
+                                    while let Some(n) = nums.next() {
+                                        match n {
+                                            0 => (underline, reverse, dim) = (false, false, false),
+                                            2 => dim = true,
+                                            22 => dim = false,
+                                            24 => underline = false,
+                                            4 => underline = true,
+                                            27 => reverse = false,
+                                            7 => reverse = true,
+                                            // skip the color values
+                                            38 | 48 => match nums.next() {
+                                                Some(5) => { nums.next(); }
+                                                Some(2) => { nums.nth(2); }
+                                                _ => {}
+                                            },
+                                            _ => {}
+                                        }
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        // operating system command, e.g. copying to the clipboard
+                        Some(']') => for c in chars.by_ref() {
+                            if c == '\x07' {
+                                break;
+                            }
+                        },
+                        _ => {}
+                    },
+                    '\r' => col = 0,
+                    '\n' => row += 1,
+                    c if !c.is_control() => {
+                        if row < h && col < w {
+                            screen.text[row][col] = c;
+                            screen.underlined[row][col] = underline;
+                            screen.reversed[row][col] = reverse;
+                            screen.dimmed[row][col] = dim;
+                            screen.written[row][col] = true;
+                        }
+                        // wide characters cover the next cell too
+                        for _ in 1..unicode_width::UnicodeWidthChar::width(c).unwrap_or(0) {
+                            col += 1;
+                            if row < h && col < w {
+                                screen.text[row][col] = WIDE_CHAR_CONTINUATION;
+                            }
+                        }
+                        col += 1;
+                    }
+                    _ => {}
+                }
+            }
+            screen
+        }
+
+        fn clear(&mut self, row: usize, cols: std::ops::Range<usize>) {
+            if row < self.text.len() {
+                for col in cols {
+                    self.text[row][col] = ' ';
+                    self.underlined[row][col] = false;
+                    self.reversed[row][col] = false;
+                    self.dimmed[row][col] = false;
+                    self.written[row][col] = false;
+                }
+            }
+        }
+
+        /// The text on a row, without trailing spaces
+        fn row(&self, row: usize) -> String {
+            self.text[row]
+                .iter()
+                .filter(|c| **c != WIDE_CHAR_CONTINUATION)
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        }
+
+        /// The underlined characters on a row, with spaces in place of the other characters
+        fn underlined(&self, row: usize) -> String {
+            Self::marked_chars(&self.text[row], &self.underlined[row])
+        }
+
+        /// The characters drawn in reverse video on a row, with spaces in place of the other
+        /// characters. Trailing spaces are removed, so this doesn't show the reversed padding.
+        fn reversed(&self, row: usize) -> String {
+            Self::marked_chars(&self.text[row], &self.reversed[row])
+        }
+
+        /// The number of cells on a row up to the last one that has been printed on, including
+        /// printed spaces
+        fn written_width(&self, row: usize) -> usize {
+            self.written[row].iter().rposition(|w| *w).map_or(0, |i| i + 1)
+        }
+
+        /// The number of cells on a row that are drawn in reverse video, including spaces
+        fn reversed_width(&self, row: usize) -> usize {
+            self.reversed[row].iter().filter(|r| **r).count()
+        }
+
+        /// The dimmed characters on a row, with spaces in place of the other characters
+        fn dimmed(&self, row: usize) -> String {
+            Self::marked_chars(&self.text[row], &self.dimmed[row])
+        }
+
+        fn marked_chars(text: &[char], marked: &[bool]) -> String {
+            text.iter()
+                .zip(marked)
+                .map(|(c, m)| if *m { *c } else { ' ' })
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        }
+    }
+
+    /// Run the UI with the given key presses, and return what's on the screen when it exits
+    fn render_with_keys(path: &Path, keys: &[KeyEvent], w: u16, h: u16) -> Screen {
+        render_with_args(path, &[], keys, w, h)
+    }
+
+    fn render_with_args(path: &Path, args: &[&str], keys: &[KeyEvent], w: u16, h: u16) -> Screen {
+        let mut output = Vec::new();
+        run_ui(path, args, keys, (w, h), Box::new(&mut output));
+        Screen::from_output(&output, w.into(), h.into())
+    }
+
+    #[test]
+    fn test_render_listing() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("also")).unwrap();
+        std::fs::write(tmp.join("a-very-long-file-name-that-does-not-fit-in-the-window.txt"), "").unwrap();
+
+        let screen = render_with_keys(&tmp, &[], 40, 8);
+        assert_eq!(screen.row(1), "..");
+        assert_eq!(screen.row(2), "alpha");
+        assert_eq!(screen.row(3), "also");
+        // long names are cut at the edge of the window
+        assert_eq!(screen.row(4), "a-very-long-file-name-that-does-not-fit-");
+        assert_eq!(screen.row(5), "");
+        assert!(screen.row(7).ends_with("1 file - 2 / 4"), "{}", screen.row(7));
+
+        // the matching characters are underlined
+        let screen = render_with_keys(&tmp, &[key!(a), key!(l)], 40, 8);
+        assert_eq!(screen.underlined(1), "");
+        assert_eq!(screen.underlined(2), "al");
+        assert_eq!(screen.underlined(3), "al");
+        assert_eq!(screen.underlined(4), "a      l");
+        assert!(screen.row(7).starts_with("search: al"), "{}", screen.row(7));
+    }
+
+
+    #[test]
+    fn test_change_dir_to_sibling() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("[alpha]")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+
+        let (reason, _) = run_with_keys(&tmp.join("[alpha]"), &[key!(alt-right)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("beta")));
+        let (reason, _) = run_with_keys(&tmp.join("beta"), &[key!(alt-left)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("[alpha]")));
+
+        // brackets can be searched for
+        let (reason, _) = run_with_keys(&tmp, &[key!('[')]);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("[alpha]")));
+    }
+
+    #[test]
+    fn test_scripted_navigation() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha").join("inner")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+
+        // the cursor starts on the first item after '..'
+        let (reason, ui) = run_with_keys(&tmp, &[key!(down), key!(enter)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("beta")));
+        assert_eq!(ui.app_state.current_path, tmp.join("beta"));
+
+        // searching changes dir automatically when there's only one match
+        let (reason, ui) = run_with_keys(&tmp, &[key!(a), key!(i)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("alpha").join("inner")));
+        assert!(!ui.app_state.is_searching());
+
+        let (reason, ui) = run_with_keys(&tmp, &[key!(x), key!(y)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.clone()));
+        assert_eq!(ui.app_state.search_string(), "xy");
+        assert_eq!(ui.app_state.num_matching_items(), 0);
+
+        let (reason, ui) = run_with_keys(&tmp, &[key!(x), key!(esc), key!(right), key!(ctrl-c)]);
+        assert_eq!(reason, ExitReason::Aborted);
+        assert_eq!(ui.app_state.current_path, tmp.join("alpha"));
+    }
+
+    #[test]
+    fn test_tab_enters_match() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("also")).unwrap();
+        std::fs::write(tmp.join("alt.txt"), "").unwrap();
+
+        // Tab enters the highlighted folder even if there are other matches, and clears the search
+        let (reason, ui) = run_with_keys(&tmp, &[key!(a), key!(l), key!(down), key!(tab)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("also")));
+        assert!(!ui.app_state.is_searching());
+
+        // files can't be entered, but they are selected when picking files
+        let keys = [key!(a), key!(l), key!(up), key!(tab)];
+        let (reason, ui) = run_with_keys(&tmp, &keys);
+        assert_eq!(reason, ExitReason::Selected(tmp.clone()));
+        assert_eq!(ui.app_state.search_string(), "al");
+        let (reason, _) = run_ui(&tmp, &["--pick-file"], &keys, (80, 24), Box::new(std::io::sink()));
+        assert_eq!(reason, ExitReason::Selected(tmp.join("alt.txt")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_open_file() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let folder = tmp.join("folder");
+        std::fs::create_dir_all(folder.join("subfolder")).unwrap();
+        std::fs::write(folder.join("file.txt"), "").unwrap();
+        std::fs::write(tmp.join("opened"), "opened").unwrap();
+
+        // the path of the file is added to the end of the command, so this stub command replaces
+        // the contents of the file
+        let stub = format!("cp {}", tmp.join("opened").display());
+        let keys = [key!(down), key!(alt-o)];
+        let (reason, ui) = run_ui(&folder, &["--open-command", &stub], &keys, (80, 24), Box::new(std::io::sink()));
+        assert_eq!(reason, ExitReason::Selected(folder.clone()));
+        assert!(!ui.app_state.info_msg.contains("error"), "{}", ui.app_state.info_msg);
+        assert_eq!(std::fs::read_to_string(folder.join("file.txt")).unwrap(), "opened");
+
+        // folders are not opened
+        let (_, ui) = run_ui(&folder, &["--open-command", "false"], &[key!(alt-o)], (80, 24), Box::new(std::io::sink()));
+        assert_eq!(ui.app_state.info_msg, "error: Only files can be opened");
+
+        // the errors of the command are shown
+        let (_, ui) = run_ui(&folder, &["--open-command", "false"], &keys, (80, 24), Box::new(std::io::sink()));
+        assert!(ui.app_state.info_msg.starts_with("error: 'false' exited with"), "{}", ui.app_state.info_msg);
+        let args = ["--open-command", "nonexistent-command-for-tere-tests"];
+        let (_, ui) = run_ui(&folder, &args, &keys, (80, 24), Box::new(std::io::sink()));
+        assert!(ui.app_state.info_msg.starts_with("error: could not run"), "{}", ui.app_state.info_msg);
+    }
+
+    #[test]
+    fn test_accept_current_dir() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::fs::write(tmp.join("file"), "").unwrap();
+        let run = |args: &[&str], keys: &[KeyEvent]| {
+            run_ui(&tmp, args, keys, (80, 24), Box::new(std::io::sink())).0
+        };
+
+        // the current folder is printed even if the cursor is on a file or a folder
+        assert_eq!(run(&[], &[key!(end), key!(alt-w)]), ExitReason::Selected(tmp.clone()));
+        assert_eq!(run(&[], &[key!(down), key!(alt-w)]), ExitReason::Selected(tmp.clone()));
+        // also while searching, and without asking for confirmation
+        let keys = [key!(i), key!(alt-w), key!(n)];
+        assert_eq!(run(&["--confirm-quit"], &keys), ExitReason::Selected(tmp.clone()));
+    }
+
+    #[test]
+    fn test_create_folder() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("zeta")).unwrap();
+        let run = |args: &[&str], keys: &[KeyEvent]| {
+            run_ui(&tmp, args, keys, (80, 24), Box::new(std::io::sink()))
+        };
+        let typed = |text: &str| -> Vec<KeyEvent> {
+            text.chars().map(|c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)).collect()
+        };
+        let keys = |name: &str, last: KeyEvent| -> Vec<KeyEvent> {
+            let mut keys = vec![key!(x), key!(f7)];
+            keys.extend(typed(name));
+            keys.extend([key!(backspace), key!(enter), last]);
+            keys
+        };
+
+        // not allowed by default
+        let (_, ui) = run(&[], &[key!(f7)]);
+        assert!(ui.app_state.info_msg.contains("--allow-mkdir"), "{}", ui.app_state.info_msg);
+
+        // the search is cleared, and the cursor is moved to the new folder
+        let (reason, ui) = run(&["--allow-mkdir"], &keys("new_", key!(ctrl-o)));
+        assert_eq!(reason, ExitReason::Selected(tmp.join("new")));
+        assert!(tmp.join("new").is_dir());
+        assert_eq!(ui.app_state.info_msg, "Created folder 'new'");
+
+        // the error is shown if the folder can't be created
+        let (reason, ui) = run(&["--allow-mkdir"], &keys("alpha_", key!(ctrl-o)));
+        assert_eq!(reason, ExitReason::Selected(tmp.join("alpha")));
+        assert!(ui.app_state.info_msg.starts_with("error: could not create"), "{}", ui.app_state.info_msg);
+        let (_, ui) = run(&["--allow-mkdir"], &keys("a/b_", key!(ctrl-o)));
+        assert!(ui.app_state.info_msg.contains("path separators"), "{}", ui.app_state.info_msg);
+
+        // Esc cancels, and the keys typed in the prompt are not used for anything else
+        let mut keys = vec![key!(f7)];
+        keys.extend(typed("q"));
+        keys.extend([key!(esc), key!(down), key!(ctrl-o)]);
+        let (reason, _) = run(&["--allow-mkdir"], &keys);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("new")));
+        assert!(!tmp.join("q").exists());
+    }
+
+    #[test]
+    fn test_confirm_quit() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+        let run = |args: &[&str], keys: &[KeyEvent]| {
+            run_ui(&tmp, args, keys, (80, 24), Box::new(std::io::sink()))
+        };
+
+        // without --confirm-quit, exit immediately
+        let (reason, ui) = run(&[], &[key!(alt-q), key!(down), key!(enter)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.clone()));
+        assert_eq!(ui.app_state.current_path, tmp);
+
+        // any key other than 'y' or Enter cancels, and the key is not used for anything else
+        let keys = [key!(alt-q), key!(n), key!(down), key!(enter), key!(alt-q), key!(y), key!(enter)];
+        let (reason, ui) = run(&["--confirm-quit"], &keys);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("beta")));
+        assert_eq!(ui.app_state.current_path, tmp.join("beta"));
+
+        // Esc clears the search without asking, and asks only when not searching
+        let (reason, ui) = run(&["--confirm-quit"], &[key!(x), key!(esc), key!(esc), key!(enter)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.clone()));
+        assert!(!ui.app_state.is_searching());
+
+        let (reason, _) = run(&["--confirm-quit"], &[key!(esc), key!(j), key!(ctrl-c)]);
+        assert_eq!(reason, ExitReason::Aborted);
+
+        let screen = render_with_args(&tmp, &["--confirm-quit"], &[key!(alt-q)], 40, 8);
+        assert_eq!(screen.row(7), "Quit? y/n");
+    }
+
+    #[test]
+    fn test_change_dir_previous() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha").join("inner")).unwrap();
+
+        let screen = render_with_keys(&tmp, &[key!(alt-'-')], 40, 8);
+        assert_eq!(screen.row(6), "No previous folder");
+
+        let (reason, _) = run_with_keys(&tmp, &[key!('/'), key!(alt-'-')]);
+        assert_eq!(reason, ExitReason::Selected(tmp.clone()));
+
+        // going back again toggles between the two folders, and refreshing doesn't count
+        let keys = [key!('/'), key!(alt-'-'), key!(ctrl-r), key!(alt-'-')];
+        let (reason, _) = run_with_keys(&tmp, &keys);
+        assert_eq!(reason, ExitReason::Selected(PathBuf::from("/")));
+
+        let keys = [key!(enter), key!(enter), key!(alt-'-'), key!(alt-'-')];
+        let (reason, ui) = run_with_keys(&tmp, &keys);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("alpha").join("inner")));
+        assert_eq!(ui.app_state.previous_path(), Some(&tmp.join("alpha")));
+    }
+
+    #[test]
+    fn test_render_layout() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        for name in ["alpha", "beta", "gamma", "delta", "epsilon"] {
+            std::fs::create_dir_all(tmp.join(name)).unwrap();
+        }
+        let render = |layout: &str, events: Vec<Event>, w: u16, h: u16| {
+            let mut output = Vec::new();
+            run_ui_with_events(&tmp, &["--layout", layout], events, (40, 8), Box::new(&mut output));
+            Screen::from_output(&output, w.into(), h.into())
+        };
+        let down = vec![Event::Key(key!(down))];
+
+        let screen = render("classic", down.clone(), 40, 8);
+        assert_eq!(screen.row(1), "..");
+        assert_eq!(screen.row(5), "epsilon");
+        assert!(screen.row(7).starts_with("search:"), "{}", screen.row(7));
+
+        // no info line, so there's room for one more item
+        let screen = render("compact", down.clone(), 40, 8);
+        assert_eq!(screen.row(1), "..");
+        assert_eq!(screen.row(6), "gamma");
+        assert!(screen.row(7).starts_with("search:"), "{}", screen.row(7));
+
+        // the footer and the info line are below the header
+        let screen = render("top-search", down.clone(), 40, 8);
+        assert!(screen.row(1).starts_with("search:"), "{}", screen.row(1));
+        assert_eq!(screen.row(3), "..");
+        assert_eq!(screen.row(7), "epsilon");
+
+        // resizing moves the rows that are relative to the bottom of the screen
+        let mut events = down.clone();
+        events.push(Event::Resize(40, 10));
+        let screen = render("classic", events.clone(), 40, 10);
+        assert_eq!(screen.row(6), "gamma");
+        assert!(screen.row(9).starts_with("search:"), "{}", screen.row(9));
+        let screen = render("top-search", events, 40, 10);
+        assert!(screen.row(1).starts_with("search:"), "{}", screen.row(1));
+        assert_eq!(screen.row(8), "gamma");
+
+        // messages are shown in the footer in the compact layout
+        let screen = render("compact", vec![Event::Key(key!(alt-'-'))], 40, 8);
+        assert!(screen.row(7).starts_with("search:"), "{}", screen.row(7));
+        assert!(screen.row(7).ends_with(" No previous folder"), "{}", screen.row(7));
+    }
+
+    /// Events that have all arrived already, so that `poll` reports them right away, like when the
+    /// terminal sends a burst of resize events
+    struct BurstEvents {
+        events: std::collections::VecDeque<Event>,
+        size: (u16, u16),
+    }
+
+    impl EventSource for BurstEvents {
+        fn size(&self) -> CTResult<(u16, u16)> {
+            Ok(self.size)
+        }
+
+        fn poll(&mut self, _timeout: Duration) -> CTResult<bool> {
+            Ok(!self.events.is_empty())
+        }
+
+        fn read(&mut self) -> CTResult<Option<Event>> {
+            let event = self.events.pop_front();
+            if let Some(Event::Resize(w, h)) = event {
+                self.size = (w, h);
+            }
+            Ok(event)
+        }
+
+        fn is_terminal(&self) -> bool {
+            false
+        }
+
+        fn enable_raw_mode(&mut self) -> CTResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_resize_burst() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        for name in ["alpha", "beta", "gamma"] {
+            std::fs::create_dir_all(tmp.join(name)).unwrap();
+        }
+
+        let run = |events: Vec<Event>| {
+            let mut output = Vec::new();
+            let source = BurstEvents { events: events.into(), size: (40, 8) };
+            let (_, ui) = run_ui_with_source(&tmp, &[], Box::new(source), Box::new(&mut output));
+            let cursor_pos = ui.app_state.cursor_pos;
+            drop(ui);
+            (output, cursor_pos)
+        };
+        let num_redraws = |output: &[u8]| {
+            String::from_utf8_lossy(output).matches("tere-test-resize-burst").count()
+        };
+
+        // a burst of resizes is drawn only once, with the last size
+        let (burst, cursor_pos) = run(vec![
+            Event::Resize(30, 5),
+            Event::Resize(50, 12),
+            Event::Resize(40, 10),
+            Event::Key(crokey::key!(down)),
+        ]);
+        let (single, _) = run(vec![Event::Resize(40, 10), Event::Key(crokey::key!(down))]);
+        assert_eq!(num_redraws(&burst), num_redraws(&single));
+        let screen = Screen::from_output(&burst, 40, 10);
+        assert!(screen.row(9).starts_with("search:"), "{}", screen.row(9));
+        // the key after the burst is not lost
+        assert_eq!(cursor_pos, 2);
+    }
+
+    #[test]
+    fn test_search_debounce() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("apple")).unwrap();
+        for i in 0..SEARCH_DEBOUNCE_MIN_ITEMS {
+            std::fs::File::create(tmp.join(format!("file{}", i))).unwrap();
+        }
+
+        let run_keys = |args: &[&str], keys: &[KeyEvent]| {
+            let events = keys.iter().map(|k| Event::Key(*k)).collect();
+            let source = BurstEvents { events, size: (40, 8) };
+            let (_, ui) = run_ui_with_source(&tmp, args, Box::new(source), Box::new(std::io::sink()));
+            (ui.app_state.current_path.clone(), ui.app_state.search_string().clone())
+        };
+        let run = |args: &[&str]| run_keys(args, &[crokey::key!(a), crokey::key!(x)]);
+
+        // the burst is searched all at once, and 'ax' doesn't match anything
+        assert_eq!(run(&[]), (tmp.clone(), "ax".to_string()));
+        // without the debounce, 'a' alone matches only 'apple', which is entered right away
+        assert_eq!(run(&["--search-debounce", "0"]).0, tmp.join("apple"));
+
+        // the keys that are mapped to actions in the current context end the burst, so 'a' is
+        // searched alone
+        let args = ["--grid", "--map", "x:Grid:CursorRight"];
+        assert_eq!(run_keys(&args, &[crokey::key!(a), crokey::key!(x)]).0, tmp.join("apple"));
+    }
+
+    #[test]
+    fn test_autocd_type_ahead() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("apple").join("inner")).unwrap();
+        std::fs::create_dir_all(tmp.join("banana")).unwrap();
+
+        let run = |args: &[&str]| {
+            // 'i' is typed while 'apple' is highlighted, before changing to it
+            let events = vec![Event::Key(crokey::key!(a)), Event::Key(crokey::key!(i))];
+            let source = BurstEvents { events: events.into(), size: (40, 8) };
+            let mut all_args = vec!["--autocd-timeout=10"];
+            all_args.extend_from_slice(args);
+            let (_, ui) = run_ui_with_source(&tmp, &all_args, Box::new(source), Box::new(std::io::sink()));
+            ui.app_state.current_path.clone()
+        };
+
+        // the type-ahead is searched in the new folder, where it matches only 'inner'
+        assert_eq!(run(&[]), tmp.join("apple").join("inner"));
+        assert_eq!(run(&["--no-autocd-discard-keys"]), tmp.join("apple").join("inner"));
+        assert_eq!(run(&["--autocd-discard-keys"]), tmp.join("apple"));
+    }
+
+    #[test]
+    fn test_inline() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        for name in ["alpha", "beta", "gamma", "delta"] {
+            std::fs::create_dir_all(tmp.join(name)).unwrap();
+        }
+
+        // only the given number of rows is used
+        let screen = render_with_args(&tmp, &["--inline", "5"], &[], 40, 20);
+        assert_eq!(screen.row(1), "..");
+        assert_eq!(screen.row(2), "alpha");
+        assert!(screen.row(4).starts_with("search:"), "{}", screen.row(4));
+        assert_eq!(screen.row(5), "");
+
+        // the whole terminal is used if it's shorter
+        let screen = render_with_args(&tmp, &["--inline", "50"], &[], 40, 8);
+        assert!(screen.row(7).starts_with("search:"), "{}", screen.row(7));
+
+        // mouse events are relative to the start of the region
+        let _cwd = crate::CwdGuard::lock();
+        let m = crate::cli_args::get_cli_args().get_matches_from(vec![
+            "tere",
+            tmp.to_str().unwrap(),
+            "--history-file=",
+            "--inline=5",
+            "--mouse=on",
+        ]);
+        let (settings, warnings) = crate::settings::TereSettings::parse_cli_args(&m).unwrap();
+        let state = TereAppState::init(settings, &warnings).unwrap();
+        let click = |row| Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Up(MouseButton::Left),
+            column: 1,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+        // the first click is above the region, and the second one is on 'alpha'
+        let events = Box::new(ScriptedEvents::new(vec![click(2), click(12)]).with_size(40, 20));
+        let mut ui = TereTui::init(state, Box::new(std::io::sink()), events).unwrap();
+        ui.set_inline_top(Arc::new(AtomicU16::new(10)));
+        assert_eq!(ui.main_event_loop().unwrap(), ExitReason::Selected(tmp.join("alpha")));
+
+        // the region is moved up if the terminal becomes too short for it
+        let m = crate::cli_args::get_cli_args().get_matches_from(vec![
+            "tere",
+            tmp.to_str().unwrap(),
+            "--history-file=",
+            "--inline=5",
+        ]);
+        let (settings, warnings) = crate::settings::TereSettings::parse_cli_args(&m).unwrap();
+        let state = TereAppState::init(settings, &warnings).unwrap();
+        let events = Box::new(ScriptedEvents::new(vec![Event::Resize(40, 12)]).with_size(40, 20));
+        let mut output = Vec::new();
+        let inline_top = Arc::new(AtomicU16::new(10));
+        let mut ui = TereTui::init(state, Box::new(&mut output), events).unwrap();
+        ui.set_inline_top(inline_top.clone());
+        ui.main_event_loop().unwrap();
+        drop(ui);
+        assert_eq!(inline_top.load(Ordering::Relaxed), 7);
+        assert!(String::from_utf8_lossy(&output).contains("\x1b[8;12r"));
+    }
+
+    #[test]
+    fn test_autocd_single_file_match() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::write(tmp.join("zeta.txt"), "").unwrap();
+
+        // the only match is a file, so the cursor is just left on it
+        let (reason, ui) = run_with_keys(&tmp, &[key!(z)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.clone()));
+        assert_eq!(ui.app_state.search_string(), "z");
+        assert_eq!(ui.app_state.get_item_under_cursor().unwrap().file_name_checked(), "zeta.txt");
+        assert!(!ui.app_state.info_msg.contains("error"), "{}", ui.app_state.info_msg);
+
+        // the same with the prefix match, which only looks at folders
+        let (reason, _) = run_ui(&tmp, &["--autocd-prefix"], &[key!(z)], (80, 24), Box::new(std::io::sink()));
+        assert_eq!(reason, ExitReason::Selected(tmp.clone()));
+
+        // when picking files, the file is selected
+        let (reason, _) = run_ui(&tmp, &["--pick-file"], &[key!(z), key!(down)], (80, 24), Box::new(std::io::sink()));
+        assert_eq!(reason, ExitReason::Selected(tmp.join("zeta.txt")));
+
+        // folders are still entered
+        let (reason, _) = run_with_keys(&tmp, &[key!(a)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("alpha")));
+    }
+
+    #[test]
+    fn test_render_header_parent_style() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let path = tmp.join("alpha").join("beta");
+        std::fs::create_dir_all(&path).unwrap();
+        let header = path.display().to_string();
+        let parent_len = header.len() - "beta".len();
+
+        // the parent folders are dimmed, and only the current folder is underlined
+        let screen = render_with_args(&path, &[], &[], 80, 6);
+        assert_eq!(screen.row(0), header);
+        assert_eq!(screen.underlined(0), format!("{}beta", " ".repeat(parent_len)));
+        assert_eq!(screen.dimmed(0), header[..parent_len]);
+
+        // if the header is cut off, the current folder is still shown in full
+        let screen = render_with_args(&path, &[], &[], 8, 6);
+        assert_eq!(screen.row(0), "/…/beta");
+        assert_eq!(screen.underlined(0), "   beta");
+        assert_eq!(screen.dimmed(0), "/…/");
+        let screen = render_with_args(&path, &[], &[], 5, 6);
+        assert_eq!(screen.row(0), "/beta");
+        assert_eq!(screen.underlined(0), " beta");
+        assert_eq!(screen.dimmed(0), "/");
+
+        // with 'bold', the whole header is drawn like the current folder
+        for style in ["bold", "red"] {
+            let screen = render_with_args(&path, &["--header-parent-style", style], &[], 80, 6);
+            assert_eq!(screen.dimmed(0), "");
+            let expected = if style == "bold" { header.clone() } else { format!("{}beta", " ".repeat(parent_len)) };
+            assert_eq!(screen.underlined(0), expected);
+        }
+
+        // the root folder has no parents
+        let screen = render_with_args(Path::new("/"), &[], &[], 80, 6);
+        assert_eq!(screen.underlined(0), "/");
+        assert_eq!(screen.dimmed(0), "");
+    }
+
+    #[test]
+    fn test_render_icons() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::fs::write(tmp.join("a.rs"), "").unwrap();
+        std::fs::write(tmp.join("b"), "").unwrap();
+
+        let screen = render_with_args(&tmp, &["--icons"], &[], 40, 8);
+        assert_eq!(screen.row(1), "\u{f07b} ..");
+        assert_eq!(screen.row(2), "\u{f07b} folder");
+        assert_eq!(screen.row(3), "\u{e7a8} a.rs");
+        assert_eq!(screen.row(4), "\u{f15b} b");
+
+        // the search matches are underlined after the icon
+        let screen = render_with_args(&tmp, &["--icons"], &[key!(a)], 40, 8);
+        assert_eq!(screen.underlined(3), "  a");
+
+        // wide icons and shortening the names to make room for the icon
+        let args = ["--icons", "--icon-map", "rs:🦀,folder:D"];
+        let screen = render_with_args(&tmp, &args, &[], 5, 8);
+        assert_eq!(screen.row(2), "D fol");
+        assert_eq!(screen.row(3), "🦀 a.");
+    }
+
+    #[test]
+    fn test_render_preview_dir() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let outer = tmp.join("outer");
+        std::fs::create_dir_all(outer.join("sub")).unwrap();
+        std::fs::create_dir_all(outer.join("node_modules")).unwrap();
+        std::fs::write(outer.join("z.txt"), "").unwrap();
+        std::fs::write(outer.join("a\x1b[2J.txt"), "").unwrap();
+
+        let screen = render_with_args(&tmp, &["--preview"], &[], 60, 8);
+        assert!(screen.row(1).ends_with("node_modules/"), "{}", screen.row(1));
+        assert!(screen.row(2).ends_with("sub/"), "{}", screen.row(2));
+        assert!(screen.row(3).ends_with("a\u{fffd}[2J.txt"), "{}", screen.row(3));
+        assert!(screen.row(4).ends_with("z.txt"), "{}", screen.row(4));
+
+        // the preview hides the same items as the listing
+        let screen = render_with_args(&tmp, &["--preview", "--exclude", "node_modules", "--folders-only"], &[], 60, 8);
+        assert!(screen.row(1).ends_with("sub/"), "{}", screen.row(1));
+        assert!(screen.row(2).ends_with('│'), "{}", screen.row(2));
+    }
+
+    #[test]
+    fn test_render_preview_control_chars() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::write(tmp.join("a.txt"), "hello\x1b[2J\x1b]0;pwned\x07 world\r!\n").unwrap();
+
+        // the escape sequences in the file are not sent to the terminal
+        let mut output = Vec::new();
+        run_ui(&tmp, &["--preview"], &[], (60, 8), Box::new(&mut output));
+        let output = String::from_utf8_lossy(&output).to_string();
+        assert!(!output.contains("\x1b[2J"));
+        assert!(!output.contains("\x1b]0;pwned"));
+
+        let screen = Screen::from_output(output.as_bytes(), 60, 8);
+        let preview = screen.row(1);
+        assert!(preview.ends_with("hello\u{fffd}[2J\u{fffd}]0;pwned\u{fffd} world\u{fffd}!"), "{}", preview);
+    }
+
+    #[test]
+    fn test_render_no_row_fill() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        for name in ["alpha", "beta"] {
+            std::fs::create_dir_all(tmp.join(name)).unwrap();
+        }
+
+        // by default, the cursor row is highlighted from edge to edge, and with the preview, the
+        // other rows are padded with spaces up to the preview. Use reverse video for the cursor,
+        // so that the highlight can be seen in the output.
+        let screen = render_with_args(&tmp, &["--preview", "--cursor-color", "none"], &[], 40, 8);
+        let padded = screen.reversed_width(2);
+        assert!(padded > "alpha".len() + 1, "{}", padded);
+        assert!(screen.written[3][4..10].iter().all(|w| *w));
+
+        // with --no-row-fill, the cursor is still highlighted past the name, but the rest of the
+        // rows is erased instead
+        let screen = render_with_args(&tmp, &["--preview", "--cursor-color", "none", "--no-row-fill"], &[], 40, 8);
+        assert_eq!(screen.reversed(2).trim_end(), "alpha");
+        assert_eq!(screen.reversed_width(2), "alpha".len() + 1);
+        assert!(screen.written[2][..6].iter().all(|w| *w));
+        assert!(screen.written[2][6..padded].iter().all(|w| !*w));
+        assert!(screen.written[3][4..padded].iter().all(|w| !*w));
+
+        // without the preview, the rows are cleared to the end of the line
+        let screen = render_with_args(&tmp, &["--cursor-color", "none", "--no-row-fill"], &[], 40, 8);
+        assert_eq!(screen.reversed_width(2), "alpha".len() + 1);
+        assert_eq!(screen.written_width(2), "alpha".len() + 1);
+        assert_eq!(screen.written_width(3), "beta".len());
+    }
+
+    #[test]
+    fn test_prefetch() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha").join("inner")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+
+        // the folder under the cursor is read in the background after a while
+        let (_, mut ui) = run_ui(&tmp, &["--prefetch"], &[], (40, 8), Box::new(std::io::sink()));
+        let alpha = ui.app_state.current_path.join("alpha");
+        let (receiver, _) = ui.app_state.take_prefetched(&alpha).unwrap();
+        let entries = receiver.recv().unwrap().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].file_name_checked(), "inner");
+
+        let (_, mut ui) = run_ui(&tmp, &[], &[], (40, 8), Box::new(std::io::sink()));
+        assert!(ui.app_state.take_prefetched(&alpha).is_none());
+
+        // entering the folder uses the prefetched listing
+        let (_, mut ui) = run_ui(&tmp, &["--prefetch"], &[key!(enter)], (40, 8), Box::new(std::io::sink()));
+        assert_eq!(ui.app_state.current_path, alpha);
+        assert_eq!(ui.app_state.get_item_under_cursor().unwrap().file_name_checked(), "inner");
+        assert!(ui.app_state.take_prefetched(&alpha).is_none());
+    }
+
+    #[test]
+    fn test_grid_keys() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        for name in ["alpha", "beta", "gamma"] {
+            std::fs::create_dir_all(tmp.join(name)).unwrap();
+        }
+        let run = |args: &[&str], keys: &[KeyEvent]| {
+            run_ui(&tmp, args, keys, (80, 8), Box::new(std::io::sink())).1
+        };
+        let item_under_cursor = |ui: &TereTui| ui.app_state.get_item_under_cursor().unwrap().file_name_checked();
+
+        // in the grid layout, the right arrow moves to the next column
+        let ui = run(&["--grid"], &[key!(right)]);
+        assert_eq!(ui.app_state.current_path, tmp);
+        assert_eq!(item_under_cursor(&ui), "beta");
+        // unless it's mapped to something else
+        let ui = run(&["--grid", "--map", "right:Grid:ChangeDir"], &[key!(right)]);
+        assert_eq!(ui.app_state.current_path, tmp.join("alpha"));
+        let ui = run(&["--grid", "--map", "right:Grid:None"], &[key!(right)]);
+        assert_eq!(ui.app_state.current_path, tmp.join("alpha"));
+    }
+
+    #[test]
+    fn test_number_mode_keys() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        for name in ["alpha", "beta", "gamma"] {
+            std::fs::create_dir_all(tmp.join(name)).unwrap();
+        }
+        let run = |args: &[&str], keys: &[KeyEvent]| {
+            run_ui(&tmp, args, keys, (80, 8), Box::new(std::io::sink())).1
+        };
+
+        // digits jump to the rows
+        let ui = run(&["--numbers"], &[key!('4')]);
+        assert_eq!(ui.app_state.get_item_under_cursor().unwrap().file_name_checked(), "gamma");
+        assert!(!ui.app_state.is_searching());
+        // and the digits that are unmapped are used for searching
+        let ui = run(&["--numbers", "--map", "4:NumberMode:None"], &[key!('4')]);
+        assert_eq!(ui.app_state.search_string(), "4");
+    }
+
+    #[test]
+    fn test_change_sort_mode() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        let week_ago = std::time::SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+        std::fs::File::create(tmp.join("a_old")).unwrap().set_modified(week_ago).unwrap();
+        std::fs::File::create(tmp.join("b_new")).unwrap();
+
+        // the folder is read again in the sort order, and the cursor stays on the same item
+        let (_, ui) = run_with_keys(&tmp, &[key!(alt-s), key!(alt-s)]);
+        assert_eq!(ui.app_state.settings().sort_mode, SortMode::Modified);
+        let names: Vec<String> =
+            ui.app_state.visible_items().iter().map(|e| e.file_name_checked()).collect();
+        assert_eq!(names, vec!["..", "b_new", "a_old"]);
+        assert_eq!(ui.app_state.get_item_under_cursor().unwrap().file_name_checked(), "a_old");
+    }
+
+    #[test]
+    fn test_render_padding() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("also")).unwrap();
+        std::fs::write(tmp.join("a-very-long-file-name-that-does-not-fit.txt"), "").unwrap();
+
+        let screen = render_with_args(&tmp, &["--padding", "2", "--cursor-color", "none"], &[], 40, 8);
+        assert_eq!(screen.row(1), "  ..");
+        assert_eq!(screen.row(2), "  alpha");
+        // the padding is not highlighted on either side, and long names are cut before the padding
+        // on the right
+        assert_eq!(screen.reversed(2), "  alpha");
+        assert_eq!(screen.reversed_width(2), 36);
+        assert_eq!(screen.row(4), "  a-very-long-file-name-that-does-not-");
+        // the header and footer are not padded
+        assert!(screen.row(0).starts_with('/'), "{}", screen.row(0));
+        assert!(screen.row(7).starts_with("search:"), "{}", screen.row(7));
+
+        // the underlines move with the names
+        let screen = render_with_args(&tmp, &["--padding", "2"], &[key!(a), key!(l)], 40, 8);
+        assert_eq!(screen.underlined(2), "  al");
+        assert_eq!(screen.underlined(4), "  a      l");
+
+        // clicking on the padding clicks on the row
+        let click = Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Up(MouseButton::Left),
+            column: 0,
+            row: 3,
+            modifiers: KeyModifiers::NONE,
+        });
+        let (reason, _) = run_ui_with_events(
+            &tmp,
+            &["--padding", "2", "--mouse=on"],
+            vec![click],
+            (40, 8),
+            Box::new(std::io::sink()),
+        );
+        assert_eq!(reason, ExitReason::Selected(tmp.join("also")));
+
+        // the preview starts after the listing
+        let screen = render_with_args(&tmp, &["--padding", "2", "--preview"], &[], 40, 8);
+        assert_eq!(screen.row(2), format!("  alpha{}{}", " ".repeat(20 - 7), PREVIEW_SEPARATOR.trim_end()));
+    }
+
+    #[test]
+    fn test_render_align_extensions() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("dir.d")).unwrap();
+        for name in ["main.rs", ".bashrc", "a.tar.gz", "a-very-long-file-name-that-does-not-fit.txt"] {
+            std::fs::write(tmp.join(name), "").unwrap();
+        }
+
+        let args = ["--align-extensions", "--cursor-color", "none"];
+        let screen = render_with_args(&tmp, &args, &[], 40, 9);
+        // folders and dotfiles are drawn as usual
+        assert_eq!(screen.row(2), "dir.d");
+        assert_eq!(screen.row(3), ".bashrc");
+        // names that don't fit are drawn as usual
+        assert_eq!(screen.row(4), "a-very-long-file-name-that-does-not-fit.");
+        // only the last extension is aligned
+        assert_eq!(screen.row(5), format!("a.tar{}.gz", " ".repeat(40 - 8)));
+        assert_eq!(screen.row(6), format!("main{}.rs", " ".repeat(40 - 7)));
+
+        // the match can span the dot, and the underlines stay on the matching letters
+        let screen = render_with_args(&tmp, &args, &[key!(m), key!(r), key!(s)], 40, 9);
+        assert_eq!(screen.underlined(6), format!("m{}rs", " ".repeat(40 - 3)));
+        // the whole cursor row is highlighted, including the gap
+        assert_eq!(screen.reversed_width(6), 40);
+
+        // in the grid layout, the extensions are aligned to the gap before the next column
+        let screen = render_with_args(&tmp, &["--align-extensions", "--grid"], &[], 100, 9);
+        let name_width = "a-very-long-file-name-that-does-not-fit.txt".len();
+        assert_eq!(
+            screen.row(3),
+            format!("a.tar{}.gz  main{}.rs", " ".repeat(name_width - 8), " ".repeat(100 - 45 - 7)),
+        );
+    }
+
+    #[test]
+    fn test_render_total_size() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::fs::write(tmp.join("folder").join("file"), "x".repeat(2048)).unwrap();
+
+        // the size is shown when the background computation is done
+        let screen = render_with_keys(&tmp, &[key!(alt-z)], 60, 8);
+        assert!(screen.row(7).ends_with("1 folder, 0 files, 2.0 KiB in total - 2 / 2"), "{}", screen.row(7));
+
+        // the files below the depth limit are left out
+        let screen = render_with_args(&tmp, &["--max-depth", "0"], &[key!(alt-z)], 80, 8);
+        assert!(
+            screen.row(7).ends_with("1 folder, 0 files, at least 0 B in total (max depth 0) - 2 / 2"),
+            "{}",
+            screen.row(7)
+        );
+    }
+
+    #[test]
+    fn test_render_footer_format() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+        std::fs::write(tmp.join("also.txt"), "").unwrap();
+
+        let args = [
+            "--footer-format", "{cursor}/{total} ({dirs}d {files}f)",
+            "--search-footer-format", "[{match} of {matches}]",
+        ];
+        let screen = render_with_args(&tmp, &args, &[], 60, 8);
+        assert!(screen.row(7).ends_with(" 2/4 (2d 1f)"), "{}", screen.row(7));
+        let screen = render_with_args(&tmp, &args, &[key!(a), key!(l)], 60, 8);
+        assert!(screen.row(7).ends_with(" [1 of 2]"), "{}", screen.row(7));
+    }
+
+    #[test]
+    fn test_render_cursor_style() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("also")).unwrap();
+
+        let screen = render_with_args(&tmp, &["--cursor-style", "arrow"], &[], 40, 8);
+        assert_eq!(screen.row(1), " ..");
+        assert_eq!(screen.row(2), ">alpha");
+        assert_eq!(screen.row(3), " also");
+
+        // the marker composes with the numbers and the search underlining
+        let screen = render_with_args(
+            &tmp,
+            &["--cursor-style", "bar", "--numbers"],
+            &[key!(a)],
+            40,
+            8,
+        );
+        assert_eq!(screen.row(2), "▌2 alpha");
+        assert_eq!(screen.row(3), " 3 also");
+        assert_eq!(screen.underlined(3), "   a");
+        assert_eq!(screen.underlined(2), "   a");
+    }
+
+    #[test]
+    fn test_render_reverse_cursor() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+
+        // by default, the cursor is drawn with colors
+        let screen = render_with_args(&tmp, &[], &[], 40, 8);
+        assert_eq!(screen.reversed(2), "");
+
+        let screen = render_with_args(&tmp, &["--cursor-color", "none"], &[], 40, 8);
+        assert_eq!(screen.reversed(1), "");
+        assert_eq!(screen.reversed(2), "alpha");
+        assert_eq!(screen.reversed(3), "");
+
+        // moving the cursor un-reverses the previous row
+        let screen = render_with_args(&tmp, &["--cursor-color", "none"], &[crokey::key!(down)], 40, 8);
+        assert_eq!(screen.reversed(2), "");
+        assert_eq!(screen.reversed(3), "beta");
+    }
+
+    #[test]
+    fn test_render_open_file_manager_error() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+
+        let screen = render_with_args(
+            &tmp,
+            &["--file-manager-command", "tere-no-such-file-manager --new-window"],
+            &[key!(alt-e)],
+            60,
+            8,
+        );
+        assert!(screen.row(6).contains("could not run 'tere-no-such-file-manager'"), "{}", screen.row(6));
+    }
+
+    #[test]
+    fn test_enter_on_file() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::write(tmp.join("file"), "").unwrap();
+
+        // by default, nothing happens, and the exit key prints the current folder
+        let screen = render_with_keys(&tmp, &[key!(enter)], 60, 8);
+        assert!(screen.row(6).starts_with("This is a file"), "{}", screen.row(6));
+        let (reason, _) = run_with_keys(&tmp, &[key!(enter), key!(alt-q)]);
+        assert_eq!(reason, ExitReason::Selected(tmp.clone()));
+
+        let (reason, _) = run_ui(
+            &tmp,
+            &["--enter-on-file", "select"],
+            &[key!(enter), key!(alt-q)],
+            (80, 24),
+            Box::new(std::io::sink()),
+        );
+        assert_eq!(reason, ExitReason::Selected(tmp.join("file")));
+    }
+
+    #[test]
+    fn test_render_footer_narrow() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+
+        // the search query has priority over the other info, and everything is cut to fit
+        let screen = render_with_keys(&tmp, &[], 3, 8);
+        assert_eq!(screen.row(7), "sea");
+        let screen = render_with_keys(&tmp, &[], 1, 8);
+        assert_eq!(screen.row(7), "s");
+
+        // the info is aligned to the right edge by its width, not its length in bytes
+        let screen = render_with_args(&tmp, &["--filter", "日本*"], &[], 60, 8);
+        assert!(screen.row(7).ends_with("files:日本* - 2 folders, 0 files - 2 / 3"), "{}", screen.row(7));
+        assert_eq!(screen.row(7).width(), 60);
+    }
+
+    #[test]
+    fn test_recursive_search() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("sub").join("deeper")).unwrap();
+        std::fs::write(tmp.join("sub").join("deeper").join("target.txt"), "").unwrap();
+        std::fs::write(tmp.join("top.txt"), "").unwrap();
+
+        // pressing Enter on a file in a subfolder goes to the subfolder
+        let keys = [key!(alt-a), key!(t), key!(enter)];
+        let (reason, ui) = run_with_keys(&tmp, &keys);
+        assert_eq!(reason, ExitReason::Selected(tmp.join("sub").join("deeper")));
+        assert_eq!(ui.app_state.get_item_under_cursor().unwrap().file_name_checked(), "target.txt");
+
+        // the search is kept when toggling
+        let screen = render_with_keys(&tmp, &[key!(t), key!(alt-a)], 60, 8);
+        assert_eq!(screen.row(4), Path::new("sub").join("deeper").join("target.txt").to_str().unwrap());
+        assert_eq!(screen.underlined(4), format!("{}t", " ".repeat("sub/deeper/".len())));
+        assert!(screen.row(7).starts_with("search: t"), "{}", screen.row(7));
+        assert!(screen.row(7).contains("recursive - 1 / 2 / 5"), "{}", screen.row(7));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_render_broken_symlink() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("folder")).unwrap();
+        std::os::unix::fs::symlink("nonexistent", tmp.join("dangling")).unwrap();
+
+        let screen = render_with_keys(&tmp, &[key!(down)], 60, 8);
+        assert_eq!(screen.row(3), "dangling -> nonexistent");
+        assert_eq!(screen.row(6), "broken link -> nonexistent");
+        // the message goes away when the cursor moves away from the link
+        let screen = render_with_keys(&tmp, &[key!(down), key!(up)], 60, 8);
+        assert_eq!(screen.row(6), "");
+    }
+
+    #[test]
+    fn test_version_info() {
+        let args = crate::cli_args::get_cli_args().get_matches_from(vec!["tere", "--sort", "modified", "--numbers"]);
+        let (mut settings, _) = TereSettings::parse_cli_args(&args).unwrap();
+        let info = version_info(&settings);
+        assert!(info.starts_with(&format!("tere {}", env!("CARGO_PKG_VERSION"))), "{}", info);
+        assert!(info.contains(std::env::consts::OS), "{}", info);
+        assert!(info.contains(" - prefs file: none - settings: "), "{}", info);
+        assert!(info.ends_with(" --sort modified"), "{}", info);
+        assert!(info.contains(" --numbers"), "{}", info);
+
+        // the current values are shown, not the arguments
+        settings.number_mode = false;
+        settings.prefs_file = Some(PathBuf::from("/tmp/prefs.json"));
+        let info = version_info(&settings);
+        assert!(info.contains(" - prefs file: /tmp/prefs.json - "), "{}", info);
+        assert!(info.contains(" --no-numbers"), "{}", info);
+    }
+
+    #[test]
+    fn test_render_version_info() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+
+        // the help view is closed when the keys run out, so look at everything that was drawn
+        let output_with_keys = |keys: &[KeyEvent]| {
+            let mut output = Vec::new();
+            run_ui(&tmp, &[], keys, (200, 8), Box::new(&mut output));
+            String::from_utf8_lossy(&output).to_string()
+        };
+        let output = output_with_keys(&[key!('?'), key!(v)]);
+        assert!(output.contains(&format!("tere {}", env!("TERE_VERSION"))));
+
+        // the next key press brings back the hint
+        let output = output_with_keys(&[key!('?'), key!(v), key!(j)]);
+        let version_pos = output.find(" - settings:").unwrap();
+        let hint_pos = output.rfind("Use ↓/↑ or j/k to scroll").unwrap();
+        assert!(hint_pos > version_pos);
+    }
+
+    #[test]
+    fn test_bell_on_no_match() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+        let bells = |args: &[&str], keys: &[KeyEvent]| {
+            let mut output = Vec::new();
+            drop(run_ui(&tmp, args, keys, (80, 8), Box::new(&mut output)));
+            output.iter().filter(|b| **b == b'\x07').count()
+        };
+
+        // 'a' matches both folders, and each of 'x' and 'y' leaves no matches
+        let keys = [key!(a), key!(x), key!(y), key!(backspace), key!(backspace)];
+        assert_eq!(bells(&["--bell-on-no-match"], &keys), 2);
+        assert_eq!(bells(&[], &keys), 0);
+    }
+
+    #[test]
+    fn test_help_view_pages() {
+        use crokey::key;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(tmp.join("alpha")).unwrap();
+        std::fs::create_dir_all(tmp.join("beta")).unwrap();
+        let output_with_keys = |keys: &[KeyEvent]| {
+            let mut output = Vec::new();
+            let (_, ui) = run_ui(&tmp, &[], keys, (80, 8), Box::new(&mut output));
+            let search = ui.app_state.search_string().clone();
+            drop(ui);
+            (String::from_utf8_lossy(&output).to_string(), search)
+        };
+        let keymap = crate::settings::DEFAULT_KEYMAP.iter().map(|(k, c, a)| ((*k, c.clone()), a.clone())).collect();
+        let n_lines = get_formatted_help_text(80, &keymap).len();
+
+        // the main window has five rows
+        let (output, _) = output_with_keys(&[key!('?'), key!(pagedown), key!(space), key!(b)]);
+        let positions: Vec<_> = output.match_indices("help - lines ").map(|(i, _)| output[i..].split('\x1b').next().unwrap()).collect();
+        assert_eq!(positions[0], format!("help - lines 1-5 / {}", n_lines));
+        assert_eq!(positions[1], format!("help - lines 6-10 / {}", n_lines));
+        assert_eq!(positions[2], format!("help - lines 11-15 / {}", n_lines));
+        assert_eq!(positions[3], format!("help - lines 6-10 / {}", n_lines));
+
+        // scrolling stops at the last page
+        let (output, _) = output_with_keys(&[key!('?'), key!(end), key!(j), key!(g)]);
+        let last_page = format!("help - lines {}-{} / {}", n_lines - 4, n_lines, n_lines);
+        assert_eq!(output.matches(&last_page).count(), 2);
+        assert!(output.contains("help - lines 1-5 /"));
+
+        // any other key closes the help, and isn't used for searching
+        let (_, search) = output_with_keys(&[key!('?'), key!(x), key!(e)]);
+        assert_eq!(search, "e");
+    }
+
+    #[test]
+    fn test_graphemes_fitting_width_ascii() {
+        assert_eq!(fitting("foobar", 10), "foobar");
+        assert_eq!(fitting("foobar", 3), "foo");
+        assert_eq!(fitting("foobar", 0), "");
+    }
+
+    #[test]
+    fn test_graphemes_fitting_width_accented() {
+        // precomposed and combining accents both take up one column
+        assert_eq!(fitting("café", 4), "café");
+        assert_eq!(fitting("cafe\u{301}s", 4), "cafe\u{301}");
+        assert_eq!(
+            graphemes_fitting_width("cafe\u{301}s", 10).last(),
+            Some(&(6, "s")),
+        );
+    }
+
+    #[test]
+    fn test_graphemes_fitting_width_cjk() {
+        assert_eq!(fitting("日本語", 6), "日本語");
+        // a wide character that doesn't fit completely is dropped
+        assert_eq!(fitting("日本語", 5), "日本");
+        assert_eq!(fitting("日本語", 1), "");
+        assert_eq!(
+            graphemes_fitting_width("a日b", 10),
+            vec![(0, "a"), (1, "日"), (4, "b")],
+        );
+    }
+
+    #[test]
+    fn test_graphemes_fitting_width_emoji() {
+        assert_eq!(fitting("📁docs", 6), "📁docs");
+        assert_eq!(fitting("📁docs", 3), "📁d");
+        assert_eq!(fitting("📁docs", 1), "");
+    }
+
+    #[test]
+    fn test_truncate_left_to_width() {
+        assert_eq!(truncate_left_to_width("/home/user", 20), "/home/user");
+        assert_eq!(truncate_left_to_width("/home/user", 4), "user");
+        assert_eq!(truncate_left_to_width("/home/日本語", 5), "本語");
+        assert_eq!(truncate_left_to_width("/tmp/cafe\u{301}", 4), "cafe\u{301}");
+        assert_eq!(truncate_left_to_width("/tmp/📁", 3), "/📁");
+    }
+}